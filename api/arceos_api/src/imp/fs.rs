@@ -1,12 +1,15 @@
-use alloc::string::String;
+use alloc::{string::String, sync::Arc, vec::Vec};
 use axerrno::AxResult;
-use axfs::fops::{Directory, File};
+use axfs::fops::{Directory, File, Watcher};
 
 pub use axfs::fops::DirEntry as AxDirEntry;
 pub use axfs::fops::FileAttr as AxFileAttr;
 pub use axfs::fops::FilePerm as AxFilePerm;
+pub use axfs::fops::FileSetAttr as AxFileSetAttr;
 pub use axfs::fops::FileType as AxFileType;
 pub use axfs::fops::OpenOptions as AxOpenOptions;
+pub use axfs::fops::WatchEvent as AxWatchEvent;
+pub use axfs::fops::WatchMask as AxWatchMask;
 pub use axio::SeekFrom as AxSeekFrom;
 
 #[cfg(feature = "myfs")]
@@ -18,6 +21,9 @@ pub struct AxFileHandle(File);
 /// A handle to an opened directory.
 pub struct AxDirHandle(Directory);
 
+/// A handle to a file change watcher.
+pub struct AxWatcherHandle(Arc<Watcher>);
+
 pub fn ax_open_file(path: &str, opts: &AxOpenOptions) -> AxResult<AxFileHandle> {
     Ok(AxFileHandle(File::open(path, opts)?))
 }
@@ -58,6 +64,10 @@ pub fn ax_file_attr(file: &AxFileHandle) -> AxResult<AxFileAttr> {
     file.0.get_attr()
 }
 
+pub fn ax_set_file_attr(file: &AxFileHandle, attr: AxFileSetAttr) -> AxResult {
+    file.0.set_attr(attr)
+}
+
 pub fn ax_read_dir(dir: &mut AxDirHandle, dirents: &mut [AxDirEntry]) -> AxResult<usize> {
     dir.0.read_dir(dirents)
 }
@@ -78,6 +88,38 @@ pub fn ax_rename(old: &str, new: &str) -> AxResult {
     axfs::api::rename(old, new)
 }
 
+pub fn ax_symlink_attr(path: &str) -> AxResult<AxFileAttr> {
+    axfs::api::symlink_metadata(path).map(|m| *m.raw_metadata())
+}
+
+pub fn ax_set_path_attr(path: &str, attr: AxFileSetAttr) -> AxResult {
+    axfs::api::set_attr(path, attr)
+}
+
+pub fn ax_read_link(path: &str) -> AxResult<String> {
+    axfs::api::read_link(path)
+}
+
+pub fn ax_soft_link(original: &str, link: &str) -> AxResult {
+    axfs::api::soft_link(original, link)
+}
+
+pub fn ax_hard_link(original: &str, link: &str) -> AxResult {
+    axfs::api::hard_link(original, link)
+}
+
+pub fn ax_mount(path: &str, fs_type: &str) -> AxResult {
+    axfs::api::mount(path, axfs::api::new_fs(fs_type)?)
+}
+
+pub fn ax_umount(path: &str) -> AxResult {
+    axfs::api::umount(path)
+}
+
+pub fn ax_mounts() -> Vec<String> {
+    axfs::api::mounts()
+}
+
 pub fn ax_current_dir() -> AxResult<String> {
     axfs::api::current_dir()
 }
@@ -85,3 +127,19 @@ pub fn ax_current_dir() -> AxResult<String> {
 pub fn ax_set_current_dir(path: &str) -> AxResult {
     axfs::api::set_current_dir(path)
 }
+
+pub fn ax_new_watcher() -> AxWatcherHandle {
+    AxWatcherHandle(Watcher::new())
+}
+
+pub fn ax_add_watch(watcher: &AxWatcherHandle, path: &str, mask: AxWatchMask) -> AxResult<usize> {
+    watcher.0.add_watch(path, mask)
+}
+
+pub fn ax_remove_watch(watcher: &AxWatcherHandle, wd: usize) -> AxResult {
+    watcher.0.remove_watch(wd)
+}
+
+pub fn ax_next_watch_event(watcher: &AxWatcherHandle) -> Option<AxWatchEvent> {
+    watcher.0.pop_event()
+}
@@ -0,0 +1,35 @@
+pub use axinput::{
+    AbsInfo as AxInputAbsInfo, EventType as AxInputEventType, InputDeviceInfo as AxInputDeviceInfo,
+    InputEvent as AxInputEvent,
+};
+
+/// Returns the number of input devices.
+pub fn ax_input_num_devices() -> usize {
+    axinput::num_devices()
+}
+
+/// Returns the information of the input device `index`.
+pub fn ax_input_device_info(index: usize) -> Option<AxInputDeviceInfo> {
+    axinput::device_info(index)
+}
+
+/// Writes the bitmap of the codes that the input device supports for the event
+/// type to `out`.
+pub fn ax_input_event_bits(index: usize, ty: AxInputEventType, out: &mut [u8]) -> bool {
+    axinput::event_bits(index, ty, out)
+}
+
+/// Returns the range of an absolute axis of the input device.
+pub fn ax_input_abs_info(index: usize, axis: u8) -> Option<AxInputAbsInfo> {
+    axinput::abs_info(index, axis)
+}
+
+/// Takes the next event of the input device, without waiting.
+pub fn ax_input_read_event(index: usize) -> Option<AxInputEvent> {
+    axinput::read_event(index)
+}
+
+/// Returns whether the input device has events to read.
+pub fn ax_input_has_events(index: usize) -> bool {
+    axinput::has_events(index)
+}
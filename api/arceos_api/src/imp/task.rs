@@ -17,6 +17,8 @@ pub fn ax_yield_now() {
 }
 
 pub fn ax_exit(_exit_code: i32) -> ! {
+    #[cfg(feature = "fs")]
+    axfs::api::sync().ok(); // write back buffer caches before terminating
     #[cfg(feature = "multitask")]
     axtask::exit(_exit_code);
     #[cfg(not(feature = "multitask"))]
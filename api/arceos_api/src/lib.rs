@@ -147,8 +147,12 @@ pub mod fs {
         pub type AxFileAttr;
         pub type AxFileType;
         pub type AxFilePerm;
+        pub type AxFileSetAttr;
         pub type AxDirEntry;
         pub type AxSeekFrom;
+        pub type AxWatcherHandle;
+        pub type AxWatchMask;
+        pub type AxWatchEvent;
         #[cfg(feature = "myfs")]
         pub type AxDisk;
         #[cfg(feature = "myfs")]
@@ -193,6 +197,8 @@ pub mod fs {
         pub fn ax_seek_file(file: &mut AxFileHandle, pos: AxSeekFrom) -> AxResult<u64>;
         /// Returns attributes of the file.
         pub fn ax_file_attr(file: &AxFileHandle) -> AxResult<AxFileAttr>;
+        /// Changes the permission, owner or timestamps of the file.
+        pub fn ax_set_file_attr(file: &AxFileHandle, attr: AxFileSetAttr) -> AxResult;
 
         /// Reads directory entries starts from the current position into the
         /// given buffer, returns the number of entries read.
@@ -212,11 +218,44 @@ pub mod fs {
         ///
         /// It will delete the original file if `old` already exists.
         pub fn ax_rename(old: &str, new: &str) -> AxResult;
+        /// Returns attributes of the file at the given path, without following
+        /// symbolic links.
+        pub fn ax_symlink_attr(path: &str) -> AxResult<AxFileAttr>;
+        /// Changes the permission, owner or timestamps of the file at the given
+        /// path, following symbolic links.
+        pub fn ax_set_path_attr(path: &str, attr: AxFileSetAttr) -> AxResult;
+        /// Reads a symbolic link, returns the path that the link points to.
+        pub fn ax_read_link(path: &str) -> AxResult<alloc::string::String>;
+        /// Creates a new symbolic link `link` which points to `original`.
+        pub fn ax_soft_link(original: &str, link: &str) -> AxResult;
+        /// Creates a new hard link `link` which refers to the same file as
+        /// `original`.
+        ///
+        /// The two paths must be in the same mounted filesystem.
+        pub fn ax_hard_link(original: &str, link: &str) -> AxResult;
+
+        /// Mounts a new filesystem of type `fs_type` (e.g., `ramfs`) at `path`.
+        pub fn ax_mount(path: &str, fs_type: &str) -> AxResult;
+        /// Unmounts the filesystem mounted at `path`.
+        pub fn ax_umount(path: &str) -> AxResult;
+        /// Returns the paths of all mount points.
+        pub fn ax_mounts() -> alloc::vec::Vec<alloc::string::String>;
 
         /// Returns the current working directory.
         pub fn ax_current_dir() -> AxResult<alloc::string::String>;
         /// Changes the current working directory to the specified path.
         pub fn ax_set_current_dir(path: &str) -> AxResult;
+
+        /// Creates a watcher to receive file change events.
+        pub fn ax_new_watcher() -> AxWatcherHandle;
+        /// Watches the file or directory at `path` for events in `mask`,
+        /// returns the watch descriptor.
+        pub fn ax_add_watch(watcher: &AxWatcherHandle, path: &str, mask: AxWatchMask) -> AxResult<usize>;
+        /// Removes the watch specified by the watch descriptor `wd`.
+        pub fn ax_remove_watch(watcher: &AxWatcherHandle, wd: usize) -> AxResult;
+        /// Removes and returns the earliest event received by the watcher, or
+        /// `None` if there is no event.
+        pub fn ax_next_watch_event(watcher: &AxWatcherHandle) -> Option<AxWatchEvent>;
     }
 }
 
@@ -331,6 +370,35 @@ pub mod display {
     }
 }
 
+/// Input device operations.
+pub mod input {
+    define_api_type! {
+        @cfg "input";
+        pub type AxInputEvent;
+        pub type AxInputEventType;
+        pub type AxInputDeviceInfo;
+        pub type AxInputAbsInfo;
+    }
+
+    define_api! {
+        @cfg "input";
+        /// Returns the number of input devices.
+        pub fn ax_input_num_devices() -> usize;
+        /// Returns the information of the input device `index`.
+        pub fn ax_input_device_info(index: usize) -> Option<AxInputDeviceInfo>;
+        /// Writes the bitmap of the codes that the input device supports for
+        /// the event type to `out`. Returns whether the device supports the
+        /// event type at all.
+        pub fn ax_input_event_bits(index: usize, ty: AxInputEventType, out: &mut [u8]) -> bool;
+        /// Returns the range of an absolute axis of the input device.
+        pub fn ax_input_abs_info(index: usize, axis: u8) -> Option<AxInputAbsInfo>;
+        /// Takes the next event of the input device, without waiting.
+        pub fn ax_input_read_event(index: usize) -> Option<AxInputEvent>;
+        /// Returns whether the input device has events to read.
+        pub fn ax_input_has_events(index: usize) -> bool;
+    }
+}
+
 /// Input/output operations.
 pub mod io {
     define_api_type! {
@@ -37,10 +37,13 @@ typedef struct {{
 
         let allow_types = [
             "stat",
+            "statfs",
             "size_t",
             "ssize_t",
             "off_t",
             "mode_t",
+            "uid_t",
+            "gid_t",
             "sock.*",
             "fd_set",
             "timeval",
@@ -53,6 +56,10 @@ typedef struct {{
             "clockid_t",
             "rlimit",
             "aibuf",
+            "inotify_event",
+            "flock",
+            "aiocb",
+            "sigevent",
         ];
         let allow_vars = [
             "O_.*",
@@ -66,6 +73,18 @@ typedef struct {{
             "EPOLL.*",
             "RLIMIT_.*",
             "EAI_.*",
+            "PROT_.*",
+            "MAP_.*",
+            "MS_.*",
+            "MNT_.*",
+            "LOCK_.*",
+            "AT_.*",
+            "RENAME_.*",
+            "UTIME_.*",
+            "AIO_.*",
+            "LIO_.*",
+            "SIGEV_.*",
+            "[RWX]_OK",
             "MAXADDRS",
         ];
 
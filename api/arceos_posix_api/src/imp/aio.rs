@@ -0,0 +1,366 @@
+//! POSIX asynchronous I/O, served by an [`IoRing`].
+//!
+//! The results of requests are written to their control blocks when they are
+//! collected from the ring, by any of `aio_error`, `aio_return`,
+//! `aio_suspend`, `aio_cancel` and `lio_listio`. The data of read requests is
+//! copied to the user buffers at the same time.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::ffi::c_int;
+
+use axerrno::{LinuxError, LinuxResult};
+use axfs::aio::{IoCompletion, IoOp, IoRequest, IoRing};
+use axhal::time::{current_time, TimeValue};
+use axsync::Mutex;
+
+use super::fs::File;
+use crate::ctypes;
+
+/// The number of worker tasks that serve the requests.
+const AIO_WORKERS: usize = 4;
+
+lazy_static::lazy_static! {
+    static ref RING: IoRing = IoRing::new(AIO_WORKERS);
+}
+
+/// The addresses of control blocks of requests in progress.
+static IN_PROGRESS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Writes the result of a completed request to its control block.
+fn finish(c: IoCompletion, in_progress: &mut Vec<usize>) {
+    let cb = c.user_data as *mut ctypes::aiocb;
+    in_progress.retain(|&addr| addr != cb as usize);
+    let cb = unsafe { &mut *cb };
+    match c.result {
+        Ok(n) => {
+            if cb.aio_lio_opcode == ctypes::LIO_READ as c_int {
+                let dst = cb.aio_buf as *mut u8;
+                unsafe { core::ptr::copy_nonoverlapping(c.buf.as_ptr(), dst, c.buf.len()) };
+            }
+            cb.__ret = n as ctypes::ssize_t;
+            cb.__err = 0;
+        }
+        Err(e) => {
+            cb.__ret = -1;
+            cb.__err = LinuxError::from(e).code();
+        }
+    }
+}
+
+/// Collects all completed requests without waiting.
+///
+/// [`IN_PROGRESS`] is locked during the collection, so that other tasks see
+/// the requests taken from the ring as completed once they can lock it.
+fn reap() {
+    let mut in_progress = IN_PROGRESS.lock();
+    while let Some(c) = RING.try_complete() {
+        finish(c, &mut in_progress);
+    }
+}
+
+/// Collects the completed requests until `done` returns true, waits for more
+/// requests to complete in between.
+///
+/// Returns `EAGAIN` if the `deadline` is reached first.
+fn wait_until(done: impl Fn() -> bool, deadline: Option<TimeValue>) -> LinuxResult {
+    loop {
+        // take the snapshot first, to not miss the events while collecting
+        let seen = RING.events();
+        reap();
+        if done() {
+            return Ok(());
+        }
+        let timeout = match deadline {
+            Some(ddl) if current_time() >= ddl => return Err(LinuxError::EAGAIN),
+            Some(ddl) => Some(ddl.saturating_sub(current_time())),
+            None => None,
+        };
+        RING.wait_event(seen, timeout);
+    }
+}
+
+/// Only notifications that do nothing are supported, including signal 0.
+fn check_sigevent(ev: &ctypes::sigevent) -> LinuxResult {
+    match ev.sigev_notify as u32 {
+        ctypes::SIGEV_NONE => Ok(()),
+        ctypes::SIGEV_SIGNAL if ev.sigev_signo == 0 => Ok(()),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Submits the request described by `cb`, with the operation `opcode`
+/// (`LIO_READ`, `LIO_WRITE`, or `LIO_NOP` for fsync).
+unsafe fn submit(cb: *mut ctypes::aiocb, opcode: u32) -> LinuxResult {
+    let cb_ref = unsafe { cb.as_mut() }.ok_or(LinuxError::EFAULT)?;
+    check_sigevent(&cb_ref.aio_sigevent)?;
+    let file = File::from_fd(cb_ref.aio_fildes)?;
+    let op = match opcode {
+        ctypes::LIO_READ | ctypes::LIO_WRITE if cb_ref.aio_offset < 0 => {
+            return Err(LinuxError::EINVAL);
+        }
+        ctypes::LIO_READ => IoOp::Read {
+            offset: cb_ref.aio_offset as u64,
+            len: cb_ref.aio_nbytes as usize,
+        },
+        ctypes::LIO_WRITE => {
+            let data = match cb_ref.aio_nbytes as usize {
+                0 => Vec::new(),
+                len => {
+                    let buf = cb_ref.aio_buf as *const u8;
+                    if buf.is_null() {
+                        return Err(LinuxError::EFAULT);
+                    }
+                    unsafe { core::slice::from_raw_parts(buf, len) }.to_vec()
+                }
+            };
+            IoOp::Write {
+                offset: cb_ref.aio_offset as u64,
+                data,
+            }
+        }
+        _ => IoOp::Fsync,
+    };
+    if let IoOp::Read { len, .. } = op {
+        if len != 0 && cb_ref.aio_buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+    }
+
+    // the opcode tells `finish` whether to copy the data
+    cb_ref.aio_lio_opcode = opcode as c_int;
+    cb_ref.__err = LinuxError::EINPROGRESS.code();
+    cb_ref.__ret = 0;
+    IN_PROGRESS.lock().push(cb as usize);
+    RING.submit(IoRequest {
+        file: file as Arc<dyn axfs::aio::AioFile>,
+        op,
+        user_data: cb as u64,
+    });
+    Ok(())
+}
+
+/// Queue an asynchronous read request.
+///
+/// Return 0 if the request is queued.
+pub unsafe fn sys_aio_read(cb: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_read <= {:#x}", cb as usize);
+    syscall_body!(sys_aio_read, {
+        unsafe { submit(cb, ctypes::LIO_READ)? };
+        Ok(0)
+    })
+}
+
+/// Queue an asynchronous write request.
+///
+/// Return 0 if the request is queued.
+pub unsafe fn sys_aio_write(cb: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_write <= {:#x}", cb as usize);
+    syscall_body!(sys_aio_write, {
+        unsafe { submit(cb, ctypes::LIO_WRITE)? };
+        Ok(0)
+    })
+}
+
+/// Queue an asynchronous fsync request, `op` must be `O_SYNC` or `O_DSYNC`.
+///
+/// Return 0 if the request is queued.
+pub unsafe fn sys_aio_fsync(op: c_int, cb: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_fsync <= {:#x} {:#x}", op, cb as usize);
+    syscall_body!(sys_aio_fsync, {
+        if op as u32 != ctypes::O_SYNC && op as u32 != ctypes::O_DSYNC {
+            return Err(LinuxError::EINVAL);
+        }
+        unsafe { submit(cb, ctypes::LIO_NOP)? };
+        Ok(0)
+    })
+}
+
+/// Get the error status of an asynchronous request.
+///
+/// Return `EINPROGRESS` if it is not completed, 0 if it succeeded, or the
+/// error number.
+pub unsafe fn sys_aio_error(cb: *const ctypes::aiocb) -> c_int {
+    debug!("sys_aio_error <= {:#x}", cb as usize);
+    syscall_body!(sys_aio_error, {
+        let cb = unsafe { cb.as_ref() }.ok_or(LinuxError::EFAULT)?;
+        reap();
+        Ok(cb.__err)
+    })
+}
+
+/// Get the return status of a completed asynchronous request.
+///
+/// Return the number of bytes transferred, or -1 if it failed.
+pub unsafe fn sys_aio_return(cb: *mut ctypes::aiocb) -> ctypes::ssize_t {
+    debug!("sys_aio_return <= {:#x}", cb as usize);
+    syscall_body!(sys_aio_return, {
+        let cb = unsafe { cb.as_mut() }.ok_or(LinuxError::EFAULT)?;
+        reap();
+        if cb.__err == LinuxError::EINPROGRESS.code() {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(cb.__ret)
+    })
+}
+
+/// Wait until at least one of the asynchronous requests in `list` is
+/// completed, or the timeout expires.
+///
+/// Return 0 if some are completed.
+pub unsafe fn sys_aio_suspend(
+    list: *const *const ctypes::aiocb,
+    nent: c_int,
+    timeout: *const ctypes::timespec,
+) -> c_int {
+    debug!("sys_aio_suspend <= {:#x} {}", list as usize, nent);
+    syscall_body!(sys_aio_suspend, {
+        if nent < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if list.is_null() && nent > 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        let list = match nent {
+            0 => &[],
+            n => unsafe { core::slice::from_raw_parts(list, n as usize) },
+        };
+        let deadline = unsafe { timeout.as_ref().map(|t| current_time() + (*t).into()) };
+        // completions may be collected by other tasks, so check the control
+        // blocks instead of the completions
+        wait_until(
+            || {
+                let in_progress = IN_PROGRESS.lock();
+                let done = list
+                    .iter()
+                    .filter_map(|&cb| unsafe { cb.as_ref() })
+                    .any(|cb| !in_progress.contains(&(cb as *const _ as usize)));
+                done || RING.in_flight() == 0
+            },
+            deadline,
+        )?;
+        Ok(0)
+    })
+}
+
+/// Cancel the asynchronous request `cb`, or all requests on `fd` if `cb` is
+/// NULL. Only requests that have not been started can be cancelled.
+///
+/// Return `AIO_CANCELED`, `AIO_NOTCANCELED` or `AIO_ALLDONE`.
+pub unsafe fn sys_aio_cancel(fd: c_int, cb: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_cancel <= {} {:#x}", fd, cb as usize);
+    syscall_body!(sys_aio_cancel, {
+        File::from_fd(fd)?;
+        if let Some(cb) = unsafe { cb.as_ref() } {
+            if cb.aio_fildes != fd {
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        reap();
+        let targets = IN_PROGRESS
+            .lock()
+            .iter()
+            .copied()
+            .filter(|&addr| {
+                if cb.is_null() {
+                    unsafe { (*(addr as *const ctypes::aiocb)).aio_fildes == fd }
+                } else {
+                    addr == cb as usize
+                }
+            })
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            return Ok(ctypes::AIO_ALLDONE);
+        }
+        let mut result = ctypes::AIO_CANCELED;
+        for addr in targets {
+            if RING.cancel(addr as u64) {
+                IN_PROGRESS.lock().retain(|&a| a != addr);
+                let cb = unsafe { &mut *(addr as *mut ctypes::aiocb) };
+                cb.__ret = -1;
+                cb.__err = LinuxError::ECANCELED.code();
+            } else {
+                result = ctypes::AIO_NOTCANCELED;
+            }
+        }
+        Ok(result)
+    })
+}
+
+/// Queue a list of asynchronous requests, and wait for all of them if `mode`
+/// is `LIO_WAIT`.
+///
+/// Return 0 if success, or `EIO` if some requests failed when waiting.
+pub unsafe fn sys_lio_listio(
+    mode: c_int,
+    list: *const *mut ctypes::aiocb,
+    nent: c_int,
+    sig: *mut ctypes::sigevent,
+) -> c_int {
+    debug!("sys_lio_listio <= {} {:#x} {}", mode, list as usize, nent);
+    syscall_body!(sys_lio_listio, {
+        let wait = match mode as u32 {
+            ctypes::LIO_WAIT => true,
+            ctypes::LIO_NOWAIT => false,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if nent < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if list.is_null() && nent > 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        if let Some(sig) = unsafe { sig.as_ref() } {
+            check_sigevent(sig)?;
+        }
+        let list = match nent {
+            0 => &[],
+            n => unsafe { core::slice::from_raw_parts(list, n as usize) },
+        };
+
+        let mut failed = false;
+        for &cb in list {
+            let Some(cb_ref) = (unsafe { cb.as_ref() }) else {
+                continue;
+            };
+            let res = match cb_ref.aio_lio_opcode as u32 {
+                op @ (ctypes::LIO_READ | ctypes::LIO_WRITE) => unsafe { submit(cb, op) },
+                _ => continue,
+            };
+            if let Err(e) = res {
+                let cb = unsafe { &mut *cb };
+                cb.__ret = -1;
+                cb.__err = e.code();
+                failed = true;
+            }
+        }
+        if !wait {
+            return if failed { Err(LinuxError::EIO) } else { Ok(0) };
+        }
+
+        let submitted = list.iter().map(|&cb| cb as usize).collect::<Vec<_>>();
+        wait_until(
+            || {
+                !IN_PROGRESS
+                    .lock()
+                    .iter()
+                    .any(|addr| submitted.contains(addr))
+            },
+            None,
+        )?;
+        let failed = list
+            .iter()
+            .filter_map(|&cb| unsafe { cb.as_ref() })
+            .filter(|cb| {
+                matches!(
+                    cb.aio_lio_opcode as u32,
+                    ctypes::LIO_READ | ctypes::LIO_WRITE
+                )
+            })
+            .any(|cb| cb.__err != 0);
+        if failed {
+            Err(LinuxError::EIO)
+        } else {
+            Ok(0)
+        }
+    })
+}
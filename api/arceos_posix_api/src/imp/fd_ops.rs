@@ -15,6 +15,31 @@ pub const AX_FILE_LIMIT: usize = 1024;
 pub trait FileLike: Send + Sync {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize>;
     fn write(&self, buf: &[u8]) -> LinuxResult<usize>;
+    /// Reads into the first non-empty buffer by default, so it does not block
+    /// again after some data is read.
+    fn read_vectored(&self, bufs: &mut [&mut [u8]]) -> LinuxResult<usize> {
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => Ok(0),
+        }
+    }
+    /// Writes the buffers one by one by default, until a write is short.
+    fn write_vectored(&self, bufs: &[&[u8]]) -> LinuxResult<usize> {
+        let mut write_len = 0;
+        for buf in bufs.iter().filter(|buf| !buf.is_empty()) {
+            match self.write(buf) {
+                Ok(n) => {
+                    write_len += n;
+                    if n < buf.len() {
+                        break;
+                    }
+                }
+                Err(e) if write_len == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(write_len)
+    }
     fn stat(&self) -> LinuxResult<ctypes::stat>;
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
     fn poll(&self) -> LinuxResult<PollState>;
@@ -58,7 +83,13 @@ pub fn sys_close(fd: c_int) -> c_int {
     if (0..=2).contains(&fd) {
         return 0; // stdin, stdout, stderr
     }
-    syscall_body!(sys_close, close_file_like(fd).map(|_| 0))
+    syscall_body!(sys_close, {
+        #[cfg(feature = "fs")]
+        if let Ok(file) = super::fs::File::from_fd(fd) {
+            file.release_locks();
+        }
+        close_file_like(fd).map(|_| 0)
+    })
 }
 
 fn dup_fd(old_fd: c_int) -> LinuxResult<c_int> {
@@ -113,6 +144,10 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
                 // TODO: Change fd flags
                 dup_fd(fd)
             }
+            #[cfg(feature = "fs")]
+            ctypes::F_GETLK | ctypes::F_SETLK | ctypes::F_SETLKW => {
+                super::fs::fcntl_lock(fd, cmd as u32, arg)
+            }
             ctypes::F_SETFL => {
                 if fd == 0 || fd == 1 || fd == 2 {
                     return Ok(0);
@@ -1,21 +1,25 @@
 use alloc::sync::Arc;
-use core::ffi::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_uint, c_void};
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axerrno::{AxError, AxResult, LinuxError, LinuxResult};
+use axfs::fops::{Cap, DirEntry, FileAttr, FilePerm, FileSetAttr, FileSystemInfo};
+use axfs::fops::{LockHandle, LockKind, LockOwner, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
 use super::fd_ops::{get_file_like, FileLike};
+use super::io::{iov_bufs, iov_bufs_mut, iovecs};
 use crate::{ctypes, utils::char_ptr_to_str};
 
 pub struct File {
     inner: Mutex<axfs::fops::File>,
+    locks: LockHandle,
 }
 
 impl File {
     fn new(inner: axfs::fops::File) -> Self {
         Self {
+            locks: inner.lock_handle(),
             inner: Mutex::new(inner),
         }
     }
@@ -24,12 +28,75 @@ impl File {
         super::fd_ops::add_file_like(Arc::new(self))
     }
 
-    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+    pub(crate) fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
         let f = super::fd_ops::get_file_like(fd)?;
         f.into_any()
             .downcast::<Self>()
             .map_err(|_| LinuxError::EINVAL)
     }
+
+    /// Reads the file at the given offset without moving the file cursor.
+    pub(crate) fn read_at(&self, offset: u64, buf: &mut [u8]) -> LinuxResult<usize> {
+        Ok(self.inner.lock().read_at(offset, buf)?)
+    }
+
+    /// Writes all data to the file at the given offset without moving the
+    /// file cursor.
+    pub(crate) fn write_all_at(&self, mut offset: u64, mut buf: &[u8]) -> LinuxResult {
+        let file = self.inner.lock();
+        while !buf.is_empty() {
+            match file.write_at(offset, buf)? {
+                0 => return Err(LinuxError::EIO),
+                n => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the access capabilities that the file is opened with.
+    pub(crate) fn access_cap(&self) -> Cap {
+        self.inner.lock().access_cap()
+    }
+
+    /// Returns the current size of the file.
+    pub(crate) fn size(&self) -> LinuxResult<u64> {
+        Ok(self.inner.lock().get_attr()?.size())
+    }
+
+    /// Releases the record locks of the current task on the file, when one of
+    /// its file descriptors is closed.
+    pub(crate) fn release_locks(&self) {
+        self.locks.unlock_range(lock_owner(), 0, u64::MAX);
+    }
+
+    /// Converts the range in `struct flock` to `start..end`.
+    fn lock_range(&self, fl: &ctypes::flock) -> LinuxResult<(u64, u64)> {
+        let base = match fl.l_whence {
+            0 => 0,
+            1 => self.inner.lock().seek(SeekFrom::Current(0))? as i64,
+            2 => self.size()? as i64,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let start = base.checked_add(fl.l_start).ok_or(LinuxError::EOVERFLOW)?;
+        let end = start.checked_add(fl.l_len).ok_or(LinuxError::EOVERFLOW)?;
+        let (start, end) = match fl.l_len {
+            0 => (start, u64::MAX),
+            len if len > 0 => (start, end as u64),
+            _ => (end, start as u64),
+        };
+        if start < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok((start as u64, end))
+    }
+}
+
+/// The owner of record locks, which is the current task like `getpid`.
+fn lock_owner() -> u64 {
+    super::task::sys_getpid() as u64
 }
 
 impl FileLike for File {
@@ -41,22 +108,16 @@ impl FileLike for File {
         Ok(self.inner.lock().write(buf)?)
     }
 
+    fn read_vectored(&self, bufs: &mut [&mut [u8]]) -> LinuxResult<usize> {
+        Ok(self.inner.lock().read_vectored(bufs)?)
+    }
+
+    fn write_vectored(&self, bufs: &[&[u8]]) -> LinuxResult<usize> {
+        Ok(self.inner.lock().write_vectored(bufs)?)
+    }
+
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        Ok(attr_to_stat(&self.inner.lock().get_attr()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -75,6 +136,240 @@ impl FileLike for File {
     }
 }
 
+impl axfs::aio::AioFile for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+        self.inner.lock().read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
+        self.inner.lock().write_at(offset, buf)
+    }
+
+    fn flush(&self) -> AxResult {
+        self.inner.lock().flush()
+    }
+}
+
+pub struct Directory {
+    inner: Mutex<axfs::fops::Directory>,
+}
+
+impl Directory {
+    fn new(inner: axfs::fops::Directory) -> Self {
+        Self {
+            inner: Mutex::new(inner),
+        }
+    }
+
+    fn add_to_fd_table(self) -> LinuxResult<c_int> {
+        super::fd_ops::add_file_like(Arc::new(self))
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        let f = super::fd_ops::get_file_like(fd)?;
+        f.into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTDIR)
+    }
+}
+
+impl FileLike for Directory {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EISDIR)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(attr_to_stat(&self.inner.lock().get_attr()?))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// Run `f` with the directory that `path` is relative to, which is indicated
+/// by `dirfd`, or the current directory if `dirfd` is `AT_FDCWD`.
+///
+/// `dirfd` is ignored if `path` is absolute.
+fn with_dir_at<T>(
+    dirfd: c_int,
+    path: &str,
+    f: impl FnOnce(&axfs::fops::Directory) -> LinuxResult<T>,
+) -> LinuxResult<T> {
+    if dirfd == ctypes::AT_FDCWD || path.starts_with('/') {
+        f(&axfs::fops::Directory::current_dir()?)
+    } else {
+        f(&Directory::from_fd(dirfd)?.inner.lock())
+    }
+}
+
+/// Handle the `F_GETLK`, `F_SETLK` and `F_SETLKW` commands of `fcntl`, `arg`
+/// points to a `struct flock`.
+///
+/// Record locks are owned by tasks, and released when the task closes any file
+/// descriptor of the file.
+pub(crate) fn fcntl_lock(fd: c_int, cmd: u32, arg: usize) -> LinuxResult<c_int> {
+    let fl = unsafe { (arg as *mut ctypes::flock).as_mut() }.ok_or(LinuxError::EFAULT)?;
+    let file = File::from_fd(fd)?;
+    let (start, end) = file.lock_range(fl)?;
+    let owner = lock_owner();
+    let kind = match fl.l_type as u32 {
+        ctypes::F_RDLCK => Some(LockKind::Shared),
+        ctypes::F_WRLCK => Some(LockKind::Exclusive),
+        ctypes::F_UNLCK => None,
+        _ => return Err(LinuxError::EINVAL),
+    };
+    if cmd == ctypes::F_GETLK {
+        let kind = kind.ok_or(LinuxError::EINVAL)?;
+        match file.locks.test_lock_range(owner, kind, start, end) {
+            Some(lock) => {
+                let (LockOwner::Task(pid) | LockOwner::File(pid)) = lock.owner;
+                fl.l_type = match lock.kind {
+                    LockKind::Shared => ctypes::F_RDLCK,
+                    LockKind::Exclusive => ctypes::F_WRLCK,
+                } as _;
+                fl.l_whence = 0;
+                fl.l_start = lock.start as _;
+                fl.l_len = if lock.end == u64::MAX {
+                    0
+                } else {
+                    (lock.end - lock.start) as _
+                };
+                fl.l_pid = pid as _;
+            }
+            None => fl.l_type = ctypes::F_UNLCK as _,
+        }
+        return Ok(0);
+    }
+    match kind {
+        Some(kind) => {
+            let wait = cmd == ctypes::F_SETLKW;
+            file.locks
+                .lock_range(owner, kind, start, end, wait)
+                .map_err(|e| match e {
+                    // not opened for reading or writing
+                    AxError::PermissionDenied => LinuxError::EBADF,
+                    e => e.into(),
+                })?;
+        }
+        None => file.locks.unlock_range(owner, start, end),
+    }
+    Ok(0)
+}
+
+/// Apply or remove an advisory lock on the whole file indicated by `fd`.
+///
+/// The lock is owned by the opened file, and released when all its file
+/// descriptors are closed. Return 0 if success.
+pub fn sys_flock(fd: c_int, operation: c_int) -> c_int {
+    debug!("sys_flock <= {} {:#x}", fd, operation);
+    syscall_body!(sys_flock, {
+        let file = File::from_fd(fd)?;
+        let wait = operation as u32 & ctypes::LOCK_NB == 0;
+        match operation as u32 & !ctypes::LOCK_NB {
+            ctypes::LOCK_SH => file.locks.lock_file(LockKind::Shared, wait)?,
+            ctypes::LOCK_EX => file.locks.lock_file(LockKind::Exclusive, wait)?,
+            ctypes::LOCK_UN => file.locks.unlock_file(),
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
+
+/// Convert file attributes to [`ctypes::stat`].
+fn attr_to_stat(attr: &FileAttr) -> ctypes::stat {
+    let ty = attr.file_type() as u8;
+    let perm = attr.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: attr.ino() as _,
+        st_nlink: attr.nlink() as _,
+        st_mode,
+        st_uid: attr.uid(),
+        st_gid: attr.gid(),
+        st_size: attr.size() as _,
+        st_blocks: attr.blocks() as _,
+        st_blksize: 512,
+        st_atime: attr.atime().into(),
+        st_mtime: attr.mtime().into(),
+        st_ctime: attr.ctime().into(),
+        ..Default::default()
+    }
+}
+
+/// Convert filesystem attributes to [`ctypes::statfs`].
+fn info_to_statfs(info: &FileSystemInfo) -> ctypes::statfs {
+    ctypes::statfs {
+        f_bsize: info.block_size() as _,
+        f_frsize: info.block_size() as _,
+        f_blocks: info.blocks(),
+        f_bfree: info.blocks_free(),
+        f_bavail: info.blocks_free(),
+        f_files: info.files(),
+        f_ffree: info.files_free(),
+        f_namelen: info.name_max() as _,
+        ..Default::default()
+    }
+}
+
+/// Convert `chmod` mode to the attributes to be changed.
+fn mode_to_set_attr(mode: ctypes::mode_t) -> FileSetAttr {
+    FileSetAttr::new().with_perm(FilePerm::from_bits_truncate(mode as u16))
+}
+
+/// Convert `chown` IDs to the attributes to be changed, `-1` means unchanged.
+fn owner_to_set_attr(owner: ctypes::uid_t, group: ctypes::gid_t) -> FileSetAttr {
+    let mut attr = FileSetAttr::new();
+    if owner != ctypes::uid_t::MAX {
+        attr = attr.with_uid(owner);
+    }
+    if group != ctypes::gid_t::MAX {
+        attr = attr.with_gid(group);
+    }
+    attr
+}
+
+/// Convert the access and modification times of `utimensat` to the
+/// attributes to be changed.
+///
+/// A null `times` means both are the current time, and `UTIME_NOW` and
+/// `UTIME_OMIT` in `tv_nsec` mean the current time and unchanged.
+unsafe fn times_to_set_attr(times: *const ctypes::timespec) -> LinuxResult<FileSetAttr> {
+    let now = axhal::time::current_time();
+    if times.is_null() {
+        return Ok(FileSetAttr::new().with_atime(now).with_mtime(now));
+    }
+    let to_time = |ts: ctypes::timespec| match ts.tv_nsec as u32 {
+        ctypes::UTIME_OMIT => Ok(None),
+        ctypes::UTIME_NOW => Ok(Some(now)),
+        _ if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) => Err(LinuxError::EINVAL),
+        _ => Ok(Some(ts.into())),
+    };
+    let mut attr = FileSetAttr::new();
+    if let Some(atime) = to_time(unsafe { *times })? {
+        attr = attr.with_atime(atime);
+    }
+    if let Some(mtime) = to_time(unsafe { *times.add(1) })? {
+        attr = attr.with_mtime(mtime);
+    }
+    Ok(attr)
+}
+
 /// Convert open flags to [`OpenOptions`].
 fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
@@ -102,6 +397,35 @@ fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     options
 }
 
+/// Open the file or directory at `path` relative to `dir`, and insert it into
+/// the file descriptor table.
+fn open_at(
+    dir: &axfs::fops::Directory,
+    path: &str,
+    flags: c_int,
+    mode: ctypes::mode_t,
+) -> LinuxResult<c_int> {
+    let options = flags_to_options(flags, mode);
+    let flags = flags as u32;
+    let is_dir = match dir.get_attr_at(path, true) {
+        Ok(attr) => attr.is_dir(),
+        Err(e) if flags & ctypes::O_DIRECTORY != 0 => return Err(e.into()),
+        Err(_) => false,
+    };
+    if flags & ctypes::O_DIRECTORY != 0 && !is_dir {
+        return Err(LinuxError::ENOTDIR);
+    }
+    // directories opened for writing are rejected by `File` with `EISDIR`
+    if is_dir
+        && flags & 0b11 == ctypes::O_RDONLY
+        && flags & (ctypes::O_CREAT | ctypes::O_TRUNC) == 0
+    {
+        Directory::new(dir.open_dir_at(path, &options)?).add_to_fd_table()
+    } else {
+        File::new(dir.open_file_at(path, &options)?).add_to_fd_table()
+    }
+}
+
 /// Open a file by `filename` and insert it into the file descriptor table.
 ///
 /// Return its index in the file table (`fd`). Return `EMFILE` if it already
@@ -110,9 +434,32 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     let filename = char_ptr_to_str(filename);
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
-        let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
-        File::new(file).add_to_fd_table()
+        let filename = filename?;
+        with_dir_at(ctypes::AT_FDCWD, filename, |dir| {
+            open_at(dir, filename, flags, mode)
+        })
+    })
+}
+
+/// Open a file by `filename` relative to the directory `dirfd`, and insert
+/// it into the file descriptor table.
+///
+/// Return its index in the file table (`fd`). Return `EMFILE` if it already
+/// has the maximum number of files open.
+pub fn sys_openat(
+    dirfd: c_int,
+    filename: *const c_char,
+    flags: c_int,
+    mode: ctypes::mode_t,
+) -> c_int {
+    let filename = char_ptr_to_str(filename);
+    debug!(
+        "sys_openat <= {} {:?} {:#o} {:#o}",
+        dirfd, filename, flags, mode
+    );
+    syscall_body!(sys_openat, {
+        let filename = filename?;
+        with_dir_at(dirfd, filename, |dir| open_at(dir, filename, flags, mode))
     })
 }
 
@@ -122,6 +469,20 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
 pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off_t {
     debug!("sys_lseek <= {} {} {}", fd, offset, whence);
     syscall_body!(sys_lseek, {
+        if let Ok(dir) = Directory::from_fd(fd) {
+            // the position of a directory is the index of the next entry
+            let mut dir = dir.inner.lock();
+            let pos = match whence {
+                0 => offset,
+                1 => (dir.position() as ctypes::off_t).saturating_add(offset),
+                _ => return Err(LinuxError::EINVAL),
+            };
+            if pos < 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            dir.set_position(pos as usize);
+            return Ok(pos as u64);
+        }
         let pos = match whence {
             0 => SeekFrom::Start(offset as _),
             1 => SeekFrom::Current(offset as _),
@@ -133,6 +494,90 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
     })
 }
 
+/// Read data from the file indicated by `fd` at the given offset into a
+/// vector of buffers, without changing the file offset.
+///
+/// Return the read size if success.
+pub unsafe fn sys_preadv(
+    fd: c_int,
+    iov: *const ctypes::iovec,
+    iocnt: c_int,
+    offset: ctypes::off_t,
+) -> ctypes::ssize_t {
+    debug!("sys_preadv <= {} {} {}", fd, iocnt, offset);
+    syscall_body!(sys_preadv, {
+        if offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut bufs = unsafe { iov_bufs_mut(iovecs(iov, iocnt)?) };
+        let file = File::from_fd(fd)?;
+        let read_len = file
+            .inner
+            .lock()
+            .read_vectored_at(offset as u64, &mut bufs)?;
+        Ok(read_len as ctypes::ssize_t)
+    })
+}
+
+/// Write data of a vector of buffers to the file indicated by `fd` at the
+/// given offset, without changing the file offset.
+///
+/// Return the written size if success.
+pub unsafe fn sys_pwritev(
+    fd: c_int,
+    iov: *const ctypes::iovec,
+    iocnt: c_int,
+    offset: ctypes::off_t,
+) -> ctypes::ssize_t {
+    debug!("sys_pwritev <= {} {} {}", fd, iocnt, offset);
+    syscall_body!(sys_pwritev, {
+        if offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let bufs = unsafe { iov_bufs(iovecs(iov, iocnt)?) };
+        let file = File::from_fd(fd)?;
+        let write_len = file.inner.lock().write_vectored_at(offset as u64, &bufs)?;
+        Ok(write_len as ctypes::ssize_t)
+    })
+}
+
+/// Write all modified data of the file indicated by `fd` back to the storage.
+///
+/// Return 0 if success.
+pub fn sys_fsync(fd: c_int) -> c_int {
+    debug!("sys_fsync <= {}", fd);
+    syscall_body!(sys_fsync, {
+        if let Ok(dir) = Directory::from_fd(fd) {
+            dir.inner.lock().flush()?;
+        } else {
+            File::from_fd(fd)?.inner.lock().flush()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Truncate or extend the file indicated by `fd` to `length` bytes.
+///
+/// Return 0 if success.
+pub fn sys_ftruncate(fd: c_int, length: ctypes::off_t) -> c_int {
+    debug!("sys_ftruncate <= {} {}", fd, length);
+    syscall_body!(sys_ftruncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        File::from_fd(fd)?.inner.lock().truncate(length as u64)?;
+        Ok(0)
+    })
+}
+
+/// Write all cached data of filesystems back to the storage.
+pub fn sys_sync() {
+    debug!("sys_sync");
+    if let Err(e) = axfs::api::sync() {
+        warn!("sys_sync: failed to write back block caches: {:?}", e);
+    }
+}
+
 /// Get the file metadata by `path` and write into `buf`.
 ///
 /// Return 0 if success.
@@ -143,11 +588,8 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let mut options = OpenOptions::new();
-        options.read(true);
-        let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file).stat()?;
-        unsafe { *buf = st };
+        let metadata = axfs::api::metadata(path?)?;
+        unsafe { *buf = attr_to_stat(metadata.raw_metadata()) };
         Ok(0)
     })
 }
@@ -177,7 +619,375 @@ pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        let metadata = axfs::api::symlink_metadata(path?)?;
+        unsafe { *buf = attr_to_stat(metadata.raw_metadata()) };
+        Ok(0)
+    })
+}
+
+/// Get the metadata of the file at `path` relative to the directory `dirfd`,
+/// and write into `buf`.
+///
+/// The symbolic link itself is used if `AT_SYMLINK_NOFOLLOW` is set in
+/// `flags`, and `dirfd` itself is used if `path` is empty and `AT_EMPTY_PATH`
+/// is set. Return 0 if success.
+pub unsafe fn sys_fstatat(
+    dirfd: c_int,
+    path: *const c_char,
+    buf: *mut ctypes::stat,
+    flags: c_int,
+) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!(
+        "sys_fstatat <= {} {:?} {:#x} {:#x}",
+        dirfd, path, buf as usize, flags
+    );
+    syscall_body!(sys_fstatat, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let flags = flags as u32;
+        if flags & !(ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = path?;
+        let st = if path.is_empty() && flags & ctypes::AT_EMPTY_PATH != 0 {
+            if dirfd == ctypes::AT_FDCWD {
+                attr_to_stat(&axfs::fops::Directory::current_dir()?.get_attr()?)
+            } else {
+                get_file_like(dirfd)?.stat()?
+            }
+        } else {
+            let follow = flags & ctypes::AT_SYMLINK_NOFOLLOW == 0;
+            with_dir_at(dirfd, path, |dir| {
+                Ok(attr_to_stat(&dir.get_attr_at(path, follow)?))
+            })?
+        };
+        unsafe { *buf = st };
+        Ok(0)
+    })
+}
+
+/// Get the attributes of the filesystem that contains the file at `path`, and
+/// write into `buf`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_statfs(path: *const c_char, buf: *mut ctypes::statfs) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_statfs <= {:?} {:#x}", path, buf as usize);
+    syscall_body!(sys_statfs, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let info = axfs::api::statfs(path?)?;
+        unsafe { *buf = info_to_statfs(&info) };
+        Ok(0)
+    })
+}
+
+/// Get the attributes of the filesystem that contains the file indicated by
+/// `fd`, and write into `buf`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_fstatfs(fd: c_int, buf: *mut ctypes::statfs) -> c_int {
+    debug!("sys_fstatfs <= {} {:#x}", fd, buf as usize);
+    syscall_body!(sys_fstatfs, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let info = match Directory::from_fd(fd) {
+            Ok(dir) => dir.inner.lock().statfs()?,
+            Err(_) => File::from_fd(fd)?.inner.lock().statfs()?,
+        };
+        unsafe { *buf = info_to_statfs(&info) };
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file at `path`.
+///
+/// Return 0 if success.
+pub fn sys_chmod(path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_chmod <= {:?} {:#o}", path, mode);
+    syscall_body!(sys_chmod, {
+        axfs::api::set_attr(path?, mode_to_set_attr(mode))?;
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file indicated by `fd`.
+///
+/// Return 0 if success.
+pub fn sys_fchmod(fd: c_int, mode: ctypes::mode_t) -> c_int {
+    debug!("sys_fchmod <= {} {:#o}", fd, mode);
+    syscall_body!(sys_fchmod, {
+        File::from_fd(fd)?
+            .inner
+            .lock()
+            .set_attr(mode_to_set_attr(mode))?;
+        Ok(0)
+    })
+}
+
+/// Change the owner and group of the file at `path`.
+///
+/// If `owner` or `group` is -1, that ID is not changed. Return 0 if success.
+pub fn sys_chown(path: *const c_char, owner: ctypes::uid_t, group: ctypes::gid_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_chown <= {:?} {} {}", path, owner, group);
+    syscall_body!(sys_chown, {
+        axfs::api::set_attr(path?, owner_to_set_attr(owner, group))?;
+        Ok(0)
+    })
+}
+
+/// Change the owner and group of the file indicated by `fd`.
+///
+/// If `owner` or `group` is -1, that ID is not changed. Return 0 if success.
+pub fn sys_fchown(fd: c_int, owner: ctypes::uid_t, group: ctypes::gid_t) -> c_int {
+    debug!("sys_fchown <= {} {} {}", fd, owner, group);
+    syscall_body!(sys_fchown, {
+        File::from_fd(fd)?
+            .inner
+            .lock()
+            .set_attr(owner_to_set_attr(owner, group))?;
+        Ok(0)
+    })
+}
+
+/// Change the access and modification times of the file at `path` relative to
+/// the directory `dirfd`.
+///
+/// `times` points to the access and modification times, see [`sys_futimens`].
+/// The symbolic link itself is changed if `AT_SYMLINK_NOFOLLOW` is set in
+/// `flags`. Return 0 if success.
+pub unsafe fn sys_utimensat(
+    dirfd: c_int,
+    path: *const c_char,
+    times: *const ctypes::timespec,
+    flags: c_int,
+) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!(
+        "sys_utimensat <= {} {:?} {:#x} {:#x}",
+        dirfd, path, times as usize, flags
+    );
+    syscall_body!(sys_utimensat, {
+        let flags = flags as u32;
+        if flags & !ctypes::AT_SYMLINK_NOFOLLOW != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let attr = unsafe { times_to_set_attr(times)? };
+        let path = path?;
+        let follow = flags & ctypes::AT_SYMLINK_NOFOLLOW == 0;
+        with_dir_at(dirfd, path, |dir| Ok(dir.set_attr_at(path, attr, follow)?))?;
+        Ok(0)
+    })
+}
+
+/// Change the access and modification times of the file indicated by `fd`.
+///
+/// `times` points to the access and modification times. If it is null, both
+/// are set to the current time, and a time with `tv_nsec` of `UTIME_NOW` or
+/// `UTIME_OMIT` is set to the current time or left unchanged. Only the owner
+/// or root can change the times. Return 0 if success.
+pub unsafe fn sys_futimens(fd: c_int, times: *const ctypes::timespec) -> c_int {
+    debug!("sys_futimens <= {} {:#x}", fd, times as usize);
+    syscall_body!(sys_futimens, {
+        let attr = unsafe { times_to_set_attr(times)? };
+        File::from_fd(fd)?.inner.lock().set_attr(attr)?;
+        Ok(0)
+    })
+}
+
+/// Check whether the current task can access the file at `path` relative to
+/// the directory `dirfd` with `mode`, which is `F_OK` or a mask of `R_OK`,
+/// `W_OK` and `X_OK`.
+///
+/// Return 0 if success.
+pub fn sys_faccessat(dirfd: c_int, path: *const c_char, mode: c_int, flags: c_int) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!(
+        "sys_faccessat <= {} {:?} {:#o} {:#x}",
+        dirfd, path, mode, flags
+    );
+    syscall_body!(sys_faccessat, {
+        let (mode, flags) = (mode as u32, flags as u32);
+        if mode & !(ctypes::R_OK | ctypes::W_OK | ctypes::X_OK) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if flags & !(ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EACCESS) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut cap = Cap::empty();
+        if mode & ctypes::R_OK != 0 {
+            cap |= Cap::READ;
+        }
+        if mode & ctypes::W_OK != 0 {
+            cap |= Cap::WRITE;
+        }
+        if mode & ctypes::X_OK != 0 {
+            cap |= Cap::EXECUTE;
+        }
+        let path = path?;
+        let follow = flags & ctypes::AT_SYMLINK_NOFOLLOW == 0;
+        with_dir_at(dirfd, path, |dir| {
+            Ok(dir.check_access_at(path, cap, follow)?)
+        })?;
+        Ok(0)
+    })
+}
+
+/// Create a directory at `path` relative to the directory `dirfd`, with the
+/// permission bits `mode`.
+///
+/// Return 0 if success.
+pub fn sys_mkdirat(dirfd: c_int, path: *const c_char, mode: ctypes::mode_t) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_mkdirat <= {} {:?} {:#o}", dirfd, path, mode);
+    syscall_body!(sys_mkdirat, {
+        let path = path?;
+        with_dir_at(dirfd, path, |dir| {
+            dir.create_dir(path)?;
+            dir.set_attr_at(path, mode_to_set_attr(mode & 0o7777), false)
+                .ok();
+            Ok(0)
+        })
+    })
+}
+
+/// Remove the file, or the empty directory if `AT_REMOVEDIR` is set in
+/// `flags`, at `path` relative to the directory `dirfd`.
+///
+/// Return 0 if success.
+pub fn sys_unlinkat(dirfd: c_int, path: *const c_char, flags: c_int) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_unlinkat <= {} {:?} {:#x}", dirfd, path, flags);
+    syscall_body!(sys_unlinkat, {
+        let flags = flags as u32;
+        if flags & !ctypes::AT_REMOVEDIR != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = path?;
+        with_dir_at(dirfd, path, |dir| {
+            if flags & ctypes::AT_REMOVEDIR != 0 {
+                dir.remove_dir(path)?;
+            } else {
+                dir.remove_file(path)?;
+            }
+            Ok(0)
+        })
+    })
+}
+
+/// Rename `old` relative to the directory `old_dirfd` to `new` relative to
+/// the directory `new_dirfd`.
+///
+/// If `RENAME_NOREPLACE` is set in `flags`, fail with `EEXIST` instead of
+/// replacing `new`. Return 0 if success.
+pub fn sys_renameat2(
+    old_dirfd: c_int,
+    old: *const c_char,
+    new_dirfd: c_int,
+    new: *const c_char,
+    flags: c_uint,
+) -> c_int {
+    syscall_body!(sys_renameat2, {
+        let old = char_ptr_to_str(old)?;
+        let new = char_ptr_to_str(new)?;
+        debug!(
+            "sys_renameat2 <= {} {:?} {} {:?} {:#x}",
+            old_dirfd, old, new_dirfd, new, flags
+        );
+        if flags & !ctypes::RENAME_NOREPLACE != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        // resolve `new` first, so that no two directories are locked at once
+        let new = with_dir_at(new_dirfd, new, |dir| Ok(dir.absolute_path_at(new)?))?;
+        with_dir_at(old_dirfd, old, |dir| {
+            if flags & ctypes::RENAME_NOREPLACE != 0 {
+                Ok(dir.rename_noreplace_at(old, &new)?)
+            } else {
+                Ok(dir.rename_at(old, &new)?)
+            }
+        })?;
+        Ok(0)
+    })
+}
+
+/// Returns the inode number of the entry in `dir`, looks it up if the
+/// filesystem does not report it.
+fn dirent_ino(dir: &axfs::fops::Directory, entry: &DirEntry) -> u64 {
+    let ino = match entry.ino() {
+        0 => core::str::from_utf8(entry.name_as_bytes())
+            .ok()
+            .and_then(|name| dir.get_attr_at(name, false).ok())
+            .map_or(0, |attr| attr.ino()),
+        ino => ino,
+    };
+    // entries with the inode number 0 are skipped by the C library
+    ino.max(1)
+}
+
+/// Read directory entries of the directory `fd` into `dirp` as
+/// `struct linux_dirent64` records, at most `count` bytes.
+///
+/// Return the number of bytes read, or 0 at the end of the directory.
+pub unsafe fn sys_getdents64(fd: c_int, dirp: *mut c_void, count: usize) -> ctypes::ssize_t {
+    debug!("sys_getdents64 <= {} {:#x} {}", fd, dirp as usize, count);
+    syscall_body!(sys_getdents64, {
+        if dirp.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        // `d_ino`, `d_off`, `d_reclen` and `d_type` before `d_name`
+        const NAME_OFFSET: usize = 8 + 8 + 2 + 1;
+        const EMPTY: DirEntry = DirEntry::default();
+
+        let buf = unsafe { core::slice::from_raw_parts_mut(dirp as *mut u8, count) };
+        let dir = Directory::from_fd(fd)?;
+        let mut dir = dir.inner.lock();
+        let mut entries = [EMPTY; 16];
+        let mut len = 0;
+        'outer: loop {
+            let start = dir.position();
+            let n = dir.read_dir(&mut entries)?;
+            if n == 0 {
+                break;
+            }
+            for (i, entry) in entries[..n].iter().enumerate() {
+                let name = entry.name_as_bytes();
+                let reclen = (NAME_OFFSET + name.len() + 1).next_multiple_of(8);
+                if len + reclen > buf.len() {
+                    // leave the remaining entries to the next call
+                    dir.set_position(start + i);
+                    if len == 0 {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    break 'outer;
+                }
+                let rec = &mut buf[len..len + reclen];
+                let off = (start + i + 1) as u64;
+                rec[..8].copy_from_slice(&dirent_ino(&dir, entry).to_ne_bytes());
+                rec[8..16].copy_from_slice(&off.to_ne_bytes());
+                rec[16..18].copy_from_slice(&(reclen as u16).to_ne_bytes());
+                rec[18] = entry.entry_type() as u8;
+                rec[NAME_OFFSET..NAME_OFFSET + name.len()].copy_from_slice(name);
+                rec[NAME_OFFSET + name.len()..].fill(0);
+                len += reclen;
+            }
+        }
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Change the current directory to the directory indicated by `fd`.
+///
+/// Return 0 if success.
+pub fn sys_fchdir(fd: c_int) -> c_int {
+    debug!("sys_fchdir <= {}", fd);
+    syscall_body!(sys_fchdir, {
+        Directory::from_fd(fd)?.inner.lock().set_current_dir()?;
         Ok(0)
     })
 }
@@ -215,3 +1025,100 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         Ok(0)
     })
 }
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_str(target)?;
+        let linkpath = char_ptr_to_str(linkpath)?;
+        debug!(
+            "sys_symlink <= target: {:?}, linkpath: {:?}",
+            target, linkpath
+        );
+        axfs::api::soft_link(target, linkpath)?;
+        Ok(0)
+    })
+}
+
+/// Read the target of the symbolic link `path` into `buf`.
+///
+/// The result is not null-terminated and truncated if `buf` is too small.
+/// Return the number of bytes placed in `buf`.
+pub unsafe fn sys_readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsiz: usize,
+) -> ctypes::ssize_t {
+    let path = char_ptr_to_str(path);
+    debug!("sys_readlink <= {:?} {:#x} {}", path, buf as usize, bufsiz);
+    syscall_body!(sys_readlink, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        if bufsiz == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let target = axfs::api::read_link(path?)?;
+        let len = target.len().min(bufsiz);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Create a new hard link `new` to the existing file `old`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_link(old: *const c_char, new: *const c_char) -> c_int {
+    syscall_body!(sys_link, {
+        let old_path = char_ptr_to_str(old)?;
+        let new_path = char_ptr_to_str(new)?;
+        debug!("sys_link <= old: {:?}, new: {:?}", old_path, new_path);
+        axfs::api::hard_link(old_path, new_path)?;
+        Ok(0)
+    })
+}
+
+/// Mount a new filesystem of type `fstype` at `target`.
+///
+/// `source`, `flags` and `data` are ignored currently. Return 0 if the
+/// operation succeeds, otherwise return -1.
+pub fn sys_mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    flags: core::ffi::c_ulong,
+    _data: *const core::ffi::c_void,
+) -> c_int {
+    syscall_body!(sys_mount, {
+        let target = char_ptr_to_str(target)?;
+        let fstype = char_ptr_to_str(fstype)?;
+        debug!(
+            "sys_mount <= source: {:?}, target: {:?}, fstype: {:?}, flags: {:#x}",
+            char_ptr_to_str(source),
+            target,
+            fstype,
+            flags
+        );
+        let fs = axfs::api::new_fs(fstype).map_err(|_| LinuxError::ENODEV)?;
+        axfs::api::mount(target, fs)?;
+        Ok(0)
+    })
+}
+
+/// Unmount the filesystem mounted at `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_umount2(target: *const c_char, flags: c_int) -> c_int {
+    syscall_body!(sys_umount2, {
+        let target = char_ptr_to_str(target)?;
+        debug!("sys_umount2 <= target: {:?}, flags: {:#x}", target, flags);
+        if flags as u32 & !(ctypes::MNT_FORCE | ctypes::MNT_DETACH | ctypes::UMOUNT_NOFOLLOW) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        axfs::api::umount(target)?;
+        Ok(0)
+    })
+}
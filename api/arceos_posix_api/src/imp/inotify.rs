@@ -0,0 +1,157 @@
+use alloc::sync::Arc;
+use core::ffi::{c_char, c_int};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axfs::fops::{WatchEvent, WatchMask, Watcher};
+use axio::PollState;
+
+use super::fd_ops::{add_file_like, get_file_like, FileLike};
+use crate::{ctypes, utils::char_ptr_to_str};
+
+/// The size of `struct inotify_event` without the name.
+const EVENT_HEADER_SIZE: usize = core::mem::size_of::<ctypes::inotify_event>();
+
+/// An inotify instance, reading it returns `struct inotify_event`s.
+pub struct Inotify {
+    watcher: Arc<Watcher>,
+    nonblocking: AtomicBool,
+}
+
+impl Inotify {
+    fn new(nonblocking: bool) -> Self {
+        Self {
+            watcher: Watcher::new(),
+            nonblocking: AtomicBool::new(nonblocking),
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+}
+
+/// The size of the event in the buffer, the name is null-terminated and
+/// padded to the alignment of the header.
+fn event_size(event: &WatchEvent) -> usize {
+    let name_len = event.name.as_ref().map_or(0, |name| {
+        (name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE)
+    });
+    EVENT_HEADER_SIZE + name_len
+}
+
+/// Writes the event as `struct inotify_event` to the buffer, which must be
+/// large enough.
+fn write_event(event: &WatchEvent, buf: &mut [u8]) -> usize {
+    let size = event_size(event);
+    let name_len = (size - EVENT_HEADER_SIZE) as u32;
+    buf[0..4].copy_from_slice(&(event.wd as i32).to_ne_bytes());
+    buf[4..8].copy_from_slice(&event.mask.bits().to_ne_bytes());
+    buf[8..12].copy_from_slice(&event.cookie.to_ne_bytes());
+    buf[12..16].copy_from_slice(&name_len.to_ne_bytes());
+    buf[EVENT_HEADER_SIZE..size].fill(0);
+    if let Some(name) = &event.name {
+        buf[EVENT_HEADER_SIZE..EVENT_HEADER_SIZE + name.len()].copy_from_slice(name.as_bytes());
+    }
+    size
+}
+
+impl FileLike for Inotify {
+    /// Blocks until at least one event is read, unless it is non-blocking.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        loop {
+            let mut read_len = 0;
+            while let Some(event) = self.watcher.peek_event() {
+                if event_size(&event) > buf.len() - read_len {
+                    break;
+                }
+                read_len += write_event(&event, &mut buf[read_len..]);
+                self.watcher.pop_event();
+            }
+            if read_len > 0 {
+                return Ok(read_len);
+            } else if self.watcher.has_events() {
+                // the buffer is too small for the next event
+                return Err(LinuxError::EINVAL);
+            } else if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            crate::sys_sched_yield(); // TODO: use synconize primitive
+        }
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode: 0o600, // rw-------
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: self.watcher.has_events(),
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Create an inotify instance, with `IN_NONBLOCK` and `IN_CLOEXEC` flags.
+///
+/// Return the file descriptor of the instance.
+pub fn sys_inotify_init1(flags: c_int) -> c_int {
+    debug!("sys_inotify_init1 <= {:#x}", flags);
+    syscall_body!(sys_inotify_init1, {
+        let flags = flags as u32;
+        if flags & !(ctypes::O_NONBLOCK | ctypes::O_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let inotify = Inotify::new(flags & ctypes::O_NONBLOCK != 0);
+        add_file_like(Arc::new(inotify))
+    })
+}
+
+/// Watch the file or directory at `path` for the events in `mask`.
+///
+/// Return the watch descriptor.
+pub fn sys_inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_inotify_add_watch <= {} {:?} {:#x}", fd, path, mask);
+    syscall_body!(sys_inotify_add_watch, {
+        let inotify = Inotify::from_fd(fd)?;
+        let wd = inotify
+            .watcher
+            .add_watch(path?, WatchMask::from_bits_truncate(mask))?;
+        Ok(wd as c_int)
+    })
+}
+
+/// Remove the watch `wd` from the inotify instance.
+///
+/// Return 0 if success.
+pub fn sys_inotify_rm_watch(fd: c_int, wd: c_int) -> c_int {
+    debug!("sys_inotify_rm_watch <= {} {}", fd, wd);
+    syscall_body!(sys_inotify_rm_watch, {
+        let inotify = Inotify::from_fd(fd)?;
+        inotify.watcher.remove_watch(wd as usize)?;
+        Ok(0)
+    })
+}
@@ -1,7 +1,10 @@
 use crate::ctypes;
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
 use core::ffi::{c_int, c_void};
 
+#[cfg(feature = "fd")]
+use alloc::vec::Vec;
+
 #[cfg(feature = "fd")]
 use crate::imp::fd_ops::get_file_like;
 #[cfg(not(feature = "fd"))]
@@ -53,20 +56,103 @@ pub fn sys_write(fd: c_int, buf: *const c_void, count: usize) -> ctypes::ssize_t
     })
 }
 
+/// Checks the `iovec` array of vectored I/O calls, returns it as a slice.
+pub(crate) unsafe fn iovecs<'a>(
+    iov: *const ctypes::iovec,
+    iocnt: c_int,
+) -> LinuxResult<&'a [ctypes::iovec]> {
+    if !(0..=1024).contains(&iocnt) {
+        return Err(LinuxError::EINVAL);
+    }
+    if iocnt == 0 {
+        return Ok(&[]);
+    }
+    if iov.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
+    if iovs.iter().any(|v| v.iov_base.is_null() && v.iov_len != 0) {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(iovs)
+}
+
+/// Returns the buffers described by the `iovec`s to read into.
+#[cfg(feature = "fd")]
+pub(crate) unsafe fn iov_bufs_mut<'a>(iovs: &[ctypes::iovec]) -> Vec<&'a mut [u8]> {
+    iovs.iter()
+        .map(|v| match v.iov_len {
+            0 => &mut [],
+            len => unsafe { core::slice::from_raw_parts_mut(v.iov_base as *mut u8, len) },
+        })
+        .collect()
+}
+
+/// Returns the buffers described by the `iovec`s to write from.
+#[cfg(feature = "fd")]
+pub(crate) unsafe fn iov_bufs<'a>(iovs: &[ctypes::iovec]) -> Vec<&'a [u8]> {
+    iovs.iter()
+        .map(|v| match v.iov_len {
+            0 => &[],
+            len => unsafe { core::slice::from_raw_parts(v.iov_base as *const u8, len) },
+        })
+        .collect()
+}
+
+/// Transfers the `iovec`s one by one with `op` (`sys_read` or `sys_write`),
+/// until a transfer is short or fails.
+#[cfg(not(feature = "fd"))]
+fn transfer_each(
+    iovs: &[ctypes::iovec],
+    op: impl Fn(*mut c_void, usize) -> ctypes::ssize_t,
+) -> ctypes::ssize_t {
+    let mut total = 0;
+    for v in iovs.iter().filter(|v| v.iov_len != 0) {
+        match op(v.iov_base, v.iov_len) {
+            n if n < 0 => return if total == 0 { n } else { total },
+            n => {
+                total += n;
+                if (n as usize) < v.iov_len {
+                    break;
+                }
+            }
+        }
+    }
+    total
+}
+
+/// Read data from the file indicated by `fd` into a vector of buffers.
+///
+/// Return the read size if success.
+pub unsafe fn sys_readv(fd: c_int, iov: *const ctypes::iovec, iocnt: c_int) -> ctypes::ssize_t {
+    debug!("sys_readv <= fd: {} iocnt: {}", fd, iocnt);
+    syscall_body!(sys_readv, {
+        let iovs = unsafe { iovecs(iov, iocnt)? };
+        #[cfg(feature = "fd")]
+        {
+            let mut bufs = unsafe { iov_bufs_mut(iovs) };
+            Ok(get_file_like(fd)?.read_vectored(&mut bufs)? as ctypes::ssize_t)
+        }
+        #[cfg(not(feature = "fd"))]
+        {
+            Ok(transfer_each(iovs, |buf, len| sys_read(fd, buf, len)))
+        }
+    })
+}
+
 /// Write a vector.
 pub unsafe fn sys_writev(fd: c_int, iov: *const ctypes::iovec, iocnt: c_int) -> ctypes::ssize_t {
-    debug!("sys_writev <= fd: {}", fd);
+    debug!("sys_writev <= fd: {} iocnt: {}", fd, iocnt);
     syscall_body!(sys_writev, {
-        if !(0..=1024).contains(&iocnt) {
-            return Err(LinuxError::EINVAL);
+        let iovs = unsafe { iovecs(iov, iocnt)? };
+        #[cfg(feature = "fd")]
+        {
+            let bufs = unsafe { iov_bufs(iovs) };
+            Ok(get_file_like(fd)?.write_vectored(&bufs)? as ctypes::ssize_t)
         }
-
-        let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
-        let mut ret = 0;
-        for iov in iovs.iter() {
-            ret += sys_write(fd, iov.iov_base, iov.iov_len);
+        #[cfg(not(feature = "fd"))]
+        {
+            Ok(transfer_each(iovs, |buf, len| sys_write(fd, buf, len)))
         }
-
-        Ok(ret)
     })
 }
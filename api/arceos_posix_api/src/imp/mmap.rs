@@ -0,0 +1,482 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ffi::{c_int, c_void};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::mem::{phys_to_virt, virt_to_phys, PhysAddr, VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::{kernel_page_table, MappingFlags, PageSize};
+use axsync::Mutex;
+
+use crate::ctypes;
+
+#[cfg(feature = "fs")]
+use {super::fs::File, alloc::sync::Arc, axfs::fops::Cap};
+
+const MMAP_START: usize = axconfig::MMAP_VADDR_BASE;
+const MMAP_END: usize = axconfig::MMAP_VADDR_BASE + axconfig::MMAP_VADDR_SIZE;
+
+/// All memory mappings created by [`sys_mmap`], indexed by the start address.
+static MMAP_AREAS: Mutex<BTreeMap<usize, MmapArea>> = Mutex::new(BTreeMap::new());
+
+/// The file which a memory mapping is created from.
+#[cfg(feature = "fs")]
+struct FileBacked {
+    file: Arc<File>,
+    /// The file offset of the first page of the mapping.
+    offset: u64,
+    /// Whether the modifications are carried through to the file.
+    shared: bool,
+}
+
+/// A contiguous range of pages mapped by [`sys_mmap`].
+///
+/// Each page is backed by a physical frame allocated from the global
+/// allocator, so its contents can be accessed through the linear mapping
+/// even if the page is mapped with `PROT_NONE`.
+struct MmapArea {
+    start: usize,
+    frames: Vec<PhysAddr>,
+    /// Mapping flags of all pages, empty for `PROT_NONE`.
+    flags: MappingFlags,
+    #[cfg(feature = "fs")]
+    backend: Option<FileBacked>,
+}
+
+impl MmapArea {
+    /// Allocates zeroed frames for `num_pages` pages starting at `start`.
+    fn new(start: usize, num_pages: usize, flags: MappingFlags) -> LinuxResult<Self> {
+        let mut area = Self {
+            start,
+            frames: Vec::with_capacity(num_pages),
+            flags,
+            #[cfg(feature = "fs")]
+            backend: None,
+        };
+        for _ in 0..num_pages {
+            // the allocated frames will be freed when `area` is dropped on failure
+            let vaddr = axalloc::global_allocator()
+                .alloc_pages(1, PAGE_SIZE_4K)
+                .map_err(|_| LinuxError::ENOMEM)?;
+            unsafe { core::ptr::write_bytes(vaddr as *mut u8, 0, PAGE_SIZE_4K) };
+            area.frames.push(virt_to_phys(vaddr.into()));
+        }
+        Ok(area)
+    }
+
+    const fn end(&self) -> usize {
+        self.start + self.frames.len() * PAGE_SIZE_4K
+    }
+
+    fn page_addrs(&self) -> impl Iterator<Item = (VirtAddr, PhysAddr)> + '_ {
+        let start = self.start;
+        self.frames
+            .iter()
+            .enumerate()
+            .map(move |(i, &frame)| (VirtAddr::from(start + i * PAGE_SIZE_4K), frame))
+    }
+
+    /// Returns the contents of the `idx`-th page through the linear mapping.
+    #[cfg(feature = "fs")]
+    #[allow(clippy::mut_from_ref)]
+    fn page_data(&self, idx: usize) -> &mut [u8] {
+        let ptr = phys_to_virt(self.frames[idx]).as_mut_ptr();
+        unsafe { core::slice::from_raw_parts_mut(ptr, PAGE_SIZE_4K) }
+    }
+
+    /// Installs all pages into the kernel page table.
+    ///
+    /// Nothing is installed for `PROT_NONE` mappings, so any access to them
+    /// causes a page fault.
+    fn map(&self) -> LinuxResult {
+        if self.flags.is_empty() {
+            return Ok(());
+        }
+        let mut pt = kernel_page_table().lock();
+        for (vaddr, frame) in self.page_addrs() {
+            pt.map(vaddr, frame, PageSize::Size4K, self.flags)
+                .map_err(|_| LinuxError::ENOMEM)?;
+            axhal::arch::flush_tlb(Some(vaddr));
+        }
+        Ok(())
+    }
+
+    /// Removes all pages from the kernel page table.
+    fn unmap(&self) {
+        if self.flags.is_empty() {
+            return;
+        }
+        let mut pt = kernel_page_table().lock();
+        for (vaddr, _) in self.page_addrs() {
+            pt.unmap(vaddr).ok();
+            axhal::arch::flush_tlb(Some(vaddr));
+        }
+    }
+
+    /// Changes the mapping flags of all pages.
+    fn protect(&mut self, flags: MappingFlags) -> LinuxResult {
+        if flags.is_empty() {
+            self.unmap();
+        } else if self.flags.is_empty() {
+            self.flags = flags;
+            self.map()?;
+        } else {
+            let mut pt = kernel_page_table().lock();
+            for (vaddr, _) in self.page_addrs() {
+                pt.update(vaddr, None, Some(flags))
+                    .map_err(|_| LinuxError::ENOMEM)?;
+                axhal::arch::flush_tlb(Some(vaddr));
+            }
+        }
+        self.flags = flags;
+        Ok(())
+    }
+
+    /// Splits the area into two at `at`, returns the upper part.
+    fn split_off(&mut self, at: usize) -> Self {
+        debug_assert!(at > self.start && at < self.end());
+        let frames = self.frames.split_off((at - self.start) / PAGE_SIZE_4K);
+        Self {
+            start: at,
+            frames,
+            flags: self.flags,
+            #[cfg(feature = "fs")]
+            backend: self.backend.as_ref().map(|b| FileBacked {
+                file: b.file.clone(),
+                offset: b.offset + (at - self.start) as u64,
+                shared: b.shared,
+            }),
+        }
+    }
+
+    /// Fills the pages with the contents of the backing file.
+    #[cfg(feature = "fs")]
+    fn load(&self) -> LinuxResult {
+        let Some(backend) = &self.backend else {
+            return Ok(());
+        };
+        for idx in 0..self.frames.len() {
+            let buf = self.page_data(idx);
+            let mut offset = backend.offset + (idx * PAGE_SIZE_4K) as u64;
+            let mut pos = 0;
+            while pos < buf.len() {
+                let n = backend.file.read_at(offset, &mut buf[pos..])?;
+                if n == 0 {
+                    return Ok(()); // reach the end of the file
+                }
+                pos += n;
+                offset += n as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the pages in `[start, end)` back to the file if this is a shared
+    /// file mapping.
+    ///
+    /// The page table does not track dirty pages, so all pages in the range
+    /// are written back. Contents beyond the end of the file are discarded.
+    #[cfg(feature = "fs")]
+    fn write_back(&self, start: usize, end: usize) -> LinuxResult {
+        let Some(backend) = self.backend.as_ref().filter(|b| b.shared) else {
+            return Ok(());
+        };
+        let file_size = backend.file.size()?;
+        let start = start.max(self.start);
+        let end = end.min(self.end());
+        for idx in (start - self.start) / PAGE_SIZE_4K..(end - self.start) / PAGE_SIZE_4K {
+            let offset = backend.offset + (idx * PAGE_SIZE_4K) as u64;
+            if offset >= file_size {
+                break;
+            }
+            let len = (file_size - offset).min(PAGE_SIZE_4K as u64) as usize;
+            backend
+                .file
+                .write_all_at(offset, &self.page_data(idx)[..len])?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "fs"))]
+    fn write_back(&self, _start: usize, _end: usize) -> LinuxResult {
+        Ok(())
+    }
+}
+
+impl Drop for MmapArea {
+    fn drop(&mut self) {
+        for frame in &self.frames {
+            axalloc::global_allocator().dealloc_pages(phys_to_virt(*frame).as_usize(), 1);
+        }
+    }
+}
+
+/// Converts `PROT_*` bits to [`MappingFlags`].
+fn prot_to_flags(prot: c_int) -> MappingFlags {
+    let prot = prot as u32;
+    let mut flags = MappingFlags::empty();
+    // all accessible pages are readable, as required by some architectures
+    if prot & (ctypes::PROT_READ | ctypes::PROT_WRITE | ctypes::PROT_EXEC) != 0 {
+        flags |= MappingFlags::READ;
+    }
+    if prot & ctypes::PROT_WRITE != 0 {
+        flags |= MappingFlags::WRITE;
+    }
+    if prot & ctypes::PROT_EXEC != 0 {
+        flags |= MappingFlags::EXECUTE;
+    }
+    flags
+}
+
+/// Finds a free virtual address range of `size` bytes in the mmap area.
+///
+/// `hint` is used if the range starting from it is free.
+fn find_free_range(areas: &BTreeMap<usize, MmapArea>, hint: usize, size: usize) -> Option<usize> {
+    let is_free = |start: usize| {
+        start >= MMAP_START
+            && start.checked_add(size).map_or(false, |end| end <= MMAP_END)
+            && !areas
+                .range(..start + size)
+                .next_back()
+                .is_some_and(|(_, a)| a.end() > start)
+    };
+    if hint != 0 && is_free(hint) {
+        return Some(hint);
+    }
+    let mut start = MMAP_START;
+    for area in areas.values() {
+        if area.start >= start + size {
+            break;
+        }
+        start = start.max(area.end());
+    }
+    if is_free(start) {
+        Some(start)
+    } else {
+        None
+    }
+}
+
+/// Splits the areas that cross the boundaries of `[start, end)`, so that each
+/// area is either fully inside or outside the range.
+fn split_areas(areas: &mut BTreeMap<usize, MmapArea>, start: usize, end: usize) {
+    for addr in [start, end] {
+        if let Some((_, area)) = areas.range_mut(..addr).next_back() {
+            if area.end() > addr {
+                let upper = area.split_off(addr);
+                areas.insert(addr, upper);
+            }
+        }
+    }
+}
+
+/// Returns whether `[start, end)` is fully covered by the areas.
+fn is_range_mapped(areas: &BTreeMap<usize, MmapArea>, start: usize, end: usize) -> bool {
+    let mut addr = start;
+    if let Some((_, area)) = areas.range(..=start).next_back() {
+        addr = addr.max(area.end().min(end));
+    }
+    for area in areas.range(start + 1..end).map(|(_, a)| a) {
+        if area.start > addr {
+            return false;
+        }
+        addr = addr.max(area.end());
+    }
+    addr >= end
+}
+
+/// Removes the areas in `[start, end)`, writes back the shared file mappings.
+fn remove_areas(areas: &mut BTreeMap<usize, MmapArea>, start: usize, end: usize) -> LinuxResult {
+    split_areas(areas, start, end);
+    let keys = areas.range(start..end).map(|(&k, _)| k).collect::<Vec<_>>();
+    for key in keys {
+        let area = areas.remove(&key).unwrap();
+        let res = area.write_back(area.start, area.end());
+        area.unmap();
+        res?;
+    }
+    Ok(())
+}
+
+/// Checks the address and length arguments, returns the page-aligned range.
+fn check_range(addr: *mut c_void, len: ctypes::size_t) -> LinuxResult<(usize, usize)> {
+    let start = addr as usize;
+    if start % PAGE_SIZE_4K != 0 || len == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let end = start
+        .checked_add(memory_addr::align_up_4k(len as usize))
+        .ok_or(LinuxError::EINVAL)?;
+    Ok((start, end))
+}
+
+/// Creates a new mapping in the virtual address space.
+///
+/// Anonymous mappings (`MAP_ANONYMOUS`) are filled with zeros. For file
+/// mappings, the contents of the file starting from `off` are loaded into the
+/// mapping. Changes to a `MAP_SHARED` file mapping are written back to the
+/// file on [`sys_msync`] and [`sys_munmap`].
+///
+/// The file must be opened for reading, and also for writing if the mapping
+/// is shared and writable, otherwise `EACCES` is returned. Existing mappings
+/// in the range of `MAP_FIXED` are only replaced after the file is validated.
+///
+/// Return the start address of the mapping, or `MAP_FAILED` on error.
+pub fn sys_mmap(
+    addr: *mut c_void,
+    len: ctypes::size_t,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    off: ctypes::off_t,
+) -> *mut c_void {
+    debug!(
+        "sys_mmap <= addr: {:#x}, len: {}, prot: {:#x}, flags: {:#x}, fd: {}, off: {}",
+        addr as usize, len, prot, flags, fd, off
+    );
+    syscall_body!(sys_mmap, {
+        let map_flags = flags as u32;
+        let shared = match map_flags & ctypes::MAP_TYPE {
+            ctypes::MAP_SHARED | ctypes::MAP_SHARED_VALIDATE => true,
+            ctypes::MAP_PRIVATE => false,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if len == 0 || off < 0 || off as usize % PAGE_SIZE_4K != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let size = memory_addr::align_up_4k(len as usize);
+        let hint = memory_addr::align_down_4k(addr as usize);
+
+        // validate the file before any existing mapping is replaced
+        #[cfg(feature = "fs")]
+        let backend = if map_flags & ctypes::MAP_ANONYMOUS == 0 {
+            let file = File::from_fd(fd).map_err(|_| LinuxError::EBADF)?;
+            let cap = file.access_cap();
+            if !cap.contains(Cap::READ)
+                || (shared && prot as u32 & ctypes::PROT_WRITE != 0 && !cap.contains(Cap::WRITE))
+            {
+                return Err(LinuxError::EACCES);
+            }
+            Some(FileBacked {
+                file,
+                offset: off as u64,
+                shared,
+            })
+        } else {
+            None
+        };
+        #[cfg(not(feature = "fs"))]
+        if map_flags & ctypes::MAP_ANONYMOUS == 0 {
+            let _ = (fd, shared);
+            return Err(LinuxError::ENODEV);
+        }
+
+        let mut areas = MMAP_AREAS.lock();
+        let start = if map_flags & ctypes::MAP_FIXED != 0 {
+            if addr as usize % PAGE_SIZE_4K != 0
+                || hint < MMAP_START
+                || hint.checked_add(size).map_or(true, |end| end > MMAP_END)
+            {
+                return Err(LinuxError::EINVAL);
+            }
+            remove_areas(&mut areas, hint, hint + size)?;
+            hint
+        } else {
+            find_free_range(&areas, hint, size).ok_or(LinuxError::ENOMEM)?
+        };
+
+        #[allow(unused_mut)]
+        let mut area = MmapArea::new(start, size / PAGE_SIZE_4K, prot_to_flags(prot))?;
+        #[cfg(feature = "fs")]
+        {
+            area.backend = backend;
+            area.load()?;
+        }
+        if let Err(e) = area.map() {
+            area.unmap();
+            return Err(e);
+        }
+        areas.insert(start, area);
+        Ok(start as *mut c_void)
+    })
+}
+
+/// Removes the mappings in the range `[addr, addr + len)`.
+///
+/// The shared file mappings in the range are written back to the files. It is
+/// not an error if the range does not contain any mapped pages.
+pub fn sys_munmap(addr: *mut c_void, len: ctypes::size_t) -> c_int {
+    debug!("sys_munmap <= addr: {:#x}, len: {}", addr as usize, len);
+    syscall_body!(sys_munmap, {
+        let (start, end) = check_range(addr, len)?;
+        remove_areas(&mut MMAP_AREAS.lock(), start, end)?;
+        Ok(0)
+    })
+}
+
+/// Changes the access protections of the mappings in the range
+/// `[addr, addr + len)`.
+///
+/// Return `ENOMEM` if some pages in the range are not mapped, or `EACCES` if
+/// a shared file mapping would become writable but the file is not opened for
+/// writing.
+pub fn sys_mprotect(addr: *mut c_void, len: ctypes::size_t, prot: c_int) -> c_int {
+    debug!(
+        "sys_mprotect <= addr: {:#x}, len: {}, prot: {:#x}",
+        addr as usize, len, prot
+    );
+    syscall_body!(sys_mprotect, {
+        let (start, end) = check_range(addr, len)?;
+        let flags = prot_to_flags(prot);
+        let mut areas = MMAP_AREAS.lock();
+        if !is_range_mapped(&areas, start, end) {
+            return Err(LinuxError::ENOMEM);
+        }
+        split_areas(&mut areas, start, end);
+        // shared mappings of files not opened for writing can not be writable
+        #[cfg(feature = "fs")]
+        if flags.contains(MappingFlags::WRITE)
+            && areas.range(start..end).any(|(_, area)| {
+                area.backend.as_ref().is_some_and(|backend| {
+                    backend.shared && !backend.file.access_cap().contains(Cap::WRITE)
+                })
+            })
+        {
+            return Err(LinuxError::EACCES);
+        }
+        for (_, area) in areas.range_mut(start..end) {
+            area.protect(flags)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Writes the modified pages of the shared file mappings in the range
+/// `[addr, addr + len)` back to the files.
+///
+/// The write-back is always synchronous, so `MS_ASYNC` has the same effect as
+/// `MS_SYNC`. Return `ENOMEM` if some pages in the range are not mapped.
+pub fn sys_msync(addr: *mut c_void, len: ctypes::size_t, flags: c_int) -> c_int {
+    debug!(
+        "sys_msync <= addr: {:#x}, len: {}, flags: {:#x}",
+        addr as usize, len, flags
+    );
+    syscall_body!(sys_msync, {
+        let flags = flags as u32;
+        if flags & !(ctypes::MS_ASYNC | ctypes::MS_SYNC | ctypes::MS_INVALIDATE) != 0
+            || (flags & ctypes::MS_ASYNC != 0 && flags & ctypes::MS_SYNC != 0)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let (start, end) = check_range(addr, len)?;
+        let areas = MMAP_AREAS.lock();
+        if !is_range_mapped(&areas, start, end) {
+            return Err(LinuxError::ENOMEM);
+        }
+        if let Some((_, area)) = areas.range(..start).next_back() {
+            area.write_back(start, end)?;
+        }
+        for (_, area) in areas.range(start..end) {
+            area.write_back(start, end)?;
+        }
+        Ok(0)
+    })
+}
@@ -57,6 +57,8 @@ impl Pthread {
             let ret = start_routine(arg.0);
             unsafe { *their_packet.result.get() = ret };
             drop(their_packet);
+            #[cfg(feature = "fs")]
+            axfs::fops::release_task_locks(super::task::sys_getpid() as u64);
         };
 
         let task_inner = axtask::spawn(main);
@@ -85,6 +87,8 @@ impl Pthread {
     fn exit_current(retval: *mut c_void) -> ! {
         let thread = Self::current().expect("fail to get current thread");
         unsafe { *thread.retval.result.get() = retval };
+        #[cfg(feature = "fs")]
+        axfs::fops::release_task_locks(super::task::sys_getpid() as u64);
         axtask::exit(0);
     }
 
@@ -33,6 +33,10 @@ pub fn sys_getpid() -> c_int {
 /// Exit current task
 pub fn sys_exit(exit_code: c_int) -> ! {
     debug!("sys_exit <= {}", exit_code);
+    #[cfg(feature = "fs")]
+    axfs::api::sync().ok(); // write back buffer caches before terminating
+    #[cfg(feature = "fs")]
+    axfs::fops::release_task_locks(sys_getpid() as u64);
     #[cfg(feature = "multitask")]
     axtask::exit(exit_code);
     #[cfg(not(feature = "multitask"))]
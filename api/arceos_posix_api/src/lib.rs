@@ -30,20 +30,35 @@ pub mod config {
 #[allow(dead_code, non_snake_case, non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms, missing_docs)]
 pub mod ctypes;
 
-pub use imp::io::{sys_read, sys_write, sys_writev};
+pub use imp::io::{sys_read, sys_readv, sys_write, sys_writev};
 pub use imp::resources::{sys_getrlimit, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
 
+#[cfg(feature = "fs")]
+pub use imp::aio::{
+    sys_aio_cancel, sys_aio_error, sys_aio_fsync, sys_aio_read, sys_aio_return, sys_aio_suspend,
+    sys_aio_write, sys_lio_listio,
+};
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    sys_chmod, sys_chown, sys_faccessat, sys_fchdir, sys_fchmod, sys_fchown, sys_flock, sys_fstat,
+    sys_fstatat, sys_fstatfs, sys_fsync, sys_ftruncate, sys_futimens, sys_getcwd, sys_getdents64,
+    sys_link, sys_lseek, sys_lstat, sys_mkdirat, sys_mount, sys_open, sys_openat, sys_preadv,
+    sys_pwritev, sys_readlink, sys_rename, sys_renameat2, sys_stat, sys_statfs, sys_symlink,
+    sys_sync, sys_umount2, sys_unlinkat, sys_utimensat,
+};
+#[cfg(feature = "fs")]
+pub use imp::inotify::{sys_inotify_add_watch, sys_inotify_init1, sys_inotify_rm_watch};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
 pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+#[cfg(feature = "mmap")]
+pub use imp::mmap::{sys_mmap, sys_mprotect, sys_msync, sys_munmap};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
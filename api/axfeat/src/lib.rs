@@ -19,11 +19,15 @@
 //!     - `sched_fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched_rr`: Use the Round-robin preemptive scheduler.
 //!     - `sched_cfs`: Use the Completely Fair Scheduler (CFS) preemptive scheduler.
-//! - Upperlayer stacks (fs, net, display)
+//! - Upperlayer stacks (fs, net, display, input)
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext4`: Support ext2/ext4 filesystems, used as the root filesystem if found.
+//!     - `initramfs`: Use the cpio archive in `AX_INITRAMFS` as the root filesystem.
+//!     - `9p`: Support filesystems shared by the host through virtio-9p.
 //!     - `net`: Enable networking support.
 //!     - `display`: Enable graphics support.
+//!     - `input`: Enable input device (keyboard, mouse and tablet) support.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
 //!     - `bus-pci`: Use PCI bus to probe all PCI devices.
@@ -10,6 +10,6 @@ struct MyFileSystemIfImpl;
 #[crate_interface::impl_interface]
 impl MyFileSystemIf for MyFileSystemIfImpl {
     fn new_myfs(_disk: AxDisk) -> Arc<dyn VfsOps> {
-        Arc::new(RamFileSystem::new())
+        Arc::new(RamFileSystem::new(None, None))
     }
 }
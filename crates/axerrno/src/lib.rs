@@ -41,8 +41,16 @@ pub enum AxError {
     ConnectionRefused,
     /// The connection was reset by the remote server.
     ConnectionReset,
+    /// Cross-device or cross-filesystem (hard) link or rename.
+    CrossesDevices,
+    /// A deadlock would occur if the operation blocked, e.g. waiting for a
+    /// file lock held by a task that is waiting for the caller.
+    Deadlock,
     /// A non-empty directory was specified where an empty directory was expected.
     DirectoryNotEmpty,
+    /// Loop in the filesystem or IO subsystem, e.g. too many levels of
+    /// symbolic links.
+    FilesystemLoop,
     /// Data not valid for the operation were encountered.
     ///
     /// Unlike [`InvalidInput`], this typically means that the operation
@@ -194,7 +202,10 @@ impl AxError {
             AlreadyExists => "Entity already exists",
             ConnectionRefused => "Connection refused",
             ConnectionReset => "Connection reset",
+            CrossesDevices => "Cross-device link or rename",
+            Deadlock => "Resource deadlock would occur",
             DirectoryNotEmpty => "Directory not empty",
+            FilesystemLoop => "Filesystem loop or indirection limit",
             InvalidData => "Invalid data",
             InvalidInput => "Invalid input parameter",
             Io => "I/O error",
@@ -247,7 +258,10 @@ impl From<AxError> for LinuxError {
             BadAddress | BadState => LinuxError::EFAULT,
             ConnectionRefused => LinuxError::ECONNREFUSED,
             ConnectionReset => LinuxError::ECONNRESET,
+            CrossesDevices => LinuxError::EXDEV,
+            Deadlock => LinuxError::EDEADLK,
             DirectoryNotEmpty => LinuxError::ENOTEMPTY,
+            FilesystemLoop => LinuxError::ELOOP,
             InvalidInput | InvalidData => LinuxError::EINVAL,
             Io => LinuxError::EIO,
             IsADirectory => LinuxError::EISDIR,
@@ -283,7 +297,7 @@ mod tests {
     #[test]
     fn test_try_from() {
         let max_code = core::mem::variant_count::<AxError>() as i32;
-        assert_eq!(max_code, 22);
+        assert_eq!(max_code, 25);
         assert_eq!(max_code, AxError::WriteZero.code());
 
         assert_eq!(AxError::AddrInUse.code(), 1);
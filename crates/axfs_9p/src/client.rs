@@ -0,0 +1,338 @@
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+use core::sync::atomic::{AtomicU32, Ordering};
+use spin::Mutex;
+
+use crate::proto::*;
+use crate::Transport;
+
+/// The maximum message size proposed to the server.
+const MAX_MSIZE: u32 = 64 * 1024;
+/// The tag of all requests except `Tversion`, since they are sent one at a
+/// time.
+const TAG: u16 = 0;
+
+/// A session of the 9P2000.L protocol over a [`Transport`].
+///
+/// Fids are allocated per session, so a transport can only be used by one
+/// client, which can be shared by multiple filesystems attached with
+/// [`NinePFileSystem::new`](crate::NinePFileSystem::new). Clunked fids are
+/// reused by later allocations.
+pub struct NinePClient {
+    transport: Box<dyn Transport>,
+    msize: u32,
+    next_fid: AtomicU32,
+    free_fids: Mutex<Vec<u32>>,
+}
+
+impl NinePClient {
+    /// Starts a session on the transport, and negotiates the maximum message
+    /// size with the server.
+    ///
+    /// Returns [`Unsupported`](VfsError::Unsupported) if the server does not
+    /// speak 9P2000.L.
+    pub fn new(transport: impl Transport + 'static) -> VfsResult<Self> {
+        let mut client = Self {
+            transport: Box::new(transport),
+            msize: MAX_MSIZE,
+            next_fid: AtomicU32::new(0),
+            free_fids: Mutex::new(Vec::new()),
+        };
+        let mut msg = Message::new(TVERSION, NOTAG);
+        msg.u32(MAX_MSIZE).str(VERSION);
+        let resp = client.rpc(&mut msg)?;
+        let mut r = Reader::new(&resp);
+        let msize = r.u32()?;
+        if r.str()? != VERSION {
+            return Err(VfsError::Unsupported);
+        }
+        if msize <= IOHDRSZ {
+            return Err(VfsError::InvalidData);
+        }
+        client.msize = msize.min(MAX_MSIZE);
+        Ok(client)
+    }
+
+    /// The maximum message size negotiated with the server.
+    pub fn msize(&self) -> u32 {
+        self.msize
+    }
+
+    /// Sends a request, and returns the fields of its response after the
+    /// header.
+    fn rpc(&self, msg: &mut Message) -> VfsResult<Vec<u8>> {
+        let ty = msg.ty();
+        let mut resp = vec![0; self.msize as usize];
+        let len = self.transport.request(msg.finish(), &mut resp)?;
+        resp.truncate(len);
+
+        let mut r = Reader::new(&resp);
+        let size = r.u32()? as usize;
+        let resp_ty = r.u8()?;
+        if size < HEADER_LEN || size > len {
+            return Err(VfsError::InvalidData);
+        }
+        resp.truncate(size);
+        if resp_ty == RLERROR {
+            let errno = Reader::new(&resp[HEADER_LEN..]).u32()?;
+            return Err(errno_to_error(errno));
+        } else if resp_ty != ty + 1 {
+            log::warn!("9p: unexpected response {} to request {}", resp_ty, ty);
+            return Err(VfsError::InvalidData);
+        }
+        resp.drain(..HEADER_LEN);
+        Ok(resp)
+    }
+
+    /// Allocates an unused fid, or returns [`NoMemory`](VfsError::NoMemory)
+    /// if all fids below `NOFID` are in use.
+    pub(crate) fn alloc_fid(&self) -> VfsResult<u32> {
+        if let Some(fid) = self.free_fids.lock().pop() {
+            return Ok(fid);
+        }
+        self.next_fid
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |fid| {
+                (fid < NOFID).then_some(fid + 1)
+            })
+            .map_err(|_| VfsError::NoMemory)
+    }
+
+    /// Returns an allocated fid that is not used on the server to be reused.
+    pub(crate) fn free_fid(&self, fid: u32) {
+        self.free_fids.lock().push(fid);
+    }
+
+    /// The maximum count of data in a `Tread` or `Twrite`.
+    fn max_io_count(&self) -> usize {
+        (self.msize - IOHDRSZ) as usize
+    }
+
+    pub(crate) fn attach(&self, fid: u32, aname: &str) -> VfsResult<Qid> {
+        let mut msg = Message::new(TATTACH, TAG);
+        // n_uname is 0, i.e. attach as root
+        msg.u32(fid).u32(NOFID).str("").str(aname).u32(0);
+        Reader::new(&self.rpc(&mut msg)?).qid()
+    }
+
+    /// Walks from `fid` through `names` to `newfid`, which is a clone of `fid`
+    /// if `names` is empty. Returns the qid of the last name.
+    ///
+    /// `newfid` is released if the walk fails.
+    pub(crate) fn walk(&self, fid: u32, newfid: u32, names: &[&str]) -> VfsResult<Option<Qid>> {
+        let mut from = fid;
+        let mut last = None;
+        let mut chunks = names.chunks(MAXWELEM);
+        let first = chunks.next().unwrap_or(&[]);
+        for chunk in core::iter::once(first).chain(chunks) {
+            let res = self.walk_once(from, newfid, chunk);
+            if res.is_err() {
+                if from == newfid {
+                    self.clunk(newfid).ok();
+                } else {
+                    self.free_fid(newfid);
+                }
+            }
+            last = res?.or(last);
+            from = newfid;
+        }
+        Ok(last)
+    }
+
+    fn walk_once(&self, fid: u32, newfid: u32, names: &[&str]) -> VfsResult<Option<Qid>> {
+        let mut msg = Message::new(TWALK, TAG);
+        msg.u32(fid).u32(newfid).u16(names.len() as u16);
+        for name in names {
+            msg.str(name);
+        }
+        let resp = self.rpc(&mut msg)?;
+        let mut r = Reader::new(&resp);
+        let nwqid = r.u16()? as usize;
+        let mut last = None;
+        for _ in 0..nwqid {
+            last = Some(r.qid()?);
+        }
+        if nwqid < names.len() {
+            // `newfid` is unaffected if the walk stops in the middle
+            return match last {
+                Some(qid) if !qid.is_dir() => Err(VfsError::NotADirectory),
+                _ => Err(VfsError::NotFound),
+            };
+        }
+        Ok(last)
+    }
+
+    /// Releases `fid` on the server. It can be reused even if this fails.
+    pub(crate) fn clunk(&self, fid: u32) -> VfsResult {
+        let mut msg = Message::new(TCLUNK, TAG);
+        msg.u32(fid);
+        let res = self.rpc(&mut msg).map(|_| ());
+        self.free_fid(fid);
+        res
+    }
+
+    pub(crate) fn lopen(&self, fid: u32, flags: u32) -> VfsResult<Qid> {
+        let mut msg = Message::new(TLOPEN, TAG);
+        msg.u32(fid).u32(flags);
+        Reader::new(&self.rpc(&mut msg)?).qid()
+    }
+
+    /// Creates a file in the directory `fid`, which becomes the opened file.
+    pub(crate) fn lcreate(&self, fid: u32, name: &str, flags: u32, mode: u32) -> VfsResult<Qid> {
+        let mut msg = Message::new(TLCREATE, TAG);
+        msg.u32(fid).str(name).u32(flags).u32(mode).u32(0);
+        Reader::new(&self.rpc(&mut msg)?).qid()
+    }
+
+    pub(crate) fn mkdir(&self, dfid: u32, name: &str, mode: u32) -> VfsResult<Qid> {
+        let mut msg = Message::new(TMKDIR, TAG);
+        msg.u32(dfid).str(name).u32(mode).u32(0);
+        Reader::new(&self.rpc(&mut msg)?).qid()
+    }
+
+    pub(crate) fn symlink(&self, dfid: u32, name: &str, target: &str) -> VfsResult<Qid> {
+        let mut msg = Message::new(TSYMLINK, TAG);
+        msg.u32(dfid).str(name).str(target).u32(0);
+        Reader::new(&self.rpc(&mut msg)?).qid()
+    }
+
+    pub(crate) fn link(&self, dfid: u32, fid: u32, name: &str) -> VfsResult {
+        let mut msg = Message::new(TLINK, TAG);
+        msg.u32(dfid).u32(fid).str(name);
+        self.rpc(&mut msg).map(|_| ())
+    }
+
+    pub(crate) fn readlink(&self, fid: u32) -> VfsResult<String> {
+        let mut msg = Message::new(TREADLINK, TAG);
+        msg.u32(fid);
+        let resp = self.rpc(&mut msg)?;
+        Reader::new(&resp).str().map(String::from)
+    }
+
+    pub(crate) fn getattr(&self, fid: u32) -> VfsResult<Stat> {
+        let mut msg = Message::new(TGETATTR, TAG);
+        msg.u32(fid).u64(GETATTR_BASIC);
+        let resp = self.rpc(&mut msg)?;
+        let mut r = Reader::new(&resp);
+        let _valid = r.u64()?;
+        let qid = r.qid()?;
+        let mode = r.u32()?;
+        let uid = r.u32()?;
+        let gid = r.u32()?;
+        let nlink = r.u64()?;
+        let _rdev = r.u64()?;
+        let size = r.u64()?;
+        let _blksize = r.u64()?;
+        let blocks = r.u64()?;
+        Ok(Stat {
+            qid,
+            mode,
+            uid,
+            gid,
+            nlink,
+            size,
+            blocks,
+            atime: r.time()?,
+            mtime: r.time()?,
+            ctime: r.time()?,
+        })
+    }
+
+    pub(crate) fn setattr(&self, fid: u32, attr: &SetAttr) -> VfsResult {
+        let mut msg = Message::new(TSETATTR, TAG);
+        msg.u32(fid)
+            .u32(attr.valid)
+            .u32(attr.mode)
+            .u32(attr.uid)
+            .u32(attr.gid)
+            .u64(attr.size)
+            .time(attr.atime)
+            .time(attr.mtime);
+        self.rpc(&mut msg).map(|_| ())
+    }
+
+    /// Reads entries of the opened directory `fid` from `offset`, which is 0
+    /// or the offset of a previous entry.
+    pub(crate) fn readdir(&self, fid: u32, offset: u64) -> VfsResult<Vec<DirEntry>> {
+        let mut msg = Message::new(TREADDIR, TAG);
+        msg.u32(fid).u64(offset).u32(self.max_io_count() as u32);
+        let resp = self.rpc(&mut msg)?;
+        let mut r = Reader::new(&resp);
+        let count = r.u32()? as usize;
+        let mut r = Reader::new(r.bytes(count)?);
+        let mut entries = Vec::new();
+        while !r.is_empty() {
+            entries.push(DirEntry {
+                qid: r.qid()?,
+                offset: r.u64()?,
+                ty: r.u8()?,
+                name: String::from(r.str()?),
+            });
+        }
+        Ok(entries)
+    }
+
+    pub(crate) fn fsync(&self, fid: u32) -> VfsResult {
+        let mut msg = Message::new(TFSYNC, TAG);
+        msg.u32(fid).u32(0);
+        self.rpc(&mut msg).map(|_| ())
+    }
+
+    pub(crate) fn renameat(
+        &self,
+        old_dfid: u32,
+        old_name: &str,
+        new_dfid: u32,
+        new_name: &str,
+    ) -> VfsResult {
+        let mut msg = Message::new(TRENAMEAT, TAG);
+        msg.u32(old_dfid).str(old_name).u32(new_dfid).str(new_name);
+        self.rpc(&mut msg).map(|_| ())
+    }
+
+    pub(crate) fn unlinkat(&self, dfid: u32, name: &str, flags: u32) -> VfsResult {
+        let mut msg = Message::new(TUNLINKAT, TAG);
+        msg.u32(dfid).str(name).u32(flags);
+        self.rpc(&mut msg).map(|_| ())
+    }
+
+    /// Reads data from the opened file `fid` until `buf` is full or the end
+    /// of the file.
+    pub(crate) fn read(&self, fid: u32, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut read = 0;
+        for chunk in buf.chunks_mut(self.max_io_count()) {
+            let mut msg = Message::new(TREAD, TAG);
+            msg.u32(fid)
+                .u64(offset + read as u64)
+                .u32(chunk.len() as u32);
+            let resp = self.rpc(&mut msg)?;
+            let mut r = Reader::new(&resp);
+            let count = r.u32()? as usize;
+            let data = r.bytes(count.min(chunk.len()))?;
+            chunk[..data.len()].copy_from_slice(data);
+            read += data.len();
+            if data.len() < chunk.len() {
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Writes all data to the opened file `fid`.
+    pub(crate) fn write(&self, fid: u32, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let len = (buf.len() - written).min(self.max_io_count());
+            let mut msg = Message::new(TWRITE, TAG);
+            msg.u32(fid)
+                .u64(offset + written as u64)
+                .u32(len as u32)
+                .bytes(&buf[written..written + len]);
+            let count = Reader::new(&self.rpc(&mut msg)?).u32()? as usize;
+            if count == 0 {
+                break;
+            }
+            written += count.min(len);
+        }
+        Ok(written)
+    }
+}
@@ -0,0 +1,88 @@
+//! [9P2000.L] client filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
+//!
+//! The implementation is based on [`axfs_vfs`].
+//!
+//! It accesses a filesystem shared by a 9P server, e.g. a host directory
+//! exported by QEMU `-virtfs`, through a [`Transport`] such as virtio-9p.
+//! Files, directories, symbolic links and hard links can be read, created,
+//! changed and removed. Nodes are identified by their paths from the root,
+//! and `..` is resolved lexically.
+//!
+//! [9P2000.L]: https://github.com/chaos/diod/blob/master/protocol.md
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod client;
+mod node;
+mod proto;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::client::NinePClient;
+pub use self::node::NinePNode;
+
+use alloc::{string::String, sync::Arc};
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use spin::RwLock;
+
+use self::node::FsInner;
+
+/// The transport that carries 9P messages to the server.
+pub trait Transport: Send + Sync {
+    /// Sends a request message and waits for its response.
+    ///
+    /// The response is written into `resp`, which can hold a message of the
+    /// maximum size. Returns the length of the response.
+    fn request(&self, req: &[u8], resp: &mut [u8]) -> VfsResult<usize>;
+}
+
+/// A 9P filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct NinePFileSystem {
+    inner: Arc<FsInner>,
+    root: Arc<NinePNode>,
+}
+
+impl NinePFileSystem {
+    /// Attaches to the filesystem `aname` exported by the server of the
+    /// client session, which is ignored by some servers such as QEMU.
+    pub fn new(client: &Arc<NinePClient>, aname: &str) -> VfsResult<Self> {
+        let root_fid = client.alloc_fid()?;
+        if let Err(e) = client.attach(root_fid, aname) {
+            client.free_fid(root_fid);
+            return Err(e);
+        }
+        let inner = Arc::new(FsInner {
+            client: client.clone(),
+            root_fid,
+            mount_parent: RwLock::new(None),
+            mount_path: RwLock::new(String::new()),
+        });
+        let root = inner.node("")?;
+        Ok(Self { inner, root })
+    }
+
+    /// Returns the root directory node in [`Arc<NinePNode>`](NinePNode).
+    pub fn root_dir_node(&self) -> Arc<NinePNode> {
+        self.root.clone()
+    }
+}
+
+impl VfsOps for NinePFileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.inner.mount_parent.write() = mount_point.parent();
+        *self.inner.mount_path.write() = String::from(path);
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        *self.inner.mount_parent.write() = None;
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root_dir_node()
+    }
+}
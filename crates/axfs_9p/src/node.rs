@@ -0,0 +1,433 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult, VfsSetAttr};
+use spin::{Mutex, RwLock};
+
+use crate::client::NinePClient;
+use crate::proto::*;
+
+/// Maximum length of names in [`VfsDirEntry`].
+const MAX_NAME_LEN: usize = 63;
+
+/// Shared state of an attached filesystem.
+pub(crate) struct FsInner {
+    pub client: Arc<NinePClient>,
+    /// Fid of the root directory, where all walks start.
+    pub root_fid: u32,
+    /// Parent of the mount point, i.e. `..` of the root directory.
+    pub mount_parent: RwLock<Option<VfsNodeRef>>,
+    /// Path of the mount point, to resolve absolute paths in `rename`.
+    pub mount_path: RwLock<String>,
+}
+
+impl FsInner {
+    /// Walks from the root to `path`, which is made up of names separated by
+    /// `/`, and returns the node.
+    pub fn node(self: &Arc<Self>, path: &str) -> VfsResult<Arc<NinePNode>> {
+        let names = path.split('/').filter(|name| !name.is_empty());
+        let names = names.collect::<Vec<_>>();
+        let fid = self.client.alloc_fid()?;
+        // the root is the only node reached without names
+        let is_dir = self
+            .client
+            .walk(self.root_fid, fid, &names)?
+            .map_or(true, |qid| qid.is_dir());
+        Ok(Arc::new(NinePNode {
+            fs: self.clone(),
+            path: names.join("/"),
+            fid,
+            is_dir,
+            io: Mutex::new(None),
+            dir_pos: Mutex::new((0, 0)),
+        }))
+    }
+
+    /// Calls `f` with the fid of the directory that contains the last
+    /// component of `path` relative to `base`, and the name of it.
+    fn with_parent<F, T>(self: &Arc<Self>, base: &str, path: &str, f: F) -> VfsResult<T>
+    where
+        F: FnOnce(u32, &str) -> VfsResult<T>,
+    {
+        let mut names = resolve(base, path).map_err(|_| VfsError::InvalidInput)?;
+        let name = names.pop().ok_or(VfsError::InvalidInput)?;
+        let dir = self.node(&names.join("/"))?;
+        if !dir.is_dir {
+            return Err(VfsError::NotADirectory);
+        }
+        f(dir.fid, name)
+    }
+}
+
+/// Resolves `path` relative to `base` (a path from the root), returns the
+/// names from the root, or the rest of `path` if it goes up out of the root.
+fn resolve<'a>(base: &'a str, path: &'a str) -> Result<Vec<&'a str>, &'a str> {
+    let mut names = base
+        .split('/')
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    let mut rest = path;
+    loop {
+        let (name, next) = match rest.split_once('/') {
+            Some((name, next)) => (name, Some(next)),
+            None => (rest, None),
+        };
+        match name {
+            "" | "." => {}
+            ".." => {
+                if names.pop().is_none() {
+                    return Err(next.unwrap_or(""));
+                }
+            }
+            _ => names.push(name),
+        }
+        match next {
+            Some(next) => rest = next,
+            None => return Ok(names),
+        }
+    }
+}
+
+impl Drop for FsInner {
+    fn drop(&mut self) {
+        self.client.clunk(self.root_fid).ok();
+    }
+}
+
+/// The node (file, directory or symbolic link) of a 9P filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`]. Each node holds a fid walked from
+/// the root, and another one opened for I/O when it is read or written, both
+/// are clunked when the node is dropped.
+pub struct NinePNode {
+    fs: Arc<FsInner>,
+    /// Path from the root, without leading and trailing `/`.
+    path: String,
+    fid: u32,
+    is_dir: bool,
+    /// The opened fid and its open flags.
+    io: Mutex<Option<(u32, u32)>>,
+    /// Index and offset of the next entry of `read_dir`, so that reading
+    /// entries sequentially does not start from the beginning each time.
+    dir_pos: Mutex<(usize, u64)>,
+}
+
+impl NinePNode {
+    /// Returns the path of the node from the root of the filesystem.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn client(&self) -> &NinePClient {
+        &self.fs.client
+    }
+
+    /// Calls `f` with the fid of the directory that contains the last
+    /// component of `path` and the name of it.
+    fn with_parent<F, T>(&self, path: &str, f: F) -> VfsResult<T>
+    where
+        F: FnOnce(u32, &str) -> VfsResult<T>,
+    {
+        self.fs.with_parent(&self.path, path, f)
+    }
+
+    /// Returns a fid opened for reading or writing, the opened fid is reused
+    /// if it has the required access mode.
+    fn io_fid(&self, write: bool) -> VfsResult<u32> {
+        let mut io = self.io.lock();
+        if let Some((fid, flags)) = *io {
+            let mode = flags & (O_WRONLY | O_RDWR);
+            if mode == O_RDWR || (mode == O_WRONLY) == write {
+                return Ok(fid);
+            }
+        }
+        // try read-write first to avoid opening again
+        let candidates: &[u32] = match (self.is_dir, write) {
+            (true, false) => &[O_RDONLY | O_DIRECTORY],
+            (true, true) => return Err(VfsError::IsADirectory),
+            (false, false) => &[O_RDWR, O_RDONLY],
+            (false, true) => &[O_RDWR, O_WRONLY],
+        };
+        let client = self.client();
+        let mut err = VfsError::PermissionDenied;
+        for &flags in candidates {
+            let fid = client.alloc_fid()?;
+            client.walk(self.fid, fid, &[])?;
+            match client.lopen(fid, flags) {
+                Ok(_) => {
+                    if let Some((old, _)) = io.replace((fid, flags)) {
+                        client.clunk(old).ok();
+                    }
+                    return Ok(fid);
+                }
+                Err(e) => {
+                    client.clunk(fid).ok();
+                    err = e;
+                    if e != VfsError::PermissionDenied {
+                        break;
+                    }
+                }
+            }
+        }
+        Err(err)
+    }
+}
+
+impl Drop for NinePNode {
+    fn drop(&mut self) {
+        if let Some((fid, _)) = self.io.get_mut().take() {
+            self.fs.client.clunk(fid).ok();
+        }
+        self.fs.client.clunk(self.fid).ok();
+    }
+}
+
+/// Converts the file type in the mode (`S_IFMT` bits shifted right by 12) or
+/// in directory entries (`DT_*`) to [`VfsNodeType`].
+fn node_type(ty: u8) -> VfsNodeType {
+    match ty {
+        0o1 => VfsNodeType::Fifo,
+        0o2 => VfsNodeType::CharDevice,
+        0o4 => VfsNodeType::Dir,
+        0o6 => VfsNodeType::BlockDevice,
+        0o12 => VfsNodeType::SymLink,
+        0o14 => VfsNodeType::Socket,
+        _ => VfsNodeType::File,
+    }
+}
+
+/// Returns the last component of `path`.
+fn last_name(path: &str) -> &str {
+    let path = path.trim_end_matches('/');
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl VfsNodeOps for NinePNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let st = self.client().getattr(self.fid)?;
+        let ty = node_type((st.mode >> 12) as u8 & 0o17);
+        let perm = VfsNodePerm::from_bits_truncate(st.mode as u16);
+        Ok(VfsNodeAttr::new(perm, ty, st.size, st.blocks)
+            .with_ino(st.qid.path)
+            .with_nlink(st.nlink as u32)
+            .with_owner(st.uid, st.gid)
+            .with_times(st.atime, st.mtime, st.ctime))
+    }
+
+    fn set_attr(&self, changes: VfsSetAttr) -> VfsResult {
+        let mut attr = SetAttr::default();
+        if let Some(perm) = changes.perm() {
+            attr.valid |= SETATTR_MODE;
+            attr.mode = perm.bits() as u32;
+        }
+        if let Some(uid) = changes.uid() {
+            attr.valid |= SETATTR_UID;
+            attr.uid = uid;
+        }
+        if let Some(gid) = changes.gid() {
+            attr.valid |= SETATTR_GID;
+            attr.gid = gid;
+        }
+        if let Some(atime) = changes.atime() {
+            attr.valid |= SETATTR_ATIME | SETATTR_ATIME_SET;
+            attr.atime = atime;
+        }
+        if let Some(mtime) = changes.mtime() {
+            attr.valid |= SETATTR_MTIME | SETATTR_MTIME_SET;
+            attr.mtime = mtime;
+        }
+        if attr.valid == 0 {
+            return Ok(());
+        }
+        self.client().setattr(self.fid, &attr)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if self.is_dir {
+            return Err(VfsError::IsADirectory);
+        }
+        let fid = self.io_fid(false)?;
+        self.client().read(fid, offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let fid = self.io_fid(true)?;
+        self.client().write(fid, offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        match *self.io.lock() {
+            Some((fid, _)) => self.client().fsync(fid),
+            None => Ok(()),
+        }
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        if self.is_dir {
+            return Err(VfsError::IsADirectory);
+        }
+        let attr = SetAttr {
+            valid: SETATTR_SIZE,
+            size,
+            ..Default::default()
+        };
+        self.client().setattr(self.fid, &attr)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.path.is_empty() {
+            return self.fs.mount_parent.read().clone();
+        }
+        let parent = self.path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        self.fs.node(parent).ok().map(|node| node as VfsNodeRef)
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let names = match resolve(&self.path, path) {
+            Ok(names) => names.join("/"),
+            Err(rest) => {
+                // go on from the parent of the mount point
+                let parent = self.fs.mount_parent.read().clone();
+                return parent.ok_or(VfsError::NotFound)?.lookup(rest);
+            }
+        };
+        let node = if names == self.path {
+            self.clone()
+        } else {
+            self.fs.node(&names)?
+        };
+        if path.ends_with('/') && !node.is_dir {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok(node)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let fid = self.io_fid(false)?;
+        let client = self.client();
+        let mut pos = self.dir_pos.lock();
+        if pos.0 != start_idx {
+            *pos = (0, 0);
+        }
+        let mut count = 0;
+        'outer: while count < dirents.len() {
+            let entries = client.readdir(fid, pos.1)?;
+            if entries.is_empty() {
+                break;
+            }
+            for ent in entries {
+                if count == dirents.len() {
+                    break 'outer;
+                }
+                pos.1 = ent.offset;
+                if ent.name.len() > MAX_NAME_LEN {
+                    log::warn!("9p: skip the entry with a long name: {}", ent.name);
+                    continue;
+                }
+                pos.0 += 1;
+                if pos.0 > start_idx {
+                    dirents[count] =
+                        VfsDirEntry::new(&ent.name, node_type(ent.ty)).with_ino(ent.qid.path);
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        log::debug!("create {:?} at 9p: {}", ty, path);
+        if matches!(last_name(path), "" | ".") {
+            return Ok(());
+        }
+        let client = self.client();
+        self.with_parent(path, |dfid, name| match ty {
+            VfsNodeType::File => {
+                // the created file is opened on the new fid
+                let fid = client.alloc_fid()?;
+                client.walk(dfid, fid, &[])?;
+                let res = client.lcreate(fid, name, O_RDWR | O_CREAT | O_EXCL, 0o666);
+                client.clunk(fid).ok();
+                res.map(|_| ())
+            }
+            VfsNodeType::Dir => client.mkdir(dfid, name, 0o755).map(|_| ()),
+            _ => Err(VfsError::Unsupported),
+        })
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        log::debug!("remove at 9p: {}", path);
+        if matches!(last_name(path), "" | "." | "..") {
+            return Err(VfsError::InvalidInput);
+        }
+        let client = self.client();
+        self.with_parent(path, |dfid, name| {
+            let fid = client.alloc_fid()?;
+            let qid = client.walk(dfid, fid, &[name])?;
+            client.clunk(fid).ok();
+            let flags = match qid {
+                Some(qid) if qid.is_dir() => AT_REMOVEDIR,
+                _ => 0,
+            };
+            client.unlinkat(dfid, name, flags)
+        })
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        log::debug!("rename at 9p: {} -> {}", src_path, dst_path);
+        // absolute paths are relative to the mount point
+        let (dst_base, dst_path) = match dst_path.strip_prefix('/') {
+            Some(path) => {
+                let mount_path = self.fs.mount_path.read();
+                let path = path
+                    .strip_prefix(mount_path.trim_start_matches('/'))
+                    .unwrap_or(path);
+                ("", String::from(path))
+            }
+            None => (self.path.as_str(), String::from(dst_path)),
+        };
+        if matches!(last_name(&dst_path), "" | "." | "..") {
+            return Err(VfsError::InvalidInput);
+        }
+        let client = self.client();
+        self.with_parent(src_path, |src_dfid, src_name| {
+            self.fs
+                .with_parent(dst_base, &dst_path, |dst_dfid, dst_name| {
+                    client.renameat(src_dfid, src_name, dst_dfid, dst_name)
+                })
+        })
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at 9p: {} -> {}", path, target);
+        let client = self.client();
+        self.with_parent(path, |dfid, name| {
+            client.symlink(dfid, name, target).map(|_| ())
+        })
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        log::debug!("link at 9p: {}", path);
+        // fids are only valid in the same session
+        let node = node
+            .as_any()
+            .downcast_ref::<Self>()
+            .filter(|node| Arc::ptr_eq(&node.fs.client, &self.fs.client))
+            .ok_or(VfsError::CrossesDevices)?;
+        let client = self.client();
+        self.with_parent(path, |dfid, name| client.link(dfid, node.fid, name))
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let target = self.client().readlink(self.fid)?;
+        let len = target.len().min(buf.len());
+        buf[..len].copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
@@ -0,0 +1,264 @@
+//! Messages of the 9P2000.L protocol.
+//!
+//! Each message starts with a header of `size[4] type[1] tag[2]`, followed by
+//! the fields of its type. Integers are little-endian, and strings are
+//! prefixed by their 2-byte length.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use axfs_vfs::{VfsError, VfsResult};
+use core::time::Duration;
+
+/// Length of the message header.
+pub const HEADER_LEN: usize = 7;
+/// Length of the header of `Tread`/`Rread` and `Twrite`, which is excluded
+/// from the maximum message size to get the maximum count of data.
+pub const IOHDRSZ: u32 = 24;
+
+pub const VERSION: &str = "9P2000.L";
+pub const NOTAG: u16 = !0;
+pub const NOFID: u32 = !0;
+/// Maximum number of names in a `Twalk`.
+pub const MAXWELEM: usize = 16;
+
+pub const RLERROR: u8 = 7;
+pub const TLOPEN: u8 = 12;
+pub const TLCREATE: u8 = 14;
+pub const TSYMLINK: u8 = 16;
+pub const TREADLINK: u8 = 22;
+pub const TGETATTR: u8 = 24;
+pub const TSETATTR: u8 = 26;
+pub const TREADDIR: u8 = 40;
+pub const TFSYNC: u8 = 50;
+pub const TLINK: u8 = 70;
+pub const TMKDIR: u8 = 72;
+pub const TRENAMEAT: u8 = 74;
+pub const TUNLINKAT: u8 = 76;
+pub const TVERSION: u8 = 100;
+pub const TATTACH: u8 = 104;
+pub const TWALK: u8 = 110;
+pub const TREAD: u8 = 116;
+pub const TWRITE: u8 = 118;
+pub const TCLUNK: u8 = 120;
+
+/// The qid type of directories.
+pub const QTDIR: u8 = 0x80;
+
+/// Flags of `Tlopen` and `Tlcreate`, the same as Linux `open` flags.
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_RDWR: u32 = 2;
+pub const O_CREAT: u32 = 0o100;
+pub const O_EXCL: u32 = 0o200;
+pub const O_DIRECTORY: u32 = 0o200000;
+
+/// The flag of `Tunlinkat` to remove a directory.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+/// Request mode, uid, gid, nlink, rdev, times, inode number, size and
+/// blocks in `Tgetattr`.
+pub const GETATTR_BASIC: u64 = 0x7ff;
+
+pub const SETATTR_MODE: u32 = 0x1;
+pub const SETATTR_UID: u32 = 0x2;
+pub const SETATTR_GID: u32 = 0x4;
+pub const SETATTR_SIZE: u32 = 0x8;
+pub const SETATTR_ATIME: u32 = 0x10;
+pub const SETATTR_MTIME: u32 = 0x20;
+/// Set the times to the given values, instead of the current time of the
+/// server.
+pub const SETATTR_ATIME_SET: u32 = 0x80;
+pub const SETATTR_MTIME_SET: u32 = 0x100;
+
+/// The unique identification of a file on the server, of which the version
+/// is not used.
+#[derive(Debug, Clone, Copy)]
+pub struct Qid {
+    pub ty: u8,
+    /// Unique among the files of the server, usually the inode number.
+    pub path: u64,
+}
+
+impl Qid {
+    pub const fn is_dir(&self) -> bool {
+        self.ty & QTDIR != 0
+    }
+}
+
+/// Attributes returned by `Rgetattr`.
+#[derive(Debug, Clone, Copy)]
+pub struct Stat {
+    pub qid: Qid,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u64,
+    pub size: u64,
+    pub blocks: u64,
+    pub atime: Duration,
+    pub mtime: Duration,
+    pub ctime: Duration,
+}
+
+/// Attributes to be changed by `Tsetattr`, fields not in `valid` are
+/// ignored.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SetAttr {
+    pub valid: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub atime: Duration,
+    pub mtime: Duration,
+}
+
+/// An entry returned by `Rreaddir`.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub qid: Qid,
+    /// Offset of the next entry, to be passed to the next `Treaddir`.
+    pub offset: u64,
+    pub ty: u8,
+    pub name: String,
+}
+
+/// A message being encoded.
+pub struct Message {
+    buf: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(ty: u8, tag: u16) -> Self {
+        let mut msg = Self {
+            buf: Vec::with_capacity(64),
+        };
+        msg.u32(0).u8(ty).u16(tag);
+        msg
+    }
+
+    /// The type of the message.
+    pub fn ty(&self) -> u8 {
+        self.buf[4]
+    }
+
+    pub fn u8(&mut self, v: u8) -> &mut Self {
+        self.buf.push(v);
+        self
+    }
+
+    pub fn u16(&mut self, v: u16) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u32(&mut self, v: u32) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, v: u64) -> &mut Self {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    pub fn str(&mut self, s: &str) -> &mut Self {
+        self.u16(s.len() as u16);
+        self.bytes(s.as_bytes())
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(data);
+        self
+    }
+
+    pub fn time(&mut self, t: Duration) -> &mut Self {
+        self.u64(t.as_secs()).u64(t.subsec_nanos() as u64)
+    }
+
+    /// Fills the size in the header, and returns the encoded message.
+    pub fn finish(&mut self) -> &[u8] {
+        let size = self.buf.len() as u32;
+        self.buf[..4].copy_from_slice(&size.to_le_bytes());
+        &self.buf
+    }
+}
+
+/// A message being decoded.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub const fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> VfsResult<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(VfsError::InvalidData);
+        }
+        let (data, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(data)
+    }
+
+    pub fn u8(&mut self) -> VfsResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> VfsResult<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> VfsResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> VfsResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn str(&mut self) -> VfsResult<&'a str> {
+        let len = self.u16()? as usize;
+        core::str::from_utf8(self.bytes(len)?).map_err(|_| VfsError::InvalidData)
+    }
+
+    pub fn qid(&mut self) -> VfsResult<Qid> {
+        let ty = self.u8()?;
+        self.bytes(4)?; // version[4]
+        let path = self.u64()?;
+        Ok(Qid { ty, path })
+    }
+
+    pub fn time(&mut self) -> VfsResult<Duration> {
+        let secs = self.u64()?;
+        let nanos = self.u64()?;
+        Ok(Duration::new(secs, nanos as u32))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+/// Converts the Linux error number in `Rlerror` to [`VfsError`].
+pub fn errno_to_error(errno: u32) -> VfsError {
+    match errno {
+        1 | 13 | 30 => VfsError::PermissionDenied, // EPERM, EACCES, EROFS
+        2 => VfsError::NotFound,                   // ENOENT
+        11 => VfsError::WouldBlock,                // EAGAIN
+        12 => VfsError::NoMemory,                  // ENOMEM
+        16 => VfsError::ResourceBusy,              // EBUSY
+        17 => VfsError::AlreadyExists,             // EEXIST
+        18 => VfsError::CrossesDevices,            // EXDEV
+        20 => VfsError::NotADirectory,             // ENOTDIR
+        21 => VfsError::IsADirectory,              // EISDIR
+        22 | 36 => VfsError::InvalidInput,         // EINVAL, ENAMETOOLONG
+        28 | 122 => VfsError::StorageFull,         // ENOSPC, EDQUOT
+        38 | 95 => VfsError::Unsupported,          // ENOSYS, EOPNOTSUPP
+        39 => VfsError::DirectoryNotEmpty,         // ENOTEMPTY
+        40 => VfsError::FilesystemLoop,            // ELOOP
+        _ => VfsError::Io,
+    }
+}
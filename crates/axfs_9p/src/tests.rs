@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::{FileExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult, VfsSetAttr};
+
+use crate::proto::*;
+use crate::*;
+
+const TVERSION_MSIZE: u32 = 4096;
+
+struct Fid {
+    path: PathBuf,
+    file: Option<File>,
+    /// Names and types of entries of an opened directory.
+    entries: Vec<(String, u8)>,
+}
+
+/// A 9P2000.L server that exports a host directory.
+struct Server {
+    root: PathBuf,
+    fids: HashMap<u32, Fid>,
+}
+
+fn qid(path: &Path) -> io::Result<Qid> {
+    let meta = fs::symlink_metadata(path)?;
+    let ty = if meta.is_dir() {
+        QTDIR
+    } else if meta.file_type().is_symlink() {
+        0x02
+    } else {
+        0
+    };
+    Ok(Qid {
+        ty,
+        path: meta.ino(),
+    })
+}
+
+fn put_qid(msg: &mut Message, qid: Qid) {
+    msg.u8(qid.ty).u32(0).u64(qid.path);
+}
+
+impl Server {
+    fn fid(&mut self, fid: u32) -> io::Result<&mut Fid> {
+        self.fids
+            .get_mut(&fid)
+            .ok_or_else(|| io::Error::from_raw_os_error(9)) // EBADF
+    }
+
+    fn path(&mut self, fid: u32) -> io::Result<PathBuf> {
+        Ok(self.fid(fid)?.path.clone())
+    }
+
+    fn add_fid(&mut self, fid: u32, path: PathBuf) {
+        let fid_state = Fid {
+            path,
+            file: None,
+            entries: Vec::new(),
+        };
+        self.fids.insert(fid, fid_state);
+    }
+
+    fn open(&mut self, fid: u32, flags: u32) -> io::Result<()> {
+        let path = self.path(fid)?;
+        if fs::metadata(&path)?.is_dir() {
+            let mut entries = vec![(".".into(), 4), ("..".into(), 4)];
+            for ent in fs::read_dir(&path)? {
+                let ent = ent?;
+                let ty = ent.file_type()?;
+                let ty = if ty.is_dir() {
+                    4
+                } else if ty.is_symlink() {
+                    10
+                } else {
+                    8
+                };
+                entries.push((ent.file_name().into_string().unwrap(), ty));
+            }
+            entries.sort();
+            self.fid(fid)?.entries = entries;
+        } else {
+            let file = OpenOptions::new()
+                .read(flags & 3 != O_WRONLY)
+                .write(flags & 3 != O_RDONLY)
+                .open(&path)?;
+            self.fid(fid)?.file = Some(file);
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, ty: u8, r: &mut Reader, resp: &mut Message) -> io::Result<()> {
+        match ty {
+            TVERSION => {
+                let msize = r.u32().unwrap();
+                resp.u32(msize.min(TVERSION_MSIZE)).str(r.str().unwrap());
+            }
+            TATTACH => {
+                let fid = r.u32().unwrap();
+                let root = self.root.clone();
+                put_qid(resp, qid(&root)?);
+                self.add_fid(fid, root);
+            }
+            TWALK => {
+                let (fid, newfid) = (r.u32().unwrap(), r.u32().unwrap());
+                let nwname = r.u16().unwrap();
+                let mut path = self.path(fid)?;
+                let mut qids = Vec::new();
+                for i in 0..nwname {
+                    let name = r.str().unwrap();
+                    let next = if name == ".." {
+                        match path.parent() {
+                            Some(parent) if path != self.root => parent.to_path_buf(),
+                            _ => path.clone(),
+                        }
+                    } else {
+                        path.join(name)
+                    };
+                    match qid(&next) {
+                        Ok(qid) => qids.push(qid),
+                        Err(e) if i == 0 => return Err(e),
+                        Err(_) => break,
+                    }
+                    path = next;
+                }
+                resp.u16(qids.len() as u16);
+                for &qid in &qids {
+                    put_qid(resp, qid);
+                }
+                if qids.len() == nwname as usize {
+                    self.add_fid(newfid, path);
+                }
+            }
+            TCLUNK => {
+                let fid = r.u32().unwrap();
+                self.fids.remove(&fid);
+            }
+            TLOPEN => {
+                let (fid, flags) = (r.u32().unwrap(), r.u32().unwrap());
+                self.open(fid, flags)?;
+                put_qid(resp, qid(&self.path(fid)?)?);
+                resp.u32(0);
+            }
+            TLCREATE => {
+                let fid = r.u32().unwrap();
+                let name = r.str().unwrap();
+                let (flags, mode) = (r.u32().unwrap(), r.u32().unwrap());
+                let path = self.path(fid)?.join(name);
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(flags & O_CREAT != 0)
+                    .create_new(flags & O_EXCL != 0)
+                    .truncate(false)
+                    .open(&path)?;
+                file.set_permissions(fs::Permissions::from_mode(mode))?;
+                put_qid(resp, qid(&path)?);
+                resp.u32(0);
+                let state = self.fid(fid)?;
+                state.path = path;
+                state.file = Some(file);
+            }
+            TMKDIR => {
+                let dir = self.path(r.u32().unwrap())?.join(r.str().unwrap());
+                fs::create_dir(&dir)?;
+                put_qid(resp, qid(&dir)?);
+            }
+            TSYMLINK => {
+                let link = self.path(r.u32().unwrap())?.join(r.str().unwrap());
+                std::os::unix::fs::symlink(r.str().unwrap(), &link)?;
+                put_qid(resp, qid(&link)?);
+            }
+            TLINK => {
+                let dir = self.path(r.u32().unwrap())?;
+                let original = self.path(r.u32().unwrap())?;
+                if original.is_dir() {
+                    return Err(io::Error::from_raw_os_error(1)); // EPERM
+                }
+                fs::hard_link(original, dir.join(r.str().unwrap()))?;
+            }
+            TREADLINK => {
+                let target = fs::read_link(self.path(r.u32().unwrap())?)?;
+                resp.str(target.to_str().unwrap());
+            }
+            TGETATTR => {
+                let path = self.path(r.u32().unwrap())?;
+                let meta = fs::symlink_metadata(&path)?;
+                resp.u64(GETATTR_BASIC);
+                put_qid(resp, qid(&path)?);
+                resp.u32(meta.mode()).u32(meta.uid()).u32(meta.gid());
+                resp.u64(meta.nlink()).u64(meta.rdev()).u64(meta.size());
+                resp.u64(meta.blksize()).u64(meta.blocks());
+                resp.u64(meta.atime() as u64).u64(meta.atime_nsec() as u64);
+                resp.u64(meta.mtime() as u64).u64(meta.mtime_nsec() as u64);
+                resp.u64(meta.ctime() as u64).u64(meta.ctime_nsec() as u64);
+                resp.u64(0).u64(0).u64(0).u64(0);
+            }
+            TSETATTR => {
+                let path = self.path(r.u32().unwrap())?;
+                let valid = r.u32().unwrap();
+                let mode = r.u32().unwrap();
+                let (_uid, _gid) = (r.u32().unwrap(), r.u32().unwrap());
+                let size = r.u64().unwrap();
+                if valid & SETATTR_MODE != 0 {
+                    fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+                }
+                if valid & SETATTR_SIZE != 0 {
+                    OpenOptions::new().write(true).open(&path)?.set_len(size)?;
+                }
+            }
+            TREADDIR => {
+                let fid = r.u32().unwrap();
+                let offset = r.u64().unwrap() as usize;
+                let count = r.u32().unwrap() as usize;
+                let mut data = Message::new(0, 0);
+                let mut len = 0;
+                let entries = &self.fid(fid)?.entries;
+                for (i, (name, ty)) in entries.iter().enumerate().skip(offset) {
+                    let ent_len = 13 + 8 + 1 + 2 + name.len();
+                    if len + ent_len > count {
+                        break;
+                    }
+                    put_qid(&mut data, Qid { ty: 0, path: 0 });
+                    data.u64(i as u64 + 1).u8(*ty).str(name);
+                    len += ent_len;
+                }
+                let data = &data.finish()[HEADER_LEN..];
+                resp.u32(data.len() as u32).bytes(data);
+            }
+            TFSYNC => {}
+            TRENAMEAT => {
+                let old = self.path(r.u32().unwrap())?.join(r.str().unwrap());
+                let new = self.path(r.u32().unwrap())?.join(r.str().unwrap());
+                fs::rename(old, new)?;
+            }
+            TUNLINKAT => {
+                let path = self.path(r.u32().unwrap())?.join(r.str().unwrap());
+                if r.u32().unwrap() & AT_REMOVEDIR != 0 {
+                    fs::remove_dir(path)?;
+                } else if path.is_dir() {
+                    return Err(io::Error::from_raw_os_error(21)); // EISDIR
+                } else {
+                    fs::remove_file(path)?;
+                }
+            }
+            TREAD => {
+                let fid = r.u32().unwrap();
+                let offset = r.u64().unwrap();
+                let mut buf = vec![0; r.u32().unwrap() as usize];
+                let file = self.fid(fid)?.file.as_ref().unwrap();
+                let n = file.read_at(&mut buf, offset)?;
+                resp.u32(n as u32).bytes(&buf[..n]);
+            }
+            TWRITE => {
+                let fid = r.u32().unwrap();
+                let offset = r.u64().unwrap();
+                let count = r.u32().unwrap() as usize;
+                let data = r.bytes(count).unwrap();
+                let file = self.fid(fid)?.file.as_ref().unwrap();
+                resp.u32(file.write_at(data, offset)? as u32);
+            }
+            _ => return Err(io::Error::from_raw_os_error(95)), // EOPNOTSUPP
+        }
+        Ok(())
+    }
+}
+
+struct TestTransport(Mutex<Server>);
+
+impl Transport for TestTransport {
+    fn request(&self, req: &[u8], resp: &mut [u8]) -> VfsResult<usize> {
+        let mut r = Reader::new(req);
+        let size = r.u32().unwrap() as usize;
+        assert_eq!(size, req.len());
+        let ty = r.u8().unwrap();
+        let tag = r.u16().unwrap();
+
+        let mut msg = Message::new(ty + 1, tag);
+        if let Err(e) = self.0.lock().unwrap().handle(ty, &mut r, &mut msg) {
+            msg = Message::new(RLERROR, tag);
+            msg.u32(e.raw_os_error().unwrap_or(5) as u32);
+        }
+        let msg = msg.finish();
+        assert!(msg.len() <= TVERSION_MSIZE as usize);
+        resp[..msg.len()].copy_from_slice(msg);
+        Ok(msg.len())
+    }
+}
+
+/// Creates an empty host directory, and a filesystem that exports it.
+fn new_fs(name: &str) -> (NinePFileSystem, PathBuf) {
+    let root = std::env::temp_dir().join(format!("axfs_9p_{}_{}", name, std::process::id()));
+    fs::remove_dir_all(&root).ok();
+    fs::create_dir_all(&root).unwrap();
+    let server = Server {
+        root: root.clone(),
+        fids: HashMap::new(),
+    };
+    let client = NinePClient::new(TestTransport(Mutex::new(server))).unwrap();
+    assert_eq!(client.msize(), TVERSION_MSIZE);
+    let fs = NinePFileSystem::new(&Arc::new(client), "").unwrap();
+    (fs, root)
+}
+
+fn read_all(root: &VfsNodeRef, path: &str) -> VfsResult<Vec<u8>> {
+    let node = root.clone().lookup(path)?;
+    let mut buf = vec![0; node.get_attr()?.size() as usize + 1];
+    let n = node.read_at(0, &mut buf)?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+fn entries(root: &VfsNodeRef, path: &str) -> Vec<String> {
+    let dir = root.clone().lookup(path).unwrap();
+    let mut buf: [VfsDirEntry; 3] = core::array::from_fn(|_| VfsDirEntry::default());
+    let mut names = Vec::new();
+    let mut start_idx = 0;
+    loop {
+        let n = dir.read_dir(start_idx, &mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        for ent in &buf[..n] {
+            names.push(String::from_utf8(ent.name_as_bytes().to_vec()).unwrap());
+        }
+        start_idx += n;
+    }
+    names
+}
+
+#[test]
+fn test_read_write() {
+    let (fs, host) = new_fs("rw");
+    let root = fs.root_dir();
+    assert!(root.get_attr().unwrap().is_dir());
+
+    root.create("short.txt", VfsNodeType::File).unwrap();
+    let file = root.clone().lookup("short.txt").unwrap();
+    assert_eq!(file.write_at(0, b"Rust is cool!\n").unwrap(), 14);
+    assert_eq!(
+        fs::read(host.join("short.txt")).unwrap(),
+        b"Rust is cool!\n"
+    );
+    assert_eq!(
+        root.create("short.txt", VfsNodeType::File).err(),
+        Some(VfsError::AlreadyExists)
+    );
+
+    // larger than the maximum message size
+    let long = (0..20000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    root.create("long.bin", VfsNodeType::File).unwrap();
+    let file = root.clone().lookup("long.bin").unwrap();
+    assert_eq!(file.write_at(0, &long).unwrap(), long.len());
+    assert_eq!(file.get_attr().unwrap().size(), long.len() as u64);
+    assert_eq!(read_all(&root, "long.bin").unwrap(), long);
+    let mut buf = [0; 10];
+    assert_eq!(file.read_at(19995, &mut buf).unwrap(), 5);
+    assert_eq!(buf[..5], long[19995..]);
+
+    file.truncate(100).unwrap();
+    assert_eq!(fs::metadata(host.join("long.bin")).unwrap().len(), 100);
+    let changes = VfsSetAttr::new().with_perm(VfsNodePerm::from_bits_truncate(0o640));
+    file.set_attr(changes).unwrap();
+    assert_eq!(file.get_attr().unwrap().perm().mode(), 0o640);
+
+    // files created on the host
+    fs::write(host.join("host.txt"), "from host\n").unwrap();
+    assert_eq!(read_all(&root, "host.txt").unwrap(), b"from host\n");
+    assert_eq!(
+        root.clone().lookup("nothing").err(),
+        Some(VfsError::NotFound)
+    );
+    assert_eq!(
+        root.clone().lookup("host.txt/").err(),
+        Some(VfsError::NotADirectory)
+    );
+    assert_eq!(
+        entries(&root, ""),
+        [".", "..", "host.txt", "long.bin", "short.txt"]
+    );
+
+    fs::remove_dir_all(host).unwrap();
+}
+
+#[test]
+fn test_dirs() {
+    let (fs, host) = new_fs("dirs");
+    let root = fs.root_dir();
+
+    root.create("a", VfsNodeType::Dir).unwrap();
+    root.create("a/b", VfsNodeType::Dir).unwrap();
+    root.create("a/b/c.txt", VfsNodeType::File).unwrap();
+    root.create("a/.", VfsNodeType::Dir).unwrap();
+    assert!(host.join("a/b/c.txt").is_file());
+    assert_eq!(entries(&root, "a/b"), [".", "..", "c.txt"]);
+
+    // `..` and the parent directories
+    let b = root.clone().lookup("a/./b/").unwrap();
+    assert!(b.clone().lookup("../b/c.txt").is_ok());
+    let a = b.parent().unwrap();
+    let a = a.as_any().downcast_ref::<NinePNode>().unwrap();
+    assert_eq!(a.path(), "a");
+    assert!(root.parent().is_none());
+    assert_eq!(root.clone().lookup("..").err(), Some(VfsError::NotFound));
+
+    // rename with relative and absolute paths
+    b.rename("c.txt", "../c.txt").unwrap();
+    assert!(host.join("a/c.txt").is_file());
+    root.rename("a/c.txt", "/d.txt").unwrap();
+    assert!(host.join("d.txt").is_file());
+    root.rename("a/b", "e").unwrap();
+    assert!(host.join("e").is_dir());
+
+    assert_eq!(root.remove("a/.").err(), Some(VfsError::InvalidInput));
+    assert_eq!(root.remove("nothing").err(), Some(VfsError::NotFound));
+    root.create("e/f", VfsNodeType::File).unwrap();
+    assert_eq!(root.remove("e").err(), Some(VfsError::DirectoryNotEmpty));
+    root.remove("e/f").unwrap();
+    root.remove("e").unwrap();
+    root.remove("d.txt").unwrap();
+    assert_eq!(entries(&root, "/"), [".", "..", "a"]);
+
+    fs::remove_dir_all(host).unwrap();
+}
+
+#[test]
+fn test_fids() {
+    let (fs, host) = new_fs("fids");
+    let root = fs.root_dir();
+    fs::write(host.join("file.txt"), "Rust is cool!\n").unwrap();
+
+    // fids of dropped nodes and failed walks are reused
+    for _ in 0..100 {
+        assert_eq!(read_all(&root, "file.txt").unwrap(), b"Rust is cool!\n");
+        assert_eq!(
+            root.clone().lookup("none/file.txt").err(),
+            Some(VfsError::NotFound)
+        );
+    }
+    let client = &fs.inner.client;
+    let fid = client.alloc_fid().unwrap();
+    assert!(fid < 4, "fid {} is not reused", fid);
+    client.free_fid(fid);
+
+    drop(root);
+    fs::remove_dir_all(host).unwrap();
+}
+
+#[test]
+fn test_links() {
+    let (fs, host) = new_fs("links");
+    let root = fs.root_dir();
+
+    fs::write(host.join("file.txt"), "Rust is cool!\n").unwrap();
+    root.symlink("rel", "file.txt").unwrap();
+    let link = root.clone().lookup("rel").unwrap();
+    assert!(link.get_attr().unwrap().is_symlink());
+    let mut buf = [0; 32];
+    assert_eq!(link.readlink(&mut buf).unwrap(), 8);
+    assert_eq!(&buf[..8], b"file.txt");
+    assert_eq!(
+        fs::read_link(host.join("rel")).unwrap(),
+        Path::new("file.txt")
+    );
+
+    let file = root.clone().lookup("file.txt").unwrap();
+    assert_eq!(file.readlink(&mut buf).err(), Some(VfsError::InvalidInput));
+    root.link("hard", &file).unwrap();
+    let attr = file.get_attr().unwrap();
+    assert_eq!(attr.nlink(), 2);
+    assert_eq!(attr.ino(), fs::metadata(host.join("hard")).unwrap().ino());
+    root.remove("file.txt").unwrap();
+    assert_eq!(read_all(&root, "hard").unwrap(), b"Rust is cool!\n");
+    assert_eq!(
+        root.link("dir", &root).err(),
+        Some(VfsError::PermissionDenied)
+    );
+
+    // nodes of another session
+    let (other, other_host) = new_fs("links_other");
+    assert_eq!(
+        other.root_dir().link("hard", &file).err(),
+        Some(VfsError::CrossesDevices)
+    );
+
+    fs::remove_dir_all(host).unwrap();
+    fs::remove_dir_all(other_host).unwrap();
+}
@@ -1,21 +1,43 @@
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::{Arc, Weak};
-use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{alloc_ino, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
 use axfs_vfs::{VfsError, VfsResult};
 use spin::RwLock;
 
+/// An entry of a device directory.
+enum Child {
+    /// A subdirectory created by [`DirNode::mkdir`], kept as a [`DirNode`] so
+    /// that devices registered later (e.g. `input/event1`) are added to the
+    /// same directory.
+    Dir(Arc<DirNode>),
+    /// A device node added by [`DirNode::add`].
+    Node(VfsNodeRef),
+}
+
+impl Child {
+    fn node(&self) -> VfsNodeRef {
+        match self {
+            Self::Dir(dir) => dir.clone(),
+            Self::Node(node) => node.clone(),
+        }
+    }
+}
+
 /// The directory node in the device filesystem.
 ///
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct DirNode {
+    ino: u64,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
-    children: RwLock<BTreeMap<&'static str, VfsNodeRef>>,
+    children: RwLock<BTreeMap<String, Child>>,
 }
 
 impl DirNode {
     pub(super) fn new(parent: Option<&VfsNodeRef>) -> Arc<Self> {
         let parent = parent.map_or(Weak::<Self>::new() as _, Arc::downgrade);
         Arc::new(Self {
+            ino: alloc_ino(),
             parent: RwLock::new(parent),
             children: RwLock::new(BTreeMap::new()),
         })
@@ -25,23 +47,29 @@ impl DirNode {
         *self.parent.write() = parent.map_or(Weak::<Self>::new() as _, Arc::downgrade);
     }
 
-    /// Create a subdirectory at this directory.
-    pub fn mkdir(self: &Arc<Self>, name: &'static str) -> Arc<Self> {
+    /// Create a subdirectory at this directory, or returns the existing one.
+    pub fn mkdir(self: &Arc<Self>, name: &str) -> Arc<Self> {
+        if let Some(Child::Dir(dir)) = self.children.read().get(name) {
+            return dir.clone();
+        }
         let parent = self.clone() as VfsNodeRef;
         let node = Self::new(Some(&parent));
-        self.children.write().insert(name, node.clone());
+        self.children
+            .write()
+            .insert(name.into(), Child::Dir(node.clone()));
         node
     }
 
-    /// Add a node to this directory.
-    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
-        self.children.write().insert(name, node);
+    /// Add a node to this directory, replacing the existing one with the same
+    /// name.
+    pub fn add(&self, name: &str, node: VfsNodeRef) {
+        self.children.write().insert(name.into(), Child::Node(node));
     }
 }
 
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(4096, 0))
+        Ok(VfsNodeAttr::new_dir(4096, 0).with_ino(self.ino))
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -57,7 +85,7 @@ impl VfsNodeOps for DirNode {
                 .children
                 .read()
                 .get(name)
-                .cloned()
+                .map(Child::node)
                 .ok_or(VfsError::NotFound),
         }?;
 
@@ -76,8 +104,9 @@ impl VfsNodeOps for DirNode {
                 0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
                 1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
                 _ => {
-                    if let Some((name, node)) = children.next() {
-                        *ent = VfsDirEntry::new(name, node.get_attr().unwrap().file_type());
+                    if let Some((name, child)) = children.next() {
+                        let attr = child.node().get_attr().unwrap();
+                        *ent = VfsDirEntry::new(name, attr.file_type()).with_ino(attr.ino());
                     } else {
                         return Ok(i);
                     }
@@ -99,6 +128,7 @@ impl VfsNodeOps for DirNode {
                     .read()
                     .get(name)
                     .ok_or(VfsError::NotFound)?
+                    .node()
                     .create(rest, ty),
             }
         } else if name.is_empty() || name == "." || name == ".." {
@@ -120,6 +150,7 @@ impl VfsNodeOps for DirNode {
                     .read()
                     .get(name)
                     .ok_or(VfsError::NotFound)?
+                    .node()
                     .remove(rest),
             }
         } else {
@@ -18,7 +18,7 @@ pub use self::null::NullDev;
 pub use self::zero::ZeroDev;
 
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use axfs_vfs::{FileSystemInfo, VfsNodeRef, VfsOps, VfsResult};
 use spin::once::Once;
 
 /// A device filesystem that implements [`axfs_vfs::VfsOps`].
@@ -36,15 +36,22 @@ impl DeviceFileSystem {
         }
     }
 
-    /// Create a subdirectory at the root directory.
-    pub fn mkdir(&self, name: &'static str) -> Arc<DirNode> {
+    /// Returns the root directory node in [`Arc<DirNode>`](DirNode).
+    pub fn root_dir_node(&self) -> Arc<DirNode> {
+        self.root.clone()
+    }
+
+    /// Create a subdirectory at the root directory, or returns the existing
+    /// one.
+    pub fn mkdir(&self, name: &str) -> Arc<DirNode> {
         self.root.mkdir(name)
     }
 
     /// Add a node to the root directory.
     ///
     /// The node must implement [`axfs_vfs::VfsNodeOps`], and be wrapped in [`Arc`].
-    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
+    /// An existing node with the same name is replaced.
+    pub fn add(&self, name: &str, node: VfsNodeRef) {
         self.root.add(name, node);
     }
 }
@@ -59,6 +66,11 @@ impl VfsOps for DeviceFileSystem {
         Ok(())
     }
 
+    /// Device nodes do not occupy any space, so all numbers are zero.
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        Ok(FileSystemInfo::new(4096, 0, 0))
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
@@ -112,4 +112,17 @@ fn test_devfs() {
 
     test_devfs_ops(&devfs).unwrap();
     test_get_parent(&devfs).unwrap();
+
+    // nodes are added to existing directories, and names can be generated
+    for i in 0..2 {
+        let name = format!("f{}", i + 3);
+        devfs
+            .mkdir("foo")
+            .mkdir("bar")
+            .add(&name, Arc::new(ZeroDev));
+    }
+    let bar = devfs.root_dir().lookup("foo/bar").unwrap();
+    assert!(bar.clone().lookup("f1").is_ok());
+    assert!(bar.clone().lookup("f3").is_ok());
+    assert!(bar.lookup("f4").is_ok());
 }
@@ -1,4 +1,5 @@
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axfs_vfs::{alloc_ino, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use spin::Once;
 
 /// A zero device behaves like `/dev/zero`.
 ///
@@ -7,12 +8,10 @@ pub struct ZeroDev;
 
 impl VfsNodeOps for ZeroDev {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new(
-            VfsNodePerm::default_file(),
-            VfsNodeType::CharDevice,
-            0,
-            0,
-        ))
+        // all instances are the same device
+        static INO: Once<u64> = Once::new();
+        let attr = VfsNodeAttr::new(VfsNodePerm::default_file(), VfsNodeType::CharDevice, 0, 0);
+        Ok(attr.with_ino(*INO.call_once(alloc_ino)))
     }
 
     fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
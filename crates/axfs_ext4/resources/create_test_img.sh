@@ -0,0 +1,28 @@
+#!/bin/bash
+
+CUR_DIR=`dirname $0`
+
+create_ext_img() {
+	local name=$1
+	local fsType=$2
+	local blkcount=$3
+	rm -rf root && mkdir -p root
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"root/long.txt"
+	done
+	echo "Rust is cool!" >>"root/short.txt"
+	mkdir -p "root/very/long/path"
+	echo "Rust is cool!" >>"root/very/long/path/test.txt"
+	mkdir -p "root/very-long-dir-name"
+	echo "Rust is cool!" >>"root/very-long-dir-name/very-long-file-name.txt"
+	ln -s "very/long/path/test.txt" "root/link.txt"
+
+	rm -f "$name"
+	E2FSPROGS_FAKE_TIME=1700000000 mke2fs -q -t $fsType -b 1024 -O ^has_journal -L "Test!" \
+	  -U 12345678-1234-1234-1234-123456789abc -E hash_seed=12345678-1234-1234-1234-123456789abc,root_owner=0:0 \
+	  -d root "$name" $blkcount
+	rm -rf root
+}
+
+create_ext_img "$CUR_DIR/ext2.img" ext2 2048
+create_ext_img "$CUR_DIR/ext4.img" ext4 2048
@@ -0,0 +1,251 @@
+//! Operations on directory entries, directories are stored as linear lists.
+//!
+//! Hashed directories (`dir_index`) are compatible with linear lists, the
+//! index is dropped when the directory is modified.
+
+use alloc::{string::String, vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::fs::{FsInner, Meta};
+use crate::layout::*;
+
+/// Maximum length of a file name.
+const NAME_MAX: usize = 255;
+
+pub(crate) struct DirEntry {
+    pub ino: u32,
+    pub name: String,
+    /// `FT_UNKNOWN` if the filesystem does not store file types in
+    /// directory entries.
+    pub file_type: u8,
+}
+
+/// A raw directory entry in a block.
+struct RawEntry {
+    ino: u32,
+    rec_len: usize,
+    name_len: usize,
+}
+
+impl FsInner {
+    fn parse_entry(&self, block: &[u8], offset: usize) -> VfsResult<RawEntry> {
+        if offset + DIRENT_HEADER_SIZE > block.len() {
+            return Err(VfsError::InvalidData);
+        }
+        let entry = RawEntry {
+            ino: read_u32(block, offset),
+            rec_len: read_u16(block, offset + 4) as usize,
+            name_len: if self.has_filetype {
+                block[offset + 6] as usize
+            } else {
+                read_u16(block, offset + 6) as usize
+            },
+        };
+        if entry.rec_len < DIRENT_HEADER_SIZE
+            || offset + entry.rec_len > block.len()
+            || DIRENT_HEADER_SIZE + entry.name_len > entry.rec_len
+        {
+            return Err(VfsError::InvalidData);
+        }
+        Ok(entry)
+    }
+
+    fn write_entry(
+        &self,
+        block: &mut [u8],
+        offset: usize,
+        ino: u32,
+        rec_len: usize,
+        name: &str,
+        ft: u8,
+    ) {
+        write_u32(block, offset, ino);
+        write_u16(block, offset + 4, rec_len as u16);
+        if self.has_filetype {
+            block[offset + 6] = name.len() as u8;
+            block[offset + 7] = ft;
+        } else {
+            write_u16(block, offset + 6, name.len() as u16);
+        }
+        block[offset + DIRENT_HEADER_SIZE..][..name.len()].copy_from_slice(name.as_bytes());
+    }
+
+    /// Calls `f` with each block of the directory and its physical block
+    /// number, until `f` returns `Some`.
+    fn walk_dir<T, F>(&self, m: &mut Meta, dir: &mut Inode, mut f: F) -> VfsResult<Option<T>>
+    where
+        F: FnMut(&mut [u8], u64) -> VfsResult<Option<T>>,
+    {
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let mut block = vec![0; self.block_size];
+        for lblk in 0..dir.size() / self.block_size as u64 {
+            let phys = self.map_block(m, dir, lblk, false)?;
+            if phys == 0 {
+                continue;
+            }
+            self.read_block(phys, &mut block)?;
+            if let Some(res) = f(&mut block, phys)? {
+                return Ok(Some(res));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads all entries of the directory, including `.` and `..`.
+    pub fn dir_entries(&self, m: &mut Meta, dir: &mut Inode) -> VfsResult<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        self.walk_dir(m, dir, |block, _| {
+            let mut offset = 0;
+            while offset < block.len() {
+                let raw = self.parse_entry(block, offset)?;
+                if raw.ino != 0 {
+                    let name = &block[offset + DIRENT_HEADER_SIZE..][..raw.name_len];
+                    entries.push(DirEntry {
+                        ino: raw.ino,
+                        name: String::from_utf8_lossy(name).into(),
+                        file_type: if self.has_filetype {
+                            block[offset + 7]
+                        } else {
+                            FT_UNKNOWN
+                        },
+                    });
+                }
+                offset += raw.rec_len;
+            }
+            Ok(None::<()>)
+        })?;
+        Ok(entries)
+    }
+
+    /// Finds the entry with the given name, returns its inode number.
+    pub fn find_entry(&self, m: &mut Meta, dir: &mut Inode, name: &str) -> VfsResult<Option<u32>> {
+        self.walk_dir(m, dir, |block, _| {
+            let mut offset = 0;
+            while offset < block.len() {
+                let raw = self.parse_entry(block, offset)?;
+                if raw.ino != 0
+                    && &block[offset + DIRENT_HEADER_SIZE..][..raw.name_len] == name.as_bytes()
+                {
+                    return Ok(Some(raw.ino));
+                }
+                offset += raw.rec_len;
+            }
+            Ok(None)
+        })
+    }
+
+    /// Adds an entry to the directory, the name must not exist. The directory
+    /// inode is written back to disk.
+    pub fn add_entry(
+        &self,
+        m: &mut Meta,
+        dir: &mut Inode,
+        name: &str,
+        ino: u32,
+        ft: u8,
+    ) -> VfsResult {
+        if name.len() > NAME_MAX {
+            return Err(VfsError::InvalidInput);
+        }
+        dir.set_flags(dir.flags() & !FLAG_INDEX);
+        let need = dirent_len(name.len());
+        let added = self.walk_dir(m, dir, |block, phys| {
+            let mut offset = 0;
+            while offset < block.len() {
+                let raw = self.parse_entry(block, offset)?;
+                let used = if raw.ino == 0 {
+                    0
+                } else {
+                    dirent_len(raw.name_len)
+                };
+                if raw.rec_len - used >= need {
+                    if used != 0 {
+                        write_u16(block, offset + 4, used as u16);
+                    }
+                    self.write_entry(block, offset + used, ino, raw.rec_len - used, name, ft);
+                    self.write_block(phys, block)?;
+                    return Ok(Some(()));
+                }
+                offset += raw.rec_len;
+            }
+            Ok(None)
+        })?;
+        if added.is_some() {
+            return self.write_inode(dir);
+        }
+
+        // no space in existing blocks, append a new one
+        let mut block = vec![0; self.block_size];
+        self.write_entry(&mut block, 0, ino, self.block_size, name, ft);
+        let size = dir.size();
+        self.write_data(m, dir, size, &block)?;
+        Ok(())
+    }
+
+    /// Removes the entry with the given name, returns its inode number. The
+    /// directory inode is written back to disk.
+    pub fn remove_entry(&self, m: &mut Meta, dir: &mut Inode, name: &str) -> VfsResult<u32> {
+        dir.set_flags(dir.flags() & !FLAG_INDEX);
+        let removed = self.walk_dir(m, dir, |block, phys| {
+            let mut offset = 0;
+            let mut prev = None;
+            while offset < block.len() {
+                let raw = self.parse_entry(block, offset)?;
+                if raw.ino != 0
+                    && &block[offset + DIRENT_HEADER_SIZE..][..raw.name_len] == name.as_bytes()
+                {
+                    match prev {
+                        // merge into the previous entry
+                        Some(prev) => {
+                            let prev_len = read_u16(block, prev + 4) as usize;
+                            write_u16(block, prev + 4, (prev_len + raw.rec_len) as u16);
+                        }
+                        None => write_u32(block, offset, 0),
+                    }
+                    self.write_block(phys, block)?;
+                    return Ok(Some(raw.ino));
+                }
+                prev = Some(offset);
+                offset += raw.rec_len;
+            }
+            Ok(None)
+        })?;
+        let ino = removed.ok_or(VfsError::NotFound)?;
+        self.write_inode(dir)?;
+        Ok(ino)
+    }
+
+    /// Points the `..` entry of the directory to `parent`.
+    pub fn set_parent_entry(&self, m: &mut Meta, dir: &mut Inode, parent: u32) -> VfsResult {
+        let phys = self.map_block(m, dir, 0, false)?;
+        let mut block = vec![0; self.block_size];
+        self.read_block(phys, &mut block)?;
+        let dot = self.parse_entry(&block, 0)?;
+        let dotdot = self.parse_entry(&block, dot.rec_len)?;
+        if &block[dot.rec_len + DIRENT_HEADER_SIZE..][..dotdot.name_len] != b".." {
+            return Err(VfsError::InvalidData);
+        }
+        write_u32(&mut block, dot.rec_len, parent);
+        self.write_block(phys, &block)
+    }
+
+    /// Writes the `.` and `..` entries to a new directory. The directory inode
+    /// is written back to disk.
+    pub fn init_dir(&self, m: &mut Meta, dir: &mut Inode, parent: u32) -> VfsResult {
+        let mut block = vec![0; self.block_size];
+        let dot_len = dirent_len(1);
+        self.write_entry(&mut block, 0, dir.ino, dot_len, ".", FT_DIR);
+        self.write_entry(
+            &mut block,
+            dot_len,
+            parent,
+            self.block_size - dot_len,
+            "..",
+            FT_DIR,
+        );
+        self.write_data(m, dir, 0, &block)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,720 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+
+use axfs_vfs::{VfsError, VfsNodeRef, VfsResult};
+use spin::{Mutex, MutexGuard, RwLock};
+
+use crate::layout::*;
+use crate::node::Ext4Node;
+use crate::BlockDevice;
+
+/// Shared state of a mounted filesystem.
+pub(crate) struct FsInner {
+    dev: Box<dyn BlockDevice>,
+    pub block_size: usize,
+    pub read_only: bool,
+    pub has_filetype: bool,
+    inode_size: usize,
+    inodes_per_group: u32,
+    blocks_per_group: u32,
+    first_data_block: u32,
+    pub first_ino: u32,
+    desc_size: usize,
+    groups_count: u32,
+    inodes_count: u32,
+    /// The first block of the inode table of each group.
+    inode_tables: Vec<u64>,
+    /// Superblock and group descriptors, the lock is held during all
+    /// operations on the filesystem.
+    meta: Mutex<Meta>,
+    /// Nodes in use, an inode is freed when it is unlinked and its node is
+    /// dropped.
+    nodes: Mutex<BTreeMap<u32, Weak<Ext4Node>>>,
+    /// Parent of the mount point, i.e. `..` of the root directory.
+    pub mount_parent: RwLock<Option<VfsNodeRef>>,
+    /// Path of the mount point, to resolve absolute paths in `rename`.
+    pub mount_path: RwLock<String>,
+}
+
+pub(crate) struct Meta {
+    sb: [u8; SUPERBLOCK_SIZE],
+    descs: Vec<u8>,
+}
+
+impl FsInner {
+    pub fn new(dev: Box<dyn BlockDevice>) -> VfsResult<Self> {
+        let mut sb = [0; SUPERBLOCK_SIZE];
+        dev.read_at(SUPERBLOCK_OFFSET, &mut sb)?;
+        if read_u16(&sb, SB_MAGIC) != EXT_MAGIC {
+            return Err(VfsError::InvalidData);
+        }
+
+        let incompat = read_u32(&sb, SB_FEATURE_INCOMPAT);
+        let ro_compat = read_u32(&sb, SB_FEATURE_RO_COMPAT);
+        if incompat & !INCOMPAT_READ != 0 {
+            log::warn!(
+                "unsupported ext4 features: {:#x}",
+                incompat & !INCOMPAT_READ
+            );
+            return Err(VfsError::Unsupported);
+        }
+        if incompat & INCOMPAT_RECOVER != 0 {
+            log::warn!("ext4 journal needs recovery, mount read-only");
+        }
+        let read_only = incompat & !INCOMPAT_WRITE != 0 || ro_compat & !RO_COMPAT_WRITE != 0;
+
+        let log_block_size = read_u32(&sb, SB_LOG_BLOCK_SIZE);
+        if log_block_size > 6 {
+            return Err(VfsError::InvalidData);
+        }
+        let block_size = 1024 << log_block_size;
+        let (inode_size, first_ino) = if read_u32(&sb, SB_REV_LEVEL) == 0 {
+            (128, 11)
+        } else {
+            (
+                read_u16(&sb, SB_INODE_SIZE) as usize,
+                read_u32(&sb, SB_FIRST_INO),
+            )
+        };
+        let desc_size = if incompat & INCOMPAT_64BIT != 0 {
+            read_u16(&sb, SB_DESC_SIZE) as usize
+        } else {
+            32
+        };
+        let blocks_per_group = read_u32(&sb, SB_BLOCKS_PER_GROUP);
+        let inodes_per_group = read_u32(&sb, SB_INODES_PER_GROUP);
+        if inode_size < 128
+            || desc_size < 32
+            || desc_size > block_size
+            || blocks_per_group == 0
+            || inodes_per_group == 0
+        {
+            return Err(VfsError::InvalidData);
+        }
+
+        let first_data_block = read_u32(&sb, SB_FIRST_DATA_BLOCK);
+        let blocks_count = read_u32(&sb, SB_BLOCKS_COUNT_LO) as u64
+            | if incompat & INCOMPAT_64BIT != 0 {
+                (read_u32(&sb, SB_BLOCKS_COUNT_HI) as u64) << 32
+            } else {
+                0
+            };
+        // the group descriptors are sized from the superblock, which must not
+        // describe more than the device
+        if blocks_count > dev.size() / block_size as u64 {
+            return Err(VfsError::InvalidData);
+        }
+        let groups_count = blocks_count
+            .checked_sub(first_data_block as u64)
+            .ok_or(VfsError::InvalidData)?
+            .div_ceil(blocks_per_group as u64);
+        let descs_pos = (first_data_block as u64 + 1) * block_size as u64;
+        let descs_len = (groups_count * desc_size as u64).next_multiple_of(block_size as u64);
+        if descs_pos + descs_len > dev.size() {
+            return Err(VfsError::InvalidData);
+        }
+        let mut descs = vec![0; descs_len as usize];
+        dev.read_at(descs_pos, &mut descs)?;
+
+        log::debug!(
+            "ext4: block size {}, {} groups, read-only: {}",
+            block_size,
+            groups_count,
+            read_only
+        );
+        let mut this = Self {
+            dev,
+            block_size,
+            read_only,
+            has_filetype: incompat & INCOMPAT_FILETYPE != 0,
+            inode_size,
+            inodes_per_group,
+            blocks_per_group,
+            first_data_block,
+            first_ino,
+            desc_size,
+            groups_count: groups_count as u32,
+            inodes_count: read_u32(&sb, SB_INODES_COUNT),
+            inode_tables: Vec::new(),
+            meta: Mutex::new(Meta { sb, descs }),
+            nodes: Mutex::new(BTreeMap::new()),
+            mount_parent: RwLock::new(None),
+            mount_path: RwLock::new(String::new()),
+        };
+        let inode_tables = {
+            let m = this.lock();
+            (0..this.groups_count)
+                .map(|group| this.desc_block(&m, group, BG_INODE_TABLE_LO, BG_INODE_TABLE_HI))
+                .collect()
+        };
+        this.inode_tables = inode_tables;
+        Ok(this)
+    }
+
+    pub fn lock(&self) -> MutexGuard<Meta> {
+        self.meta.lock()
+    }
+
+    pub fn flush(&self) -> VfsResult {
+        self.dev.flush()
+    }
+
+    /// Returns the node of the inode, creates it if it is not in use.
+    pub fn node(self: &Arc<Self>, ino: u32) -> Arc<Ext4Node> {
+        let mut nodes = self.nodes.lock();
+        if let Some(node) = nodes.get(&ino).and_then(Weak::upgrade) {
+            return node;
+        }
+        let node = Arc::new(Ext4Node::new(self.clone(), ino));
+        nodes.insert(ino, Arc::downgrade(&node));
+        node
+    }
+
+    /// Whether the inode has a node in use.
+    pub fn in_use(&self, ino: u32) -> bool {
+        self.nodes
+            .lock()
+            .get(&ino)
+            .is_some_and(|node| node.strong_count() > 0)
+    }
+
+    /// Called when the node of the inode is dropped, frees the inode if it has
+    /// been unlinked.
+    pub fn release(&self, ino: u32) -> VfsResult {
+        {
+            let mut nodes = self.nodes.lock();
+            match nodes.get(&ino) {
+                Some(node) if node.strong_count() == 0 => nodes.remove(&ino),
+                _ => return Ok(()), // still in use by a new node
+            };
+        }
+        if self.read_only {
+            return Ok(());
+        }
+        let mut m = self.lock();
+        let inode = self.read_inode(ino)?;
+        if inode.links_count() == 0 && inode.mode() != 0 {
+            self.free_inode(&mut m, inode)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_block(&self, block: u64, buf: &mut [u8]) -> VfsResult {
+        self.dev.read_at(block * self.block_size as u64, buf)
+    }
+
+    pub fn write_block(&self, block: u64, buf: &[u8]) -> VfsResult {
+        self.dev.write_at(block * self.block_size as u64, buf)
+    }
+
+    fn desc<'a>(&self, m: &'a Meta, group: u32) -> &'a [u8] {
+        let start = group as usize * self.desc_size;
+        &m.descs[start..start + self.desc_size]
+    }
+
+    fn desc_mut<'a>(&self, m: &'a mut Meta, group: u32) -> &'a mut [u8] {
+        let start = group as usize * self.desc_size;
+        &mut m.descs[start..start + self.desc_size]
+    }
+
+    /// Reads a block number field of the group descriptor.
+    fn desc_block(&self, m: &Meta, group: u32, lo: usize, hi: usize) -> u64 {
+        let desc = self.desc(m, group);
+        let mut block = read_u32(desc, lo) as u64;
+        if self.desc_size >= 64 {
+            block |= (read_u32(desc, hi) as u64) << 32;
+        }
+        block
+    }
+
+    /// Adds `delta` to a 16-bit counter of the group descriptor (only the low
+    /// part is used for writable filesystems).
+    fn desc_add(&self, m: &mut Meta, group: u32, field: usize, delta: i32) {
+        let desc = self.desc_mut(m, group);
+        let value = read_u16(desc, field) as i32 + delta;
+        write_u16(desc, field, value as u16);
+    }
+
+    fn sb_add(&self, m: &mut Meta, field: usize, delta: i32) {
+        let value = read_u32(&m.sb, field) as i64 + delta as i64;
+        write_u32(&mut m.sb, field, value as u32);
+    }
+
+    /// Writes the superblock and the descriptor of `group` back to disk.
+    fn write_meta(&self, m: &Meta, group: u32) -> VfsResult {
+        self.dev.write_at(SUPERBLOCK_OFFSET, &m.sb)?;
+        let block_idx = group as usize * self.desc_size / self.block_size;
+        let start = block_idx * self.block_size;
+        self.write_block(
+            self.first_data_block as u64 + 1 + block_idx as u64,
+            &m.descs[start..start + self.block_size],
+        )
+    }
+
+    fn inode_pos(&self, ino: u32) -> VfsResult<u64> {
+        if ino == 0 || ino > self.inodes_count {
+            return Err(VfsError::InvalidData);
+        }
+        let table = self.inode_tables[((ino - 1) / self.inodes_per_group) as usize];
+        let index = (ino - 1) % self.inodes_per_group;
+        Ok(table * self.block_size as u64 + index as u64 * self.inode_size as u64)
+    }
+
+    pub fn read_inode(&self, ino: u32) -> VfsResult<Inode> {
+        let mut raw = vec![0; self.inode_size];
+        self.dev.read_at(self.inode_pos(ino)?, &mut raw)?;
+        Ok(Inode { ino, raw })
+    }
+
+    pub fn write_inode(&self, inode: &Inode) -> VfsResult {
+        self.dev.write_at(self.inode_pos(inode.ino)?, &inode.raw)
+    }
+
+    /// Finds and sets a clear bit in the bitmap block, starting from `start`.
+    fn alloc_bit(&self, bitmap_block: u64, start: usize, count: usize) -> VfsResult<Option<usize>> {
+        let mut bitmap = vec![0; self.block_size];
+        self.read_block(bitmap_block, &mut bitmap)?;
+        let found = (start..count)
+            .chain(0..start)
+            .find(|&i| bitmap[i / 8] & (1 << (i % 8)) == 0);
+        if let Some(i) = found {
+            bitmap[i / 8] |= 1 << (i % 8);
+            self.write_block(bitmap_block, &bitmap)?;
+        }
+        Ok(found)
+    }
+
+    fn clear_bit(&self, bitmap_block: u64, idx: usize) -> VfsResult {
+        let mut bitmap = vec![0; self.block_size];
+        self.read_block(bitmap_block, &mut bitmap)?;
+        if bitmap[idx / 8] & (1 << (idx % 8)) == 0 {
+            log::warn!("ext4: freeing a free object in bitmap {}", bitmap_block);
+            return Err(VfsError::InvalidData);
+        }
+        bitmap[idx / 8] &= !(1 << (idx % 8));
+        self.write_block(bitmap_block, &bitmap)
+    }
+
+    fn group_blocks(&self, m: &Meta, group: u32) -> usize {
+        let blocks_count = read_u32(&m.sb, SB_BLOCKS_COUNT_LO);
+        let start = self.first_data_block + group * self.blocks_per_group;
+        (blocks_count - start).min(self.blocks_per_group) as usize
+    }
+
+    /// Allocates a zeroed block, preferably in the group of `goal`.
+    pub fn alloc_block(&self, m: &mut Meta, goal: u64) -> VfsResult<u64> {
+        if read_u32(&m.sb, SB_FREE_BLOCKS_COUNT_LO) == 0 {
+            return Err(VfsError::StorageFull);
+        }
+        let goal = goal.saturating_sub(self.first_data_block as u64);
+        let goal_group = (goal / self.blocks_per_group as u64) as u32 % self.groups_count;
+        for i in 0..self.groups_count {
+            let group = (goal_group + i) % self.groups_count;
+            if read_u16(self.desc(m, group), BG_FREE_BLOCKS_COUNT_LO) == 0 {
+                continue;
+            }
+            let bitmap = self.desc_block(m, group, BG_BLOCK_BITMAP_LO, BG_BLOCK_BITMAP_HI);
+            let start = if i == 0 {
+                (goal % self.blocks_per_group as u64) as usize
+            } else {
+                0
+            };
+            let count = self.group_blocks(m, group);
+            if let Some(idx) = self.alloc_bit(bitmap, start.min(count - 1), count)? {
+                self.desc_add(m, group, BG_FREE_BLOCKS_COUNT_LO, -1);
+                self.sb_add(m, SB_FREE_BLOCKS_COUNT_LO, -1);
+                self.write_meta(m, group)?;
+                let block = self.first_data_block as u64
+                    + group as u64 * self.blocks_per_group as u64
+                    + idx as u64;
+                self.write_block(block, &vec![0; self.block_size])?;
+                return Ok(block);
+            }
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    pub fn free_block(&self, m: &mut Meta, block: u64) -> VfsResult {
+        let rel = block
+            .checked_sub(self.first_data_block as u64)
+            .ok_or(VfsError::InvalidData)?;
+        let group = (rel / self.blocks_per_group as u64) as u32;
+        if group >= self.groups_count {
+            return Err(VfsError::InvalidData);
+        }
+        let bitmap = self.desc_block(m, group, BG_BLOCK_BITMAP_LO, BG_BLOCK_BITMAP_HI);
+        self.clear_bit(bitmap, (rel % self.blocks_per_group as u64) as usize)?;
+        self.desc_add(m, group, BG_FREE_BLOCKS_COUNT_LO, 1);
+        self.sb_add(m, SB_FREE_BLOCKS_COUNT_LO, 1);
+        self.write_meta(m, group)
+    }
+
+    /// Allocates an inode with the given mode, preferably in the group of
+    /// `goal`. The inode is not written to disk.
+    pub fn alloc_inode(&self, m: &mut Meta, mode: u16, goal: u32) -> VfsResult<Inode> {
+        if read_u32(&m.sb, SB_FREE_INODES_COUNT) == 0 {
+            return Err(VfsError::StorageFull);
+        }
+        let goal_group = (goal.max(1) - 1) / self.inodes_per_group;
+        for i in 0..self.groups_count {
+            let group = (goal_group + i) % self.groups_count;
+            if read_u16(self.desc(m, group), BG_FREE_INODES_COUNT_LO) == 0 {
+                continue;
+            }
+            let bitmap = self.desc_block(m, group, BG_INODE_BITMAP_LO, BG_INODE_BITMAP_HI);
+            let Some(idx) = self.alloc_bit(bitmap, 0, self.inodes_per_group as usize)? else {
+                continue;
+            };
+            let ino = group * self.inodes_per_group + idx as u32 + 1;
+            if ino < self.first_ino {
+                log::warn!("ext4: reserved inode {} is not marked in use", ino);
+                continue;
+            }
+            self.desc_add(m, group, BG_FREE_INODES_COUNT_LO, -1);
+            if mode & S_IFMT == S_IFDIR {
+                self.desc_add(m, group, BG_USED_DIRS_COUNT_LO, 1);
+            }
+            self.sb_add(m, SB_FREE_INODES_COUNT, -1);
+            self.write_meta(m, group)?;
+
+            let mut inode = Inode {
+                ino,
+                raw: vec![0; self.inode_size],
+            };
+            inode.set_mode(mode);
+            return Ok(inode);
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    /// Frees all blocks of the inode and the inode itself.
+    pub fn free_inode(&self, m: &mut Meta, mut inode: Inode) -> VfsResult {
+        if !self.is_fast_symlink(&inode) {
+            self.truncate_blocks(m, &mut inode, 0)?;
+        }
+        let ino = inode.ino;
+        let group = (ino - 1) / self.inodes_per_group;
+        let bitmap = self.desc_block(m, group, BG_INODE_BITMAP_LO, BG_INODE_BITMAP_HI);
+        self.clear_bit(bitmap, ((ino - 1) % self.inodes_per_group) as usize)?;
+        self.desc_add(m, group, BG_FREE_INODES_COUNT_LO, 1);
+        if inode.is_dir() {
+            self.desc_add(m, group, BG_USED_DIRS_COUNT_LO, -1);
+        }
+        self.sb_add(m, SB_FREE_INODES_COUNT, 1);
+        self.write_meta(m, group)?;
+
+        inode.raw.fill(0);
+        self.write_inode(&inode)
+    }
+
+    /// Number of 512-byte sectors in a block.
+    fn block_sectors(&self) -> u64 {
+        self.block_size as u64 / 512
+    }
+
+    pub fn is_fast_symlink(&self, inode: &Inode) -> bool {
+        let acl_sectors = if inode.file_acl() != 0 {
+            self.block_sectors()
+        } else {
+            0
+        };
+        inode.is_symlink()
+            && inode.flags() & (FLAG_EXTENTS | FLAG_INLINE_DATA) == 0
+            && inode.blocks() == acl_sectors
+    }
+
+    /// Maps the logical block of the inode to the physical block, returns 0
+    /// for holes.
+    ///
+    /// Missing blocks are allocated if `alloc` is true, which is not
+    /// supported for inodes using extents.
+    pub fn map_block(
+        &self,
+        m: &mut Meta,
+        inode: &mut Inode,
+        lblk: u64,
+        alloc: bool,
+    ) -> VfsResult<u64> {
+        if inode.flags() & FLAG_EXTENTS != 0 {
+            if alloc {
+                return Err(VfsError::PermissionDenied);
+            }
+            return self.map_extent(inode, lblk);
+        }
+        if inode.flags() & FLAG_INLINE_DATA != 0 {
+            return Err(VfsError::Unsupported);
+        }
+
+        // the indices of the pointer at each level
+        let per_block = (self.block_size / 4) as u64;
+        let mut path = [0usize; 4];
+        let depth = if lblk < N_DIRECT as u64 {
+            path[0] = lblk as usize;
+            1
+        } else if lblk < N_DIRECT as u64 + per_block {
+            path[0] = N_DIRECT;
+            path[1] = (lblk - N_DIRECT as u64) as usize;
+            2
+        } else if lblk < N_DIRECT as u64 + per_block + per_block * per_block {
+            let rel = lblk - N_DIRECT as u64 - per_block;
+            path[0] = N_DIRECT + 1;
+            path[1] = (rel / per_block) as usize;
+            path[2] = (rel % per_block) as usize;
+            3
+        } else {
+            let rel = lblk - N_DIRECT as u64 - per_block - per_block * per_block;
+            if rel >= per_block * per_block * per_block {
+                return Err(VfsError::InvalidInput); // too large
+            }
+            path[0] = N_DIRECT + 2;
+            path[1] = (rel / per_block / per_block) as usize;
+            path[2] = (rel / per_block % per_block) as usize;
+            path[3] = (rel % per_block) as usize;
+            4
+        };
+
+        let mut block = inode.block(path[0]) as u64;
+        if block == 0 {
+            if !alloc {
+                return Ok(0);
+            }
+            let goal = (1..=path[0])
+                .rev()
+                .map(|i| inode.block(i - 1))
+                .find(|&b| b != 0);
+            block = self.alloc_block(m, goal.unwrap_or(0) as u64)?;
+            inode.set_block(path[0], block as u32);
+            inode.set_blocks(inode.blocks() + self.block_sectors());
+        }
+        let mut buf = vec![0; self.block_size];
+        for &idx in &path[1..depth] {
+            self.read_block(block, &mut buf)?;
+            let next = read_u32(&buf, idx * 4) as u64;
+            if next != 0 {
+                block = next;
+                continue;
+            }
+            if !alloc {
+                return Ok(0);
+            }
+            let next = self.alloc_block(m, block)?;
+            write_u32(&mut buf, idx * 4, next as u32);
+            self.write_block(block, &buf)?;
+            inode.set_blocks(inode.blocks() + self.block_sectors());
+            block = next;
+        }
+        Ok(block)
+    }
+
+    fn map_extent(&self, inode: &Inode, lblk: u64) -> VfsResult<u64> {
+        let mut node = inode.i_block().to_vec();
+        let mut buf = vec![0; self.block_size];
+        loop {
+            if read_u16(&node, 0) != EXTENT_MAGIC {
+                return Err(VfsError::InvalidData);
+            }
+            let entries = read_u16(&node, 2) as usize;
+            let depth = read_u16(&node, 6);
+            if EXTENT_HEADER_SIZE + entries * EXTENT_ENTRY_SIZE > node.len() {
+                return Err(VfsError::InvalidData);
+            }
+            let entry_at = |i: usize| &node[EXTENT_HEADER_SIZE + i * EXTENT_ENTRY_SIZE..];
+            // the last entry that starts at or before `lblk`
+            let Some(i) = (0..entries)
+                .rev()
+                .find(|&i| read_u32(entry_at(i), 0) as u64 <= lblk)
+            else {
+                return Ok(0);
+            };
+            let entry = entry_at(i);
+            if depth == 0 {
+                let start = read_u32(entry, 0) as u64;
+                let mut len = read_u16(entry, 4);
+                let uninit = len > EXTENT_INIT_MAX_LEN;
+                if uninit {
+                    len -= EXTENT_INIT_MAX_LEN;
+                }
+                if lblk >= start + len as u64 || uninit {
+                    return Ok(0);
+                }
+                let phys = (read_u16(entry, 6) as u64) << 32 | read_u32(entry, 8) as u64;
+                return Ok(phys + lblk - start);
+            }
+            let child = (read_u16(entry, 8) as u64) << 32 | read_u32(entry, 4) as u64;
+            self.read_block(child, &mut buf)?;
+            node.clear();
+            node.extend_from_slice(&buf);
+        }
+    }
+
+    /// Reads the data of the inode, returns the number of bytes read.
+    pub fn read_data(
+        &self,
+        m: &mut Meta,
+        inode: &mut Inode,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> VfsResult<usize> {
+        let size = inode.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let bs = self.block_size as u64;
+        let mut block_buf = vec![0; self.block_size];
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done as u64;
+            let in_block = (pos % bs) as usize;
+            let count = (self.block_size - in_block).min(len - done);
+            let block = self.map_block(m, inode, pos / bs, false)?;
+            let dst = &mut buf[done..done + count];
+            if block == 0 {
+                dst.fill(0); // hole
+            } else if count == self.block_size {
+                self.read_block(block, dst)?;
+            } else {
+                self.read_block(block, &mut block_buf)?;
+                dst.copy_from_slice(&block_buf[in_block..in_block + count]);
+            }
+            done += count;
+        }
+        Ok(len)
+    }
+
+    /// Writes data to the inode and extends its size if needed. The inode is
+    /// written back to disk.
+    pub fn write_data(
+        &self,
+        m: &mut Meta,
+        inode: &mut Inode,
+        offset: u64,
+        buf: &[u8],
+    ) -> VfsResult<usize> {
+        let bs = self.block_size as u64;
+        let mut block_buf = vec![0; self.block_size];
+        let mut done = 0;
+        let res = loop {
+            if done == buf.len() {
+                break Ok(());
+            }
+            let pos = offset + done as u64;
+            let in_block = (pos % bs) as usize;
+            let count = (self.block_size - in_block).min(buf.len() - done);
+            let block = match self.map_block(m, inode, pos / bs, true) {
+                Ok(block) => block,
+                Err(e) => break Err(e),
+            };
+            let src = &buf[done..done + count];
+            let res = if count == self.block_size {
+                self.write_block(block, src)
+            } else {
+                self.read_block(block, &mut block_buf).and_then(|_| {
+                    block_buf[in_block..in_block + count].copy_from_slice(src);
+                    self.write_block(block, &block_buf)
+                })
+            };
+            if let Err(e) = res {
+                break Err(e);
+            }
+            done += count;
+        };
+        let end = offset + done as u64;
+        if end > inode.size() {
+            inode.set_size(end);
+        }
+        // blocks may have been allocated even if failed
+        self.write_inode(inode)?;
+        match res {
+            Err(e) if done == 0 => Err(e),
+            _ => Ok(done),
+        }
+    }
+
+    /// Changes the size of the inode, frees the blocks beyond the new size.
+    /// The inode is written back to disk.
+    pub fn truncate(&self, m: &mut Meta, inode: &mut Inode, size: u64) -> VfsResult {
+        let bs = self.block_size as u64;
+        if size < inode.size() {
+            self.truncate_blocks(m, inode, size.div_ceil(bs))?;
+            // the tail of the last block must be read as zeros if extended
+            // later
+            if size % bs != 0 {
+                let block = self.map_block(m, inode, size / bs, false)?;
+                if block != 0 {
+                    let mut buf = vec![0; self.block_size];
+                    self.read_block(block, &mut buf)?;
+                    buf[(size % bs) as usize..].fill(0);
+                    self.write_block(block, &buf)?;
+                }
+            }
+        }
+        inode.set_size(size);
+        self.write_inode(inode)
+    }
+
+    /// Frees the blocks of logical index `from` and beyond.
+    fn truncate_blocks(&self, m: &mut Meta, inode: &mut Inode, from: u64) -> VfsResult {
+        if inode.flags() & (FLAG_EXTENTS | FLAG_INLINE_DATA) != 0 {
+            return Err(VfsError::PermissionDenied);
+        }
+        let per_block = (self.block_size / 4) as u64;
+        let mut base = 0;
+        for idx in 0..N_BLOCKS {
+            let level = idx.saturating_sub(N_DIRECT - 1) as u32;
+            let span = per_block.pow(level);
+            let block = inode.block(idx) as u64;
+            if block != 0
+                && base + span > from
+                && self.free_tree(m, inode, block, level, base, from)?
+            {
+                inode.set_block(idx, 0);
+            }
+            base += span;
+        }
+        Ok(())
+    }
+
+    /// Frees the blocks of logical index `from` and beyond in the (indirect)
+    /// block tree of `level`, which covers the logical blocks starting at
+    /// `base`. Returns whether `block` itself is freed.
+    fn free_tree(
+        &self,
+        m: &mut Meta,
+        inode: &mut Inode,
+        block: u64,
+        level: u32,
+        base: u64,
+        from: u64,
+    ) -> VfsResult<bool> {
+        if level > 0 {
+            let span = (self.block_size as u64 / 4).pow(level - 1);
+            let mut buf = vec![0; self.block_size];
+            self.read_block(block, &mut buf)?;
+            let mut modified = false;
+            for i in 0..self.block_size / 4 {
+                let child = read_u32(&buf, i * 4) as u64;
+                let child_base = base + i as u64 * span;
+                if child != 0
+                    && child_base + span > from
+                    && self.free_tree(m, inode, child, level - 1, child_base, from)?
+                {
+                    write_u32(&mut buf, i * 4, 0);
+                    modified = true;
+                }
+            }
+            if base < from {
+                if modified {
+                    self.write_block(block, &buf)?;
+                }
+                return Ok(false);
+            }
+        } else if base < from {
+            return Ok(false);
+        }
+        self.free_block(m, block)?;
+        inode.set_blocks(inode.blocks().saturating_sub(self.block_sectors()));
+        Ok(true)
+    }
+}
@@ -0,0 +1,211 @@
+//! On-disk structures of the ext2/ext3/ext4 filesystem.
+//!
+//! See <https://www.kernel.org/doc/html/latest/filesystems/ext4/index.html>.
+
+use alloc::vec::Vec;
+
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+pub const SUPERBLOCK_SIZE: usize = 1024;
+pub const EXT_MAGIC: u16 = 0xef53;
+pub const ROOT_INO: u32 = 2;
+
+// superblock fields
+pub const SB_INODES_COUNT: usize = 0;
+pub const SB_BLOCKS_COUNT_LO: usize = 4;
+pub const SB_FREE_BLOCKS_COUNT_LO: usize = 12;
+pub const SB_FREE_INODES_COUNT: usize = 16;
+pub const SB_FIRST_DATA_BLOCK: usize = 20;
+pub const SB_LOG_BLOCK_SIZE: usize = 24;
+pub const SB_BLOCKS_PER_GROUP: usize = 32;
+pub const SB_INODES_PER_GROUP: usize = 40;
+pub const SB_MAGIC: usize = 56;
+pub const SB_REV_LEVEL: usize = 76;
+pub const SB_FIRST_INO: usize = 84;
+pub const SB_INODE_SIZE: usize = 88;
+pub const SB_FEATURE_INCOMPAT: usize = 96;
+pub const SB_FEATURE_RO_COMPAT: usize = 100;
+pub const SB_DESC_SIZE: usize = 254;
+pub const SB_BLOCKS_COUNT_HI: usize = 336;
+
+pub const INCOMPAT_FILETYPE: u32 = 0x2;
+pub const INCOMPAT_RECOVER: u32 = 0x4;
+pub const INCOMPAT_EXTENTS: u32 = 0x40;
+pub const INCOMPAT_64BIT: u32 = 0x80;
+pub const INCOMPAT_FLEX_BG: u32 = 0x200;
+pub const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+pub const INCOMPAT_LARGEDIR: u32 = 0x4000;
+
+pub const RO_COMPAT_SPARSE_SUPER: u32 = 0x1;
+pub const RO_COMPAT_LARGE_FILE: u32 = 0x2;
+pub const RO_COMPAT_BTREE_DIR: u32 = 0x4;
+
+/// Incompatible features that can be read.
+pub const INCOMPAT_READ: u32 = INCOMPAT_FILETYPE
+    | INCOMPAT_RECOVER
+    | INCOMPAT_EXTENTS
+    | INCOMPAT_64BIT
+    | INCOMPAT_FLEX_BG
+    | INCOMPAT_CSUM_SEED
+    | INCOMPAT_LARGEDIR;
+/// Incompatible features that can be written.
+pub const INCOMPAT_WRITE: u32 = INCOMPAT_FILETYPE;
+/// Read-only compatible features that can be written.
+pub const RO_COMPAT_WRITE: u32 =
+    RO_COMPAT_SPARSE_SUPER | RO_COMPAT_LARGE_FILE | RO_COMPAT_BTREE_DIR;
+
+// block group descriptor fields
+pub const BG_BLOCK_BITMAP_LO: usize = 0;
+pub const BG_INODE_BITMAP_LO: usize = 4;
+pub const BG_INODE_TABLE_LO: usize = 8;
+pub const BG_FREE_BLOCKS_COUNT_LO: usize = 12;
+pub const BG_FREE_INODES_COUNT_LO: usize = 14;
+pub const BG_USED_DIRS_COUNT_LO: usize = 16;
+pub const BG_BLOCK_BITMAP_HI: usize = 32;
+pub const BG_INODE_BITMAP_HI: usize = 36;
+pub const BG_INODE_TABLE_HI: usize = 40;
+
+// inode fields
+pub const I_MODE: usize = 0;
+pub const I_SIZE_LO: usize = 4;
+pub const I_LINKS_COUNT: usize = 26;
+pub const I_BLOCKS_LO: usize = 28;
+pub const I_FLAGS: usize = 32;
+pub const I_BLOCK: usize = 40;
+pub const I_FILE_ACL_LO: usize = 104;
+pub const I_SIZE_HIGH: usize = 108;
+pub const I_BLOCKS_HIGH: usize = 116;
+
+/// Number of `u32` block pointers in `i_block`.
+pub const N_BLOCKS: usize = 15;
+/// Number of direct block pointers in `i_block`.
+pub const N_DIRECT: usize = 12;
+/// Size of `i_block`, the maximum length of fast symbolic links.
+pub const I_BLOCK_SIZE: usize = N_BLOCKS * 4;
+
+pub const FLAG_INDEX: u32 = 0x1000;
+pub const FLAG_HUGE_FILE: u32 = 0x40000;
+pub const FLAG_EXTENTS: u32 = 0x80000;
+pub const FLAG_INLINE_DATA: u32 = 0x1000_0000;
+
+pub const S_IFMT: u16 = 0xf000;
+pub const S_IFDIR: u16 = 0x4000;
+pub const S_IFREG: u16 = 0x8000;
+pub const S_IFLNK: u16 = 0xa000;
+
+// extent tree
+pub const EXTENT_MAGIC: u16 = 0xf30a;
+pub const EXTENT_HEADER_SIZE: usize = 12;
+pub const EXTENT_ENTRY_SIZE: usize = 12;
+/// Extents longer than this are uninitialized (read as zeros).
+pub const EXTENT_INIT_MAX_LEN: u16 = 32768;
+
+// directory entries
+pub const DIRENT_HEADER_SIZE: usize = 8;
+pub const FT_UNKNOWN: u8 = 0;
+pub const FT_REG_FILE: u8 = 1;
+pub const FT_DIR: u8 = 2;
+pub const FT_SYMLINK: u8 = 7;
+
+pub fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+pub fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn write_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// An inode read from the inode table.
+pub struct Inode {
+    pub ino: u32,
+    pub raw: Vec<u8>,
+}
+
+impl Inode {
+    pub fn mode(&self) -> u16 {
+        read_u16(&self.raw, I_MODE)
+    }
+
+    pub fn set_mode(&mut self, mode: u16) {
+        write_u16(&mut self.raw, I_MODE, mode)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode() & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode() & S_IFMT == S_IFLNK
+    }
+
+    pub fn size(&self) -> u64 {
+        read_u32(&self.raw, I_SIZE_LO) as u64 | (read_u32(&self.raw, I_SIZE_HIGH) as u64) << 32
+    }
+
+    pub fn set_size(&mut self, size: u64) {
+        write_u32(&mut self.raw, I_SIZE_LO, size as u32);
+        write_u32(&mut self.raw, I_SIZE_HIGH, (size >> 32) as u32);
+    }
+
+    pub fn links_count(&self) -> u16 {
+        read_u16(&self.raw, I_LINKS_COUNT)
+    }
+
+    pub fn set_links_count(&mut self, count: u16) {
+        write_u16(&mut self.raw, I_LINKS_COUNT, count)
+    }
+
+    /// Number of 512-byte sectors (or filesystem blocks if `FLAG_HUGE_FILE`
+    /// is set) allocated.
+    pub fn blocks(&self) -> u64 {
+        read_u32(&self.raw, I_BLOCKS_LO) as u64 | (read_u16(&self.raw, I_BLOCKS_HIGH) as u64) << 32
+    }
+
+    pub fn set_blocks(&mut self, blocks: u64) {
+        write_u32(&mut self.raw, I_BLOCKS_LO, blocks as u32);
+        write_u16(&mut self.raw, I_BLOCKS_HIGH, (blocks >> 32) as u16);
+    }
+
+    pub fn flags(&self) -> u32 {
+        read_u32(&self.raw, I_FLAGS)
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        write_u32(&mut self.raw, I_FLAGS, flags)
+    }
+
+    pub fn file_acl(&self) -> u32 {
+        read_u32(&self.raw, I_FILE_ACL_LO)
+    }
+
+    /// The `idx`-th block pointer in `i_block`.
+    pub fn block(&self, idx: usize) -> u32 {
+        read_u32(&self.raw, I_BLOCK + idx * 4)
+    }
+
+    pub fn set_block(&mut self, idx: usize, block: u32) {
+        write_u32(&mut self.raw, I_BLOCK + idx * 4, block)
+    }
+
+    /// The raw `i_block` area, which contains the root of the extent tree or
+    /// the target of a fast symbolic link.
+    pub fn i_block(&self) -> &[u8] {
+        &self.raw[I_BLOCK..I_BLOCK + I_BLOCK_SIZE]
+    }
+
+    pub fn i_block_mut(&mut self) -> &mut [u8] {
+        &mut self.raw[I_BLOCK..I_BLOCK + I_BLOCK_SIZE]
+    }
+}
+
+/// Length of a directory entry with the given name length, 4-byte aligned.
+pub const fn dirent_len(name_len: usize) -> usize {
+    (DIRENT_HEADER_SIZE + name_len + 3) & !3
+}
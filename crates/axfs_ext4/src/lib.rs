@@ -0,0 +1,99 @@
+//! Ext2/ext4 filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
+//!
+//! The implementation is based on [`axfs_vfs`].
+//!
+//! Ext2 (and ext3 without journaling) filesystems can be read and written.
+//! Filesystems using ext4 features, such as extents, 64-bit block numbers
+//! and flexible block groups, can only be read, they are mounted read-only
+//! and all modifications fail with [`PermissionDenied`].
+//!
+//! [`PermissionDenied`]: axfs_vfs::VfsError::PermissionDenied
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod dir;
+mod fs;
+mod layout;
+mod node;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::node::Ext4Node;
+
+use alloc::{boxed::Box, string::String, sync::Arc};
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+
+use self::fs::FsInner;
+use self::layout::{EXT_MAGIC, ROOT_INO, SB_MAGIC, SUPERBLOCK_OFFSET};
+
+/// The storage that contains an ext filesystem.
+pub trait BlockDevice: Send + Sync {
+    /// Reads data at the given byte offset.
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> VfsResult;
+    /// Writes data at the given byte offset.
+    fn write_at(&self, pos: u64, buf: &[u8]) -> VfsResult;
+    /// Returns the size of the device in bytes.
+    fn size(&self) -> u64;
+    /// Flushes cached data to the storage.
+    fn flush(&self) -> VfsResult {
+        Ok(())
+    }
+}
+
+/// An ext2/ext4 filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct Ext4FileSystem {
+    inner: Arc<FsInner>,
+}
+
+impl Ext4FileSystem {
+    /// Opens the filesystem on the device.
+    ///
+    /// Returns [`InvalidData`](axfs_vfs::VfsError::InvalidData) if the device
+    /// does not contain an ext filesystem, or its superblock describes more
+    /// blocks than the device has, or
+    /// [`Unsupported`](axfs_vfs::VfsError::Unsupported) if it uses features
+    /// that cannot be read.
+    pub fn new(dev: impl BlockDevice + 'static) -> VfsResult<Self> {
+        Ok(Self {
+            inner: Arc::new(FsInner::new(Box::new(dev))?),
+        })
+    }
+
+    /// Whether the device contains an ext filesystem.
+    pub fn probe(dev: &dyn BlockDevice) -> bool {
+        let mut magic = [0; 2];
+        dev.read_at(SUPERBLOCK_OFFSET + SB_MAGIC as u64, &mut magic)
+            .is_ok_and(|_| u16::from_le_bytes(magic) == EXT_MAGIC)
+    }
+
+    /// Whether the filesystem is mounted read-only because it uses features
+    /// that cannot be written.
+    pub fn is_read_only(&self) -> bool {
+        self.inner.read_only
+    }
+
+    /// Returns the root directory node in [`Arc<Ext4Node>`](Ext4Node).
+    pub fn root_dir_node(&self) -> Arc<Ext4Node> {
+        self.inner.node(ROOT_INO)
+    }
+}
+
+impl VfsOps for Ext4FileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.inner.mount_parent.write() = mount_point.parent();
+        *self.inner.mount_path.write() = String::from(path);
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        *self.inner.mount_parent.write() = None;
+        self.inner.flush()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root_dir_node()
+    }
+}
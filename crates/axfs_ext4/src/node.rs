@@ -0,0 +1,486 @@
+use alloc::sync::Arc;
+use alloc::{string::String, vec};
+
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::fs::{FsInner, Meta};
+use crate::layout::*;
+
+/// The node (file, directory or symbolic link) of an ext filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`]. There is at most one node for each
+/// inode at a time, the inode is freed when it has been removed and the node
+/// is dropped.
+pub struct Ext4Node {
+    fs: Arc<FsInner>,
+    ino: u32,
+}
+
+impl Ext4Node {
+    pub(crate) fn new(fs: Arc<FsInner>, ino: u32) -> Self {
+        Self { fs, ino }
+    }
+
+    /// Returns the inode number.
+    pub fn ino(&self) -> u32 {
+        self.ino
+    }
+
+    fn check_writable(&self) -> VfsResult {
+        if self.fs.read_only {
+            Err(VfsError::PermissionDenied)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Finds the child with the given name, returns its inode number.
+    fn find(&self, name: &str) -> VfsResult<u32> {
+        let mut m = self.fs.lock();
+        let mut dir = self.fs.read_inode(self.ino)?;
+        self.fs
+            .find_entry(&mut m, &mut dir, name)?
+            .ok_or(VfsError::NotFound)
+    }
+
+    /// Calls `f` with the directory that contains the last component of
+    /// `path` and the name of it, or `on_self` if `path` refers to this
+    /// directory itself.
+    fn with_parent<F>(&self, path: &str, on_self: VfsResult, f: F) -> VfsResult
+    where
+        F: FnOnce(&Self, &str) -> VfsResult,
+    {
+        let (name, rest) = split_path(path);
+        match rest {
+            Some(rest) => match name {
+                "" | "." => self.with_parent(rest, on_self, f),
+                ".." => {
+                    let parent = self.parent().ok_or(VfsError::NotFound)?;
+                    match parent.as_any().downcast_ref::<Self>() {
+                        Some(parent) => parent.with_parent(rest, on_self, f),
+                        None => Err(VfsError::Unsupported), // out of the filesystem
+                    }
+                }
+                _ => self.fs.node(self.find(name)?).with_parent(rest, on_self, f),
+            },
+            None if name.is_empty() || name == "." || name == ".." => on_self,
+            None => f(self, name),
+        }
+    }
+
+    /// Resolves `path` in the filesystem, starting from `dir`.
+    fn resolve(&self, m: &mut Meta, mut dir: u32, path: &str) -> VfsResult<u32> {
+        for name in path
+            .split('/')
+            .filter(|&name| !name.is_empty() && name != ".")
+        {
+            let mut inode = self.fs.read_inode(dir)?;
+            dir = self
+                .fs
+                .find_entry(m, &mut inode, name)?
+                .ok_or(VfsError::NotFound)?;
+        }
+        Ok(dir)
+    }
+
+    fn create_child(&self, name: &str, ty: VfsNodeType) -> VfsResult {
+        self.check_writable()?;
+        let fs = &self.fs;
+        let mut m = fs.lock();
+        let mut dir = fs.read_inode(self.ino)?;
+        if fs.find_entry(&mut m, &mut dir, name)?.is_some() {
+            return Ok(()); // already exists
+        }
+        match ty {
+            VfsNodeType::File => {
+                let mode = S_IFREG | VfsNodePerm::default_file().bits();
+                let mut inode = fs.alloc_inode(&mut m, mode, self.ino)?;
+                inode.set_links_count(1);
+                fs.write_inode(&inode)?;
+                fs.add_entry(&mut m, &mut dir, name, inode.ino, FT_REG_FILE)
+            }
+            VfsNodeType::Dir => {
+                let mode = S_IFDIR | VfsNodePerm::default_dir().bits();
+                let mut inode = fs.alloc_inode(&mut m, mode, self.ino)?;
+                inode.set_links_count(2);
+                fs.init_dir(&mut m, &mut inode, self.ino)?;
+                fs.add_entry(&mut m, &mut dir, name, inode.ino, FT_DIR)?;
+                dir.set_links_count(dir.links_count() + 1);
+                fs.write_inode(&dir)
+            }
+            _ => Err(VfsError::Unsupported),
+        }
+    }
+
+    fn remove_child(&self, name: &str) -> VfsResult {
+        self.check_writable()?;
+        let fs = &self.fs;
+        let mut m = fs.lock();
+        let mut dir = fs.read_inode(self.ino)?;
+        let ino = fs
+            .find_entry(&mut m, &mut dir, name)?
+            .ok_or(VfsError::NotFound)?;
+        let mut inode = fs.read_inode(ino)?;
+        if inode.is_dir() {
+            let entries = fs.dir_entries(&mut m, &mut inode)?;
+            if entries.iter().any(|e| e.name != "." && e.name != "..") {
+                return Err(VfsError::DirectoryNotEmpty);
+            }
+        }
+
+        fs.remove_entry(&mut m, &mut dir, name)?;
+        if inode.is_dir() {
+            dir.set_links_count(dir.links_count() - 1);
+            fs.write_inode(&dir)?;
+            inode.set_links_count(0);
+        } else {
+            inode.set_links_count(inode.links_count().saturating_sub(1));
+        }
+        if inode.links_count() == 0 && !fs.in_use(ino) {
+            fs.free_inode(&mut m, inode)
+        } else {
+            fs.write_inode(&inode) // freed when the node is dropped
+        }
+    }
+
+    fn symlink_child(&self, name: &str, target: &str) -> VfsResult {
+        self.check_writable()?;
+        let fs = &self.fs;
+        let mut m = fs.lock();
+        let mut dir = fs.read_inode(self.ino)?;
+        if fs.find_entry(&mut m, &mut dir, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let mode = S_IFLNK | VfsNodePerm::default_symlink().bits();
+        let mut inode = fs.alloc_inode(&mut m, mode, self.ino)?;
+        inode.set_links_count(1);
+        if target.len() < I_BLOCK_SIZE {
+            inode.i_block_mut()[..target.len()].copy_from_slice(target.as_bytes());
+            inode.set_size(target.len() as u64);
+            fs.write_inode(&inode)?;
+        } else {
+            fs.write_data(&mut m, &mut inode, 0, target.as_bytes())?;
+        }
+        fs.add_entry(&mut m, &mut dir, name, inode.ino, FT_SYMLINK)
+    }
+
+    fn link_child(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        self.check_writable()?;
+        let target = match node.as_any().downcast_ref::<Self>() {
+            Some(target) if Arc::ptr_eq(&target.fs, &self.fs) => target,
+            _ => return Err(VfsError::CrossesDevices),
+        };
+        let fs = &self.fs;
+        let mut m = fs.lock();
+        let mut inode = fs.read_inode(target.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::PermissionDenied);
+        } else if inode.links_count() == 0 {
+            return Err(VfsError::NotFound); // already removed
+        }
+        let mut dir = fs.read_inode(self.ino)?;
+        if fs.find_entry(&mut m, &mut dir, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let ft = if inode.is_symlink() {
+            FT_SYMLINK
+        } else {
+            FT_REG_FILE
+        };
+        fs.add_entry(&mut m, &mut dir, name, inode.ino, ft)?;
+        inode.set_links_count(inode.links_count() + 1);
+        fs.write_inode(&inode)
+    }
+
+    /// Moves the child `src_name` to `dst_path`, which is relative to the
+    /// directory `base` if it is not absolute.
+    fn rename_child(&self, src_name: &str, base: u32, dst_path: &str) -> VfsResult {
+        self.check_writable()?;
+        let fs = &self.fs;
+        let mut m = fs.lock();
+
+        // absolute paths are relative to the mount point
+        let (dst_start, dst_path) = match dst_path.strip_prefix('/') {
+            Some(path) => {
+                let mount_path = fs.mount_path.read();
+                let path = path
+                    .strip_prefix(mount_path.trim_start_matches('/'))
+                    .unwrap_or(path);
+                (ROOT_INO, String::from(path))
+            }
+            None => (base, String::from(dst_path)),
+        };
+        let dst_path = dst_path.trim_end_matches('/');
+        let (dst_dir, dst_name) = match dst_path.rsplit_once('/') {
+            Some((dir, name)) => (self.resolve(&mut m, dst_start, dir)?, name),
+            None => (dst_start, dst_path),
+        };
+        if dst_name.is_empty() || dst_name == "." || dst_name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+
+        let mut src = fs.read_inode(self.ino)?;
+        let ino = fs
+            .find_entry(&mut m, &mut src, src_name)?
+            .ok_or(VfsError::NotFound)?;
+        let mut dst = fs.read_inode(dst_dir)?;
+        match fs.find_entry(&mut m, &mut dst, dst_name)? {
+            Some(dst_ino) if dst_ino == ino => return Ok(()),
+            Some(_) => return Err(VfsError::AlreadyExists),
+            None => {}
+        }
+        let mut inode = fs.read_inode(ino)?;
+        let is_dir = inode.is_dir();
+        if is_dir {
+            // cannot move a directory into itself
+            let mut cur = dst_dir;
+            while cur != ROOT_INO {
+                if cur == ino {
+                    return Err(VfsError::InvalidInput);
+                }
+                cur = self.resolve(&mut m, cur, "..")?;
+            }
+        }
+
+        let ft = if is_dir {
+            FT_DIR
+        } else if inode.is_symlink() {
+            FT_SYMLINK
+        } else {
+            FT_REG_FILE
+        };
+        fs.add_entry(&mut m, &mut dst, dst_name, ino, ft)?;
+        let mut src = fs.read_inode(self.ino)?; // may be the same as `dst`
+        fs.remove_entry(&mut m, &mut src, src_name)?;
+        if is_dir && dst_dir != self.ino {
+            fs.set_parent_entry(&mut m, &mut inode, dst_dir)?;
+            src.set_links_count(src.links_count() - 1);
+            fs.write_inode(&src)?;
+            let mut dst = fs.read_inode(dst_dir)?;
+            dst.set_links_count(dst.links_count() + 1);
+            fs.write_inode(&dst)?;
+        }
+        Ok(())
+    }
+}
+
+impl VfsNodeOps for Ext4Node {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let inode = self.fs.read_inode(self.ino)?;
+        let mode = inode.mode();
+        let perm = VfsNodePerm::from_bits_truncate(mode & 0o777);
+        let ty = match mode & S_IFMT {
+            S_IFDIR => VfsNodeType::Dir,
+            S_IFLNK => VfsNodeType::SymLink,
+            0x1000 => VfsNodeType::Fifo,
+            0x2000 => VfsNodeType::CharDevice,
+            0x6000 => VfsNodeType::BlockDevice,
+            0xc000 => VfsNodeType::Socket,
+            _ => VfsNodeType::File,
+        };
+        let mut blocks = inode.blocks();
+        if inode.flags() & FLAG_HUGE_FILE != 0 {
+            blocks *= self.fs.block_size as u64 / 512;
+        }
+        Ok(VfsNodeAttr::new(perm, ty, inode.size(), blocks)
+            .with_ino(self.ino as u64)
+            .with_nlink(inode.links_count() as u32))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut m = self.fs.lock();
+        let mut inode = self.fs.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        self.fs.read_data(&mut m, &mut inode, offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.check_writable()?;
+        let mut m = self.fs.lock();
+        let mut inode = self.fs.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        self.fs.write_data(&mut m, &mut inode, offset, buf)
+    }
+
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [&mut [u8]]) -> VfsResult<usize> {
+        let mut m = self.fs.lock();
+        let mut inode = self.fs.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        let mut read_len = 0;
+        for buf in bufs {
+            let n = self
+                .fs
+                .read_data(&mut m, &mut inode, offset + read_len as u64, buf)?;
+            read_len += n;
+            if n < buf.len() {
+                break;
+            }
+        }
+        Ok(read_len)
+    }
+
+    fn write_vectored_at(&self, offset: u64, bufs: &[&[u8]]) -> VfsResult<usize> {
+        self.check_writable()?;
+        let mut m = self.fs.lock();
+        let mut inode = self.fs.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        let mut write_len = 0;
+        for buf in bufs {
+            let pos = offset + write_len as u64;
+            match self.fs.write_data(&mut m, &mut inode, pos, buf) {
+                Ok(n) => {
+                    write_len += n;
+                    if n < buf.len() {
+                        break;
+                    }
+                }
+                Err(e) if write_len == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(write_len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.fs.flush()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.check_writable()?;
+        let mut m = self.fs.lock();
+        let mut inode = self.fs.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        self.fs.truncate(&mut m, &mut inode, size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.ino == ROOT_INO {
+            return self.fs.mount_parent.read().clone();
+        }
+        let ino = self.find("..").ok()?;
+        Some(self.fs.node(ino))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        log::debug!("lookup at ext4: {}", path);
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "" | "." => {
+                if !self.fs.read_inode(self.ino)?.is_dir() {
+                    return Err(VfsError::NotADirectory);
+                }
+                self.clone()
+            }
+            ".." => self.parent().ok_or(VfsError::NotFound)?,
+            _ => self.fs.node(self.find(name)?),
+        };
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        log::debug!("create {:?} at ext4: {}", ty, path);
+        self.with_parent(path, Ok(()), |dir, name| dir.create_child(name, ty))
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        log::debug!("remove at ext4: {}", path);
+        let on_self = Err(VfsError::InvalidInput); // remove '.' or '..'
+        self.with_parent(path, on_self, |dir, name| dir.remove_child(name))
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let entries = {
+            let mut m = self.fs.lock();
+            let mut dir = self.fs.read_inode(self.ino)?;
+            self.fs.dir_entries(&mut m, &mut dir)?
+        };
+        let mut count = 0;
+        for (entry, out) in entries.iter().skip(start_idx).zip(dirents.iter_mut()) {
+            let ty = match entry.file_type {
+                FT_REG_FILE => VfsNodeType::File,
+                FT_DIR => VfsNodeType::Dir,
+                FT_SYMLINK => VfsNodeType::SymLink,
+                3 => VfsNodeType::CharDevice,
+                4 => VfsNodeType::BlockDevice,
+                5 => VfsNodeType::Fifo,
+                6 => VfsNodeType::Socket,
+                _ => self.fs.node(entry.ino).get_attr()?.file_type(),
+            };
+            *out = VfsDirEntry::new(&entry.name, ty).with_ino(entry.ino as u64);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        log::debug!("rename at ext4: {} -> {}", src_path, dst_path);
+        let on_self = Err(VfsError::InvalidInput);
+        let base = self.ino;
+        self.with_parent(src_path, on_self, |dir, name| {
+            dir.rename_child(name, base, dst_path)
+        })
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at ext4: {} -> {}", path, target);
+        let on_self = Err(VfsError::AlreadyExists);
+        self.with_parent(path, on_self, |dir, name| dir.symlink_child(name, target))
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        log::debug!("link at ext4: {}", path);
+        let on_self = Err(VfsError::AlreadyExists);
+        self.with_parent(path, on_self, |dir, name| dir.link_child(name, node))
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut inode = self.fs.read_inode(self.ino)?;
+        if !inode.is_symlink() {
+            return Err(VfsError::InvalidInput);
+        }
+        if self.fs.is_fast_symlink(&inode) {
+            let len = (inode.size() as usize).min(I_BLOCK_SIZE).min(buf.len());
+            buf[..len].copy_from_slice(&inode.i_block()[..len]);
+            return Ok(len);
+        }
+        let mut m = self.fs.lock();
+        let mut target = vec![0; inode.size() as usize];
+        let len = self.fs.read_data(&mut m, &mut inode, 0, &mut target)?;
+        let len = len.min(buf.len());
+        buf[..len].copy_from_slice(&target[..len]);
+        Ok(len)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl Drop for Ext4Node {
+    fn drop(&mut self) {
+        if let Err(e) = self.fs.release(self.ino) {
+            log::warn!("ext4: failed to release inode {}: {:?}", self.ino, e);
+        }
+    }
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
@@ -0,0 +1,292 @@
+use std::sync::{Arc, Mutex};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeType, VfsOps, VfsResult};
+
+use crate::*;
+
+const LONG_LEN: u64 = 14 * 1000;
+const CONTENT: &[u8] = b"Rust is cool!\n";
+
+struct MemDevice(Arc<Mutex<Vec<u8>>>);
+
+impl BlockDevice for MemDevice {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> VfsResult {
+        let data = self.0.lock().unwrap();
+        let pos = pos as usize;
+        if pos + buf.len() > data.len() {
+            return Err(VfsError::InvalidInput);
+        }
+        buf.copy_from_slice(&data[pos..pos + buf.len()]);
+        Ok(())
+    }
+
+    fn write_at(&self, pos: u64, buf: &[u8]) -> VfsResult {
+        let mut data = self.0.lock().unwrap();
+        let pos = pos as usize;
+        if pos + buf.len() > data.len() {
+            return Err(VfsError::InvalidInput);
+        }
+        data[pos..pos + buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.0.lock().unwrap().len() as u64
+    }
+}
+
+fn load_image(name: &str) -> Arc<Mutex<Vec<u8>>> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join(name);
+    let data = std::fs::read(path).expect("failed to load disk image");
+    Arc::new(Mutex::new(data))
+}
+
+fn open_fs(image: &Arc<Mutex<Vec<u8>>>) -> Ext4FileSystem {
+    let dev = MemDevice(image.clone());
+    assert!(Ext4FileSystem::probe(&dev));
+    Ext4FileSystem::new(dev).expect("failed to open ext filesystem")
+}
+
+fn read_all(fs: &Ext4FileSystem, path: &str) -> VfsResult<Vec<u8>> {
+    let node = fs.root_dir().lookup(path)?;
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    assert_eq!(node.read_at(0, &mut buf)?, buf.len());
+    Ok(buf)
+}
+
+fn test_read(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+    assert!(root.get_attr()?.is_dir());
+
+    let long = read_all(fs, "long.txt")?;
+    assert_eq!(long.len() as u64, LONG_LEN);
+    assert!(long.chunks(CONTENT.len()).all(|line| line == CONTENT));
+    assert_eq!(read_all(fs, "/very/long/path/test.txt")?, CONTENT);
+    assert_eq!(
+        read_all(fs, "./very-long-dir-name//very-long-file-name.txt")?,
+        CONTENT
+    );
+
+    let node = root.clone().lookup("long.txt")?;
+    let mut buf = [0; 64];
+    assert_eq!(node.read_at(LONG_LEN - 10, &mut buf)?, 10);
+    assert_eq!(&buf[..10], &CONTENT[4..]);
+    assert_eq!(node.read_at(LONG_LEN, &mut buf)?, 0);
+    let (mut head, mut tail) = ([0; 4], [0; 16]);
+    let mut bufs: [&mut [u8]; 2] = [&mut head, &mut tail];
+    assert_eq!(node.read_vectored_at(LONG_LEN - 14, &mut bufs)?, 14);
+    assert_eq!(&head, b"Rust");
+    assert_eq!(&tail[..10], &CONTENT[4..]);
+
+    let link = root.clone().lookup("link.txt")?;
+    assert_eq!(link.get_attr()?.file_type(), VfsNodeType::SymLink);
+    let n = link.readlink(&mut buf)?;
+    assert_eq!(&buf[..n], b"very/long/path/test.txt");
+
+    let mut dirents: [VfsDirEntry; 16] = core::array::from_fn(|_| VfsDirEntry::default());
+    let n = root.read_dir(0, &mut dirents)?;
+    let mut names = dirents[..n]
+        .iter()
+        .map(|e| String::from_utf8_lossy(e.name_as_bytes()).into_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            ".",
+            "..",
+            "link.txt",
+            "long.txt",
+            "lost+found",
+            "short.txt",
+            "very",
+            "very-long-dir-name"
+        ]
+    );
+
+    assert_eq!(
+        root.clone().lookup("not-exist").err(),
+        Some(VfsError::NotFound)
+    );
+    assert_eq!(
+        root.clone().lookup("short.txt/").err(),
+        Some(VfsError::NotADirectory)
+    );
+    assert_eq!(
+        root.lookup("very").unwrap().read_at(0, &mut buf).err(),
+        Some(VfsError::IsADirectory)
+    );
+    Ok(())
+}
+
+fn test_write(fs: &Ext4FileSystem) -> VfsResult {
+    let root = fs.root_dir();
+
+    // extend a file to use indirect blocks
+    let node = root.clone().lookup("short.txt")?;
+    let data = (0..300_000).map(|i| i as u8).collect::<Vec<_>>();
+    assert_eq!(node.write_at(1000, &data)?, data.len());
+    let contents = read_all(fs, "short.txt")?;
+    assert_eq!(contents.len(), 1000 + data.len());
+    assert_eq!(&contents[..CONTENT.len()], CONTENT);
+    assert!(contents[CONTENT.len()..1000].iter().all(|&b| b == 0));
+    assert_eq!(&contents[1000..], &data[..]);
+
+    node.truncate(10)?;
+    assert_eq!(read_all(fs, "short.txt")?, &CONTENT[..10]);
+    node.truncate(20)?;
+    assert_eq!(read_all(fs, "short.txt")?[10..], [0; 10]);
+
+    // create files and directories
+    root.create("new-dir", VfsNodeType::Dir)?;
+    root.create("new-dir/sub", VfsNodeType::Dir)?;
+    root.create("./new-dir//file.txt", VfsNodeType::File)?;
+    let file = root.clone().lookup("new-dir/file.txt")?;
+    assert_eq!(file.write_at(0, CONTENT)?, CONTENT.len());
+    assert_eq!(read_all(fs, "new-dir/sub/../file.txt")?, CONTENT);
+    let bufs: [&[u8]; 2] = [&CONTENT[..5], &CONTENT[5..]];
+    assert_eq!(
+        file.write_vectored_at(CONTENT.len() as _, &bufs)?,
+        CONTENT.len()
+    );
+    assert_eq!(read_all(fs, "new-dir/file.txt")?, CONTENT.repeat(2));
+    file.truncate(CONTENT.len() as _)?;
+    let sub = root.clone().lookup("new-dir/sub")?;
+    assert!(Arc::ptr_eq(
+        &sub.parent().unwrap(),
+        &root.clone().lookup("new-dir")?
+    ));
+
+    // many entries to span multiple directory blocks
+    for i in 0..100 {
+        root.create(&format!("new-dir/f{:03}", i), VfsNodeType::File)?;
+    }
+    for i in (0..100).step_by(2) {
+        root.remove(&format!("new-dir/f{:03}", i))?;
+    }
+    assert_eq!(
+        root.clone().lookup("new-dir/f000").err(),
+        Some(VfsError::NotFound)
+    );
+    assert!(root.clone().lookup("new-dir/f099").is_ok());
+
+    assert_eq!(
+        root.remove("new-dir").err(),
+        Some(VfsError::DirectoryNotEmpty)
+    );
+
+    // symbolic and hard links
+    let long_target = "a/".repeat(100);
+    root.symlink("new-dir/fast", "file.txt")?;
+    root.symlink("new-dir/slow", &long_target)?;
+    let mut buf = [0; 256];
+    let n = root.clone().lookup("new-dir/slow")?.readlink(&mut buf)?;
+    assert_eq!(&buf[..n], long_target.as_bytes());
+    root.link("hard.txt", &file)?;
+    assert_eq!(read_all(fs, "hard.txt")?, CONTENT);
+    let hard = root.clone().lookup("hard.txt")?.get_attr()?;
+    assert_eq!(hard.nlink(), 2);
+    assert_eq!(hard.ino(), file.get_attr()?.ino());
+    assert_eq!(
+        root.link("dir-link", &sub).err(),
+        Some(VfsError::PermissionDenied)
+    );
+
+    // rename
+    root.rename("new-dir/file.txt", "renamed.txt")?;
+    assert_eq!(read_all(fs, "renamed.txt")?, CONTENT);
+    root.rename("new-dir/sub", "/very/sub")?;
+    assert!(Arc::ptr_eq(
+        &root.clone().lookup("very/sub/..")?,
+        &root.clone().lookup("very")?
+    ));
+    assert_eq!(
+        root.rename("very", "very/sub/very").err(),
+        Some(VfsError::InvalidInput)
+    );
+
+    // an unlinked file can be used until the node is dropped
+    root.remove("renamed.txt")?;
+    root.remove("hard.txt")?;
+    assert_eq!(file.write_at(0, CONTENT)?, CONTENT.len());
+    drop(file);
+
+    for i in (1..100).step_by(2) {
+        root.remove(&format!("new-dir/f{:03}", i))?;
+    }
+    root.remove("new-dir/fast")?;
+    root.remove("new-dir/slow")?;
+    root.remove("new-dir")?;
+    assert_eq!(
+        root.clone().lookup("new-dir").err(),
+        Some(VfsError::NotFound)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ext2() {
+    let image = load_image("ext2.img");
+    let fs = open_fs(&image);
+    assert!(!fs.is_read_only());
+    test_read(&fs).expect("test_read() failed");
+    test_write(&fs).expect("test_write() failed");
+    drop(fs);
+
+    // the new contents are persistent
+    let fs = open_fs(&image);
+    assert_eq!(read_all(&fs, "short.txt").unwrap()[..10], CONTENT[..10]);
+    assert!(fs.root_dir().lookup("very/sub").is_ok());
+}
+
+#[test]
+fn test_ext4() {
+    let fs = open_fs(&load_image("ext4.img"));
+    assert!(fs.is_read_only());
+    test_read(&fs).expect("test_read() failed");
+
+    let root = fs.root_dir();
+    assert_eq!(
+        root.create("new.txt", VfsNodeType::File).err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(
+        root.clone()
+            .lookup("short.txt")
+            .unwrap()
+            .write_at(0, b"x")
+            .err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(
+        root.remove("short.txt").err(),
+        Some(VfsError::PermissionDenied)
+    );
+}
+
+#[test]
+fn test_bad_superblock() {
+    let image = load_image("ext2.img");
+    let data = image.lock().unwrap().clone();
+    let open_with = |blocks_count: u32, first_data_block: u32| {
+        let mut data = data.clone();
+        let sb = &mut data[SUPERBLOCK_OFFSET as usize..];
+        layout::write_u32(sb, layout::SB_BLOCKS_COUNT_LO, blocks_count);
+        layout::write_u32(sb, layout::SB_FIRST_DATA_BLOCK, first_data_block);
+        Ext4FileSystem::new(MemDevice(Arc::new(Mutex::new(data)))).err()
+    };
+
+    let sb = &data[SUPERBLOCK_OFFSET as usize..];
+    let blocks_count = layout::read_u32(sb, layout::SB_BLOCKS_COUNT_LO);
+    let first_data_block = layout::read_u32(sb, layout::SB_FIRST_DATA_BLOCK);
+    assert!(open_with(blocks_count, first_data_block).is_none());
+    // fewer blocks than the first data block
+    assert_eq!(open_with(0, 1), Some(VfsError::InvalidData));
+    // more blocks than the device
+    assert_eq!(
+        open_with(u32::MAX, first_data_block),
+        Some(VfsError::InvalidData)
+    );
+}
@@ -0,0 +1,73 @@
+//! Overlay (union) filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
+//!
+//! The implementation is based on [`axfs_vfs`].
+//!
+//! An [`OverlayFileSystem`] stacks a writable upper filesystem over a lower
+//! one, which is never modified:
+//!
+//! - Files and directories are looked up in the upper layer first, and
+//!   directories that exist in both layers are merged.
+//! - Files in the lower layer are copied up to the upper layer (with their
+//!   parent directories) before they are modified.
+//! - Removed files of the lower layer are hidden by whiteouts in the upper
+//!   layer, which are empty files named `.wh.<name>`. A directory created
+//!   over a removed one is marked opaque by a `.wh..wh..opq` file in it, so
+//!   that the lower directory is not merged. Like AUFS, these are regular
+//!   files so that any filesystem can be the upper layer, and they are not
+//!   visible in the overlay.
+//!
+//! Renaming a directory that exists in the lower layer is not supported, and
+//! fails with [`CrossesDevices`](axfs_vfs::VfsError::CrossesDevices) like
+//! Linux, so that tools can fall back to copying.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod node;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::node::OverlayNode;
+
+use alloc::sync::Arc;
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+
+use self::node::Layers;
+
+/// An overlay filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct OverlayFileSystem {
+    layers: Arc<Layers>,
+    root: Arc<OverlayNode>,
+}
+
+impl OverlayFileSystem {
+    /// Create a new instance, with the writable `upper` filesystem stacked
+    /// over the `lower` one.
+    pub fn new(lower: Arc<dyn VfsOps>, upper: Arc<dyn VfsOps>) -> Self {
+        let layers = Arc::new(Layers::new(lower, upper));
+        Self {
+            root: OverlayNode::new(layers.clone(), ""),
+            layers,
+        }
+    }
+
+    /// Returns the root directory node in [`Arc<OverlayNode>`](OverlayNode).
+    pub fn root_dir_node(&self) -> Arc<OverlayNode> {
+        self.root.clone()
+    }
+}
+
+impl VfsOps for OverlayFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            self.layers.parent.call_once(|| parent);
+        }
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
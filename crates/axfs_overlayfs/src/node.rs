@@ -0,0 +1,585 @@
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult, VfsSetAttr};
+use spin::{Mutex, Once};
+
+/// Prefix of the names of whiteouts.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// Name of the file that marks a directory as opaque.
+const OPAQUE: &str = ".wh..wh..opq";
+
+/// The two layers of an overlay filesystem.
+pub(crate) struct Layers {
+    pub lower: Arc<dyn VfsOps>,
+    pub upper: Arc<dyn VfsOps>,
+    /// The parent of the mount point.
+    pub parent: Once<VfsNodeRef>,
+    /// Serializes operations that modify the upper layer.
+    lock: Mutex<()>,
+    /// Increased whenever the tree of the upper layer is modified, e.g. a
+    /// file is copied up, so that nodes resolve their paths again.
+    version: AtomicU64,
+}
+
+/// The nodes of a path in the two layers.
+#[derive(Clone)]
+struct Entry {
+    upper: Option<VfsNodeRef>,
+    /// The lower node, which is not hidden by a whiteout or an opaque
+    /// directory. It may be shadowed by a non-directory upper node.
+    lower: Option<VfsNodeRef>,
+}
+
+impl Entry {
+    /// The node that provides the contents and attributes.
+    fn node(&self) -> &VfsNodeRef {
+        self.upper.as_ref().or(self.lower.as_ref()).unwrap()
+    }
+
+    fn is_dir(&self) -> VfsResult<bool> {
+        Ok(self.node().get_attr()?.is_dir())
+    }
+
+    /// The lower directory to be merged, if both nodes are directories or
+    /// there is no upper node.
+    fn lower_dir(&self) -> VfsResult<Option<&VfsNodeRef>> {
+        match &self.lower {
+            Some(lower) if self.is_dir()? && lower.get_attr()?.is_dir() => Ok(Some(lower)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Splits a path into the parent directory and the name.
+fn split_parent(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
+
+fn whiteout_name(name: &str) -> String {
+    String::from(WHITEOUT_PREFIX) + name
+}
+
+/// Looks up `name` in `dir`, returns `None` if it does not exist.
+fn child(dir: &VfsNodeRef, name: &str) -> VfsResult<Option<VfsNodeRef>> {
+    match dir.clone().lookup(name) {
+        Ok(node) => Ok(Some(node)),
+        Err(VfsError::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads all entries of `dir` except `.` and `..`.
+fn read_all(dir: &VfsNodeRef) -> VfsResult<Vec<(String, VfsNodeType)>> {
+    let mut entries = Vec::new();
+    let mut buf: [VfsDirEntry; 16] = core::array::from_fn(|_| VfsDirEntry::default());
+    let mut start_idx = 0;
+    loop {
+        let n = dir.read_dir(start_idx, &mut buf)?;
+        if n == 0 {
+            return Ok(entries);
+        }
+        for ent in &buf[..n] {
+            let name = String::from_utf8_lossy(ent.name_as_bytes());
+            if name != "." && name != ".." {
+                entries.push((name.into_owned(), ent.entry_type()));
+            }
+        }
+        start_idx += n;
+    }
+}
+
+impl Layers {
+    pub fn new(lower: Arc<dyn VfsOps>, upper: Arc<dyn VfsOps>) -> Self {
+        Self {
+            lower,
+            upper,
+            parent: Once::new(),
+            lock: Mutex::new(()),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Marks the resolved entries of all nodes as outdated.
+    fn invalidate(&self) {
+        self.version.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Finds the nodes of the normalized `path` in both layers.
+    fn resolve(&self, path: &str) -> VfsResult<Entry> {
+        let mut entry = Entry {
+            upper: Some(self.upper.root_dir()),
+            lower: Some(self.lower.root_dir()),
+        };
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            if !entry.is_dir()? {
+                return Err(VfsError::NotADirectory);
+            } else if name.starts_with(WHITEOUT_PREFIX) {
+                return Err(VfsError::NotFound);
+            }
+            let mut lower_dir = entry.lower_dir()?.cloned();
+            let mut next = Entry {
+                upper: None,
+                lower: None,
+            };
+            if let Some(dir) = &entry.upper {
+                if child(dir, &whiteout_name(name))?.is_some() {
+                    return Err(VfsError::NotFound);
+                }
+                if child(dir, OPAQUE)?.is_some() {
+                    lower_dir = None;
+                }
+                next.upper = child(dir, name)?;
+            }
+            if let Some(dir) = lower_dir {
+                next.lower = child(&dir, name)?;
+            }
+            if next.upper.is_none() && next.lower.is_none() {
+                return Err(VfsError::NotFound);
+            }
+            entry = next;
+        }
+        Ok(entry)
+    }
+
+    /// Returns the merged entries of the directory.
+    fn list(&self, entry: &Entry) -> VfsResult<Vec<(String, VfsNodeType)>> {
+        let mut entries = Vec::new();
+        // names in the upper layer and whiteouts
+        let mut hidden = BTreeSet::new();
+        let mut opaque = false;
+        if let Some(dir) = &entry.upper {
+            for (name, ty) in read_all(dir)? {
+                if name == OPAQUE {
+                    opaque = true;
+                } else if let Some(name) = name.strip_prefix(WHITEOUT_PREFIX) {
+                    hidden.insert(name.to_string());
+                } else {
+                    hidden.insert(name.clone());
+                    entries.push((name, ty));
+                }
+            }
+        }
+        if let Some(dir) = entry.lower_dir()?.filter(|_| !opaque) {
+            for (name, ty) in read_all(dir)? {
+                if !hidden.contains(&name) {
+                    entries.push((name, ty));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Returns the upper node of `path`, copies it and its parent directories
+    /// up from the lower layer if it is not in the upper layer.
+    fn copy_up(&self, path: &str) -> VfsResult<VfsNodeRef> {
+        let entry = self.resolve(path)?;
+        let lower = match entry.upper {
+            Some(upper) => return Ok(upper),
+            None => entry.lower.unwrap(),
+        };
+        let (parent, name) = split_parent(path);
+        let dir = self.copy_up(parent)?;
+        self.invalidate();
+        let attr = lower.get_attr()?;
+        log::debug!("copy up {:?} at overlayfs: {}", attr.file_type(), path);
+        match attr.file_type() {
+            VfsNodeType::Dir | VfsNodeType::File => dir.create(name, attr.file_type())?,
+            VfsNodeType::SymLink => {
+                let mut target = alloc::vec![0; attr.size() as usize];
+                let len = lower.readlink(&mut target)?;
+                let target =
+                    core::str::from_utf8(&target[..len]).map_err(|_| VfsError::InvalidData)?;
+                dir.symlink(name, target)?;
+            }
+            _ => return Err(VfsError::Unsupported),
+        }
+        let upper = dir.lookup(name)?;
+        if attr.is_file() {
+            let mut buf = [0; 1024];
+            let mut offset = 0;
+            loop {
+                let n = lower.read_at(offset, &mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                upper.write_at(offset, &buf[..n])?;
+                offset += n as u64;
+            }
+        }
+        let changes = VfsSetAttr::new()
+            .with_perm(attr.perm())
+            .with_uid(attr.uid())
+            .with_gid(attr.gid())
+            .with_atime(attr.atime())
+            .with_mtime(attr.mtime());
+        upper.set_attr(changes)?;
+        Ok(upper)
+    }
+
+    /// Creates a new node at `path` by `f`, which is called with the parent
+    /// directory in the upper layer and the name.
+    fn create_with<F>(&self, path: &str, is_dir: bool, f: F) -> VfsResult
+    where
+        F: FnOnce(&VfsNodeRef, &str) -> VfsResult,
+    {
+        let (parent, name) = split_parent(path);
+        if name.starts_with(WHITEOUT_PREFIX) {
+            return Err(VfsError::InvalidInput);
+        }
+        match self.resolve(path) {
+            Ok(_) => return Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        if !self.resolve(parent)?.is_dir()? {
+            return Err(VfsError::NotADirectory);
+        }
+        let dir = self.copy_up(parent)?;
+        self.invalidate();
+        let replaced = self.remove_whiteout(&dir, name)?;
+        f(&dir, name)?;
+        if replaced && is_dir {
+            dir.clone()
+                .lookup(name)?
+                .create(OPAQUE, VfsNodeType::File)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the whiteout of `name` in the upper directory, returns whether
+    /// it existed.
+    fn remove_whiteout(&self, dir: &VfsNodeRef, name: &str) -> VfsResult<bool> {
+        let whiteout = whiteout_name(name);
+        if child(dir, &whiteout)?.is_some() {
+            dir.remove(&whiteout)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        let (parent, name) = split_parent(path);
+        let entry = self.resolve(path)?;
+        if entry.is_dir()? && !self.list(&entry)?.is_empty() {
+            return Err(VfsError::DirectoryNotEmpty);
+        }
+        let dir = self.copy_up(parent)?;
+        self.invalidate();
+        if let Some(upper) = &entry.upper {
+            if upper.get_attr()?.is_dir() {
+                // only whiteouts are left
+                for (name, _) in read_all(upper)? {
+                    upper.remove(&name)?;
+                }
+            }
+            dir.remove(name)?;
+        }
+        if entry.lower.is_some() {
+            dir.create(&whiteout_name(name), VfsNodeType::File)?;
+        }
+        Ok(())
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let src = self.resolve(src_path)?;
+        let is_dir = src.is_dir()?;
+        if src_path == dst_path {
+            return Ok(());
+        } else if is_dir && src.lower.is_some() {
+            return Err(VfsError::CrossesDevices);
+        } else if dst_path.starts_with(src_path) && dst_path[src_path.len()..].starts_with('/') {
+            return Err(VfsError::InvalidInput); // move into itself
+        }
+        let (dst_parent, dst_name) = split_parent(dst_path);
+        if dst_name.starts_with(WHITEOUT_PREFIX) {
+            return Err(VfsError::InvalidInput);
+        }
+        let dst_exists = match self.resolve(dst_path) {
+            Ok(dst) => {
+                match (is_dir, dst.is_dir()?) {
+                    (true, false) => return Err(VfsError::NotADirectory),
+                    (false, true) => return Err(VfsError::IsADirectory),
+                    _ => {}
+                }
+                true
+            }
+            Err(VfsError::NotFound) => {
+                if !self.resolve(dst_parent)?.is_dir()? {
+                    return Err(VfsError::NotADirectory);
+                }
+                false
+            }
+            Err(e) => return Err(e),
+        };
+
+        let upper = self.copy_up(src_path)?;
+        let dst_dir = self.copy_up(dst_parent)?;
+        // remove the destination after the copy-ups, so that it is kept if
+        // they fail
+        if dst_exists {
+            self.remove(dst_path)?;
+        }
+        self.invalidate();
+        let replaced = self.remove_whiteout(&dst_dir, dst_name)?;
+        match self.upper.root_dir().rename(src_path, dst_path) {
+            // move files by hard links if the upper layer cannot rename
+            Err(VfsError::Unsupported) if !is_dir => {
+                dst_dir.link(dst_name, &upper)?;
+                self.upper.root_dir().remove(src_path)?;
+            }
+            res => res?,
+        }
+        if src.lower.is_some() {
+            let (src_parent, src_name) = split_parent(src_path);
+            let src_dir = self.copy_up(src_parent)?;
+            src_dir.create(&whiteout_name(src_name), VfsNodeType::File)?;
+        }
+        if replaced && is_dir {
+            dst_dir
+                .lookup(dst_name)?
+                .create(OPAQUE, VfsNodeType::File)?;
+        }
+        Ok(())
+    }
+}
+
+/// A file, directory or symbolic link in the overlay filesystem.
+///
+/// It refers to a path instead of the nodes in the layers, so that it always
+/// accesses the upper node after the file is copied up. The nodes of the
+/// path are cached until the upper layer is modified.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct OverlayNode {
+    layers: Arc<Layers>,
+    /// The path from the root of the overlay, without `.` or `..`.
+    path: String,
+    /// The resolved nodes of the path, and the version of the layers then.
+    cached: Mutex<Option<(u64, Entry)>>,
+}
+
+impl OverlayNode {
+    pub(super) fn new(layers: Arc<Layers>, path: &str) -> Arc<Self> {
+        Arc::new(Self {
+            layers,
+            path: path.into(),
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Returns the path of the node from the root of the overlay.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn entry(&self) -> VfsResult<Entry> {
+        let version = self.layers.version();
+        if let Some((v, entry)) = &*self.cached.lock() {
+            if *v == version {
+                return Ok(entry.clone());
+            }
+        }
+        let entry = self.layers.resolve(&self.path)?;
+        *self.cached.lock() = Some((version, entry.clone()));
+        Ok(entry)
+    }
+
+    /// Returns the upper node, copies the node up if needed.
+    fn upper(&self) -> VfsResult<VfsNodeRef> {
+        if let Some(upper) = self.entry()?.upper {
+            return Ok(upper);
+        }
+        let _guard = self.layers.lock.lock();
+        self.layers.copy_up(&self.path)
+    }
+
+    /// Returns the normalized path of `path` relative to this node.
+    fn full_path(&self, path: &str) -> VfsResult<String> {
+        let mut names: Vec<&str> = self.path.split('/').filter(|s| !s.is_empty()).collect();
+        for name in path.split('/') {
+            match name {
+                "" | "." => {}
+                ".." => {
+                    names.pop().ok_or(VfsError::InvalidInput)?;
+                }
+                _ => names.push(name),
+            }
+        }
+        Ok(names.join("/"))
+    }
+}
+
+impl VfsNodeOps for OverlayNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.entry()?.node().get_attr()
+    }
+
+    fn set_attr(&self, attr: VfsSetAttr) -> VfsResult {
+        self.upper()?.set_attr(attr)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.entry()?.node().read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.upper()?.write_at(offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        match self.entry()?.upper {
+            Some(upper) => upper.fsync(),
+            None => Ok(()),
+        }
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.upper()?.truncate(size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.path.is_empty() {
+            self.layers.parent.get().cloned()
+        } else {
+            let (parent, _) = split_parent(&self.path);
+            Some(Self::new(self.layers.clone(), parent))
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        if !self.entry()?.is_dir()? {
+            return Err(VfsError::NotADirectory);
+        }
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "" | "." => self.clone(),
+            ".." => self.parent().ok_or(VfsError::NotFound)?,
+            _ => {
+                let path = self.full_path(name)?;
+                let version = self.layers.version();
+                let entry = self.layers.resolve(&path)?;
+                let node = Self::new(self.layers.clone(), &path);
+                *node.cached.lock() = Some((version, entry));
+                node
+            }
+        };
+        match rest {
+            Some(rest) => node.lookup(rest),
+            None => Ok(node),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let entry = self.entry()?;
+        if !entry.is_dir()? {
+            return Err(VfsError::NotADirectory);
+        }
+        let entries = self.layers.list(&entry)?;
+        let mut entries = entries.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, ty)) = entries.next() {
+                        *ent = VfsDirEntry::new(name, *ty);
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        log::debug!("create {:?} at overlayfs: {}", ty, path);
+        let path = self.full_path(path)?;
+        if path.is_empty() {
+            return Ok(()); // already exists
+        }
+        let _guard = self.layers.lock.lock();
+        self.layers
+            .create_with(&path, ty == VfsNodeType::Dir, |dir, name| {
+                dir.create(name, ty)
+            })
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        log::debug!("remove at overlayfs: {}", path);
+        let name = path.trim_end_matches('/').rsplit('/').next();
+        if matches!(name, Some("" | "." | "..")) {
+            return Err(VfsError::InvalidInput); // remove '.' or '..'
+        }
+        let path = self.full_path(path)?;
+        let _guard = self.layers.lock.lock();
+        self.layers.remove(&path)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        log::debug!("rename at overlayfs: {} -> {}", src_path, dst_path);
+        let src_path = self.full_path(src_path)?;
+        let dst_path = self.full_path(dst_path)?;
+        if src_path.is_empty() || dst_path.is_empty() {
+            return Err(VfsError::InvalidInput);
+        }
+        let _guard = self.layers.lock.lock();
+        self.layers.rename(&src_path, &dst_path)
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at overlayfs: {} -> {}", path, target);
+        let path = self.full_path(path)?;
+        if path.is_empty() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let _guard = self.layers.lock.lock();
+        self.layers
+            .create_with(&path, false, |dir, name| dir.symlink(name, target))
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        log::debug!("link at overlayfs: {}", path);
+        let src = node
+            .as_any()
+            .downcast_ref::<Self>()
+            .filter(|src| Arc::ptr_eq(&src.layers, &self.layers))
+            .ok_or(VfsError::CrossesDevices)?;
+        if src.get_attr()?.is_dir() {
+            return Err(VfsError::PermissionDenied);
+        }
+        let path = self.full_path(path)?;
+        if path.is_empty() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let _guard = self.layers.lock.lock();
+        let upper = self.layers.copy_up(&src.path)?;
+        self.layers
+            .create_with(&path, false, |dir, name| dir.link(name, &upper))
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        self.entry()?.node().readlink(buf)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
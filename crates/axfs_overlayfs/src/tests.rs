@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use axfs_ramfs::RamFileSystem;
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsResult, VfsSetAttr};
+
+use crate::*;
+
+fn read(root: &VfsNodeRef, path: &str) -> VfsResult<String> {
+    let node = root.clone().lookup(path)?;
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    node.read_at(0, &mut buf)?;
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+fn write(root: &VfsNodeRef, path: &str, data: &str) -> VfsResult {
+    let node = root.clone().lookup(path)?;
+    node.truncate(0)?;
+    node.write_at(0, data.as_bytes())?;
+    Ok(())
+}
+
+fn entries(root: &VfsNodeRef, path: &str) -> Vec<String> {
+    let dir = root.clone().lookup(path).unwrap();
+    let mut buf: [VfsDirEntry; 4] = core::array::from_fn(|_| VfsDirEntry::default());
+    let mut names = Vec::new();
+    let mut start_idx = 0;
+    loop {
+        let n = dir.read_dir(start_idx, &mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        for ent in &buf[..n] {
+            names.push(String::from_utf8(ent.name_as_bytes().to_vec()).unwrap());
+        }
+        start_idx += n;
+    }
+    names.sort();
+    names
+}
+
+/// Creates the lower layer:
+///
+/// ```text
+/// .
+/// ├── etc
+/// │   ├── hostname
+/// │   └── rc.d
+/// │       └── boot
+/// ├── data -> etc/hostname
+/// └── readme
+/// ```
+fn make_lower() -> Arc<RamFileSystem> {
+    let lower = Arc::new(RamFileSystem::new(None, None));
+    let root = lower.root_dir();
+    root.create("etc", VfsNodeType::Dir).unwrap();
+    root.create("etc/rc.d", VfsNodeType::Dir).unwrap();
+    root.create("etc/hostname", VfsNodeType::File).unwrap();
+    root.create("etc/rc.d/boot", VfsNodeType::File).unwrap();
+    root.create("readme", VfsNodeType::File).unwrap();
+    root.symlink("data", "etc/hostname").unwrap();
+    write(&root, "etc/hostname", "arceos\n").unwrap();
+    write(&root, "etc/rc.d/boot", "echo boot\n").unwrap();
+    write(&root, "readme", "read me\n").unwrap();
+    let changes = VfsSetAttr::new()
+        .with_perm(VfsNodePerm::from_bits_truncate(0o600))
+        .with_uid(1000);
+    root.clone()
+        .lookup("etc/hostname")
+        .unwrap()
+        .set_attr(changes)
+        .unwrap();
+    lower
+}
+
+#[test]
+fn test_copy_up() {
+    let lower = make_lower();
+    let upper = Arc::new(RamFileSystem::new(None, None));
+    let overlay = OverlayFileSystem::new(lower.clone(), upper.clone());
+    let root = overlay.root_dir();
+    let lower_root = lower.root_dir();
+    let upper_root = upper.root_dir();
+
+    assert_eq!(read(&root, "etc/hostname").unwrap(), "arceos\n");
+    assert_eq!(entries(&root, "/"), [".", "..", "data", "etc", "readme"]);
+    assert!(upper_root.clone().lookup("etc").is_err());
+    assert_eq!(
+        root.clone().lookup("readme/").err(),
+        Some(VfsError::NotADirectory)
+    );
+
+    // the file and its parent are copied up on write
+    let hostname = root.clone().lookup("etc/hostname").unwrap();
+    hostname.write_at(0, b"ArceOS").unwrap();
+    assert_eq!(read(&root, "etc/hostname").unwrap(), "ArceOS\n");
+    assert_eq!(read(&lower_root, "etc/hostname").unwrap(), "arceos\n");
+    assert_eq!(read(&upper_root, "etc/hostname").unwrap(), "ArceOS\n");
+    let attr = upper_root
+        .clone()
+        .lookup("etc/hostname")
+        .unwrap()
+        .get_attr()
+        .unwrap();
+    assert_eq!(attr.perm().mode(), 0o600);
+    assert_eq!(attr.uid(), 1000);
+    // the lower directory is still merged
+    assert_eq!(entries(&root, "etc"), [".", "..", "hostname", "rc.d"]);
+    assert_eq!(read(&root, "etc/rc.d/boot").unwrap(), "echo boot\n");
+    // nodes looked up before the copy-up access the upper node after it
+    let boot = root.clone().lookup("etc/rc.d/boot").unwrap();
+    write(&root, "etc/rc.d/boot", "echo up\n").unwrap();
+    let mut content = [0; 8];
+    assert_eq!(boot.read_at(0, &mut content), Ok(8));
+    assert_eq!(&content, b"echo up\n");
+
+    // attributes and symbolic links
+    let readme = root.clone().lookup("readme").unwrap();
+    let changes = VfsSetAttr::new().with_perm(VfsNodePerm::from_bits_truncate(0o444));
+    readme.set_attr(changes).unwrap();
+    assert_eq!(readme.get_attr().unwrap().perm().mode(), 0o444);
+    assert_eq!(
+        lower_root
+            .clone()
+            .lookup("readme")
+            .unwrap()
+            .get_attr()
+            .unwrap()
+            .perm()
+            .mode(),
+        0o666
+    );
+    let data = root.clone().lookup("data").unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(data.readlink(&mut buf).unwrap(), 12);
+    assert_eq!(&buf[..12], b"etc/hostname");
+
+    // hard links to a lower file
+    root.link("readme2", &readme).unwrap();
+    readme.truncate(0).unwrap();
+    assert_eq!(read(&root, "readme2").unwrap(), "");
+    assert_eq!(read(&lower_root, "readme").unwrap(), "read me\n");
+}
+
+#[test]
+fn test_whiteout() {
+    let lower = make_lower();
+    let upper = Arc::new(RamFileSystem::new(None, None));
+    let overlay = OverlayFileSystem::new(lower.clone(), upper.clone());
+    let root = overlay.root_dir();
+    let lower_root = lower.root_dir();
+
+    // remove files in the lower layer
+    root.remove("readme").unwrap();
+    assert_eq!(
+        root.clone().lookup("readme").err(),
+        Some(VfsError::NotFound)
+    );
+    assert!(lower_root.clone().lookup("readme").is_ok());
+    assert_eq!(entries(&root, "/"), [".", "..", "data", "etc"]);
+    assert_eq!(root.remove("readme").err(), Some(VfsError::NotFound));
+    assert_eq!(root.remove("etc").err(), Some(VfsError::DirectoryNotEmpty));
+    assert_eq!(
+        root.clone().lookup(".wh.readme").err(),
+        Some(VfsError::NotFound)
+    );
+    assert_eq!(
+        root.create(".wh.x", VfsNodeType::File).err(),
+        Some(VfsError::InvalidInput)
+    );
+
+    // create a file over a whiteout
+    root.create("readme", VfsNodeType::File).unwrap();
+    assert_eq!(read(&root, "readme").unwrap(), "");
+    write(&root, "readme", "new\n").unwrap();
+    assert_eq!(read(&root, "readme").unwrap(), "new\n");
+
+    // remove a lower directory, and create an opaque one at its place
+    root.remove("etc/rc.d/boot").unwrap();
+    assert_eq!(entries(&root, "etc/rc.d"), [".", ".."]);
+    root.remove("etc/rc.d").unwrap();
+    root.remove("etc/hostname").unwrap();
+    root.remove("etc").unwrap();
+    assert_eq!(entries(&root, "/"), [".", "..", "data", "readme"]);
+    root.create("etc", VfsNodeType::Dir).unwrap();
+    root.create("etc/passwd", VfsNodeType::File).unwrap();
+    assert_eq!(entries(&root, "etc"), [".", "..", "passwd"]);
+    assert_eq!(
+        root.clone().lookup("etc/hostname").err(),
+        Some(VfsError::NotFound)
+    );
+    assert_eq!(read(&lower_root, "etc/rc.d/boot").unwrap(), "echo boot\n");
+}
+
+#[test]
+fn test_rename() {
+    let lower = make_lower();
+    let upper = Arc::new(RamFileSystem::new(None, None));
+    let overlay = OverlayFileSystem::new(lower.clone(), upper);
+    let root = overlay.root_dir();
+    let lower_root = lower.root_dir();
+
+    // files are moved by hard links in ramfs
+    root.rename("readme", "etc/readme").unwrap();
+    assert_eq!(read(&root, "etc/readme").unwrap(), "read me\n");
+    assert_eq!(
+        root.clone().lookup("readme").err(),
+        Some(VfsError::NotFound)
+    );
+    assert!(lower_root.clone().lookup("readme").is_ok());
+
+    // replace an existing file
+    root.rename("etc/readme", "etc/hostname").unwrap();
+    assert_eq!(read(&root, "etc/hostname").unwrap(), "read me\n");
+    assert_eq!(entries(&root, "etc"), [".", "..", "hostname", "rc.d"]);
+
+    assert_eq!(
+        root.rename("etc/rc.d", "rc.d").err(),
+        Some(VfsError::CrossesDevices)
+    );
+    assert_eq!(
+        root.rename("etc/hostname", "etc/rc.d").err(),
+        Some(VfsError::IsADirectory)
+    );
+    assert_eq!(root.rename("nothing", "x").err(), Some(VfsError::NotFound));
+}
+
+#[test]
+fn test_parent() {
+    let overlay = OverlayFileSystem::new(make_lower(), Arc::new(RamFileSystem::new(None, None)));
+    let root = overlay.root_dir();
+    assert!(root.parent().is_none());
+
+    let boot = root.clone().lookup("etc/rc.d/boot").unwrap();
+    let rc = boot.parent().unwrap();
+    assert_eq!(
+        rc.as_any().downcast_ref::<OverlayNode>().unwrap().path(),
+        "etc/rc.d"
+    );
+    let etc = rc.clone().lookup("..").unwrap();
+    assert!(etc.clone().lookup("hostname").is_ok());
+    let node = root.lookup("etc/rc.d/../..").unwrap();
+    assert_eq!(
+        node.as_any().downcast_ref::<OverlayNode>().unwrap().path(),
+        ""
+    );
+}
@@ -0,0 +1,205 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use axfs_vfs::{alloc_ino, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+use spin::RwLock;
+
+type ListFn = dyn Fn() -> Vec<String> + Send + Sync;
+type OpenFn = dyn Fn(&str) -> Option<Arc<ProcDir>> + Send + Sync;
+
+/// A fixed child, subdirectories are kept in their concrete type so that
+/// [`ProcDir::mkdir`] can return the existing one.
+enum Child {
+    Dir(Arc<ProcDir>),
+    Node(VfsNodeRef),
+}
+
+impl Child {
+    fn node(&self) -> VfsNodeRef {
+        match self {
+            Self::Dir(dir) => dir.clone(),
+            Self::Node(node) => node.clone(),
+        }
+    }
+}
+
+/// Generator of the dynamic subdirectories.
+struct DynamicEntries {
+    list: Box<ListFn>,
+    open: Box<OpenFn>,
+}
+
+/// The directory node in the process information filesystem.
+///
+/// Besides the fixed children, it can have subdirectories generated on
+/// demand, see [`ProcDir::set_dynamic`].
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct ProcDir {
+    ino: u64,
+    parent: RwLock<Weak<dyn VfsNodeOps>>,
+    children: RwLock<BTreeMap<String, Child>>,
+    dynamic: RwLock<Option<DynamicEntries>>,
+}
+
+impl ProcDir {
+    /// Create a new directory without parent.
+    ///
+    /// It is used for the root or dynamic subdirectories, whose parent is set
+    /// when they are looked up.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            ino: alloc_ino(),
+            parent: RwLock::new(Weak::<Self>::new()),
+            children: RwLock::new(BTreeMap::new()),
+            dynamic: RwLock::new(None),
+        })
+    }
+
+    pub(super) fn set_parent(&self, parent: Option<&VfsNodeRef>) {
+        *self.parent.write() = parent.map_or(Weak::<Self>::new() as _, Arc::downgrade);
+    }
+
+    /// Create a subdirectory at this directory, or returns the existing one.
+    pub fn mkdir(self: &Arc<Self>, name: &str) -> Arc<Self> {
+        if let Some(Child::Dir(dir)) = self.children.read().get(name) {
+            return dir.clone();
+        }
+        let node = Self::new();
+        node.set_parent(Some(&(self.clone() as VfsNodeRef)));
+        self.children
+            .write()
+            .insert(name.into(), Child::Dir(node.clone()));
+        node
+    }
+
+    /// Add a node (usually a [`ProcFile`](crate::ProcFile)) to this directory.
+    pub fn add(&self, name: &str, node: VfsNodeRef) {
+        self.children.write().insert(name.into(), Child::Node(node));
+    }
+
+    /// Set the generator of dynamic subdirectories.
+    ///
+    /// `list` returns the names of the subdirectories to be listed, and `open`
+    /// creates the subdirectory by its name, or returns `None` if it does not
+    /// exist. Names that are not listed (e.g. `self`) can also be opened.
+    /// Fixed children take precedence over the dynamic ones.
+    pub fn set_dynamic<L, O>(&self, list: L, open: O)
+    where
+        L: Fn() -> Vec<String> + Send + Sync + 'static,
+        O: Fn(&str) -> Option<Arc<Self>> + Send + Sync + 'static,
+    {
+        *self.dynamic.write() = Some(DynamicEntries {
+            list: Box::new(list),
+            open: Box::new(open),
+        });
+    }
+
+    fn child(self: &Arc<Self>, name: &str) -> VfsResult<VfsNodeRef> {
+        if let Some(child) = self.children.read().get(name) {
+            return Ok(child.node());
+        }
+        let dynamic = self.dynamic.read();
+        let dir = dynamic
+            .as_ref()
+            .and_then(|d| (d.open)(name))
+            .ok_or(VfsError::NotFound)?;
+        dir.set_parent(Some(&(self.clone() as VfsNodeRef)));
+        Ok(dir)
+    }
+}
+
+impl VfsNodeOps for ProcDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(4096, 0).with_ino(self.ino))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.read().upgrade()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => Ok(self.clone() as VfsNodeRef),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self.child(name),
+        }?;
+
+        if let Some(rest) = rest {
+            node.lookup(rest)
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children = self.children.read();
+        let dynamic = self.dynamic.read();
+        let dynamic_names = dynamic.as_ref().map_or(Vec::new(), |d| (d.list)());
+        let mut entries = children
+            .iter()
+            .map(|(name, child)| {
+                let ty = child.node().get_attr()?.file_type();
+                Ok((name.as_str(), ty))
+            })
+            .chain(
+                dynamic_names
+                    .iter()
+                    .map(|name| Ok((name.as_str(), VfsNodeType::Dir))),
+            )
+            .skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some(res) = entries.next() {
+                        let (name, ty) = res?;
+                        *ent = VfsDirEntry::new(name, ty);
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        log::debug!("create {:?} at procfs: {}", ty, path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.create(rest, ty),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
+                _ => self
+                    .children
+                    .read()
+                    .get(name)
+                    .ok_or(VfsError::NotFound)?
+                    .node()
+                    .create(rest, ty),
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Ok(()) // already exists
+        } else {
+            Err(VfsError::PermissionDenied) // do not support to create nodes dynamically
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        log::debug!("remove at procfs: {}", path);
+        Err(VfsError::PermissionDenied) // do not support to remove nodes
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
@@ -0,0 +1,89 @@
+use alloc::{boxed::Box, string::String};
+use axfs_vfs::{alloc_ino, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+type ReadFn = dyn Fn() -> String + Send + Sync;
+type WriteFn = dyn Fn(&str) -> VfsResult + Send + Sync;
+
+/// The file node in the process information filesystem.
+///
+/// Its contents are generated by a callback each time it is read. If it is
+/// writable, the written data are passed to another callback as a string.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct ProcFile {
+    ino: u64,
+    read: Box<ReadFn>,
+    write: Option<Box<WriteFn>>,
+}
+
+impl ProcFile {
+    /// Create a read-only file whose contents are generated by `read`.
+    pub fn new<R>(read: R) -> Self
+    where
+        R: Fn() -> String + Send + Sync + 'static,
+    {
+        Self {
+            ino: alloc_ino(),
+            read: Box::new(read),
+            write: None,
+        }
+    }
+
+    /// Create a writable file. The contents are generated by `read`, and
+    /// `write` is called with the data written from offset 0.
+    pub fn new_rw<R, W>(read: R, write: W) -> Self
+    where
+        R: Fn() -> String + Send + Sync + 'static,
+        W: Fn(&str) -> VfsResult + Send + Sync + 'static,
+    {
+        Self {
+            ino: alloc_ino(),
+            read: Box::new(read),
+            write: Some(Box::new(write)),
+        }
+    }
+}
+
+impl VfsNodeOps for ProcFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = if self.write.is_some() { 0o644 } else { 0o444 };
+        // like Linux, the size is unknown until the contents are generated
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(perm),
+            VfsNodeType::File,
+            0,
+            0,
+        )
+        .with_ino(self.ino))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = (self.read)();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(start + buf.len());
+        let src = &content.as_bytes()[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let write = self.write.as_ref().ok_or(VfsError::PermissionDenied)?;
+        if offset != 0 {
+            return Err(VfsError::InvalidInput);
+        }
+        let value = core::str::from_utf8(buf).map_err(|_| VfsError::InvalidInput)?;
+        write(value)?;
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        // allow opening with `O_TRUNC` before writing
+        if self.write.is_some() {
+            Ok(())
+        } else {
+            Err(VfsError::PermissionDenied)
+        }
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
@@ -0,0 +1,67 @@
+//! Process information pseudo-filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
+//!
+//! The implementation is based on [`axfs_vfs`].
+//!
+//! Files in it do not store data, the contents are generated by callbacks
+//! each time they are read, and writes are passed to callbacks to change
+//! kernel parameters. Directories can also generate their entries
+//! dynamically, e.g. one for each task.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod dir;
+mod file;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::dir::ProcDir;
+pub use self::file::ProcFile;
+
+use alloc::sync::Arc;
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use spin::once::Once;
+
+/// A process information filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct ProcFileSystem {
+    parent: Once<VfsNodeRef>,
+    root: Arc<ProcDir>,
+}
+
+impl ProcFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            parent: Once::new(),
+            root: ProcDir::new(),
+        }
+    }
+
+    /// Returns the root directory node in [`Arc<ProcDir>`](ProcDir).
+    pub fn root_dir_node(&self) -> Arc<ProcDir> {
+        self.root.clone()
+    }
+}
+
+impl VfsOps for ProcFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            self.root.set_parent(Some(self.parent.call_once(|| parent)));
+        } else {
+            self.root.set_parent(None);
+        }
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+impl Default for ProcFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeType, VfsResult};
+
+use crate::*;
+
+static VALUE: AtomicUsize = AtomicUsize::new(42);
+
+fn read_to_string(node: &axfs_vfs::VfsNodeRef) -> VfsResult<String> {
+    let mut buf = [0; 64];
+    let mut content = Vec::new();
+    loop {
+        let n = node.read_at(content.len() as u64, &mut buf[..5])?;
+        if n == 0 {
+            break;
+        }
+        content.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8(content).unwrap())
+}
+
+fn new_procfs() -> ProcFileSystem {
+    let procfs = ProcFileSystem::new();
+    let root = procfs.root_dir_node();
+    root.add(
+        "version",
+        Arc::new(ProcFile::new(|| "ArceOS version 0.1.0\n".into())),
+    );
+    let vm = root.mkdir("sys").mkdir("vm");
+    vm.add(
+        "value",
+        Arc::new(ProcFile::new_rw(
+            || format!("{}\n", VALUE.load(Ordering::Relaxed)),
+            |s| {
+                let value = s.trim().parse().map_err(|_| VfsError::InvalidInput)?;
+                VALUE.store(value, Ordering::Relaxed);
+                Ok(())
+            },
+        )),
+    );
+    root.set_dynamic(
+        || vec!["1".into(), "2".into()],
+        |name| {
+            let id: usize = match name {
+                "self" => 1,
+                _ => name.parse().ok().filter(|&id| id == 1 || id == 2)?,
+            };
+            let dir = ProcDir::new();
+            dir.add("id", Arc::new(ProcFile::new(move || format!("{}\n", id))));
+            Some(dir)
+        },
+    );
+    procfs
+}
+
+fn test_procfs_ops(procfs: &ProcFileSystem) -> VfsResult {
+    let root = procfs.root_dir();
+
+    // generated contents
+    let version = root.clone().lookup("version")?;
+    assert_eq!(version.get_attr()?.file_type(), VfsNodeType::File);
+    assert_eq!(read_to_string(&version)?, "ArceOS version 0.1.0\n");
+    assert_eq!(
+        version.write_at(0, b"1").err(),
+        Some(VfsError::PermissionDenied)
+    );
+
+    // writable files change the state
+    let value = root.clone().lookup("/sys//vm/value")?;
+    assert_eq!(read_to_string(&value)?, "42\n");
+    value.truncate(0)?;
+    assert_eq!(value.write_at(0, b"100\n")?, 4);
+    assert_eq!(VALUE.load(Ordering::Relaxed), 100);
+    assert_eq!(read_to_string(&value)?, "100\n");
+    assert_eq!(
+        value.write_at(0, b"abc").err(),
+        Some(VfsError::InvalidInput)
+    );
+    assert_eq!(VALUE.load(Ordering::Relaxed), 100);
+
+    // `mkdir` returns the existing directory
+    let sys = procfs.root_dir_node().mkdir("sys");
+    assert!(Arc::ptr_eq(
+        &(sys.mkdir("vm") as axfs_vfs::VfsNodeRef),
+        &root.clone().lookup("sys/vm")?
+    ));
+
+    // dynamic directories
+    assert_eq!(read_to_string(&root.clone().lookup("2/id")?)?, "2\n");
+    assert_eq!(read_to_string(&root.clone().lookup("self/id")?)?, "1\n");
+    assert_eq!(read_to_string(&root.clone().lookup("1/../2/id")?)?, "2\n");
+    assert_eq!(root.clone().lookup("3").err(), Some(VfsError::NotFound));
+
+    let mut dirents: [VfsDirEntry; 8] = core::array::from_fn(|_| VfsDirEntry::default());
+    let n = root.read_dir(0, &mut dirents)?;
+    let names = dirents[..n]
+        .iter()
+        .map(|e| {
+            (
+                std::str::from_utf8(e.name_as_bytes()).unwrap(),
+                e.entry_type(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            (".", VfsNodeType::Dir),
+            ("..", VfsNodeType::Dir),
+            ("sys", VfsNodeType::Dir),
+            ("version", VfsNodeType::File),
+            ("1", VfsNodeType::Dir),
+            ("2", VfsNodeType::Dir),
+        ]
+    );
+    assert_eq!(root.read_dir(5, &mut dirents)?, 1);
+
+    assert_eq!(
+        root.create("new", VfsNodeType::File).err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(
+        root.remove("version").err(),
+        Some(VfsError::PermissionDenied)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_procfs() {
+    let procfs = new_procfs();
+    test_procfs_ops(&procfs).expect("test_procfs_ops() failed");
+}
@@ -0,0 +1,194 @@
+//! Unpacking of newc-format cpio archives, e.g. initramfs images.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use axfs_vfs::{VfsError, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult, VfsSetAttr};
+use core::time::Duration;
+
+use crate::RamFileSystem;
+
+/// Length of the header of each entry.
+const HEADER_LEN: usize = 110;
+/// Name of the last entry.
+const TRAILER: &str = "TRAILER!!!";
+
+/// Mask of the file type bits in the mode.
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// The header of an entry, made up of 8-digit hexadecimal fields after the
+/// magic number.
+struct Header {
+    ino: u32,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    nlink: u32,
+    mtime: u32,
+    filesize: u32,
+    namesize: u32,
+}
+
+impl Header {
+    fn parse(buf: &[u8]) -> VfsResult<Self> {
+        let buf = buf.get(..HEADER_LEN).ok_or(VfsError::InvalidData)?;
+        // "070702" is the same format with checksums of the data
+        if &buf[..6] != b"070701" && &buf[..6] != b"070702" {
+            return Err(VfsError::InvalidData);
+        }
+        let field = |idx: usize| {
+            let digits = &buf[6 + idx * 8..14 + idx * 8];
+            core::str::from_utf8(digits)
+                .ok()
+                .and_then(|s| u32::from_str_radix(s, 16).ok())
+                .ok_or(VfsError::InvalidData)
+        };
+        Ok(Self {
+            ino: field(0)?,
+            mode: field(1)?,
+            uid: field(2)?,
+            gid: field(3)?,
+            nlink: field(4)?,
+            mtime: field(5)?,
+            filesize: field(6)?,
+            // skip the device numbers
+            namesize: field(11)?,
+        })
+    }
+
+    fn attr(&self) -> VfsSetAttr {
+        VfsSetAttr::new()
+            .with_perm(VfsNodePerm::from_bits_truncate(self.mode as u16))
+            .with_uid(self.uid)
+            .with_gid(self.gid)
+            .with_mtime(Duration::from_secs(self.mtime as u64))
+    }
+}
+
+const fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// Creates the missing parent directories of `path`.
+fn create_parents(root: &VfsNodeRef, path: &str) -> VfsResult {
+    for (idx, _) in path.match_indices('/') {
+        match root.create(&path[..idx], VfsNodeType::Dir) {
+            Ok(()) | Err(VfsError::AlreadyExists) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Removes the existing file or symbolic link at `path`, so that it can be
+/// replaced by a later entry of the archive.
+fn remove_existing(root: &VfsNodeRef, path: &str) -> VfsResult {
+    match root.clone().lookup(path) {
+        Ok(node) if node.get_attr()?.is_dir() => Err(VfsError::IsADirectory),
+        Ok(_) => root.remove(path),
+        Err(_) => Ok(()),
+    }
+}
+
+impl RamFileSystem {
+    /// Creates a RAM filesystem with the contents of a newc-format cpio
+    /// archive, see [`unpack_cpio`](Self::unpack_cpio).
+    pub fn from_cpio(archive: &[u8]) -> VfsResult<Self> {
+        let fs = Self::default();
+        fs.unpack_cpio(archive)?;
+        Ok(fs)
+    }
+
+    /// Extracts a newc-format cpio archive (`cpio -H newc`) into the
+    /// filesystem.
+    ///
+    /// Directories, regular files (including hard links) and symbolic links
+    /// are extracted with their permission bits, owners and modification
+    /// times. Other types of files (device nodes, FIFOs and sockets) are
+    /// skipped. Missing parent directories are created, and existing files
+    /// are replaced by later entries.
+    ///
+    /// Returns [`InvalidData`](VfsError::InvalidData) if the archive is
+    /// malformed.
+    pub fn unpack_cpio(&self, archive: &[u8]) -> VfsResult {
+        let root = self.root_dir();
+        // the first path of each hard linked inode
+        let mut inodes = BTreeMap::<u32, String>::new();
+        // attributes of directories are set at last, since creating entries
+        // in them changes their modification times
+        let mut dirs = Vec::new();
+        let mut offset = 0;
+        loop {
+            let header = Header::parse(archive.get(offset..).ok_or(VfsError::InvalidData)?)?;
+            let name_start = offset + HEADER_LEN;
+            let name_end = name_start + header.namesize as usize;
+            let data_start = align4(name_end);
+            let data_end = data_start + header.filesize as usize;
+            let name = archive
+                .get(name_start..name_end)
+                .ok_or(VfsError::InvalidData)?;
+            let name = name.strip_suffix(&[0]).unwrap_or(name);
+            let name = core::str::from_utf8(name).map_err(|_| VfsError::InvalidData)?;
+            let data = archive
+                .get(data_start..data_end)
+                .ok_or(VfsError::InvalidData)?;
+            offset = align4(data_end);
+            if name == TRAILER {
+                break;
+            }
+
+            let path = name.trim_start_matches("./").trim_start_matches('/');
+            log::debug!("unpack cpio: {} ({:#o})", path, header.mode);
+            create_parents(&root, path)?;
+            let node = match header.mode & S_IFMT {
+                S_IFDIR => {
+                    match root.create(path, VfsNodeType::Dir) {
+                        Ok(()) | Err(VfsError::AlreadyExists) => {}
+                        Err(e) => return Err(e),
+                    }
+                    dirs.push((root.clone().lookup(path)?, header.attr()));
+                    continue;
+                }
+                S_IFREG => {
+                    remove_existing(&root, path)?;
+                    // the data is stored in the last entry of hard links
+                    match inodes.get(&header.ino) {
+                        Some(first) if header.nlink > 1 => {
+                            root.link(path, &root.clone().lookup(first)?)?
+                        }
+                        _ => {
+                            root.create(path, VfsNodeType::File)?;
+                            if header.nlink > 1 {
+                                inodes.insert(header.ino, String::from(path));
+                            }
+                        }
+                    }
+                    let node = root.clone().lookup(path)?;
+                    if !data.is_empty() {
+                        node.truncate(0)?;
+                        node.write_at(0, data)?;
+                    }
+                    node
+                }
+                S_IFLNK => {
+                    let target = core::str::from_utf8(data).map_err(|_| VfsError::InvalidData)?;
+                    remove_existing(&root, path)?;
+                    root.symlink(path, target)?;
+                    root.clone().lookup(path)?
+                }
+                _ => {
+                    log::warn!("unpack cpio: skip {} of mode {:#o}", path, header.mode);
+                    continue;
+                }
+            };
+            node.set_attr(header.attr())?;
+        }
+        for (dir, attr) in dirs {
+            dir.set_attr(attr)?;
+        }
+        Ok(())
+    }
+}
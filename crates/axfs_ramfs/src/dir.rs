@@ -3,10 +3,13 @@ use alloc::sync::{Arc, Weak};
 use alloc::{string::String, vec::Vec};
 
 use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
-use axfs_vfs::{VfsError, VfsResult};
+use axfs_vfs::{VfsError, VfsResult, VfsSetAttr};
 use spin::RwLock;
 
 use crate::file::FileNode;
+use crate::meta::NodeMeta;
+use crate::symlink::SymlinkNode;
+use crate::usage::Usage;
 
 /// The directory node in the RAM filesystem.
 ///
@@ -15,14 +18,19 @@ pub struct DirNode {
     this: Weak<DirNode>,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
     children: RwLock<BTreeMap<String, VfsNodeRef>>,
+    meta: NodeMeta,
+    usage: Arc<Usage>,
 }
 
 impl DirNode {
-    pub(super) fn new(parent: Option<Weak<dyn VfsNodeOps>>) -> Arc<Self> {
+    /// Creates an empty directory, whose inode is reserved in `usage`.
+    pub(super) fn new(parent: Option<Weak<dyn VfsNodeOps>>, usage: Arc<Usage>) -> Arc<Self> {
         Arc::new_cyclic(|this| Self {
             this: this.clone(),
             parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
             children: RwLock::new(BTreeMap::new()),
+            meta: NodeMeta::new(VfsNodeAttr::new_dir(0, 0)),
+            usage,
         })
     }
 
@@ -46,12 +54,57 @@ impl DirNode {
             log::error!("AlreadyExists {}", name);
             return Err(VfsError::AlreadyExists);
         }
-        let node: VfsNodeRef = match ty {
-            VfsNodeType::File => Arc::new(FileNode::new()),
-            VfsNodeType::Dir => Self::new(Some(self.this.clone())),
-            _ => return Err(VfsError::Unsupported),
+        if !matches!(ty, VfsNodeType::File | VfsNodeType::Dir) {
+            return Err(VfsError::Unsupported);
+        }
+        self.usage.alloc_inode()?;
+        let node: VfsNodeRef = if ty == VfsNodeType::File {
+            Arc::new(FileNode::new(self.usage.clone()))
+        } else {
+            Self::new(Some(self.this.clone()), self.usage.clone())
         };
         self.children.write().insert(name.into(), node);
+        self.meta.modified();
+        Ok(())
+    }
+
+    /// Creates a new symbolic link with the given name in this directory, which
+    /// points to `target`.
+    pub fn create_symlink(&self, name: &str, target: &str) -> VfsResult {
+        if self.exist(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        self.usage.alloc_inode()?;
+        let node = Arc::new(SymlinkNode::new(target, self.usage.clone()));
+        self.children.write().insert(name.into(), node);
+        self.meta.modified();
+        Ok(())
+    }
+
+    /// Adds the existing `node` to this directory with the given name.
+    ///
+    /// Only files and symbolic links of the same RAM filesystem can be
+    /// linked.
+    pub fn link_node(&self, name: &str, node: &VfsNodeRef) -> VfsResult {
+        if self.exist(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        let any = node.as_any();
+        let (usage, meta) = if any.is::<DirNode>() {
+            return Err(VfsError::PermissionDenied);
+        } else if let Some(file) = any.downcast_ref::<FileNode>() {
+            (file.usage(), file.meta())
+        } else if let Some(symlink) = any.downcast_ref::<SymlinkNode>() {
+            (symlink.usage(), symlink.meta())
+        } else {
+            return Err(VfsError::CrossesDevices);
+        };
+        if !Arc::ptr_eq(usage, &self.usage) {
+            return Err(VfsError::CrossesDevices);
+        }
+        self.children.write().insert(name.into(), node.clone());
+        meta.add_links(1);
+        self.meta.modified();
         Ok(())
     }
 
@@ -59,19 +112,121 @@ impl DirNode {
     pub fn remove_node(&self, name: &str) -> VfsResult {
         let mut children = self.children.write();
         let node = children.get(name).ok_or(VfsError::NotFound)?;
-        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+        let any = node.as_any();
+        if let Some(dir) = any.downcast_ref::<DirNode>() {
             if !dir.children.read().is_empty() {
                 return Err(VfsError::DirectoryNotEmpty);
             }
+        } else {
+            unlink(node);
         }
         children.remove(name);
+        self.meta.modified();
+        Ok(())
+    }
+
+    /// Returns the directory that contains the entry at `path`, and the name
+    /// of the entry.
+    fn parent_of<'a>(&self, path: &'a str) -> VfsResult<(VfsNodeRef, &'a str)> {
+        let path = path.trim_matches('/');
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+        if matches!(name, "" | "." | "..") {
+            return Err(VfsError::InvalidInput);
+        }
+        let this = self.this.upgrade().ok_or(VfsError::NotFound)?;
+        let dir = this.lookup(dir)?;
+        if !dir.as_any().is::<DirNode>() {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok((dir, name))
+    }
+
+    /// Moves the entry at `src_path` to `dst_path`, both relative to this
+    /// directory. An existing file or empty directory at `dst_path` is
+    /// replaced.
+    pub fn rename_node(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let (src_ref, src_name) = self.parent_of(src_path)?;
+        let (dst_ref, dst_name) = self.parent_of(dst_path)?;
+        let src_dir = src_ref.as_any().downcast_ref::<DirNode>().unwrap();
+        let dst_dir = dst_ref.as_any().downcast_ref::<DirNode>().unwrap();
+
+        let node = src_dir
+            .children
+            .read()
+            .get(src_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        let is_dir = node.as_any().is::<DirNode>();
+        if let Some(old) = dst_dir.children.read().get(dst_name) {
+            if Arc::ptr_eq(old, &node) {
+                return Ok(()); // the same node
+            }
+            match old.as_any().downcast_ref::<DirNode>() {
+                Some(_) if !is_dir => return Err(VfsError::IsADirectory),
+                Some(old) if !old.children.read().is_empty() => {
+                    return Err(VfsError::DirectoryNotEmpty)
+                }
+                None if is_dir => return Err(VfsError::NotADirectory),
+                _ => {}
+            }
+        }
+        // a directory cannot be moved into itself
+        let mut ancestor = Some(dst_ref.clone());
+        while let Some(dir) = ancestor {
+            if Arc::ptr_eq(&dir, &node) {
+                return Err(VfsError::InvalidInput);
+            }
+            ancestor = dir.parent();
+        }
+
+        src_dir.children.write().remove(src_name);
+        if let Some(old) = dst_dir
+            .children
+            .write()
+            .insert(dst_name.into(), node.clone())
+        {
+            unlink(&old);
+        }
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+            dir.set_parent(Some(&dst_ref));
+        }
+        src_dir.meta.modified();
+        dst_dir.meta.modified();
         Ok(())
     }
 }
 
+/// Drops a link to the file or symbolic link `node`.
+fn unlink(node: &VfsNodeRef) {
+    let any = node.as_any();
+    if let Some(file) = any.downcast_ref::<FileNode>() {
+        file.meta().add_links(-1);
+    } else if let Some(symlink) = any.downcast_ref::<SymlinkNode>() {
+        symlink.meta().add_links(-1);
+    }
+}
+
+impl Drop for DirNode {
+    fn drop(&mut self) {
+        self.usage.free_inode();
+    }
+}
+
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(4096, 0))
+        // linked from the parent, `.` and `..` of each subdirectory
+        let subdirs = self
+            .children
+            .read()
+            .values()
+            .filter(|node| node.as_any().is::<DirNode>())
+            .count();
+        Ok(self.meta.attr(4096, 0).with_nlink(2 + subdirs as u32))
+    }
+
+    fn set_attr(&self, attr: VfsSetAttr) -> VfsResult {
+        self.meta.set(attr);
+        Ok(())
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -107,7 +262,8 @@ impl VfsNodeOps for DirNode {
                 1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
                 _ => {
                     if let Some((name, node)) = children.next() {
-                        *ent = VfsDirEntry::new(name, node.get_attr().unwrap().file_type());
+                        let attr = node.get_attr().unwrap();
+                        *ent = VfsDirEntry::new(name, attr.file_type()).with_ino(attr.ino());
                     } else {
                         return Ok(i);
                     }
@@ -165,6 +321,62 @@ impl VfsNodeOps for DirNode {
         }
     }
 
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at ramfs: {} -> {}", path, target);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.symlink(rest, target),
+                ".." => self
+                    .parent()
+                    .ok_or(VfsError::NotFound)?
+                    .symlink(rest, target),
+                _ => {
+                    let subdir = self
+                        .children
+                        .read()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.symlink(rest, target)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.create_symlink(name, target)
+        }
+    }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        log::debug!("link at ramfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.link(rest, node),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.link(rest, node),
+                _ => {
+                    let subdir = self
+                        .children
+                        .read()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.link(rest, node)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.link_node(name, node)
+        }
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        log::debug!("rename at ramfs: {} -> {}", src_path, dst_path);
+        self.rename_node(src_path, dst_path)
+    }
+
     axfs_vfs::impl_vfs_dir_default! {}
 }
 
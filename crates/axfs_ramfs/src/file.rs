@@ -1,34 +1,70 @@
-use alloc::vec::Vec;
-use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use alloc::{sync::Arc, vec::Vec};
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult, VfsSetAttr};
 use spin::RwLock;
 
+use crate::meta::NodeMeta;
+use crate::usage::Usage;
+
 /// The file node in the RAM filesystem.
 ///
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct FileNode {
     content: RwLock<Vec<u8>>,
+    meta: NodeMeta,
+    usage: Arc<Usage>,
 }
 
 impl FileNode {
-    pub(super) const fn new() -> Self {
+    /// Creates an empty file, whose inode is reserved in `usage`.
+    pub(super) fn new(usage: Arc<Usage>) -> Self {
         Self {
             content: RwLock::new(Vec::new()),
+            meta: NodeMeta::new(VfsNodeAttr::new_file(0, 0)),
+            usage,
+        }
+    }
+
+    pub(super) fn usage(&self) -> &Arc<Usage> {
+        &self.usage
+    }
+
+    pub(super) fn meta(&self) -> &NodeMeta {
+        &self.meta
+    }
+
+    /// Resizes the content, and updates the used bytes of the filesystem.
+    fn resize(&self, content: &mut Vec<u8>, size: usize) -> VfsResult {
+        if size > content.len() {
+            self.usage.alloc_bytes(size - content.len())?;
+        } else {
+            self.usage.free_bytes(content.len() - size);
         }
+        content.resize(size, 0);
+        Ok(())
+    }
+}
+
+impl Drop for FileNode {
+    fn drop(&mut self) {
+        self.usage.free_bytes(self.content.get_mut().len());
+        self.usage.free_inode();
     }
 }
 
 impl VfsNodeOps for FileNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_file(self.content.read().len() as _, 0))
+        Ok(self.meta.attr(self.content.read().len() as _, 0))
+    }
+
+    fn set_attr(&self, attr: VfsSetAttr) -> VfsResult {
+        self.meta.set(attr);
+        Ok(())
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
         let mut content = self.content.write();
-        if size < content.len() as u64 {
-            content.truncate(size as _);
-        } else {
-            content.resize(size as _, 0);
-        }
+        self.resize(&mut content, size as _)?;
+        self.meta.modified();
         Ok(())
     }
 
@@ -38,6 +74,7 @@ impl VfsNodeOps for FileNode {
         let end = content.len().min(offset as usize + buf.len());
         let src = &content[start..end];
         buf[..src.len()].copy_from_slice(src);
+        self.meta.accessed();
         Ok(src.len())
     }
 
@@ -45,12 +82,44 @@ impl VfsNodeOps for FileNode {
         let offset = offset as usize;
         let mut content = self.content.write();
         if offset + buf.len() > content.len() {
-            content.resize(offset + buf.len(), 0);
+            self.resize(&mut content, offset + buf.len())?;
         }
         let dst = &mut content[offset..offset + buf.len()];
         dst.copy_from_slice(&buf[..dst.len()]);
+        self.meta.modified();
         Ok(buf.len())
     }
 
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [&mut [u8]]) -> VfsResult<usize> {
+        let content = self.content.read();
+        let mut src = &content[content.len().min(offset as usize)..];
+        let mut read_len = 0;
+        for buf in bufs {
+            let len = buf.len().min(src.len());
+            buf[..len].copy_from_slice(&src[..len]);
+            src = &src[len..];
+            read_len += len;
+        }
+        self.meta.accessed();
+        Ok(read_len)
+    }
+
+    fn write_vectored_at(&self, offset: u64, bufs: &[&[u8]]) -> VfsResult<usize> {
+        let offset = offset as usize;
+        let total_len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        let mut content = self.content.write();
+        if offset + total_len > content.len() {
+            self.resize(&mut content, offset + total_len)?;
+        }
+        let mut dst = &mut content[offset..offset + total_len];
+        for buf in bufs {
+            let (head, rest) = dst.split_at_mut(buf.len());
+            head.copy_from_slice(buf);
+            dst = rest;
+        }
+        self.meta.modified();
+        Ok(total_len)
+    }
+
     impl_vfs_non_dir_default! {}
 }
@@ -1,36 +1,56 @@
 //! RAM filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
-//! The implementation is based on [`axfs_vfs`].
+//! The implementation is based on [`axfs_vfs`]. It can be populated from a
+//! newc-format cpio archive by [`RamFileSystem::from_cpio`], e.g. as an
+//! initramfs. The bytes of file contents and the number of nodes can be
+//! limited, see [`RamFileSystem::new`].
 
 #![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
+mod cpio;
 mod dir;
 mod file;
+mod meta;
+mod symlink;
+mod usage;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::dir::DirNode;
 pub use self::file::FileNode;
+pub use self::symlink::SymlinkNode;
 
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use axfs_vfs::{FileSystemInfo, VfsNodeRef, VfsOps, VfsResult};
 use spin::once::Once;
 
+use self::usage::Usage;
+
+/// The block size reported by [`statfs`](VfsOps::statfs).
+const BLOCK_SIZE: usize = 4096;
+
 /// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
 pub struct RamFileSystem {
     parent: Once<VfsNodeRef>,
     root: Arc<DirNode>,
+    usage: Arc<Usage>,
 }
 
 impl RamFileSystem {
-    /// Create a new instance.
-    pub fn new() -> Self {
+    /// Create a new instance, with optional limits on the total bytes of file
+    /// contents and the number of nodes (including the root directory).
+    ///
+    /// Operations that exceed the limits fail with
+    /// [`StorageFull`](axfs_vfs::VfsError::StorageFull).
+    pub fn new(max_bytes: Option<usize>, max_inodes: Option<usize>) -> Self {
+        let usage = Arc::new(Usage::new(max_bytes, max_inodes));
         Self {
             parent: Once::new(),
-            root: DirNode::new(None),
+            root: DirNode::new(None, usage.clone()),
+            usage,
         }
     }
 
@@ -50,6 +70,19 @@ impl VfsOps for RamFileSystem {
         Ok(())
     }
 
+    /// The numbers of blocks and inodes are zero if they are not limited.
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        let used_blocks = self.usage.bytes().div_ceil(BLOCK_SIZE);
+        let blocks = self.usage.max_bytes().map_or(0, |max| max / BLOCK_SIZE);
+        let files = self.usage.max_inodes().unwrap_or(0);
+        let info = FileSystemInfo::new(
+            BLOCK_SIZE as _,
+            blocks as _,
+            blocks.saturating_sub(used_blocks) as _,
+        );
+        Ok(info.with_files(files as _, files.saturating_sub(self.usage.inodes()) as _))
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
@@ -57,6 +90,6 @@ impl VfsOps for RamFileSystem {
 
 impl Default for RamFileSystem {
     fn default() -> Self {
-        Self::new()
+        Self::new(None, None)
     }
 }
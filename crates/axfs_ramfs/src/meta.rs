@@ -0,0 +1,53 @@
+use axfs_vfs::{alloc_ino, current_time, VfsNodeAttr, VfsSetAttr};
+use spin::RwLock;
+
+/// The inode number, link count, owner, permission and timestamps of a node.
+pub(crate) struct NodeMeta(RwLock<VfsNodeAttr>);
+
+impl NodeMeta {
+    /// Creates the metadata from the permission and type of `attr`, with a
+    /// new inode number. All timestamps are set to the current time.
+    pub fn new(attr: VfsNodeAttr) -> Self {
+        let now = current_time();
+        Self(RwLock::new(
+            attr.with_ino(alloc_ino()).with_times(now, now, now),
+        ))
+    }
+
+    /// Returns the attributes of the node with the given size.
+    pub fn attr(&self, size: u64, blocks: u64) -> VfsNodeAttr {
+        let meta = self.0.read();
+        VfsNodeAttr::new(meta.perm(), meta.file_type(), size, blocks)
+            .with_ino(meta.ino())
+            .with_nlink(meta.nlink())
+            .with_owner(meta.uid(), meta.gid())
+            .with_times(meta.atime(), meta.mtime(), meta.ctime())
+    }
+
+    pub fn set(&self, changes: VfsSetAttr) {
+        let mut meta = self.0.write();
+        changes.apply(&mut meta);
+        *meta = meta.with_times(meta.atime(), meta.mtime(), current_time());
+    }
+
+    /// Adds `delta` to the number of hard links, and updates the change time.
+    pub fn add_links(&self, delta: i32) {
+        let mut meta = self.0.write();
+        *meta = meta
+            .with_nlink(meta.nlink().saturating_add_signed(delta))
+            .with_times(meta.atime(), meta.mtime(), current_time());
+    }
+
+    /// Updates the access time.
+    pub fn accessed(&self) {
+        let mut meta = self.0.write();
+        *meta = meta.with_times(current_time(), meta.mtime(), meta.ctime());
+    }
+
+    /// Updates the modification and change times.
+    pub fn modified(&self) {
+        let now = current_time();
+        let mut meta = self.0.write();
+        *meta = meta.with_times(meta.atime(), now, now);
+    }
+}
@@ -0,0 +1,63 @@
+use alloc::{string::String, sync::Arc};
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult, VfsSetAttr};
+
+use crate::meta::NodeMeta;
+use crate::usage::Usage;
+
+/// The symbolic link node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct SymlinkNode {
+    target: String,
+    meta: NodeMeta,
+    usage: Arc<Usage>,
+}
+
+impl SymlinkNode {
+    /// Creates a symbolic link, whose inode is reserved in `usage`.
+    pub(super) fn new(target: &str, usage: Arc<Usage>) -> Self {
+        Self {
+            target: target.into(),
+            meta: NodeMeta::new(VfsNodeAttr::new_symlink(0)),
+            usage,
+        }
+    }
+
+    pub(super) fn usage(&self) -> &Arc<Usage> {
+        &self.usage
+    }
+
+    pub(super) fn meta(&self) -> &NodeMeta {
+        &self.meta
+    }
+
+    /// Returns the target path of the symbolic link.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+impl Drop for SymlinkNode {
+    fn drop(&mut self) {
+        self.usage.free_inode();
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(self.meta.attr(self.target.len() as _, 0))
+    }
+
+    fn set_attr(&self, attr: VfsSetAttr) -> VfsResult {
+        self.meta.set(attr);
+        Ok(())
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let len = self.target.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.target.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    impl_vfs_non_dir_default! {}
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeType, VfsResult};
 
 use crate::*;
 
@@ -98,7 +98,7 @@ fn test_ramfs() {
     // ├── f1
     // └── f2
 
-    let ramfs = RamFileSystem::new();
+    let ramfs = RamFileSystem::new(None, None);
     let root = ramfs.root_dir();
     root.create("f1", VfsNodeType::File).unwrap();
     root.create("f2", VfsNodeType::File).unwrap();
@@ -134,3 +134,333 @@ fn test_ramfs() {
     assert_eq!(root.remove("./foo"), Ok(()));
     assert!(ramfs.root_dir_node().get_entries().is_empty());
 }
+
+#[test]
+fn test_links() {
+    let ramfs = RamFileSystem::new(None, None);
+    let root = ramfs.root_dir();
+    root.create("foo", VfsNodeType::Dir).unwrap();
+    root.create("foo/f1", VfsNodeType::File).unwrap();
+    let f1 = root.clone().lookup("foo/f1").unwrap();
+    f1.write_at(0, b"hello").unwrap();
+
+    // symbolic links are not followed by ramfs itself
+    root.symlink("foo/s1", "f1").unwrap();
+    root.symlink("s2", "/foo").unwrap();
+    assert_eq!(
+        root.symlink("./foo/s1", "f2").err(),
+        Some(VfsError::AlreadyExists)
+    );
+    assert_eq!(root.symlink("bar/s3", "f1").err(), Some(VfsError::NotFound));
+    let s1 = root.clone().lookup("foo/s1").unwrap();
+    let attr = s1.get_attr().unwrap();
+    assert!(attr.is_symlink());
+    assert_eq!(attr.size(), 2);
+    let mut buf = [0; 8];
+    assert_eq!(s1.readlink(&mut buf), Ok(2));
+    assert_eq!(&buf[..2], b"f1");
+    assert_eq!(s1.readlink(&mut buf[..1]), Ok(1));
+    assert_eq!(f1.readlink(&mut buf).err(), Some(VfsError::InvalidInput));
+    assert_eq!(
+        root.clone().lookup("s2/f1").err(),
+        Some(VfsError::NotADirectory)
+    );
+
+    // hard links share the same node
+    root.link("f2", &f1).unwrap();
+    let f2 = root.clone().lookup("f2").unwrap();
+    assert!(Arc::ptr_eq(&f1, &f2));
+    assert_eq!(f1.get_attr().unwrap().nlink(), 2);
+    assert_ne!(f1.get_attr().unwrap().ino(), s1.get_attr().unwrap().ino());
+    // directory entries report the inode numbers of the nodes
+    let mut dirents = [VfsDirEntry::default(), VfsDirEntry::default()];
+    assert_eq!(root.read_dir(2, &mut dirents), Ok(2));
+    let f2_entry = dirents.iter().find(|e| e.name_as_bytes() == b"f2").unwrap();
+    assert_eq!(f2_entry.ino(), f1.get_attr().unwrap().ino());
+    root.link("foo/s4", &s1).unwrap();
+    assert_eq!(root.link("f2", &f1).err(), Some(VfsError::AlreadyExists));
+    let foo = root.clone().lookup("foo").unwrap();
+    assert_eq!(
+        root.link("foo2", &foo).err(),
+        Some(VfsError::PermissionDenied)
+    );
+
+    // the content is still available after removing one of the links
+    root.remove("foo/f1").unwrap();
+    assert_eq!(f2.read_at(0, &mut buf), Ok(5));
+    assert_eq!(f2.get_attr().unwrap().nlink(), 1);
+    // `.`, `..` of `foo` and the entry in the parent
+    assert_eq!(root.get_attr().unwrap().nlink(), 3);
+    assert_eq!(&buf[..5], b"hello");
+
+    let mut entries = ramfs.root_dir_node().get_entries();
+    entries.sort();
+    assert_eq!(entries, ["f2", "foo", "s2"]);
+}
+
+#[test]
+fn test_rename() {
+    let ramfs = RamFileSystem::new(None, None);
+    let root = ramfs.root_dir();
+    root.create("foo/bar", VfsNodeType::Dir).unwrap_err();
+    root.create("foo", VfsNodeType::Dir).unwrap();
+    root.create("foo/bar", VfsNodeType::Dir).unwrap();
+    root.create("foo/f1", VfsNodeType::File).unwrap();
+    let f1 = root.clone().lookup("foo/f1").unwrap();
+    f1.write_at(0, b"hello").unwrap();
+
+    // files and directories are moved with their contents
+    root.rename("foo/f1", "/f2").unwrap();
+    assert!(Arc::ptr_eq(&root.clone().lookup("f2").unwrap(), &f1));
+    assert_eq!(
+        root.clone().lookup("foo/f1").err(),
+        Some(VfsError::NotFound)
+    );
+    root.rename("foo/bar", "baz").unwrap();
+    let baz = root.clone().lookup("baz").unwrap();
+    assert!(Arc::ptr_eq(&baz.parent().unwrap(), &root));
+    root.rename("f2", "baz/f3").unwrap();
+    assert!(Arc::ptr_eq(&root.clone().lookup("baz/f3").unwrap(), &f1));
+
+    // replacing
+    root.create("f4", VfsNodeType::File).unwrap();
+    root.rename("f4", "baz/f3").unwrap();
+    assert_eq!(f1.get_attr().unwrap().nlink(), 0);
+    assert!(!Arc::ptr_eq(&root.clone().lookup("baz/f3").unwrap(), &f1));
+    assert_eq!(
+        root.rename("foo", "baz").err(),
+        Some(VfsError::DirectoryNotEmpty)
+    );
+    assert_eq!(
+        root.rename("baz/f3", "foo").err(),
+        Some(VfsError::IsADirectory)
+    );
+    assert_eq!(
+        root.rename("foo", "baz/f3").err(),
+        Some(VfsError::NotADirectory)
+    );
+
+    // error cases
+    assert_eq!(root.rename("none", "f5").err(), Some(VfsError::NotFound));
+    assert_eq!(
+        root.rename("baz", "baz/sub").err(),
+        Some(VfsError::InvalidInput)
+    );
+    assert_eq!(
+        root.rename("baz/..", "f5").err(),
+        Some(VfsError::InvalidInput)
+    );
+    root.rename("foo", "baz/foo").unwrap();
+    let mut entries = ramfs.root_dir_node().get_entries();
+    entries.sort();
+    assert_eq!(entries, ["baz"]);
+}
+
+#[test]
+fn test_attr() {
+    use axfs_vfs::{VfsNodePerm, VfsSetAttr};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    // a clock that ticks on every read
+    static CLOCK: AtomicU64 = AtomicU64::new(1);
+    axfs_vfs::set_time_source(|| Duration::from_secs(CLOCK.fetch_add(1, Ordering::Relaxed)));
+
+    let ramfs = RamFileSystem::new(None, None);
+    let root = ramfs.root_dir();
+    root.create("f1", VfsNodeType::File).unwrap();
+    let f1 = root.clone().lookup("f1").unwrap();
+    let created = f1.get_attr().unwrap();
+    assert!(created.mtime() > Duration::ZERO);
+    assert_eq!(created.mtime(), created.ctime());
+    assert!(root.get_attr().unwrap().mtime() >= created.mtime());
+
+    f1.write_at(0, b"hello").unwrap();
+    let written = f1.get_attr().unwrap();
+    assert!(written.mtime() > created.mtime());
+    assert_eq!(written.atime(), created.atime());
+    f1.read_at(0, &mut [0; 5]).unwrap();
+    assert!(f1.get_attr().unwrap().atime() > written.mtime());
+
+    let changes = VfsSetAttr::new()
+        .with_perm(VfsNodePerm::from_bits_truncate(0o600))
+        .with_uid(1000)
+        .with_gid(100)
+        .with_mtime(Duration::from_secs(1));
+    f1.set_attr(changes).unwrap();
+    let attr = f1.get_attr().unwrap();
+    assert_eq!(attr.perm().mode(), 0o600);
+    assert_eq!((attr.uid(), attr.gid()), (1000, 100));
+    assert_eq!(attr.mtime(), Duration::from_secs(1));
+    assert!(attr.ctime() > written.ctime());
+    assert_eq!(attr.size(), 5);
+}
+
+/// Builds an entry of a newc-format cpio archive.
+fn cpio_entry(name: &str, ino: u32, mode: u32, nlink: u32, data: &[u8]) -> Vec<u8> {
+    let mut entry = format!(
+        "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+        ino,
+        mode,
+        1000, // uid
+        100,  // gid
+        nlink,
+        1_700_000_000, // mtime
+        data.len(),
+        0,
+        0,
+        0,
+        0,
+        name.len() + 1,
+        0,
+    )
+    .into_bytes();
+    entry.extend(name.as_bytes());
+    entry.push(0);
+    entry.resize(entry.len().next_multiple_of(4), 0);
+    entry.extend(data);
+    entry.resize(entry.len().next_multiple_of(4), 0);
+    entry
+}
+
+#[test]
+fn test_cpio() {
+    use std::time::Duration;
+
+    let archive = [
+        cpio_entry(".", 1, 0o040755, 2, b""),
+        cpio_entry("etc", 2, 0o040700, 2, b""),
+        cpio_entry("etc/hostname", 3, 0o100644, 1, b"arceos\n"),
+        cpio_entry("./bin/run.sh", 4, 0o100755, 1, b"#!/bin/sh\n"),
+        cpio_entry("bin/sh", 5, 0o120777, 1, b"run.sh"),
+        cpio_entry("hard1", 6, 0o100600, 2, b""),
+        cpio_entry("hard2", 6, 0o100600, 2, b"shared"),
+        cpio_entry("dev/console", 7, 0o020600, 1, b""),
+        cpio_entry("TRAILER!!!", 0, 0, 1, b""),
+    ]
+    .concat();
+
+    let ramfs = RamFileSystem::from_cpio(&archive).unwrap();
+    let root = ramfs.root_dir();
+    let mut entries = ramfs.root_dir_node().get_entries();
+    entries.sort();
+    assert_eq!(entries, ["bin", "dev", "etc", "hard1", "hard2"]);
+    assert_eq!(root.get_attr().unwrap().perm().mode(), 0o755);
+
+    let etc = root.clone().lookup("etc").unwrap().get_attr().unwrap();
+    assert!(etc.is_dir());
+    assert_eq!(etc.perm().mode(), 0o700);
+    assert_eq!(etc.mtime(), Duration::from_secs(1_700_000_000));
+
+    let hostname = root.clone().lookup("etc/hostname").unwrap();
+    let attr = hostname.get_attr().unwrap();
+    assert_eq!(attr.perm().mode(), 0o644);
+    assert_eq!((attr.uid(), attr.gid()), (1000, 100));
+    assert_eq!(attr.mtime(), Duration::from_secs(1_700_000_000));
+    let mut buf = [0; 16];
+    assert_eq!(hostname.read_at(0, &mut buf).unwrap(), 7);
+    assert_eq!(&buf[..7], b"arceos\n");
+
+    // missing parent directory is created
+    let script = root.clone().lookup("bin/run.sh").unwrap();
+    assert_eq!(script.get_attr().unwrap().perm().mode(), 0o755);
+    let sh = root.clone().lookup("bin/sh").unwrap();
+    assert!(sh.get_attr().unwrap().is_symlink());
+    assert_eq!(sh.readlink(&mut buf).unwrap(), 6);
+    assert_eq!(&buf[..6], b"run.sh");
+
+    // hard links share the data of the last entry
+    let hard1 = root.clone().lookup("hard1").unwrap();
+    assert!(Arc::ptr_eq(&hard1, &root.clone().lookup("hard2").unwrap()));
+    assert_eq!(hard1.read_at(0, &mut buf).unwrap(), 6);
+
+    // device nodes are skipped
+    assert_eq!(
+        root.clone().lookup("dev/console").err(),
+        Some(VfsError::NotFound)
+    );
+
+    // truncated or malformed archives
+    assert_eq!(
+        RamFileSystem::from_cpio(&archive[..archive.len() - 120]).err(),
+        Some(VfsError::InvalidData)
+    );
+    assert_eq!(
+        RamFileSystem::from_cpio(b"070707").err(),
+        Some(VfsError::InvalidData)
+    );
+}
+
+#[test]
+fn test_limits() {
+    let ramfs = RamFileSystem::new(Some(8192), Some(4));
+    let root = ramfs.root_dir();
+    let info = ramfs.statfs().unwrap();
+    assert_eq!(
+        (info.block_size(), info.blocks(), info.blocks_free()),
+        (4096, 2, 2)
+    );
+    assert_eq!((info.files(), info.files_free()), (4, 3));
+
+    root.create("foo", VfsNodeType::Dir).unwrap();
+    root.create("foo/f1", VfsNodeType::File).unwrap();
+    root.symlink("s1", "foo/f1").unwrap();
+    assert_eq!(
+        root.create("f2", VfsNodeType::File).err(),
+        Some(VfsError::StorageFull)
+    );
+    assert_eq!(root.symlink("s2", "f1").err(), Some(VfsError::StorageFull));
+    // hard links do not need new inodes
+    let f1 = root.clone().lookup("foo/f1").unwrap();
+    root.link("f1", &f1).unwrap();
+
+    assert_eq!(f1.write_at(0, &[1; 5000]).unwrap(), 5000);
+    let info = ramfs.statfs().unwrap();
+    assert_eq!((info.blocks_free(), info.files_free()), (0, 0));
+    assert_eq!(
+        f1.write_at(5000, &[1; 4000]).err(),
+        Some(VfsError::StorageFull)
+    );
+    assert_eq!(f1.truncate(9000).err(), Some(VfsError::StorageFull));
+    assert_eq!(f1.get_attr().unwrap().size(), 5000);
+    f1.truncate(4096).unwrap();
+    assert_eq!(ramfs.statfs().unwrap().blocks_free(), 1);
+
+    // space is released when the last link is removed
+    root.remove("foo/f1").unwrap();
+    drop(f1);
+    assert_eq!(ramfs.statfs().unwrap().blocks_free(), 1);
+    root.remove("f1").unwrap();
+    root.remove("s1").unwrap();
+    let info = ramfs.statfs().unwrap();
+    assert_eq!((info.blocks_free(), info.files_free()), (2, 2));
+
+    // nodes of another filesystem can not be linked
+    let other = RamFileSystem::new(None, None);
+    other.root_dir().create("f3", VfsNodeType::File).unwrap();
+    let f3 = other.root_dir().lookup("f3").unwrap();
+    assert_eq!(root.link("f3", &f3).err(), Some(VfsError::CrossesDevices));
+    assert_eq!(other.statfs().unwrap().blocks(), 0);
+}
+
+#[test]
+fn test_vectored() {
+    let ramfs = RamFileSystem::new(None, None);
+    let root = ramfs.root_dir();
+    root.create("f1", VfsNodeType::File).unwrap();
+    let f1 = root.lookup("f1").unwrap();
+
+    let bufs: [&[u8]; 3] = [b"hello", b"", b", world"];
+    assert_eq!(f1.write_vectored_at(2, &bufs).unwrap(), 12);
+    assert_eq!(f1.get_attr().unwrap().size(), 14);
+
+    let (mut a, mut b, mut c) = ([1; 4], [1; 6], [1; 8]);
+    let mut bufs: [&mut [u8]; 3] = [&mut a, &mut b, &mut c];
+    assert_eq!(f1.read_vectored_at(0, &mut bufs).unwrap(), 14);
+    assert_eq!(&a, b"\0\0he");
+    assert_eq!(&b, b"llo, w");
+    assert_eq!(&c, b"orld\x01\x01\x01\x01");
+
+    let mut bufs: [&mut [u8]; 2] = [&mut a, &mut b];
+    assert_eq!(f1.read_vectored_at(20, &mut bufs).unwrap(), 0);
+}
@@ -0,0 +1,69 @@
+use axfs_vfs::{VfsError, VfsResult};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The bytes of file contents and the inodes used by a RAM filesystem, with
+/// their optional limits.
+pub(crate) struct Usage {
+    max_bytes: Option<usize>,
+    max_inodes: Option<usize>,
+    bytes: AtomicUsize,
+    inodes: AtomicUsize,
+}
+
+impl Usage {
+    /// Creates the usage of a new filesystem, where only the root directory
+    /// exists.
+    pub const fn new(max_bytes: Option<usize>, max_inodes: Option<usize>) -> Self {
+        Self {
+            max_bytes,
+            max_inodes,
+            bytes: AtomicUsize::new(0),
+            inodes: AtomicUsize::new(1),
+        }
+    }
+
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    pub fn max_inodes(&self) -> Option<usize> {
+        self.max_inodes
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn inodes(&self) -> usize {
+        self.inodes.load(Ordering::Relaxed)
+    }
+
+    /// Reserves `n` more bytes, returns [`StorageFull`](VfsError::StorageFull)
+    /// if the limit would be exceeded.
+    pub fn alloc_bytes(&self, n: usize) -> VfsResult {
+        alloc(&self.bytes, n, self.max_bytes)
+    }
+
+    pub fn free_bytes(&self, n: usize) {
+        self.bytes.fetch_sub(n, Ordering::Relaxed);
+    }
+
+    /// Reserves an inode for a new node, which is released when the node is
+    /// dropped.
+    pub fn alloc_inode(&self) -> VfsResult {
+        alloc(&self.inodes, 1, self.max_inodes)
+    }
+
+    pub fn free_inode(&self) {
+        self.inodes.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn alloc(used: &AtomicUsize, n: usize, max: Option<usize>) -> VfsResult {
+    used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        used.checked_add(n)
+            .filter(|&new| max.map_or(true, |max| new <= max))
+    })
+    .map(|_| ())
+    .map_err(|_| VfsError::StorageFull)
+}
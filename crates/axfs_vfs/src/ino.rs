@@ -0,0 +1,11 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_INO: AtomicU64 = AtomicU64::new(1);
+
+/// Allocates an inode number for a filesystem that does not store them, e.g.
+/// one in memory.
+///
+/// Numbers are never reused, so they are unique among all such filesystems.
+pub fn alloc_ino() -> u64 {
+    NEXT_INO.fetch_add(1, Ordering::Relaxed)
+}
@@ -1,7 +1,7 @@
 //! Virtual filesystem interfaces used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
-//! A filesystem is a set of files and directories (symbol links are not
-//! supported currently), collectively referred to as **nodes**, which are
+//! A filesystem is a set of files, directories and symbolic links,
+//! collectively referred to as **nodes**, which are
 //! conceptually similar to [inodes] in Linux. A file system needs to implement
 //! the [`VfsOps`] trait, its files and directories need to implement the
 //! [`VfsNodeOps`] trait.
@@ -14,16 +14,19 @@
 //! - [`statfs()`](VfsOps::statfs): Get the attributes of the filesystem.
 //! - [`root_dir()`](VfsOps::root_dir): Get root directory of the filesystem.
 //!
-//! The [`VfsNodeOps`] trait provides the following operations on a file or a
-//! directory:
+//! The [`VfsNodeOps`] trait provides the following operations on a file, a
+//! directory or a symbolic link:
 //!
-//! | Operation | Description | file/directory |
+//! | Operation | Description | file/directory/symlink |
 //! | --- | --- | --- |
-//! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | both |
-//! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | both |
-//! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | both |
+//! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | all |
+//! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | all |
+//! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | all |
+//! | [`set_attr()`](VfsNodeOps::set_attr) | Change the owner, permission or timestamps | all |
 //! | [`read_at()`](VfsNodeOps::read_at) | Read data from the file | file |
 //! | [`write_at()`](VfsNodeOps::write_at) | Write data to the file | file |
+//! | [`read_vectored_at()`](VfsNodeOps::read_vectored_at) | Read data into several buffers | file |
+//! | [`write_vectored_at()`](VfsNodeOps::write_vectored_at) | Write data of several buffers | file |
 //! | [`fsync()`](VfsNodeOps::fsync) | Synchronize the file data to disk | file |
 //! | [`truncate()`](VfsNodeOps::truncate) | Truncate the file | file |
 //! | [`parent()`](VfsNodeOps::parent) | Get the parent directory | directory |
@@ -31,6 +34,13 @@
 //! | [`create()`](VfsNodeOps::create) | Create a new node with the given path | directory |
 //! | [`remove()`](VfsNodeOps::remove) | Remove the node with the given path | directory |
 //! | [`read_dir()`](VfsNodeOps::read_dir) | Read directory entries | directory |
+//! | [`symlink()`](VfsNodeOps::symlink) | Create a symbolic link with the given path | directory |
+//! | [`link()`](VfsNodeOps::link) | Create a hard link with the given path | directory |
+//! | [`readlink()`](VfsNodeOps::readlink) | Read the target of the symbolic link | symlink |
+//!
+//! Filesystems get the timestamps of nodes from [`current_time()`], which is
+//! provided by the kernel with [`set_time_source()`]. Those that do not store
+//! inode numbers (e.g. in-memory ones) get them from [`alloc_ino()`].
 //!
 //! [inodes]: https://en.wikipedia.org/wiki/Inode
 
@@ -38,15 +48,20 @@
 
 extern crate alloc;
 
+mod ino;
 mod macros;
 mod structs;
+mod time;
 
 pub mod path;
 
 use alloc::sync::Arc;
 use axerrno::{ax_err, AxError, AxResult};
 
+pub use self::ino::alloc_ino;
+pub use self::structs::VfsSetAttr;
 pub use self::structs::{FileSystemInfo, VfsDirEntry, VfsNodeAttr, VfsNodePerm, VfsNodeType};
+pub use self::time::{current_time, set_time_source};
 
 /// A wrapper of [`Arc<dyn VfsNodeOps>`].
 pub type VfsNodeRef = Arc<dyn VfsNodeOps>;
@@ -100,6 +115,11 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Change the owner, permission mode or timestamps of the node.
+    fn set_attr(&self, _attr: VfsSetAttr) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
     // file operations:
 
     /// Read data from the file at the given offset.
@@ -112,6 +132,51 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(InvalidInput)
     }
 
+    /// Read data from the file at the given offset into several buffers in
+    /// order. Returns the total number of bytes read.
+    ///
+    /// A buffer is filled up before the next one unless the end of the file is
+    /// reached. The default implementation calls [`read_at()`](Self::read_at)
+    /// for each buffer, filesystems can override it to read them at once.
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [&mut [u8]]) -> VfsResult<usize> {
+        let mut read_len = 0;
+        for buf in bufs {
+            match self.read_at(offset + read_len as u64, buf) {
+                Ok(n) => {
+                    read_len += n;
+                    if n < buf.len() {
+                        break;
+                    }
+                }
+                Err(e) if read_len == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(read_len)
+    }
+
+    /// Write data of several buffers to the file at the given offset in order.
+    /// Returns the total number of bytes written.
+    ///
+    /// The default implementation calls [`write_at()`](Self::write_at) for
+    /// each buffer, filesystems can override it to write them at once.
+    fn write_vectored_at(&self, offset: u64, bufs: &[&[u8]]) -> VfsResult<usize> {
+        let mut write_len = 0;
+        for buf in bufs {
+            match self.write_at(offset + write_len as u64, buf) {
+                Ok(n) => {
+                    write_len += n;
+                    if n < buf.len() {
+                        break;
+                    }
+                }
+                Err(e) if write_len == 0 => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(write_len)
+    }
+
     /// Flush the file, synchronize the data to disk.
     fn fsync(&self) -> VfsResult {
         ax_err!(InvalidInput)
@@ -160,6 +225,28 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Create a symbolic link with the given `path` in the directory, which
+    /// points to `target`.
+    fn symlink(&self, _path: &str, _target: &str) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Create a hard link with the given `path` in the directory, which refers
+    /// to the existing `node`.
+    fn link(&self, _path: &str, _node: &VfsNodeRef) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    // symbolic link operations:
+
+    /// Read the target path of the symbolic link into `buf`.
+    ///
+    /// Return the length of the target path, which may be truncated if `buf`
+    /// is too small.
+    fn readlink(&self, _buf: &mut [u8]) -> VfsResult<usize> {
+        ax_err!(InvalidInput)
+    }
+
     /// Convert `&self` to [`&dyn Any`][1] that can use
     /// [`Any::downcast_ref`][2].
     ///
@@ -1,8 +1,23 @@
-/// Filesystem attributes.
+use core::time::Duration;
+
+/// Filesystem attributes, returned by [`VfsOps::statfs`].
 ///
-/// Currently not used.
-#[non_exhaustive]
-pub struct FileSystemInfo;
+/// [`VfsOps::statfs`]: crate::VfsOps::statfs
+#[derive(Debug, Clone, Copy)]
+pub struct FileSystemInfo {
+    /// Size of a block, in bytes.
+    block_size: u64,
+    /// Total number of blocks.
+    blocks: u64,
+    /// Number of free blocks.
+    blocks_free: u64,
+    /// Total number of inodes.
+    files: u64,
+    /// Number of free inodes.
+    files_free: u64,
+    /// Maximum length of a file name, in bytes.
+    name_max: u64,
+}
 
 /// Node (file/directory) attributes.
 #[allow(dead_code)]
@@ -16,6 +31,34 @@ pub struct VfsNodeAttr {
     size: u64,
     /// Number of 512B blocks allocated.
     blocks: u64,
+    /// Inode number, unique in the filesystem.
+    ino: u64,
+    /// Number of hard links.
+    nlink: u32,
+    /// User ID of the owner.
+    uid: u32,
+    /// Group ID of the owner.
+    gid: u32,
+    /// Time of the last access.
+    atime: Duration,
+    /// Time of the last modification of the contents.
+    mtime: Duration,
+    /// Time of the last change of the contents or attributes.
+    ctime: Duration,
+}
+
+/// Node attributes to be changed by [`VfsNodeOps::set_attr`].
+///
+/// Attributes that are `None` are left unchanged.
+///
+/// [`VfsNodeOps::set_attr`]: crate::VfsNodeOps::set_attr
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VfsSetAttr {
+    mode: Option<VfsNodePerm>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    atime: Option<Duration>,
+    mtime: Option<Duration>,
 }
 
 bitflags::bitflags! {
@@ -42,6 +85,10 @@ bitflags::bitflags! {
         const OTHER_WRITE = 0o2;
         /// Others have execute permission.
         const OTHER_EXEC = 0o1;
+
+        /// Entries of the directory can only be removed or renamed by their
+        /// owners, or the owner of the directory.
+        const STICKY = 0o1000;
     }
 }
 
@@ -67,6 +114,7 @@ pub enum VfsNodeType {
 
 /// Directory entry.
 pub struct VfsDirEntry {
+    d_ino: u64,
     d_type: VfsNodeType,
     d_name: [u8; 63],
 }
@@ -87,6 +135,14 @@ impl VfsNodePerm {
         Self::from_bits_truncate(0o755)
     }
 
+    /// Returns the default permission for a symbolic link.
+    ///
+    /// The default permission is `0o777`, the permission of a symbolic link is
+    /// not used in path resolution.
+    pub const fn default_symlink() -> Self {
+        Self::from_bits_truncate(0o777)
+    }
+
     /// Returns the underlying raw `st_mode` bits that contain the standard
     /// Unix permissions for this file.
     pub const fn mode(&self) -> u32 {
@@ -196,37 +252,138 @@ impl VfsNodeType {
     }
 }
 
+impl FileSystemInfo {
+    /// Creates a new `FileSystemInfo` with the given block size, total and free
+    /// number of blocks.
+    ///
+    /// The numbers of inodes are zero (i.e. not limited), and the maximum
+    /// length of names is 255.
+    pub const fn new(block_size: u64, blocks: u64, blocks_free: u64) -> Self {
+        Self {
+            block_size,
+            blocks,
+            blocks_free,
+            files: 0,
+            files_free: 0,
+            name_max: 255,
+        }
+    }
+
+    /// Returns a copy with the given total and free number of inodes.
+    pub const fn with_files(self, files: u64, files_free: u64) -> Self {
+        Self {
+            files,
+            files_free,
+            ..self
+        }
+    }
+
+    /// Returns a copy with the given maximum length of names.
+    pub const fn with_name_max(self, name_max: u64) -> Self {
+        Self { name_max, ..self }
+    }
+
+    /// Size of a block, in bytes.
+    pub const fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Total number of blocks.
+    pub const fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Number of free blocks.
+    pub const fn blocks_free(&self) -> u64 {
+        self.blocks_free
+    }
+
+    /// Total number of inodes, zero if it is not limited.
+    pub const fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Number of free inodes.
+    pub const fn files_free(&self) -> u64 {
+        self.files_free
+    }
+
+    /// Maximum length of a file name, in bytes.
+    pub const fn name_max(&self) -> u64 {
+        self.name_max
+    }
+}
+
 impl VfsNodeAttr {
     /// Creates a new `VfsNodeAttr` with the given permission mode, type, size
     /// and number of blocks.
+    ///
+    /// The node is owned by root, has inode number 0 and one link, and all
+    /// timestamps are zero.
     pub const fn new(mode: VfsNodePerm, ty: VfsNodeType, size: u64, blocks: u64) -> Self {
         Self {
             mode,
             ty,
             size,
             blocks,
+            ino: 0,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            atime: Duration::ZERO,
+            mtime: Duration::ZERO,
+            ctime: Duration::ZERO,
         }
     }
 
     /// Creates a new `VfsNodeAttr` for a file, with the default file permission.
     pub const fn new_file(size: u64, blocks: u64) -> Self {
-        Self {
-            mode: VfsNodePerm::default_file(),
-            ty: VfsNodeType::File,
-            size,
-            blocks,
-        }
+        Self::new(VfsNodePerm::default_file(), VfsNodeType::File, size, blocks)
     }
 
     /// Creates a new `VfsNodeAttr` for a directory, with the default directory
     /// permission.
     pub const fn new_dir(size: u64, blocks: u64) -> Self {
-        Self {
-            mode: VfsNodePerm::default_dir(),
-            ty: VfsNodeType::Dir,
+        Self::new(VfsNodePerm::default_dir(), VfsNodeType::Dir, size, blocks)
+    }
+
+    /// Creates a new `VfsNodeAttr` for a symbolic link, with the default
+    /// symbolic link permission, `size` is the length of the target path.
+    pub const fn new_symlink(size: u64) -> Self {
+        Self::new(
+            VfsNodePerm::default_symlink(),
+            VfsNodeType::SymLink,
             size,
-            blocks,
-        }
+            0,
+        )
+    }
+
+    /// Sets the inode number.
+    pub const fn with_ino(mut self, ino: u64) -> Self {
+        self.ino = ino;
+        self
+    }
+
+    /// Sets the number of hard links.
+    pub const fn with_nlink(mut self, nlink: u32) -> Self {
+        self.nlink = nlink;
+        self
+    }
+
+    /// Sets the user and group IDs of the owner.
+    pub const fn with_owner(mut self, uid: u32, gid: u32) -> Self {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+
+    /// Sets the access, modification and change times, as durations since the
+    /// Unix epoch.
+    pub const fn with_times(mut self, atime: Duration, mtime: Duration, ctime: Duration) -> Self {
+        self.atime = atime;
+        self.mtime = mtime;
+        self.ctime = ctime;
+        self
     }
 
     /// Returns the size of the node.
@@ -239,6 +396,16 @@ impl VfsNodeAttr {
         self.blocks
     }
 
+    /// Returns the inode number.
+    pub const fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// Returns the number of hard links.
+    pub const fn nlink(&self) -> u32 {
+        self.nlink
+    }
+
     /// Returns the permission of the node.
     pub const fn perm(&self) -> VfsNodePerm {
         self.mode
@@ -249,6 +416,31 @@ impl VfsNodeAttr {
         self.mode = perm
     }
 
+    /// Returns the user ID of the owner.
+    pub const fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the group ID of the owner.
+    pub const fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns the time of the last access.
+    pub const fn atime(&self) -> Duration {
+        self.atime
+    }
+
+    /// Returns the time of the last modification of the contents.
+    pub const fn mtime(&self) -> Duration {
+        self.mtime
+    }
+
+    /// Returns the time of the last change of the contents or attributes.
+    pub const fn ctime(&self) -> Duration {
+        self.ctime
+    }
+
     /// Returns the type of the node.
     pub const fn file_type(&self) -> VfsNodeType {
         self.ty
@@ -263,12 +455,105 @@ impl VfsNodeAttr {
     pub const fn is_dir(&self) -> bool {
         self.ty.is_dir()
     }
+
+    /// Whether the node is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.ty.is_symlink()
+    }
+}
+
+impl VfsSetAttr {
+    /// Creates a `VfsSetAttr` that changes nothing.
+    pub const fn new() -> Self {
+        Self {
+            mode: None,
+            uid: None,
+            gid: None,
+            atime: None,
+            mtime: None,
+        }
+    }
+
+    /// Changes the permission mode.
+    pub const fn with_perm(mut self, perm: VfsNodePerm) -> Self {
+        self.mode = Some(perm);
+        self
+    }
+
+    /// Changes the user ID of the owner.
+    pub const fn with_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Changes the group ID of the owner.
+    pub const fn with_gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Changes the time of the last access.
+    pub const fn with_atime(mut self, atime: Duration) -> Self {
+        self.atime = Some(atime);
+        self
+    }
+
+    /// Changes the time of the last modification.
+    pub const fn with_mtime(mut self, mtime: Duration) -> Self {
+        self.mtime = Some(mtime);
+        self
+    }
+
+    /// Returns the new permission mode.
+    pub const fn perm(&self) -> Option<VfsNodePerm> {
+        self.mode
+    }
+
+    /// Returns the new user ID of the owner.
+    pub const fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    /// Returns the new group ID of the owner.
+    pub const fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+
+    /// Returns the new time of the last access.
+    pub const fn atime(&self) -> Option<Duration> {
+        self.atime
+    }
+
+    /// Returns the new time of the last modification.
+    pub const fn mtime(&self) -> Option<Duration> {
+        self.mtime
+    }
+
+    /// Applies the changes to `attr`.
+    pub fn apply(&self, attr: &mut VfsNodeAttr) {
+        if let Some(mode) = self.mode {
+            attr.mode = mode;
+        }
+        if let Some(uid) = self.uid {
+            attr.uid = uid;
+        }
+        if let Some(gid) = self.gid {
+            attr.gid = gid;
+        }
+        if let Some(atime) = self.atime {
+            attr.atime = atime;
+        }
+        if let Some(mtime) = self.mtime {
+            attr.mtime = mtime;
+        }
+    }
 }
 
 impl VfsDirEntry {
     /// Creates an empty `VfsDirEntry`.
     pub const fn default() -> Self {
         Self {
+            d_ino: 0,
             d_type: VfsNodeType::File,
             d_name: [0; 63],
         }
@@ -285,7 +570,23 @@ impl VfsDirEntry {
             );
         }
         d_name[..name.len()].copy_from_slice(name.as_bytes());
-        Self { d_type: ty, d_name }
+        Self {
+            d_ino: 0,
+            d_type: ty,
+            d_name,
+        }
+    }
+
+    /// Sets the inode number of the entry.
+    pub const fn with_ino(mut self, ino: u64) -> Self {
+        self.d_ino = ino;
+        self
+    }
+
+    /// Returns the inode number of the entry, or 0 if the filesystem does not
+    /// report it.
+    pub fn ino(&self) -> u64 {
+        self.d_ino
     }
 
     /// Returns the type of the entry.
@@ -0,0 +1,27 @@
+use core::sync::atomic::{AtomicPtr, Ordering};
+use core::time::Duration;
+
+static TIME_SOURCE: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Sets the function that returns the current time for timestamps.
+///
+/// It should be a duration since the Unix epoch, but kernels without a
+/// real-time clock may provide the time since boot instead, which is also
+/// what they report as `CLOCK_REALTIME`.
+pub fn set_time_source(f: fn() -> Duration) {
+    TIME_SOURCE.store(f as *mut (), Ordering::Release);
+}
+
+/// Returns the current time for timestamps of nodes.
+///
+/// Returns zero if no time source is set.
+pub fn current_time() -> Duration {
+    let f = TIME_SOURCE.load(Ordering::Acquire);
+    if f.is_null() {
+        Duration::ZERO
+    } else {
+        // SAFETY: only `fn() -> Duration` pointers are stored.
+        let f: fn() -> Duration = unsafe { core::mem::transmute(f) };
+        f()
+    }
+}
@@ -0,0 +1,41 @@
+//! Common traits and types for [9P] transport device drivers (e.g.
+//! virtio-9p), which carry messages of a filesystem shared by the host.
+//!
+//! [9P]: https://en.wikipedia.org/wiki/9P_(protocol)
+
+#![no_std]
+
+use core::time::Duration;
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// Services of the kernel used by drivers that poll for responses.
+#[derive(Clone, Copy)]
+pub struct WaitHooks {
+    /// Returns the current monotonic time, to give up waiting after a
+    /// timeout.
+    pub now: fn() -> Duration,
+    /// Called between polls, e.g. to yield the CPU to other tasks.
+    pub relax: fn(),
+}
+
+/// Operations that require a 9P transport driver to implement.
+pub trait NinePDriverOps: BaseDriverOps {
+    /// The tag that identifies the shared filesystem, e.g. the `mount_tag`
+    /// of QEMU `-virtfs` options.
+    fn mount_tag(&self) -> &str;
+
+    /// Sends a request message and waits for its response.
+    ///
+    /// The response is written into `resp`, which must be large enough to hold
+    /// the maximum message size. Returns the length of the response.
+    fn request(&mut self, req: &[u8], resp: &mut [u8]) -> DevResult<usize>;
+
+    /// Sets the hooks used while waiting for responses.
+    ///
+    /// Drivers that do not poll ignore them.
+    fn set_wait_hooks(&mut self, hooks: WaitHooks) {
+        let _ = hooks;
+    }
+}
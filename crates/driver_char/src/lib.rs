@@ -0,0 +1,52 @@
+//! Common traits and types for character device drivers (e.g. serial ports
+//! and virtio-console).
+//!
+//! A character device has one or more ports, each of them is a byte stream in
+//! both directions. Ports are numbered from 0, and devices with only one port
+//! (e.g. a UART) use port 0.
+
+#![no_std]
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// Whether a port can be read or written without [`DevError::Again`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PollState {
+    /// There are received bytes to read.
+    pub readable: bool,
+    /// There is room to write bytes.
+    pub writable: bool,
+}
+
+/// Operations that require a character device driver to implement.
+///
+/// All operations are non-blocking. Operations on a port that does not exist
+/// fail with [`DevError::InvalidParam`].
+pub trait CharDriverOps: BaseDriverOps {
+    /// The number of ports of the device.
+    fn num_ports(&self) -> usize {
+        1
+    }
+
+    /// The name of the port, if the device provides one (e.g. the `name` of
+    /// QEMU `virtserialport` devices).
+    fn port_name(&self, _port: usize) -> Option<&str> {
+        None
+    }
+
+    /// Reads received bytes of the port into `buf`.
+    ///
+    /// Returns the number of bytes read, or [`DevError::Again`] if nothing has
+    /// been received.
+    fn read(&mut self, port: usize, buf: &mut [u8]) -> DevResult<usize>;
+
+    /// Writes the bytes in `buf` to the port.
+    ///
+    /// Returns the number of bytes written, which is less than `buf.len()` if
+    /// the device is busy, or [`DevError::Again`] if nothing can be written.
+    fn write(&mut self, port: usize, buf: &[u8]) -> DevResult<usize>;
+
+    /// Returns whether the port can be read or written.
+    fn poll(&mut self, port: usize) -> DevResult<PollState>;
+}
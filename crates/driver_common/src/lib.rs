@@ -7,11 +7,17 @@
 //! - [`driver_block`][2]: Common traits for block storage drivers.
 //! - [`driver_display`][3]: Common traits and types for graphics display drivers.
 //! - [`driver_net`][4]: Common traits and types for network (NIC) drivers.
+//! - [`driver_9p`][5]: Common traits for 9P transport drivers.
+//! - [`driver_char`][6]: Common traits and types for character device drivers.
+//! - [`driver_input`][7]: Common traits and types for input device drivers.
 //!
 //! [1]: https://github.com/rcore-os/arceos
 //! [2]: ../driver_block/index.html
 //! [3]: ../driver_display/index.html
 //! [4]: ../driver_net/index.html
+//! [5]: ../driver_9p/index.html
+//! [6]: ../driver_char/index.html
+//! [7]: ../driver_input/index.html
 
 #![no_std]
 #![feature(const_trait_impl)]
@@ -27,6 +33,10 @@ pub enum DeviceType {
     Net,
     /// Graphic display device (e.g., GPU)
     Display,
+    /// 9P transport of a filesystem shared by the host (e.g., virtio-9p).
+    NineP,
+    /// Input device (e.g., keyboard, mouse).
+    Input,
 }
 
 /// The error type for device operation failures.
@@ -0,0 +1,128 @@
+//! Common traits and types for input device drivers (e.g. keyboards, mice and
+//! tablets).
+//!
+//! Events follow the Linux evdev protocol: each of them has a type (e.g.
+//! [`EventType::Key`]), a code (e.g. which key) and a value (e.g. pressed or
+//! released), and a group of events is terminated by a
+//! [`EventType::Synchronization`] event.
+
+#![no_std]
+
+#[doc(no_inline)]
+pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+/// Types of input events, as `EV_*` of Linux.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    /// Separates groups of events (`EV_SYN`).
+    Synchronization = 0x00,
+    /// Keys and buttons (`EV_KEY`).
+    Key = 0x01,
+    /// Relative axes, e.g. mouse movements (`EV_REL`).
+    Relative = 0x02,
+    /// Absolute axes, e.g. tablet positions (`EV_ABS`).
+    Absolute = 0x03,
+    /// Miscellaneous events (`EV_MSC`).
+    Misc = 0x04,
+    /// Binary switches (`EV_SW`).
+    Switch = 0x05,
+    /// LEDs, e.g. caps lock (`EV_LED`).
+    Led = 0x11,
+    /// Sounds, e.g. beeps (`EV_SND`).
+    Sound = 0x12,
+    /// Key repeat settings (`EV_REP`).
+    Repeat = 0x14,
+    /// Force feedback (`EV_FF`).
+    ForceFeedback = 0x15,
+    /// Power buttons (`EV_PWR`).
+    Power = 0x16,
+    /// Force feedback status (`EV_FF_STATUS`).
+    ForceFeedbackStatus = 0x17,
+}
+
+impl EventType {
+    /// The number of codes of the event type, i.e. the number of bits of
+    /// [`InputDriverOps::get_event_bits`].
+    pub const fn bits_count(&self) -> usize {
+        match self {
+            Self::Synchronization => 0x10,
+            Self::Key => 0x300,
+            Self::Relative => 0x10,
+            Self::Absolute => 0x40,
+            Self::Misc => 0x08,
+            Self::Switch => 0x11,
+            Self::Led => 0x10,
+            Self::Sound => 0x08,
+            Self::Repeat => 0x02,
+            Self::ForceFeedback => 0x80,
+            Self::Power => 0x01,
+            Self::ForceFeedbackStatus => 0x02,
+        }
+    }
+}
+
+/// An input event reported by the device.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    /// The type of the event, see [`EventType`].
+    pub event_type: u16,
+    /// The code of the event, e.g. `KEY_A` or `REL_X`.
+    pub code: u16,
+    /// The value of the event, e.g. 1 for a key press, or the distance of a
+    /// relative movement.
+    pub value: i32,
+}
+
+/// The identity of an input device, as `struct input_id` of Linux.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputDeviceId {
+    /// The bus type, e.g. `BUS_VIRTUAL`.
+    pub bus_type: u16,
+    /// The vendor ID.
+    pub vendor: u16,
+    /// The product ID.
+    pub product: u16,
+    /// The version.
+    pub version: u16,
+}
+
+/// The range of an absolute axis, as `struct input_absinfo` of Linux.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AbsInfo {
+    /// The minimum value of the axis.
+    pub min: u32,
+    /// The maximum value of the axis.
+    pub max: u32,
+    /// The noise that is filtered out.
+    pub fuzz: u32,
+    /// The values around the center that are reported as the center.
+    pub flat: u32,
+    /// The resolution, in units per millimeter.
+    pub res: u32,
+}
+
+/// Operations that require an input device driver to implement.
+pub trait InputDriverOps: BaseDriverOps {
+    /// The identity of the device.
+    fn device_id(&self) -> InputDeviceId;
+
+    /// The name of the device, e.g. `QEMU Virtio Keyboard`.
+    fn name(&self) -> &str;
+
+    /// Writes the bitmap of the codes that the device supports for the event
+    /// type `ty` to `out`.
+    ///
+    /// Returns whether the device supports the event type at all.
+    fn get_event_bits(&mut self, ty: EventType, out: &mut [u8]) -> DevResult<bool>;
+
+    /// Returns the range of the absolute axis `axis`.
+    fn get_abs_info(&mut self, _axis: u8) -> DevResult<AbsInfo> {
+        Err(DevError::Unsupported)
+    }
+
+    /// Reads the next pending event.
+    ///
+    /// Returns [`DevError::Again`] if there is no pending event.
+    fn read_event(&mut self) -> DevResult<Event>;
+}
@@ -0,0 +1,421 @@
+use alloc::{string::String, vec::Vec};
+use core::ptr::addr_of;
+
+use driver_char::{CharDriverOps, PollState};
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use virtio_drivers::transport::{DeviceStatus, Transport};
+use virtio_drivers::{Hal, PhysAddr};
+
+use crate::queue::{VirtQueue, PAGE_SIZE, VIRTQ_DESC_F_WRITE};
+
+extern crate alloc;
+
+/// The device supports multiple ports, and the control virtqueues.
+const VIRTIO_CONSOLE_F_MULTIPORT: u64 = 1 << 1;
+/// Compliance with the virtio 1.0 specification.
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+const SUPPORTED_FEATURES: u64 = VIRTIO_CONSOLE_F_MULTIPORT | VIRTIO_F_VERSION_1;
+
+/// Maximum number of ports used, the device may provide more.
+const MAX_PORTS: usize = 4;
+/// Maximum length of port names.
+const MAX_NAME_LEN: usize = 64;
+
+/// The virtqueues of port 0, and the control virtqueues. Virtqueues of port
+/// `n` (`n > 0`) are `2n + 2` and `2n + 3`.
+const QUEUE_RECEIVE0: u16 = 0;
+const QUEUE_CONTROL_RECEIVE: u16 = 2;
+const QUEUE_CONTROL_TRANSMIT: u16 = 3;
+
+/// Number of descriptors in each virtqueue, each of them has its own buffer.
+const QUEUE_SIZE: usize = 16;
+/// Size of the buffer of each descriptor, all buffers fill one page.
+const BUF_SIZE: usize = PAGE_SIZE / QUEUE_SIZE;
+
+// Events of control messages.
+const VIRTIO_CONSOLE_DEVICE_READY: u16 = 0;
+const VIRTIO_CONSOLE_DEVICE_ADD: u16 = 1;
+const VIRTIO_CONSOLE_DEVICE_REMOVE: u16 = 2;
+const VIRTIO_CONSOLE_PORT_READY: u16 = 3;
+const VIRTIO_CONSOLE_CONSOLE_PORT: u16 = 4;
+const VIRTIO_CONSOLE_PORT_OPEN: u16 = 6;
+const VIRTIO_CONSOLE_PORT_NAME: u16 = 7;
+
+/// The size of `struct virtio_console_control { le32 id; le16 event; le16
+/// value; }`, which may be followed by data (the name of `PORT_NAME`).
+const CONTROL_MSG_LEN: usize = 8;
+
+#[repr(C)]
+struct ConsoleConfig {
+    cols: u16,
+    rows: u16,
+    max_nr_ports: u32,
+    emerg_wr: u32,
+}
+
+/// A virtqueue with a buffer for each descriptor, in an extra page after
+/// the rings.
+///
+/// Receive queues keep all descriptors available to the device. Transmit
+/// queues make descriptors available when sending, and take them back when
+/// the device has used them.
+struct BufQueue<H: Hal> {
+    queue: VirtQueue<H, QUEUE_SIZE>,
+    /// Descriptors that are not available to the device.
+    free: Vec<u16>,
+    /// The used buffer being read: its descriptor, length and read offset.
+    pending: Option<(u16, usize, usize)>,
+}
+
+impl<H: Hal> BufQueue<H> {
+    fn new<T: Transport>(transport: &mut T, index: u16) -> DevResult<Self> {
+        Ok(Self {
+            queue: VirtQueue::new(transport, index, 1)?,
+            free: (0..QUEUE_SIZE as u16).rev().collect(),
+            pending: None,
+        })
+    }
+
+    fn index(&self) -> u16 {
+        self.queue.index()
+    }
+
+    fn buf(&self, id: u16) -> (PhysAddr, *mut u8) {
+        self.queue.extra(id as usize * BUF_SIZE)
+    }
+
+    /// Makes the descriptor `id` available to the device, with `len` bytes of
+    /// its buffer.
+    fn push(&mut self, id: u16, len: usize, device_writable: bool) {
+        let flags = if device_writable {
+            VIRTQ_DESC_F_WRITE
+        } else {
+            0
+        };
+        let (paddr, _) = self.buf(id);
+        self.queue.set_desc(id, paddr, len, flags, 0);
+        self.queue.push(id);
+    }
+
+    /// Takes a buffer used by the device, returns its descriptor and the
+    /// number of bytes written by the device. Used buffers with invalid
+    /// descriptors are dropped.
+    fn pop_used(&mut self) -> Option<(u16, usize)> {
+        let (id, len) = self.queue.pop_used().ok().flatten()?;
+        Some((id, len.min(BUF_SIZE)))
+    }
+
+    /// Makes all buffers available to the device, for a receive queue.
+    fn fill<T: Transport>(&mut self, transport: &mut T) {
+        while let Some(id) = self.free.pop() {
+            self.push(id, BUF_SIZE, true);
+        }
+        transport.notify(self.index());
+    }
+
+    /// Whether there are received bytes to read, for a receive queue.
+    fn can_recv(&self) -> bool {
+        self.pending.is_some() || self.queue.has_used()
+    }
+
+    /// Reads received bytes into `buf`, and gives the buffers that have been
+    /// read back to the device, for a receive queue.
+    fn recv<T: Transport>(&mut self, transport: &mut T, buf: &mut [u8]) -> usize {
+        let mut read_len = 0;
+        let mut recycled = false;
+        while read_len < buf.len() {
+            if self.pending.is_none() {
+                self.pending = self.pop_used().map(|(id, len)| (id, len, 0));
+            }
+            let Some((id, len, offset)) = self.pending else {
+                break;
+            };
+            let count = (len - offset).min(buf.len() - read_len);
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.buf(id).1.add(offset),
+                    buf[read_len..].as_mut_ptr(),
+                    count,
+                )
+            };
+            read_len += count;
+            if offset + count == len {
+                self.pending = None;
+                self.push(id, BUF_SIZE, true);
+                recycled = true;
+            } else {
+                self.pending = Some((id, len, offset + count));
+            }
+        }
+        if recycled {
+            transport.notify(self.index());
+        }
+        read_len
+    }
+
+    /// Takes one whole received buffer, and gives it back to the device, for
+    /// the control receive queue. Returns the length of the message.
+    fn recv_msg<T: Transport>(&mut self, transport: &mut T, msg: &mut [u8]) -> Option<usize> {
+        let (id, len) = self.pop_used()?;
+        let len = len.min(msg.len());
+        unsafe { core::ptr::copy_nonoverlapping(self.buf(id).1, msg.as_mut_ptr(), len) };
+        self.push(id, BUF_SIZE, true);
+        transport.notify(self.index());
+        Some(len)
+    }
+
+    /// Takes back the buffers that the device has sent, for a transmit queue.
+    fn reclaim(&mut self) {
+        while let Some((id, _)) = self.pop_used() {
+            self.free.push(id);
+        }
+    }
+
+    /// Whether there is a free buffer to send bytes, for a transmit queue.
+    fn can_send(&mut self) -> bool {
+        self.reclaim();
+        !self.free.is_empty()
+    }
+
+    /// Copies bytes in `data` to free buffers and makes them available to the
+    /// device, for a transmit queue. Returns the number of bytes sent.
+    fn send<T: Transport>(&mut self, transport: &mut T, data: &[u8]) -> usize {
+        self.reclaim();
+        let mut sent = 0;
+        while sent < data.len() {
+            let Some(id) = self.free.pop() else {
+                break;
+            };
+            let count = (data.len() - sent).min(BUF_SIZE);
+            unsafe { core::ptr::copy_nonoverlapping(data[sent..].as_ptr(), self.buf(id).1, count) };
+            self.push(id, count, false);
+            sent += count;
+        }
+        if sent > 0 {
+            transport.notify(self.index());
+        }
+        sent
+    }
+}
+
+/// A port and its virtqueues.
+struct Port<H: Hal> {
+    receive: BufQueue<H>,
+    transmit: BufQueue<H>,
+    /// Whether the device has added the port. Port 0 of a device without
+    /// multiport support is always added.
+    added: bool,
+    name: Option<String>,
+}
+
+/// The VirtIO console device driver.
+///
+/// With the multiport feature (e.g. QEMU `virtio-serial`), each port (e.g.
+/// `virtconsole` and `virtserialport`) is a separate channel to the host.
+/// Ports are added by the device through control messages, which are handled
+/// whenever the driver is used. At most 4 ports are supported.
+pub struct VirtIoConsoleDev<H: Hal, T: Transport> {
+    transport: T,
+    ports: Vec<Port<H>>,
+    /// The control virtqueues, if the device supports multiport.
+    control: Option<(BufQueue<H>, BufQueue<H>)>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoConsoleDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoConsoleDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoConsoleDev<H, T> {
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        transport.set_status(DeviceStatus::empty());
+        transport.set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER);
+        let features = transport.read_device_features() & SUPPORTED_FEATURES;
+        transport.write_driver_features(features);
+        transport.set_status(
+            DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK,
+        );
+        transport.set_guest_page_size(PAGE_SIZE as u32);
+
+        let multiport = features & VIRTIO_CONSOLE_F_MULTIPORT != 0;
+        let num_ports = if multiport {
+            let config = transport
+                .config_space::<ConsoleConfig>()
+                .map_err(crate::as_dev_err)?;
+            let max_ports =
+                u32::from_le(unsafe { addr_of!((*config.as_ptr()).max_nr_ports).read_volatile() });
+            (max_ports as usize).clamp(1, MAX_PORTS)
+        } else {
+            1
+        };
+
+        let mut ports = Vec::with_capacity(num_ports);
+        for i in 0..num_ports as u16 {
+            let receive = if i == 0 { QUEUE_RECEIVE0 } else { 2 * i + 2 };
+            ports.push(Port {
+                receive: BufQueue::new(&mut transport, receive)?,
+                transmit: BufQueue::new(&mut transport, receive + 1)?,
+                added: !multiport,
+                name: None,
+            });
+        }
+        let control = if multiport {
+            Some((
+                BufQueue::new(&mut transport, QUEUE_CONTROL_RECEIVE)?,
+                BufQueue::new(&mut transport, QUEUE_CONTROL_TRANSMIT)?,
+            ))
+        } else {
+            None
+        };
+        transport.finish_init();
+
+        let mut dev = Self {
+            transport,
+            ports,
+            control,
+        };
+        for port in dev.ports.iter_mut() {
+            port.receive.fill(&mut dev.transport);
+        }
+        if let Some((receive, _)) = dev.control.as_mut() {
+            receive.fill(&mut dev.transport);
+        }
+        if multiport {
+            dev.send_control(0, VIRTIO_CONSOLE_DEVICE_READY, 1);
+            dev.handle_control();
+        }
+        Ok(dev)
+    }
+
+    /// Sends a control message, waits if the control transmit queue is full.
+    fn send_control(&mut self, id: u32, event: u16, value: u16) {
+        let Some((_, transmit)) = self.control.as_mut() else {
+            return;
+        };
+        let mut msg = [0; CONTROL_MSG_LEN];
+        msg[0..4].copy_from_slice(&id.to_le_bytes());
+        msg[4..6].copy_from_slice(&event.to_le_bytes());
+        msg[6..8].copy_from_slice(&value.to_le_bytes());
+        while transmit.send(&mut self.transport, &msg) == 0 {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Handles all received control messages.
+    fn handle_control(&mut self) {
+        let mut msg = [0; BUF_SIZE];
+        loop {
+            let Some((receive, _)) = self.control.as_mut() else {
+                return;
+            };
+            let Some(len) = receive.recv_msg(&mut self.transport, &mut msg) else {
+                return;
+            };
+            if len < CONTROL_MSG_LEN {
+                continue;
+            }
+            let id = u32::from_le_bytes(msg[0..4].try_into().unwrap());
+            let event = u16::from_le_bytes([msg[4], msg[5]]);
+            let Some(port) = self.ports.get_mut(id as usize) else {
+                if event == VIRTIO_CONSOLE_DEVICE_ADD {
+                    // too many ports, tell the device that it is not usable
+                    self.send_control(id, VIRTIO_CONSOLE_PORT_READY, 0);
+                }
+                continue;
+            };
+            match event {
+                VIRTIO_CONSOLE_DEVICE_ADD => {
+                    port.added = true;
+                    self.send_control(id, VIRTIO_CONSOLE_PORT_READY, 1);
+                }
+                VIRTIO_CONSOLE_DEVICE_REMOVE => {
+                    port.added = false;
+                    port.name = None;
+                }
+                VIRTIO_CONSOLE_CONSOLE_PORT | VIRTIO_CONSOLE_PORT_OPEN => {
+                    // open the port from the guest side, the host may not
+                    // send data until it is opened
+                    self.send_control(id, VIRTIO_CONSOLE_PORT_OPEN, 1);
+                }
+                VIRTIO_CONSOLE_PORT_NAME => {
+                    let name = &msg[CONTROL_MSG_LEN..len];
+                    let name = &name[..name.len().min(MAX_NAME_LEN)];
+                    let name = name.split(|&b| b == 0).next().unwrap_or_default();
+                    port.name = Some(String::from_utf8_lossy(name).into());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Handles control messages, and checks that the port `port` is added.
+    fn check_port(&mut self, port: usize) -> DevResult {
+        self.handle_control();
+        match self.ports.get(port) {
+            Some(p) if p.added => Ok(()),
+            Some(_) => Err(DevError::Again),
+            None => Err(DevError::InvalidParam),
+        }
+    }
+}
+
+impl<H: Hal, T: Transport> Drop for VirtIoConsoleDev<H, T> {
+    fn drop(&mut self) {
+        self.transport.set_status(DeviceStatus::empty());
+        for port in self.ports.iter() {
+            self.transport.queue_unset(port.receive.index());
+            self.transport.queue_unset(port.transmit.index());
+        }
+        if self.control.is_some() {
+            self.transport.queue_unset(QUEUE_CONTROL_RECEIVE);
+            self.transport.queue_unset(QUEUE_CONTROL_TRANSMIT);
+        }
+    }
+}
+
+impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoConsoleDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-console"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Char
+    }
+}
+
+impl<H: Hal, T: Transport> CharDriverOps for VirtIoConsoleDev<H, T> {
+    fn num_ports(&self) -> usize {
+        self.ports.len()
+    }
+
+    fn port_name(&self, port: usize) -> Option<&str> {
+        self.ports.get(port)?.name.as_deref()
+    }
+
+    fn read(&mut self, port: usize, buf: &mut [u8]) -> DevResult<usize> {
+        self.check_port(port)?;
+        match self.ports[port].receive.recv(&mut self.transport, buf) {
+            0 if !buf.is_empty() => Err(DevError::Again),
+            n => Ok(n),
+        }
+    }
+
+    fn write(&mut self, port: usize, buf: &[u8]) -> DevResult<usize> {
+        self.check_port(port)?;
+        match self.ports[port].transmit.send(&mut self.transport, buf) {
+            0 if !buf.is_empty() => Err(DevError::Again),
+            n => Ok(n),
+        }
+    }
+
+    fn poll(&mut self, port: usize) -> DevResult<PollState> {
+        match self.check_port(port) {
+            Ok(()) => Ok(PollState {
+                readable: self.ports[port].receive.can_recv(),
+                writable: self.ports[port].transmit.can_send(),
+            }),
+            Err(DevError::Again) => Ok(PollState::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
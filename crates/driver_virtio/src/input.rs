@@ -0,0 +1,105 @@
+extern crate alloc;
+
+use alloc::string::String;
+
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use driver_input::{AbsInfo, Event, EventType, InputDeviceId, InputDriverOps};
+use virtio_drivers::device::input::{InputConfigSelect, VirtIOInput as InnerDev};
+use virtio_drivers::{transport::Transport, Hal};
+
+/// The VirtIO input device driver.
+pub struct VirtIoInputDev<H: Hal, T: Transport> {
+    inner: InnerDev<H, T>,
+    device_id: InputDeviceId,
+    name: String,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIoInputDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIoInputDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIoInputDev<H, T> {
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(transport: T) -> DevResult<Self> {
+        let mut inner = InnerDev::new(transport).map_err(crate::as_dev_err)?;
+
+        let mut buf = [0; 128];
+        let len = inner.query_config_select(InputConfigSelect::IdName, 0, &mut buf);
+        let name = String::from_utf8_lossy(&buf[..(len as usize).min(buf.len())]).into();
+
+        // struct virtio_input_devids { le16 bustype; le16 vendor;
+        //                              le16 product; le16 version; }
+        let mut ids = [0; 8];
+        inner.query_config_select(InputConfigSelect::IdDevids, 0, &mut ids);
+        let id = |i: usize| u16::from_le_bytes([ids[2 * i], ids[2 * i + 1]]);
+        let device_id = InputDeviceId {
+            bus_type: id(0),
+            vendor: id(1),
+            product: id(2),
+            version: id(3),
+        };
+
+        Ok(Self {
+            inner,
+            device_id,
+            name,
+        })
+    }
+}
+
+impl<H: Hal, T: Transport> const BaseDriverOps for VirtIoInputDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-input"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Input
+    }
+}
+
+impl<H: Hal, T: Transport> InputDriverOps for VirtIoInputDev<H, T> {
+    fn device_id(&self) -> InputDeviceId {
+        self.device_id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_event_bits(&mut self, ty: EventType, out: &mut [u8]) -> DevResult<bool> {
+        out.fill(0);
+        let len = self
+            .inner
+            .query_config_select(InputConfigSelect::EvBits, ty as u8, out);
+        Ok(len != 0)
+    }
+
+    fn get_abs_info(&mut self, axis: u8) -> DevResult<AbsInfo> {
+        // struct virtio_input_absinfo { le32 min; le32 max; le32 fuzz;
+        //                               le32 flat; le32 res; }
+        let mut buf = [0; 20];
+        let len = self
+            .inner
+            .query_config_select(InputConfigSelect::AbsInfo, axis, &mut buf);
+        if len == 0 {
+            return Err(DevError::InvalidParam);
+        }
+        let field = |i: usize| u32::from_le_bytes(buf[4 * i..4 * i + 4].try_into().unwrap());
+        Ok(AbsInfo {
+            min: field(0),
+            max: field(1),
+            fuzz: field(2),
+            flat: field(3),
+            res: field(4),
+        })
+    }
+
+    fn read_event(&mut self) -> DevResult<Event> {
+        let event = self.inner.pop_pending_event().ok_or(DevError::Again)?;
+        Ok(Event {
+            event_type: event.event_type,
+            code: event.code,
+            value: event.value as i32,
+        })
+    }
+}
@@ -16,17 +16,31 @@
 
 #[cfg(feature = "block")]
 mod blk;
+#[cfg(feature = "char")]
+mod console;
 #[cfg(feature = "gpu")]
 mod gpu;
+#[cfg(feature = "input")]
+mod input;
 #[cfg(feature = "net")]
 mod net;
+#[cfg(feature = "9p")]
+mod ninep;
+#[cfg(any(feature = "9p", feature = "char"))]
+mod queue;
 
 #[cfg(feature = "block")]
 pub use self::blk::VirtIoBlkDev;
+#[cfg(feature = "char")]
+pub use self::console::VirtIoConsoleDev;
 #[cfg(feature = "gpu")]
 pub use self::gpu::VirtIoGpuDev;
+#[cfg(feature = "input")]
+pub use self::input::VirtIoInputDev;
 #[cfg(feature = "net")]
 pub use self::net::VirtIoNetDev;
+#[cfg(feature = "9p")]
+pub use self::ninep::VirtIo9pDev;
 
 pub use virtio_drivers::transport::pci::bus as pci;
 pub use virtio_drivers::transport::{mmio::MmioTransport, pci::PciTransport, Transport};
@@ -75,6 +89,9 @@ const fn as_dev_type(t: VirtIoDevType) -> Option<DeviceType> {
         Block => Some(DeviceType::Block),
         Network => Some(DeviceType::Net),
         GPU => Some(DeviceType::Display),
+        _9P => Some(DeviceType::NineP),
+        Console => Some(DeviceType::Char),
+        Input => Some(DeviceType::Input),
         _ => None,
     }
 }
@@ -0,0 +1,179 @@
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use driver_9p::{NinePDriverOps, WaitHooks};
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+use virtio_drivers::transport::{DeviceStatus, Transport};
+use virtio_drivers::{BufferDirection, Hal};
+
+use crate::queue::{VirtQueue, PAGE_SIZE, VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
+
+/// The device supports the mount tag in the config space.
+const VIRTIO_9P_MOUNT_TAG: u64 = 1 << 0;
+/// Compliance with the virtio 1.0 specification.
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+const SUPPORTED_FEATURES: u64 = VIRTIO_9P_MOUNT_TAG | VIRTIO_F_VERSION_1;
+
+/// The only virtqueue of the device, for requests.
+const QUEUE_REQUEST: u16 = 0;
+/// Each request uses two descriptors, one for the request message and one for
+/// the response.
+const QUEUE_SIZE: usize = 2;
+
+/// Maximum length of the mount tag.
+const MAX_TAG_LEN: usize = 64;
+
+/// Maximum number of polls for a response without [`WaitHooks`], after which
+/// the device is considered dead.
+const MAX_POLLS: usize = 1 << 30;
+
+/// Maximum time to wait for a response with [`WaitHooks`], after which the
+/// device is considered dead.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The VirtIO 9P transport device driver.
+///
+/// Requests are sent one at a time, each of them waits for its response by
+/// polling. If the device does not respond in time, it is reset and all later
+/// requests fail. Without [`WaitHooks`], it spins between polls and the time
+/// is bounded by the number of polls.
+pub struct VirtIo9pDev<H: Hal, T: Transport> {
+    transport: T,
+    queue: VirtQueue<H, QUEUE_SIZE>,
+    tag: [u8; MAX_TAG_LEN],
+    tag_len: usize,
+    broken: bool,
+    hooks: Option<WaitHooks>,
+}
+
+unsafe impl<H: Hal, T: Transport> Send for VirtIo9pDev<H, T> {}
+unsafe impl<H: Hal, T: Transport> Sync for VirtIo9pDev<H, T> {}
+
+impl<H: Hal, T: Transport> VirtIo9pDev<H, T> {
+    /// Creates a new driver instance and initializes the device, or returns
+    /// an error if any step fails.
+    pub fn try_new(mut transport: T) -> DevResult<Self> {
+        transport.set_status(DeviceStatus::empty());
+        transport.set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER);
+        let features = transport.read_device_features() & SUPPORTED_FEATURES;
+        transport.write_driver_features(features);
+        transport.set_status(
+            DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER | DeviceStatus::FEATURES_OK,
+        );
+        transport.set_guest_page_size(PAGE_SIZE as u32);
+
+        let mut tag = [0; MAX_TAG_LEN];
+        let mut tag_len = 0;
+        if features & VIRTIO_9P_MOUNT_TAG != 0 {
+            // struct virtio_9p_config { le16 tag_len; u8 tag[]; }
+            let config = transport.config_space::<u16>().map_err(crate::as_dev_err)?;
+            tag_len = (u16::from_le(unsafe { config.as_ptr().read_volatile() }) as usize)
+                .min(MAX_TAG_LEN);
+            let tag_ptr = unsafe { config.as_ptr().add(1) as *const u8 };
+            for (i, b) in tag[..tag_len].iter_mut().enumerate() {
+                *b = unsafe { tag_ptr.add(i).read_volatile() };
+            }
+        }
+
+        let queue = VirtQueue::new(&mut transport, QUEUE_REQUEST, 0)?;
+        transport.finish_init();
+        Ok(Self {
+            transport,
+            queue,
+            tag,
+            tag_len,
+            broken: false,
+            hooks: None,
+        })
+    }
+
+    /// Adds the buffers of a request to the virtqueue, and returns after the
+    /// device has used them, with the number of bytes written into `resp`.
+    fn submit(&mut self, req: &[u8], resp: &mut [u8]) -> DevResult<usize> {
+        let req_buf = NonNull::from(req);
+        let resp_buf = NonNull::from(&mut *resp);
+        let req_paddr = unsafe { H::share(req_buf, BufferDirection::DriverToDevice) };
+        let resp_paddr = unsafe { H::share(resp_buf, BufferDirection::DeviceToDriver) };
+        self.queue
+            .set_desc(0, req_paddr, req.len(), VIRTQ_DESC_F_NEXT, 1);
+        self.queue
+            .set_desc(1, resp_paddr, resp.len(), VIRTQ_DESC_F_WRITE, 0);
+        self.queue.push(0);
+        self.transport.notify(QUEUE_REQUEST);
+
+        let used = self.wait_used();
+        let res = match used {
+            Ok(Some((0, len))) => Ok(len.min(resp.len())),
+            Ok(Some(_)) | Err(_) => Err(DevError::BadState),
+            Ok(None) => {
+                // stop the device from accessing the buffers
+                self.transport.set_status(DeviceStatus::empty());
+                self.broken = true;
+                Err(DevError::Io)
+            }
+        };
+        unsafe {
+            H::unshare(req_paddr, req_buf, BufferDirection::DriverToDevice);
+            H::unshare(resp_paddr, resp_buf, BufferDirection::DeviceToDriver);
+        }
+        res
+    }
+
+    /// Polls the used ring until the device responds, or the time is out.
+    fn wait_used(&mut self) -> DevResult<Option<(u16, usize)>> {
+        match self.hooks {
+            Some(hooks) => {
+                let deadline = (hooks.now)() + RESPONSE_TIMEOUT;
+                loop {
+                    match self.queue.pop_used() {
+                        Ok(None) if (hooks.now)() < deadline => (hooks.relax)(),
+                        used => return used,
+                    }
+                }
+            }
+            None => {
+                for _ in 0..MAX_POLLS {
+                    match self.queue.pop_used() {
+                        Ok(None) => core::hint::spin_loop(),
+                        used => return used,
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<H: Hal, T: Transport> Drop for VirtIo9pDev<H, T> {
+    fn drop(&mut self) {
+        self.transport.set_status(DeviceStatus::empty());
+        self.transport.queue_unset(QUEUE_REQUEST);
+    }
+}
+
+impl<H: Hal, T: Transport> const BaseDriverOps for VirtIo9pDev<H, T> {
+    fn device_name(&self) -> &str {
+        "virtio-9p"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::NineP
+    }
+}
+
+impl<H: Hal, T: Transport> NinePDriverOps for VirtIo9pDev<H, T> {
+    fn mount_tag(&self) -> &str {
+        core::str::from_utf8(&self.tag[..self.tag_len]).unwrap_or_default()
+    }
+
+    fn request(&mut self, req: &[u8], resp: &mut [u8]) -> DevResult<usize> {
+        if self.broken {
+            return Err(DevError::BadState);
+        }
+        self.submit(req, resp)
+    }
+
+    fn set_wait_hooks(&mut self, hooks: WaitHooks) {
+        self.hooks = Some(hooks);
+    }
+}
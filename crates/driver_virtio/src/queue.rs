@@ -0,0 +1,188 @@
+//! Split virtqueues for the drivers that are not provided by the
+//! [`virtio-drivers`](virtio_drivers) crate.
+
+use core::marker::PhantomData;
+use core::ptr::{addr_of, addr_of_mut, NonNull};
+use core::sync::atomic::{fence, Ordering};
+
+use driver_common::{DevError, DevResult};
+use virtio_drivers::transport::Transport;
+use virtio_drivers::{BufferDirection, Hal, PhysAddr};
+
+pub(crate) const PAGE_SIZE: usize = 0x1000;
+
+/// The descriptor continues via the `next` field.
+pub(crate) const VIRTQ_DESC_F_NEXT: u16 = 1;
+/// The buffer of the descriptor is written by the device.
+pub(crate) const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct AvailRing<const SIZE: usize> {
+    flags: u16,
+    idx: u16,
+    ring: [u16; SIZE],
+    used_event: u16,
+}
+
+#[repr(C)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct UsedRing<const SIZE: usize> {
+    flags: u16,
+    idx: u16,
+    ring: [UsedElem; SIZE],
+    avail_event: u16,
+}
+
+/// A virtqueue of `SIZE` descriptors in DMA pages, laid out as the legacy
+/// interface requires: the descriptor table and the available ring in the
+/// first page, and the used ring in the second one. Drivers may ask for more
+/// pages after them, e.g. for the buffers of descriptors.
+pub(crate) struct VirtQueue<H: Hal, const SIZE: usize> {
+    index: u16,
+    paddr: PhysAddr,
+    vaddr: NonNull<u8>,
+    pages: usize,
+    avail_idx: u16,
+    last_used_idx: u16,
+    _hal: PhantomData<H>,
+}
+
+impl<H: Hal, const SIZE: usize> VirtQueue<H, SIZE> {
+    const DESC_OFFSET: usize = 0;
+    const AVAIL_OFFSET: usize = core::mem::size_of::<Descriptor>() * SIZE;
+    const USED_OFFSET: usize = PAGE_SIZE;
+    const EXTRA_OFFSET: usize = 2 * PAGE_SIZE;
+
+    /// Allocates the virtqueue `index` with `extra_pages` pages after the
+    /// rings, and sets it up on the device.
+    pub fn new<T: Transport>(transport: &mut T, index: u16, extra_pages: usize) -> DevResult<Self> {
+        if (transport.max_queue_size(index) as usize) < SIZE {
+            return Err(DevError::Unsupported);
+        }
+        let pages = 2 + extra_pages;
+        let (paddr, vaddr) = H::dma_alloc(pages, BufferDirection::Both);
+        if paddr == 0 {
+            return Err(DevError::NoMemory);
+        }
+        unsafe { vaddr.as_ptr().write_bytes(0, pages * PAGE_SIZE) };
+        transport.queue_set(
+            index,
+            SIZE as u32,
+            paddr + Self::DESC_OFFSET,
+            paddr + Self::AVAIL_OFFSET,
+            paddr + Self::USED_OFFSET,
+        );
+        Ok(Self {
+            index,
+            paddr,
+            vaddr,
+            pages,
+            avail_idx: 0,
+            last_used_idx: 0,
+            _hal: PhantomData,
+        })
+    }
+
+    /// The index of the virtqueue on the device.
+    #[cfg_attr(not(feature = "char"), allow(dead_code))]
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// The physical and virtual addresses of the extra pages at `offset`.
+    #[cfg_attr(not(feature = "char"), allow(dead_code))]
+    pub fn extra(&self, offset: usize) -> (PhysAddr, *mut u8) {
+        let offset = Self::EXTRA_OFFSET + offset;
+        debug_assert!(offset < self.pages * PAGE_SIZE);
+        (self.paddr + offset, unsafe {
+            self.vaddr.as_ptr().add(offset)
+        })
+    }
+
+    fn desc(&self, id: u16) -> *mut Descriptor {
+        let table = unsafe { self.vaddr.as_ptr().add(Self::DESC_OFFSET) } as *mut Descriptor;
+        unsafe { table.add(id as usize % SIZE) }
+    }
+
+    fn avail(&self) -> *mut AvailRing<SIZE> {
+        unsafe { self.vaddr.as_ptr().add(Self::AVAIL_OFFSET) as _ }
+    }
+
+    fn used(&self) -> *mut UsedRing<SIZE> {
+        unsafe { self.vaddr.as_ptr().add(Self::USED_OFFSET) as _ }
+    }
+
+    /// Sets the buffer of the descriptor `id`, and the next descriptor if
+    /// `flags` contains [`VIRTQ_DESC_F_NEXT`].
+    pub fn set_desc(&mut self, id: u16, addr: PhysAddr, len: usize, flags: u16, next: u16) {
+        unsafe {
+            self.desc(id).write_volatile(Descriptor {
+                addr: addr as u64,
+                len: len as u32,
+                flags,
+                next,
+            })
+        };
+    }
+
+    /// Makes the descriptor chain starting at `head` available to the device.
+    ///
+    /// The device is not notified.
+    pub fn push(&mut self, head: u16) {
+        unsafe {
+            let avail = self.avail();
+            let slot = self.avail_idx as usize % SIZE;
+            addr_of_mut!((*avail).ring[slot]).write_volatile(head);
+            // the descriptors must be visible before the index is updated
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            addr_of_mut!((*avail).idx).write_volatile(self.avail_idx);
+            fence(Ordering::SeqCst);
+        }
+    }
+
+    /// Whether the device has used a descriptor chain that is not popped.
+    pub fn has_used(&self) -> bool {
+        unsafe { addr_of!((*self.used()).idx).read_volatile() != self.last_used_idx }
+    }
+
+    /// Takes a descriptor chain used by the device, returns its head and the
+    /// number of bytes written by the device.
+    ///
+    /// Returns [`DevError::BadState`] if the device reports a head out of
+    /// the queue.
+    pub fn pop_used(&mut self) -> DevResult<Option<(u16, usize)>> {
+        if !self.has_used() {
+            return Ok(None);
+        }
+        fence(Ordering::SeqCst);
+        let used = self.used();
+        let slot = self.last_used_idx as usize % SIZE;
+        let id = unsafe { addr_of!((*used).ring[slot].id).read_volatile() };
+        let len = unsafe { addr_of!((*used).ring[slot].len).read_volatile() };
+        self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        if id as usize >= SIZE {
+            return Err(DevError::BadState);
+        }
+        Ok(Some((id as u16, len as usize)))
+    }
+}
+
+impl<H: Hal, const SIZE: usize> Drop for VirtQueue<H, SIZE> {
+    fn drop(&mut self) {
+        unsafe { H::dma_dealloc(self.paddr, self.vaddr, self.pages) };
+    }
+}
@@ -11,6 +11,10 @@ pub struct Sv39MetaData;
 #[derive(Clone, Copy)]
 pub struct Sv48MetaData;
 
+/// Metadata of RISC-V Sv57 page tables.
+#[derive(Clone, Copy)]
+pub struct Sv57MetaData;
+
 impl const PagingMetaData for Sv39MetaData {
     const LEVELS: usize = 3;
     const PA_MAX_BITS: usize = 56;
@@ -23,8 +27,17 @@ impl const PagingMetaData for Sv48MetaData {
     const VA_MAX_BITS: usize = 48;
 }
 
+impl const PagingMetaData for Sv57MetaData {
+    const LEVELS: usize = 5;
+    const PA_MAX_BITS: usize = 56;
+    const VA_MAX_BITS: usize = 57;
+}
+
 /// Sv39: Page-Based 39-bit (3 levels) Virtual-Memory System.
 pub type Sv39PageTable<I> = PageTable64<Sv39MetaData, Rv64PTE, I>;
 
 /// Sv48: Page-Based 48-bit (4 levels) Virtual-Memory System.
 pub type Sv48PageTable<I> = PageTable64<Sv48MetaData, Rv64PTE, I>;
+
+/// Sv57: Page-Based 57-bit (5 levels) Virtual-Memory System.
+pub type Sv57PageTable<I> = PageTable64<Sv57MetaData, Rv64PTE, I>;
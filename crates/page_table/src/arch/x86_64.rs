@@ -6,11 +6,23 @@ use page_table_entry::x86_64::X64PTE;
 /// metadata of x86_64 page tables.
 pub struct X64PagingMetaData;
 
+/// metadata of x86_64 page tables with 5-level paging (LA57) enabled.
+pub struct X64La57PagingMetaData;
+
 impl const PagingMetaData for X64PagingMetaData {
     const LEVELS: usize = 4;
     const PA_MAX_BITS: usize = 52;
     const VA_MAX_BITS: usize = 48;
 }
 
+impl const PagingMetaData for X64La57PagingMetaData {
+    const LEVELS: usize = 5;
+    const PA_MAX_BITS: usize = 52;
+    const VA_MAX_BITS: usize = 57;
+}
+
 /// x86_64 page table.
 pub type X64PageTable<I> = PageTable64<X64PagingMetaData, X64PTE, I>;
+
+/// x86_64 page table with 5-level paging (LA57).
+pub type X64La57PageTable<I> = PageTable64<X64La57PagingMetaData, X64PTE, I>;
@@ -9,25 +9,20 @@ use crate::{GenericPTE, PagingIf, PagingMetaData};
 use crate::{MappingFlags, PageSize, PagingError, PagingResult};
 
 const ENTRY_COUNT: usize = 512;
+const MAX_LEVELS: usize = 5;
 
-const fn p4_index(vaddr: VirtAddr) -> usize {
-    (vaddr.as_usize() >> (12 + 27)) & (ENTRY_COUNT - 1)
-}
-
-const fn p3_index(vaddr: VirtAddr) -> usize {
-    (vaddr.as_usize() >> (12 + 18)) & (ENTRY_COUNT - 1)
-}
-
-const fn p2_index(vaddr: VirtAddr) -> usize {
-    (vaddr.as_usize() >> (12 + 9)) & (ENTRY_COUNT - 1)
-}
-
-const fn p1_index(vaddr: VirtAddr) -> usize {
-    (vaddr.as_usize() >> 12) & (ENTRY_COUNT - 1)
+/// Returns the index of the entry for `vaddr` in the level-`n` page table,
+/// where `n` starts with `1` for the leaf page table.
+const fn table_index(vaddr: VirtAddr, n: usize) -> usize {
+    (vaddr.as_usize() >> (12 + (n - 1) * 9)) & (ENTRY_COUNT - 1)
 }
 
 /// A generic page table struct for 64-bit platform.
 ///
+/// The number of levels is given by [`PagingMetaData::LEVELS`], which can be
+/// 3, 4, or 5. Each level has 512 entries, and huge pages can only be mapped
+/// in the level-2 (2M) and level-3 (1G) page tables.
+///
 /// It also tracks all intermediate level tables. They will be deallocated
 /// When the [`PageTable64`] itself is dropped.
 pub struct PageTable64<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> {
@@ -224,6 +219,52 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
         Ok(())
     }
 
+    /// Changes the mapping flags of a contiguous virtual memory region.
+    ///
+    /// The region starts with `vaddr` and its size is `size`. The address and
+    /// `size` must be aligned to 4K, otherwise it will return
+    /// [`Err(PagingError::NotAligned)`]. Huge pages must be fully covered by
+    /// the region, otherwise it will return [`Err(PagingError::MappedToHugePage)`].
+    ///
+    /// Returns [`Err(PagingError::NotMapped)`] if any page in the region is not
+    /// mapped. The caller is responsible for flushing the TLB.
+    ///
+    /// [`Err(PagingError::NotAligned)`]: PagingError::NotAligned
+    /// [`Err(PagingError::MappedToHugePage)`]: PagingError::MappedToHugePage
+    /// [`Err(PagingError::NotMapped)`]: PagingError::NotMapped
+    pub fn protect_region(
+        &mut self,
+        vaddr: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+    ) -> PagingResult {
+        if !vaddr.is_aligned(PageSize::Size4K) || !memory_addr::is_aligned(size, PAGE_SIZE_4K) {
+            return Err(PagingError::NotAligned);
+        }
+        trace!(
+            "protect_region({:#x}) [{:#x}, {:#x}) {:?}",
+            self.root_paddr(),
+            vaddr,
+            vaddr + size,
+            flags,
+        );
+        let mut vaddr = vaddr;
+        let mut size = size;
+        while size > 0 {
+            let (entry, page_size) = self.get_entry_mut(vaddr)?;
+            if entry.is_unused() {
+                return Err(PagingError::NotMapped);
+            }
+            if !vaddr.is_aligned(page_size) || page_size as usize > size {
+                return Err(PagingError::MappedToHugePage);
+            }
+            entry.set_flags(flags, page_size.is_huge());
+            vaddr += page_size as usize;
+            size -= page_size as usize;
+        }
+        Ok(())
+    }
+
     /// Walk the page table recursively.
     ///
     /// When reaching the leaf page table, call `func` on the current page table
@@ -246,6 +287,56 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
             func,
         )
     }
+
+    /// Creates a deep copy of the page table.
+    ///
+    /// All intermediate level tables are duplicated, while the leaf entries
+    /// of the new page table point to the same physical frames as `self`.
+    pub fn try_clone(&self) -> PagingResult<Self> {
+        let mut new = Self::try_new()?;
+        new.clone_recursive(self.table_of(self.root_paddr()), new.root_paddr(), 0, false)?;
+        Ok(new)
+    }
+
+    /// Creates a copy-on-write clone of the page table.
+    ///
+    /// Same as [`PageTable64::try_clone`], except that all writable leaf
+    /// entries are made read-only in both page tables, so that a write to the
+    /// shared frames will cause a page fault. The OS should track which pages
+    /// are shared, copy the frame on fault, and restore the write permission
+    /// with [`PageTable64::update`].
+    ///
+    /// The caller is responsible for flushing the TLB of `self`.
+    pub fn clone_cow(&mut self) -> PagingResult<Self> {
+        let mut new = Self::try_new()?;
+        new.clone_recursive(self.table_of(self.root_paddr()), new.root_paddr(), 0, true)?;
+        // write-protect `self` only after the clone succeeds
+        let root = self.table_of_mut(self.root_paddr());
+        self.for_each_leaf_mut(root, 0, &mut |entry, is_huge| {
+            let flags = entry.flags();
+            if entry.is_present() && flags.contains(MappingFlags::WRITE) {
+                entry.set_flags(flags - MappingFlags::WRITE, is_huge);
+            }
+        })?;
+        Ok(new)
+    }
+
+    /// Returns an iterator over all mapped leaf entries, which reads and
+    /// clears their accessed and dirty bits.
+    ///
+    /// It can be used to estimate the working set or to find the pages
+    /// modified since the last call. The caller is responsible for flushing
+    /// the TLB after the iteration, otherwise the hardware may not set the
+    /// bits again.
+    pub fn harvest_accessed_dirty(&mut self) -> AccessedDirtyIter<'_, M, PTE, IF> {
+        let mut tables = [(PhysAddr::from(0), 0); MAX_LEVELS];
+        tables[0] = (self.root_paddr(), 0);
+        AccessedDirtyIter {
+            pt: self,
+            tables,
+            depth: 1,
+        }
+    }
 }
 
 // Private implements.
@@ -292,29 +383,19 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
     }
 
     fn get_entry_mut(&self, vaddr: VirtAddr) -> PagingResult<(&mut PTE, PageSize)> {
-        let p3 = if M::LEVELS == 3 {
-            self.table_of_mut(self.root_paddr())
-        } else if M::LEVELS == 4 {
-            let p4 = self.table_of_mut(self.root_paddr());
-            let p4e = &mut p4[p4_index(vaddr)];
-            self.next_table_mut(p4e)?
-        } else {
-            unreachable!()
-        };
-        let p3e = &mut p3[p3_index(vaddr)];
-        if p3e.is_huge() {
-            return Ok((p3e, PageSize::Size1G));
-        }
-
-        let p2 = self.next_table_mut(p3e)?;
-        let p2e = &mut p2[p2_index(vaddr)];
-        if p2e.is_huge() {
-            return Ok((p2e, PageSize::Size2M));
+        let mut table = self.table_of_mut(self.root_paddr());
+        for n in (2..=M::LEVELS).rev() {
+            let entry = &mut table[table_index(vaddr, n)];
+            if entry.is_huge() {
+                match n {
+                    3 => return Ok((entry, PageSize::Size1G)),
+                    2 => return Ok((entry, PageSize::Size2M)),
+                    _ => {}
+                }
+            }
+            table = self.next_table_mut(entry)?;
         }
-
-        let p1 = self.next_table_mut(p2e)?;
-        let p1e = &mut p1[p1_index(vaddr)];
-        Ok((p1e, PageSize::Size4K))
+        Ok((&mut table[table_index(vaddr, 1)], PageSize::Size4K))
     }
 
     fn get_entry_mut_or_create(
@@ -322,29 +403,65 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
         vaddr: VirtAddr,
         page_size: PageSize,
     ) -> PagingResult<&mut PTE> {
-        let p3 = if M::LEVELS == 3 {
-            self.table_of_mut(self.root_paddr())
-        } else if M::LEVELS == 4 {
-            let p4 = self.table_of_mut(self.root_paddr());
-            let p4e = &mut p4[p4_index(vaddr)];
-            self.next_table_mut_or_create(p4e)?
-        } else {
-            unreachable!()
+        let leaf = match page_size {
+            PageSize::Size4K => 1,
+            PageSize::Size2M => 2,
+            PageSize::Size1G => 3,
         };
-        let p3e = &mut p3[p3_index(vaddr)];
-        if page_size == PageSize::Size1G {
-            return Ok(p3e);
+        let mut table = self.table_of_mut(self.root_paddr());
+        for n in (leaf + 1..=M::LEVELS).rev() {
+            let entry = &mut table[table_index(vaddr, n)];
+            table = self.next_table_mut_or_create(entry)?;
         }
+        Ok(&mut table[table_index(vaddr, leaf)])
+    }
 
-        let p2 = self.next_table_mut_or_create(p3e)?;
-        let p2e = &mut p2[p2_index(vaddr)];
-        if page_size == PageSize::Size2M {
-            return Ok(p2e);
+    fn clone_recursive(
+        &mut self,
+        src: &[PTE],
+        dst_paddr: PhysAddr,
+        level: usize,
+        cow: bool,
+    ) -> PagingResult {
+        let dst = self.table_of_mut(dst_paddr);
+        for (i, entry) in src.iter().enumerate() {
+            if entry.is_unused() {
+                continue;
+            }
+            let mut new_entry = *entry;
+            if level < M::LEVELS - 1 && entry.is_present() && !entry.is_huge() {
+                let paddr = Self::alloc_table()?;
+                self.intrm_tables.push(paddr);
+                new_entry.set_paddr(paddr);
+                dst[i] = new_entry;
+                self.clone_recursive(self.table_of(entry.paddr()), paddr, level + 1, cow)?;
+            } else {
+                let flags = entry.flags();
+                if cow && entry.is_present() && flags.contains(MappingFlags::WRITE) {
+                    new_entry.set_flags(flags - MappingFlags::WRITE, level < M::LEVELS - 1);
+                }
+                dst[i] = new_entry;
+            }
         }
+        Ok(())
+    }
 
-        let p1 = self.next_table_mut_or_create(p2e)?;
-        let p1e = &mut p1[p1_index(vaddr)];
-        Ok(p1e)
+    fn for_each_leaf_mut<F>(&self, table: &mut [PTE], level: usize, func: &mut F) -> PagingResult
+    where
+        F: FnMut(&mut PTE, bool),
+    {
+        for entry in table.iter_mut() {
+            if entry.is_unused() {
+                continue;
+            }
+            if level < M::LEVELS - 1 && entry.is_present() && !entry.is_huge() {
+                let next = self.next_table_mut(entry)?;
+                self.for_each_leaf_mut(next, level + 1, func)?;
+            } else {
+                func(entry, level < M::LEVELS - 1);
+            }
+        }
+        Ok(())
     }
 
     fn walk_recursive<F>(
@@ -377,6 +494,92 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
     }
 }
 
+/// The accessed and dirty state of a mapped page, returned by
+/// [`PageTable64::harvest_accessed_dirty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageAccessInfo {
+    /// The virtual address of the page.
+    pub vaddr: VirtAddr,
+    /// The physical address of the frame mapped by the page.
+    pub paddr: PhysAddr,
+    /// The size of the page.
+    pub size: PageSize,
+    /// Whether the page has been accessed since the last harvest.
+    pub accessed: bool,
+    /// Whether the page has been written since the last harvest.
+    pub dirty: bool,
+}
+
+/// An iterator over the mapped leaf entries of a [`PageTable64`], which clears
+/// the accessed and dirty bits of each entry.
+///
+/// Created by [`PageTable64::harvest_accessed_dirty`].
+pub struct AccessedDirtyIter<'a, M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> {
+    pt: &'a mut PageTable64<M, PTE, IF>,
+    /// The table and the next entry index of each level being visited.
+    tables: [(PhysAddr, usize); MAX_LEVELS],
+    depth: usize,
+}
+
+impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> AccessedDirtyIter<'_, M, PTE, IF> {
+    /// Returns the virtual address mapped by the last visited entry.
+    ///
+    /// Same as [`PageTable64::walk`], the top bits above
+    /// [`PagingMetaData::VA_MAX_BITS`] are always zero.
+    fn current_vaddr(&self) -> VirtAddr {
+        let mut vaddr = 0;
+        for (level, &(_, next)) in self.tables[..self.depth].iter().enumerate() {
+            vaddr |= (next - 1) << (12 + (M::LEVELS - 1 - level) * 9);
+        }
+        VirtAddr::from(vaddr)
+    }
+}
+
+impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> Iterator
+    for AccessedDirtyIter<'_, M, PTE, IF>
+{
+    type Item = PageAccessInfo;
+
+    fn next(&mut self) -> Option<PageAccessInfo> {
+        while self.depth > 0 {
+            let level = self.depth - 1;
+            let (table_paddr, idx) = self.tables[level];
+            if idx >= ENTRY_COUNT {
+                self.depth -= 1;
+                continue;
+            }
+            self.tables[level].1 += 1;
+
+            let entry = &mut self.pt.table_of_mut(table_paddr)[idx];
+            if !entry.is_present() {
+                continue;
+            }
+            if level < M::LEVELS - 1 && !entry.is_huge() {
+                self.tables[self.depth] = (entry.paddr(), 0);
+                self.depth += 1;
+                continue;
+            }
+
+            let size = match M::LEVELS - 1 - level {
+                0 => PageSize::Size4K,
+                1 => PageSize::Size2M,
+                _ => PageSize::Size1G,
+            };
+            let info = PageAccessInfo {
+                vaddr: self.current_vaddr(),
+                paddr: entry.paddr(),
+                size,
+                accessed: entry.is_accessed(),
+                dirty: entry.is_dirty(),
+            };
+            entry.clear_accessed();
+            entry.clear_dirty();
+            return Some(info);
+        }
+        None
+    }
+}
+
 impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> Drop for PageTable64<M, PTE, IF> {
     fn drop(&mut self) {
         for frame in &self.intrm_tables {
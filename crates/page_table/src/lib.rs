@@ -13,11 +13,12 @@
 //!
 //! Currently supported architectures and page table structures:
 //!
-//! - x86: [`x86_64::X64PageTable`]
+//! - x86: [`x86_64::X64PageTable`], [`x86_64::X64La57PageTable`]
 //! - ARM: [`aarch64::A64PageTable`]
-//! - RISC-V: [`riscv::Sv39PageTable`], [`riscv::Sv48PageTable`]
+//! - RISC-V: [`riscv::Sv39PageTable`], [`riscv::Sv48PageTable`],
+//!   [`riscv::Sv57PageTable`]
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![feature(const_trait_impl)]
 #![feature(doc_auto_cfg)]
 
@@ -27,10 +28,13 @@ extern crate log;
 mod arch;
 mod bits64;
 
+#[cfg(test)]
+mod tests;
+
 use memory_addr::{PhysAddr, VirtAddr};
 
 pub use self::arch::*;
-pub use self::bits64::PageTable64;
+pub use self::bits64::{AccessedDirtyIter, PageAccessInfo, PageTable64};
 
 #[doc(no_inline)]
 pub use page_table_entry::{GenericPTE, MappingFlags};
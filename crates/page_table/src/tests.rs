@@ -0,0 +1,338 @@
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::cell::{Cell, RefCell};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::vec::Vec;
+
+use memory_addr::{PhysAddr, VirtAddr, PAGE_SIZE_4K};
+use page_table_entry::{aarch64::A64PTE, riscv::Rv64PTE};
+
+use crate::aarch64::A64PagingMetaData;
+use crate::riscv::{Sv39MetaData, Sv39PageTable, Sv48MetaData, Sv57MetaData, Sv57PageTable};
+use crate::{
+    GenericPTE, MappingFlags, PageSize, PageTable64, PagingError, PagingIf, PagingMetaData,
+};
+
+thread_local! {
+    static ALLOCATED_FRAMES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Serializes the tests that depend on the global hardware A/D update mode of
+/// the page table entries.
+static HW_ACCESS_DIRTY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Sets the hardware A/D update mode of the RISC-V and AArch64 entries, and
+/// restores the default (disabled) mode when dropped.
+struct HwAccessDirty {
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl HwAccessDirty {
+    fn set(enabled: bool) -> Self {
+        let guard = HW_ACCESS_DIRTY_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        Rv64PTE::set_hw_access_dirty(enabled);
+        A64PTE::set_hw_access_dirty(enabled, enabled);
+        Self { _guard: guard }
+    }
+}
+
+impl Drop for HwAccessDirty {
+    fn drop(&mut self) {
+        Rv64PTE::set_hw_access_dirty(false);
+        A64PTE::set_hw_access_dirty(false, false);
+    }
+}
+
+const FRAME_LAYOUT: Layout =
+    unsafe { Layout::from_size_align_unchecked(PAGE_SIZE_4K, PAGE_SIZE_4K) };
+
+/// Allocates frames from the host heap, physical addresses are identical to
+/// virtual addresses.
+struct HostPagingIf;
+
+impl PagingIf for HostPagingIf {
+    fn alloc_frame() -> Option<PhysAddr> {
+        ALLOCATED_FRAMES.with(|n| n.set(n.get() + 1));
+        let ptr = unsafe { alloc_zeroed(FRAME_LAYOUT) };
+        (!ptr.is_null()).then(|| PhysAddr::from(ptr as usize))
+    }
+
+    fn dealloc_frame(paddr: PhysAddr) {
+        ALLOCATED_FRAMES.with(|n| n.set(n.get() - 1));
+        unsafe { dealloc(paddr.as_usize() as *mut u8, FRAME_LAYOUT) }
+    }
+
+    fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
+        VirtAddr::from(paddr.as_usize())
+    }
+}
+
+const FLAGS: MappingFlags = MappingFlags::READ.union(MappingFlags::WRITE);
+
+/// Collects the leaf entries by walking the page table.
+fn leaf_entries<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf>(
+    pt: &PageTable64<M, PTE, IF>,
+) -> Vec<(usize, usize, PhysAddr)> {
+    let entries = RefCell::new(Vec::new());
+    pt.walk(usize::MAX, &|level, _, vaddr, entry| {
+        if level == M::LEVELS - 1 || entry.is_huge() {
+            entries
+                .borrow_mut()
+                .push((level, vaddr.as_usize(), entry.paddr()));
+        }
+    })
+    .unwrap();
+    entries.into_inner()
+}
+
+/// Returns the index of `vaddr` in the root page table.
+fn root_index<M: PagingMetaData>(vaddr: usize) -> usize {
+    (vaddr >> (12 + (M::LEVELS - 1) * 9)) & 511
+}
+
+fn test_map_region<M: PagingMetaData, PTE: GenericPTE>(vaddr: usize) {
+    const PADDR: usize = 0x8000_0000;
+    const SIZE: usize = 0x4000_0000 + 0x20_0000 + 0x3000; // 1G + 2M + 12K
+
+    let mut pt = PageTable64::<M, PTE, HostPagingIf>::try_new().unwrap();
+    let start = VirtAddr::from(vaddr);
+    pt.map_region(start, PADDR.into(), SIZE, FLAGS, true)
+        .unwrap();
+
+    let (paddr, flags, size) = pt.query(start + 0x1234).unwrap();
+    assert_eq!(paddr, PhysAddr::from(PADDR + 0x1234));
+    assert!(flags.contains(FLAGS));
+    assert_eq!(size, PageSize::Size1G);
+    let (paddr, _, size) = pt.query(start + 0x4010_0000).unwrap();
+    assert_eq!(paddr, PhysAddr::from(PADDR + 0x4010_0000));
+    assert_eq!(size, PageSize::Size2M);
+    let (paddr, _, size) = pt.query(start + 0x4020_2000).unwrap();
+    assert_eq!(paddr, PhysAddr::from(PADDR + 0x4020_2000));
+    assert_eq!(size, PageSize::Size4K);
+    assert!(pt.query(start + SIZE).is_err());
+
+    let entries = leaf_entries(&pt);
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[0], (M::LEVELS - 3, vaddr, PADDR.into()));
+    assert_eq!(
+        entries[1],
+        (
+            M::LEVELS - 2,
+            vaddr + 0x4000_0000,
+            (PADDR + 0x4000_0000).into()
+        )
+    );
+    for i in 0..3 {
+        let off = 0x4020_0000 + i * PAGE_SIZE_4K;
+        assert_eq!(
+            entries[2 + i],
+            (M::LEVELS - 1, vaddr + off, (PADDR + off).into())
+        );
+    }
+
+    let roots = RefCell::new(Vec::new());
+    pt.walk(usize::MAX, &|level, idx, _, _| {
+        if level == 0 {
+            roots.borrow_mut().push(idx);
+        }
+    })
+    .unwrap();
+    assert_eq!(roots.into_inner()[0], root_index::<M>(vaddr));
+
+    pt.unmap_region(start, SIZE).unwrap();
+    assert!(pt.query(start).is_err());
+    assert!(leaf_entries(&pt).is_empty());
+
+    drop(pt);
+    assert_eq!(ALLOCATED_FRAMES.with(|n| n.get()), 0);
+}
+
+#[test]
+fn test_sv39() {
+    test_map_region::<Sv39MetaData, Rv64PTE>(0x40_0000_0000);
+}
+
+#[test]
+fn test_sv48() {
+    // above the Sv39 address space
+    test_map_region::<Sv48MetaData, Rv64PTE>(0x7f80_0000_0000);
+}
+
+#[test]
+fn test_sv57() {
+    // above the Sv48 address space
+    test_map_region::<Sv57MetaData, Rv64PTE>(0xab_cd80_0000_0000);
+}
+
+#[test]
+fn test_aarch64() {
+    test_map_region::<A64PagingMetaData, A64PTE>(0x7f80_0000_0000);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_x86_64() {
+    use crate::x86_64::{X64La57PagingMetaData, X64PagingMetaData};
+    use page_table_entry::x86_64::X64PTE;
+    test_map_region::<X64PagingMetaData, X64PTE>(0x7f80_0000_0000);
+    // above the 4-level address space
+    test_map_region::<X64La57PagingMetaData, X64PTE>(0xab_cd80_0000_0000);
+}
+
+#[test]
+fn test_levels_are_distinct() {
+    // the same address in the 4-level page table (sign extension ignored)
+    // and the 5-level page table is placed in different root entries
+    let vaddr = 0x12_3480_0000_0000;
+    assert_eq!(root_index::<Sv48MetaData>(vaddr), 0x69);
+    assert_eq!(root_index::<Sv57MetaData>(vaddr), 0x12);
+
+    let mut pt = Sv57PageTable::<HostPagingIf>::try_new().unwrap();
+    pt.map(vaddr.into(), 0x1000.into(), PageSize::Size4K, FLAGS)
+        .unwrap();
+    assert!(pt.query((vaddr & ((1 << 48) - 1)).into()).is_err());
+    assert_eq!(
+        pt.query((vaddr + 0x10).into()).unwrap().0,
+        PhysAddr::from(0x1010)
+    );
+}
+
+fn test_bulk_ops<M: PagingMetaData, PTE: GenericPTE>(vaddr: usize) {
+    const PADDR: usize = 0x8000_0000;
+    let start = VirtAddr::from(vaddr);
+    let huge = start + 0x20_0000;
+
+    let mut pt = PageTable64::<M, PTE, HostPagingIf>::try_new().unwrap();
+    pt.map_region(start, PADDR.into(), 0x4000, FLAGS, false)
+        .unwrap();
+    pt.map(huge, PADDR.into(), PageSize::Size2M, FLAGS).unwrap();
+
+    // protect_region
+    pt.protect_region(start + 0x1000, 0x2000, MappingFlags::READ)
+        .unwrap();
+    assert!(pt.query(start).unwrap().1.contains(MappingFlags::WRITE));
+    assert!(!pt
+        .query(start + 0x1000)
+        .unwrap()
+        .1
+        .contains(MappingFlags::WRITE));
+    assert!(!pt
+        .query(start + 0x2000)
+        .unwrap()
+        .1
+        .contains(MappingFlags::WRITE));
+    assert!(pt
+        .query(start + 0x3000)
+        .unwrap()
+        .1
+        .contains(MappingFlags::WRITE));
+    assert!(matches!(
+        pt.protect_region(start + 0x3000, 0x2000, FLAGS),
+        Err(PagingError::NotMapped)
+    ));
+    assert!(matches!(
+        pt.protect_region(huge, 0x1000, MappingFlags::READ),
+        Err(PagingError::MappedToHugePage)
+    ));
+    pt.protect_region(start, 0x4000, FLAGS).unwrap();
+
+    // try_clone
+    let mut cloned = pt.try_clone().unwrap();
+    assert_eq!(leaf_entries(&cloned), leaf_entries(&pt));
+    assert_ne!(cloned.root_paddr(), pt.root_paddr());
+    cloned.unmap(start).unwrap();
+    assert!(cloned.query(start).is_err());
+    assert!(pt.query(start).is_ok());
+    drop(cloned);
+
+    // clone_cow
+    let cow = pt.clone_cow().unwrap();
+    assert_eq!(leaf_entries(&cow), leaf_entries(&pt));
+    for t in [&pt, &cow] {
+        for va in [start, start + 0x3000, huge] {
+            let (paddr, flags, _) = t.query(va).unwrap();
+            assert_eq!(paddr, pt.query(va).unwrap().0);
+            assert!(flags.contains(MappingFlags::READ));
+            assert!(!flags.contains(MappingFlags::WRITE));
+        }
+    }
+    drop(cow);
+
+    // harvest_accessed_dirty, the top bits are not sign extended
+    let va_mask = (1 << M::VA_MAX_BITS) - 1;
+    let pages = pt.harvest_accessed_dirty().collect::<Vec<_>>();
+    assert_eq!(pages.len(), 5);
+    for (i, page) in pages[..4].iter().enumerate() {
+        assert_eq!(page.vaddr.as_usize(), (vaddr + i * PAGE_SIZE_4K) & va_mask);
+        assert_eq!(page.paddr, PhysAddr::from(PADDR + i * PAGE_SIZE_4K));
+        assert_eq!(page.size, PageSize::Size4K);
+    }
+    assert_eq!(pages[4].vaddr.as_usize(), huge.as_usize() & va_mask);
+    assert_eq!(pages[4].size, PageSize::Size2M);
+    assert!(pt
+        .harvest_accessed_dirty()
+        .all(|page| !page.accessed && !page.dirty));
+
+    drop(pt);
+    assert_eq!(ALLOCATED_FRAMES.with(|n| n.get()), 0);
+}
+
+#[test]
+fn test_bulk_ops_riscv() {
+    let _hw = HwAccessDirty::set(true);
+    test_bulk_ops::<Sv39MetaData, Rv64PTE>(0x4000_0000);
+    // higher half
+    test_bulk_ops::<Sv39MetaData, Rv64PTE>(0xffff_ffc0_8000_0000);
+    test_bulk_ops::<Sv57MetaData, Rv64PTE>(0xff80_0000_0000_0000);
+}
+
+#[test]
+fn test_bulk_ops_aarch64() {
+    // the bits are never cleared unless the hardware sets them again
+    let hw = HwAccessDirty::set(false);
+    let mut pt = PageTable64::<A64PagingMetaData, A64PTE, HostPagingIf>::try_new().unwrap();
+    pt.map(0x4000_0000.into(), 0x1000.into(), PageSize::Size4K, FLAGS)
+        .unwrap();
+    for _ in 0..2 {
+        let page = pt.harvest_accessed_dirty().next().unwrap();
+        assert!(page.accessed && page.dirty);
+    }
+    drop(pt);
+    drop(hw);
+
+    let _hw = HwAccessDirty::set(true);
+    test_bulk_ops::<A64PagingMetaData, A64PTE>(0x4000_0000);
+    test_bulk_ops::<A64PagingMetaData, A64PTE>(0xffff_0000_8000_0000);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_bulk_ops_x86_64() {
+    use crate::x86_64::{X64La57PagingMetaData, X64PagingMetaData};
+    use page_table_entry::x86_64::X64PTE;
+    test_bulk_ops::<X64PagingMetaData, X64PTE>(0xffff_ff80_0000_0000);
+    test_bulk_ops::<X64La57PagingMetaData, X64PTE>(0x4000_0000);
+}
+
+#[test]
+fn test_accessed_dirty() {
+    let vaddr = VirtAddr::from(0x4000_0000);
+    let mut pt = Sv39PageTable::<HostPagingIf>::try_new().unwrap();
+    pt.map(vaddr, 0x1000.into(), PageSize::Size4K, FLAGS)
+        .unwrap();
+    // without Svadu, the A and D bits are never cleared
+    let hw = HwAccessDirty::set(false);
+    for _ in 0..2 {
+        let page = pt.harvest_accessed_dirty().next().unwrap();
+        assert!(page.accessed && page.dirty);
+    }
+    drop(hw);
+
+    let _hw = HwAccessDirty::set(true);
+    // the A and D bits are set on creation
+    let page = pt.harvest_accessed_dirty().next().unwrap();
+    assert!(page.accessed && page.dirty);
+    let page = pt.harvest_accessed_dirty().next().unwrap();
+    assert!(!page.accessed && !page.dirty);
+}
@@ -2,6 +2,7 @@
 
 use aarch64_cpu::registers::MAIR_EL1;
 use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
 use memory_addr::PhysAddr;
 
 use crate::{GenericPTE, MappingFlags};
@@ -34,6 +35,9 @@ bitflags::bitflags! {
         const AF =          1 << 10;
         /// The not global bit.
         const NG =          1 << 11;
+        /// The Dirty Bit Modifier. When set, the descriptor is writable and the
+        /// `AP_RO` bit indicates whether the page is clean.
+        const DBM =         1 <<  51;
         /// Indicates that 16 adjacent translation table entries point to contiguous memory regions.
         const CONTIGUOUS =  1 <<  52;
         /// The Privileged execute-never field.
@@ -117,7 +121,7 @@ impl From<DescriptorAttr> for MappingFlags {
         if attr.contains(DescriptorAttr::VALID) {
             flags |= Self::READ;
         }
-        if !attr.contains(DescriptorAttr::AP_RO) {
+        if !attr.contains(DescriptorAttr::AP_RO) || attr.contains(DescriptorAttr::DBM) {
             flags |= Self::WRITE;
         }
         if attr.contains(DescriptorAttr::AP_EL0) {
@@ -149,7 +153,9 @@ impl From<MappingFlags> for DescriptorAttr {
         if flags.contains(MappingFlags::READ) {
             attr |= Self::VALID;
         }
-        if !flags.contains(MappingFlags::WRITE) {
+        if flags.contains(MappingFlags::WRITE) {
+            attr |= Self::DBM;
+        } else {
             attr |= Self::AP_RO;
         }
         if flags.contains(MappingFlags::USER) {
@@ -172,10 +178,22 @@ impl From<MappingFlags> for DescriptorAttr {
 /// Note that the **AttrIndx\[2:0\]** (bit\[4:2\]) field is set to `0` for device
 /// memory, and `1` for normal memory. The system must configure the MAIR_ELx
 /// system register accordingly.
+///
+/// Writable pages are mapped with the **DBM** bit set, a page is clean if it is
+/// also marked read-only (**AP\[2\]**). The access flag and the dirty state
+/// can only be cleared if the hardware updates them on access (`FEAT_HAFDBS`
+/// enabled by TCR_ELx.HA and TCR_ELx.HD), which must be reported by
+/// [`A64PTE::set_hw_access_dirty`]. Otherwise, clearing them does nothing, and
+/// mapped pages are always accessed, and dirty if they are writable.
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct A64PTE(u64);
 
+/// Whether the hardware updates the access flag.
+static HW_ACCESS: AtomicBool = AtomicBool::new(false);
+/// Whether the hardware updates the dirty state.
+static HW_DIRTY: AtomicBool = AtomicBool::new(false);
+
 impl A64PTE {
     const PHYS_ADDR_MASK: u64 = 0x0000_ffff_ffff_f000; // bits 12..48
 
@@ -183,6 +201,13 @@ impl A64PTE {
     pub const fn empty() -> Self {
         Self(0)
     }
+
+    /// Reports whether the hardware updates the access flag (TCR_ELx.HA) and
+    /// the dirty state (TCR_ELx.HD) of the descriptors.
+    pub fn set_hw_access_dirty(access: bool, dirty: bool) {
+        HW_ACCESS.store(access, Ordering::Relaxed);
+        HW_DIRTY.store(access && dirty, Ordering::Relaxed);
+    }
 }
 
 impl GenericPTE for A64PTE {
@@ -226,6 +251,28 @@ impl GenericPTE for A64PTE {
     fn clear(&mut self) {
         self.0 = 0
     }
+
+    fn is_accessed(&self) -> bool {
+        DescriptorAttr::from_bits_truncate(self.0).contains(DescriptorAttr::AF)
+    }
+    fn is_dirty(&self) -> bool {
+        let attr = DescriptorAttr::from_bits_truncate(self.0);
+        attr.contains(DescriptorAttr::DBM) && !attr.contains(DescriptorAttr::AP_RO)
+    }
+    fn clear_accessed(&mut self) {
+        // there is no access flag fault handler to set it again
+        if HW_ACCESS.load(Ordering::Relaxed) {
+            self.0 &= !DescriptorAttr::AF.bits()
+        }
+    }
+    fn clear_dirty(&mut self) {
+        // there is no permission fault handler to make the page writable again
+        if HW_DIRTY.load(Ordering::Relaxed)
+            && DescriptorAttr::from_bits_truncate(self.0).contains(DescriptorAttr::DBM)
+        {
+            self.0 |= DescriptorAttr::AP_RO.bits()
+        }
+    }
 }
 
 impl fmt::Debug for A64PTE {
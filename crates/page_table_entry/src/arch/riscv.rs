@@ -1,6 +1,7 @@
 //! RISC-V page table entries.
 
 use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
 use memory_addr::PhysAddr;
 
 use crate::{GenericPTE, MappingFlags};
@@ -71,13 +72,28 @@ impl From<MappingFlags> for PTEFlags {
     }
 }
 
-/// Sv39 and Sv48 page table entry for RV64 systems.
+/// Sv39, Sv48 and Sv57 page table entry for RV64 systems.
+///
+/// The A and D bits are set when the entry is created. They can only be
+/// cleared if the hardware sets them again on access (the Svadu extension
+/// enabled by `menvcfg.ADUE`), which must be reported by
+/// [`Rv64PTE::set_hw_access_dirty`]. Otherwise, clearing them does nothing,
+/// since the resulting page faults are not handled.
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct Rv64PTE(u64);
 
+/// Whether the hardware updates the A and D bits.
+static HW_ACCESS_DIRTY: AtomicBool = AtomicBool::new(false);
+
 impl Rv64PTE {
     const PHYS_ADDR_MASK: u64 = (1 << 54) - (1 << 10); // bits 10..54
+
+    /// Reports whether the hardware updates the A and D bits of the entries
+    /// (Svadu).
+    pub fn set_hw_access_dirty(enabled: bool) {
+        HW_ACCESS_DIRTY.store(enabled, Ordering::Relaxed);
+    }
 }
 
 impl GenericPTE for Rv64PTE {
@@ -117,6 +133,24 @@ impl GenericPTE for Rv64PTE {
     fn clear(&mut self) {
         self.0 = 0
     }
+
+    fn is_accessed(&self) -> bool {
+        PTEFlags::from_bits_truncate(self.0 as usize).contains(PTEFlags::A)
+    }
+    fn is_dirty(&self) -> bool {
+        PTEFlags::from_bits_truncate(self.0 as usize).contains(PTEFlags::D)
+    }
+    fn clear_accessed(&mut self) {
+        // there is no page fault handler to set it again
+        if HW_ACCESS_DIRTY.load(Ordering::Relaxed) {
+            self.0 &= !(PTEFlags::A.bits() as u64)
+        }
+    }
+    fn clear_dirty(&mut self) {
+        if HW_ACCESS_DIRTY.load(Ordering::Relaxed) {
+            self.0 &= !(PTEFlags::D.bits() as u64)
+        }
+    }
 }
 
 impl fmt::Debug for Rv64PTE {
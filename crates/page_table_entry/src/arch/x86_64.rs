@@ -101,6 +101,19 @@ impl GenericPTE for X64PTE {
     fn clear(&mut self) {
         self.0 = 0
     }
+
+    fn is_accessed(&self) -> bool {
+        PTF::from_bits_truncate(self.0).contains(PTF::ACCESSED)
+    }
+    fn is_dirty(&self) -> bool {
+        PTF::from_bits_truncate(self.0).contains(PTF::DIRTY)
+    }
+    fn clear_accessed(&mut self) {
+        self.0 &= !PTF::ACCESSED.bits()
+    }
+    fn clear_dirty(&mut self) {
+        self.0 &= !PTF::DIRTY.bits()
+    }
 }
 
 impl fmt::Debug for X64PTE {
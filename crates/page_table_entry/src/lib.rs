@@ -69,4 +69,15 @@ pub trait GenericPTE: Debug + Clone + Copy + Sync + Send + Sized {
     fn is_huge(&self) -> bool;
     /// Set this entry to zero.
     fn clear(&mut self);
+
+    /// Returns whether the mapped page has been accessed since the last time
+    /// the accessed bit was cleared.
+    fn is_accessed(&self) -> bool;
+    /// Returns whether the mapped page has been written since the last time
+    /// the dirty bit was cleared.
+    fn is_dirty(&self) -> bool;
+    /// Clears the accessed bit of the entry.
+    fn clear_accessed(&mut self);
+    /// Clears the dirty bit of the entry.
+    fn clear_dirty(&mut self);
 }
@@ -0,0 +1,231 @@
+//! Dynamically allocated per-CPU data.
+//!
+//! A fixed-size region ([`DYNAMIC_AREA_SIZE`] bytes) is reserved in every
+//! per-CPU data area. [`PerCpu::new`] carves a slot at the same offset of the
+//! region on all CPUs, so the data on the current CPU can be reached with the
+//! same thread pointer register relative access as [`def_percpu`] variables.
+//!
+//! [`def_percpu`]: crate::def_percpu
+
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+
+use percpu_macros::def_percpu;
+
+/// To use `percpu::__priv::NoPreemptGuard::new()` in macro expansion.
+#[allow(unused_imports)]
+#[cfg(feature = "preempt")]
+use crate as percpu;
+
+/// Size in bytes of the region reserved for dynamic per-CPU data on each CPU.
+pub const DYNAMIC_AREA_SIZE: usize = 0x1000;
+
+/// Allocation granularity of the dynamic region.
+const UNIT_SIZE: usize = 8;
+const NUM_UNITS: usize = DYNAMIC_AREA_SIZE / UNIT_SIZE;
+/// Maximum alignment supported, the alignment of the region itself.
+const MAX_ALIGN: usize = 0x40;
+
+#[repr(C, align(64))]
+struct DynamicArea([u8; DYNAMIC_AREA_SIZE]);
+
+#[def_percpu]
+static DYNAMIC_AREA: DynamicArea = DynamicArea([0; DYNAMIC_AREA_SIZE]);
+
+/// Which units of the dynamic region are in use, shared by all CPUs.
+static ALLOCATOR: spin::Mutex<UnitBitmap> = spin::Mutex::new(UnitBitmap([0; NUM_UNITS / 64]));
+
+struct UnitBitmap([u64; NUM_UNITS / 64]);
+
+impl UnitBitmap {
+    fn is_free(&self, idx: usize) -> bool {
+        self.0[idx / 64] & (1 << (idx % 64)) == 0
+    }
+
+    fn set(&mut self, start: usize, count: usize, used: bool) {
+        for idx in start..start + count {
+            if used {
+                self.0[idx / 64] |= 1 << (idx % 64);
+            } else {
+                self.0[idx / 64] &= !(1 << (idx % 64));
+            }
+        }
+    }
+
+    /// First-fit allocation, returns the offset in bytes.
+    fn alloc(&mut self, size: usize, align: usize) -> Option<usize> {
+        let count = size.max(1).div_ceil(UNIT_SIZE);
+        let step = align.max(UNIT_SIZE) / UNIT_SIZE;
+        let mut start = 0;
+        while start + count <= NUM_UNITS {
+            match (start..start + count).find(|&idx| !self.is_free(idx)) {
+                Some(used) => start = (used + 1).next_multiple_of(step),
+                None => {
+                    self.set(start, count, true);
+                    return Some(start * UNIT_SIZE);
+                }
+            }
+        }
+        None
+    }
+
+    fn dealloc(&mut self, offset: usize, size: usize) {
+        self.set(offset / UNIT_SIZE, size.max(1).div_ceil(UNIT_SIZE), false);
+    }
+}
+
+/// Per-CPU data allocated at runtime.
+///
+/// Each CPU has its own copy of `T`, located at the same offset in every
+/// per-CPU data area. The copies are dropped and the space is given back
+/// when the `PerCpu` is dropped.
+///
+/// It must be created after [`init`](crate::init) is called.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+///
+/// percpu::init(4);
+/// percpu::set_local_thread_pointer(0);
+///
+/// let counter = percpu::PerCpu::new_with(|_| AtomicUsize::new(0));
+/// counter.this_cpu(|c| c.fetch_add(1, Ordering::Relaxed));
+///
+/// let mut sum = 0;
+/// counter.for_each_cpu(|_cpu_id, c| sum += c.load(Ordering::Relaxed));
+/// assert_eq!(sum, 1);
+/// ```
+pub struct PerCpu<T> {
+    /// Offset relative to the start of the dynamic region.
+    offset: usize,
+    _phantom: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for PerCpu<T> {}
+unsafe impl<T: Send + Sync> Sync for PerCpu<T> {}
+
+impl<T> PerCpu<T> {
+    /// Allocates a per-CPU slot and initializes the copy of every CPU with
+    /// `init`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dynamic region is exhausted or `T` is aligned to more
+    /// than 64 bytes.
+    pub fn new(init: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::new_with(|_| init.clone())
+    }
+
+    /// Allocates a per-CPU slot and initializes the copy of every CPU with
+    /// the return value of `f`, which receives the CPU ID.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dynamic region is exhausted or `T` is aligned to more
+    /// than 64 bytes.
+    pub fn new_with<F>(f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        Self::try_new_with(f).expect("dynamic per-CPU area exhausted")
+    }
+
+    /// Like [`PerCpu::new_with`], but returns `None` if the space cannot be
+    /// allocated.
+    pub fn try_new_with<F>(mut f: F) -> Option<Self>
+    where
+        F: FnMut(usize) -> T,
+    {
+        if align_of::<T>() > MAX_ALIGN {
+            return None;
+        }
+        let offset = ALLOCATOR.lock().alloc(size_of::<T>(), align_of::<T>())?;
+        let this = Self {
+            offset,
+            _phantom: PhantomData,
+        };
+        for cpu_id in 0..crate::imp::max_cpu_num() {
+            unsafe { this.remote_ptr(cpu_id).write(f(cpu_id)) };
+        }
+        Some(this)
+    }
+
+    /// Returns the offset of the data relative to the per-CPU data area base.
+    ///
+    /// For "sp-naive" use, it's the address of the data.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        DYNAMIC_AREA.offset() + self.offset
+    }
+
+    /// Returns the raw pointer of the data on the current CPU.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that preemption is disabled on the current CPU.
+    #[inline]
+    pub unsafe fn this_cpu_ptr(&self) -> *mut T {
+        (DYNAMIC_AREA.current_ptr() as usize + self.offset) as *mut T
+    }
+
+    /// Returns the raw pointer of the data on the given CPU.
+    ///
+    /// # Safety
+    ///
+    /// `cpu_id` must be less than the number of CPUs passed to
+    /// [`init`](crate::init). Caller must ensure that the data is not being
+    /// accessed by that CPU in a conflicting way.
+    #[inline]
+    pub unsafe fn remote_ptr(&self, cpu_id: usize) -> *mut T {
+        (crate::imp::area_base(cpu_id) + self.offset()) as *mut T
+    }
+
+    /// Manipulate the data on the current CPU in the given closure.
+    /// Preemption will be disabled during the call.
+    pub fn this_cpu<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        #[cfg(feature = "preempt")]
+        let _guard = crate::__priv::NoPreemptGuard::new();
+        f(unsafe { &*self.this_cpu_ptr() })
+    }
+
+    /// Visits the data of every CPU, e.g. to sum up per-CPU counters.
+    ///
+    /// The data can be modified by its owner CPU concurrently, so interior
+    /// mutability that is safe to share (e.g. atomics) is required.
+    pub fn for_each_cpu<F>(&self, mut f: F)
+    where
+        F: FnMut(usize, &T),
+        T: Sync,
+    {
+        for cpu_id in 0..crate::imp::max_cpu_num() {
+            f(cpu_id, unsafe { &*self.remote_ptr(cpu_id) });
+        }
+    }
+
+    /// Visits the data of every CPU mutably.
+    pub fn for_each_cpu_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &mut T),
+    {
+        for cpu_id in 0..crate::imp::max_cpu_num() {
+            f(cpu_id, unsafe { &mut *self.remote_ptr(cpu_id) });
+        }
+    }
+}
+
+impl<T> Drop for PerCpu<T> {
+    fn drop(&mut self) {
+        for cpu_id in 0..crate::imp::max_cpu_num() {
+            unsafe { self.remote_ptr(cpu_id).drop_in_place() };
+        }
+        ALLOCATOR.lock().dealloc(self.offset, size_of::<T>());
+    }
+}
@@ -1,8 +1,12 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 const fn align_up_64(val: usize) -> usize {
     const SIZE_64BIT: usize = 0x40;
     (val + SIZE_64BIT - 1) & !(SIZE_64BIT - 1)
 }
 
+static MAX_CPU_NUM: AtomicUsize = AtomicUsize::new(1);
+
 #[cfg(not(target_os = "none"))]
 static PERCPU_AREA_BASE: spin::once::Once<usize> = spin::once::Once::new();
 
@@ -47,6 +51,8 @@ pub fn init(max_cpu_num: usize) {
         PERCPU_AREA_BASE.call_once(|| unsafe { std::alloc::alloc(layout) as usize });
     }
 
+    MAX_CPU_NUM.store(max_cpu_num, Ordering::Release);
+
     let base = percpu_area_base(0);
     for i in 1..max_cpu_num {
         let secondary_base = percpu_area_base(i);
@@ -57,6 +63,16 @@ pub fn init(max_cpu_num: usize) {
     }
 }
 
+/// Returns the number of CPUs passed to [`init`].
+pub(crate) fn max_cpu_num() -> usize {
+    MAX_CPU_NUM.load(Ordering::Acquire)
+}
+
+/// Returns the base address of the per-CPU data area on the given CPU.
+pub(crate) fn area_base(cpu_id: usize) -> usize {
+    percpu_area_base(cpu_id)
+}
+
 /// Read the architecture-specific thread pointer register on the current CPU.
 pub fn get_local_thread_pointer() -> usize {
     let tp;
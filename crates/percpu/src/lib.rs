@@ -33,6 +33,19 @@
 //! println!("{}", CPU_ID.read_current()); // prints "1"
 //! ```
 //!
+//! Per-CPU data can also be allocated at runtime with [`PerCpu`], which is
+//! placed in a region of [`DYNAMIC_AREA_SIZE`] bytes reserved in each per-CPU
+//! data area, and accessed in the same way:
+//!
+//! ```no_run
+//! percpu::init(4);
+//! percpu::set_local_thread_pointer(0);
+//!
+//! let data = percpu::PerCpu::new(core::cell::Cell::new(0));
+//! data.this_cpu(|v| v.set(v.get() + 1));
+//! println!("{}", data.this_cpu(|v| v.get())); // prints "1"
+//! ```
+//!
 //! # Cargo Features
 //!
 //! - `sp-naive`: For **single-core** use. In this case, each per-CPU data is
@@ -50,6 +63,9 @@ extern crate percpu_macros;
 #[cfg_attr(feature = "sp-naive", path = "naive.rs")]
 mod imp;
 
+mod dynamic;
+
+pub use self::dynamic::{PerCpu, DYNAMIC_AREA_SIZE};
 pub use self::imp::*;
 pub use percpu_macros::def_percpu;
 
@@ -8,3 +8,13 @@ pub fn get_local_thread_pointer() -> usize {
 
 /// No effect for "sp-naive" use.
 pub fn set_local_thread_pointer(_cpu_id: usize) {}
+
+/// Always returns `1` for "sp-naive" use.
+pub(crate) fn max_cpu_num() -> usize {
+    1
+}
+
+/// Always returns `0` for "sp-naive" use, offsets are absolute addresses.
+pub(crate) fn area_base(_cpu_id: usize) -> usize {
+    0
+}
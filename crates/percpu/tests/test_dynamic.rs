@@ -0,0 +1,68 @@
+#![cfg(not(target_os = "macos"))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use percpu::*;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_dynamic() {
+    #[cfg(feature = "sp-naive")]
+    let (base, cpu_num) = (0, 1);
+
+    #[cfg(not(feature = "sp-naive"))]
+    let (base, cpu_num) = {
+        init(4);
+        set_local_thread_pointer(0);
+        (get_local_thread_pointer(), 4)
+    };
+
+    let counter = PerCpu::new_with(|_| AtomicUsize::new(10));
+    let mut pair = PerCpu::new((0u8, 0u64));
+    println!("counter offset: {:#x}", counter.offset());
+    println!("pair offset: {:#x}", pair.offset());
+    assert_ne!(counter.offset(), pair.offset());
+    assert_eq!(pair.offset() % 8, 0);
+
+    unsafe {
+        assert_eq!(base + counter.offset(), counter.this_cpu_ptr() as usize);
+        assert_eq!(counter.remote_ptr(0), counter.this_cpu_ptr());
+    }
+
+    counter.this_cpu(|c| c.fetch_add(5, Ordering::Relaxed));
+    assert_eq!(counter.this_cpu(|c| c.load(Ordering::Relaxed)), 15);
+
+    let mut values = Vec::new();
+    counter.for_each_cpu(|cpu_id, c| values.push((cpu_id, c.load(Ordering::Relaxed))));
+    let mut expected = vec![(0, 15)];
+    expected.extend((1..cpu_num).map(|cpu_id| (cpu_id, 10)));
+    assert_eq!(values, expected);
+
+    pair.for_each_cpu_mut(|cpu_id, p| *p = (cpu_id as u8, cpu_id as u64 * 100));
+    assert_eq!(pair.this_cpu(|p| *p), (0, 0));
+    if cpu_num > 1 {
+        assert_eq!(
+            unsafe { *pair.remote_ptr(cpu_num - 1) }.1,
+            (cpu_num as u64 - 1) * 100
+        );
+    }
+
+    // the space is reused after dropping
+    let offset = pair.offset();
+    drop(pair);
+    let reused = PerCpu::new(0u64);
+    assert_eq!(reused.offset(), offset);
+
+    // all copies are dropped
+    let rc = std::rc::Rc::new(());
+    let rcs = PerCpu::new(rc.clone());
+    assert_eq!(std::rc::Rc::strong_count(&rc), cpu_num + 1);
+    drop(rcs);
+    assert_eq!(std::rc::Rc::strong_count(&rc), 1);
+
+    // over-aligned types and exhaustion
+    #[repr(align(128))]
+    struct OverAligned;
+    assert!(PerCpu::try_new_with(|_| OverAligned).is_none());
+    assert!(PerCpu::try_new_with(|_| [0u8; DYNAMIC_AREA_SIZE]).is_none());
+}
@@ -0,0 +1,188 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) DMA memory management.
+//!
+//! All device drivers should use this module to access the memory shared
+//! with devices, instead of calling the global allocator directly. It provides:
+//!
+//! - Coherent allocations ([`alloc_coherent`]), which can be accessed by the
+//!   CPU and devices at the same time, for descriptor rings, etc.
+//! - Pools of small coherent buffers ([`DmaPool`]).
+//! - Streaming mappings ([`map_single`] and [`unmap_single`]) of existing
+//!   buffers, which transfer the ownership of the buffer between the CPU and
+//!   the device, and perform cache maintenance if required by the platform.
+//!
+//! Devices access the memory through bus addresses ([`BusAddr`]), which
+//! differ from physical addresses by [`axconfig::PHYS_BUS_OFFSET`].
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod pool;
+
+use core::{alloc::Layout, fmt, ptr::NonNull};
+
+use allocator::{AllocError, AllocResult};
+use axalloc::global_allocator;
+use axhal::mem::{phys_to_virt, virt_to_phys, PhysAddr, VirtAddr, PAGE_SIZE_4K};
+
+pub use self::pool::DmaPool;
+
+/// A bus memory address, used by devices to access the memory.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BusAddr(u64);
+
+impl BusAddr {
+    /// Converts a `u64` to a bus address.
+    pub const fn new(addr: u64) -> Self {
+        Self(addr)
+    }
+
+    /// Converts the bus address to a `u64`.
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for BusAddr {
+    fn from(addr: u64) -> Self {
+        Self(addr)
+    }
+}
+
+impl fmt::Debug for BusAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("BA:{:#x}", self.0))
+    }
+}
+
+impl fmt::LowerHex for BusAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+/// A DMA memory region, which is accessed by the CPU through `cpu_addr`, and
+/// by devices through `bus_addr`.
+#[derive(Debug, Clone, Copy)]
+pub struct DMAInfo {
+    /// The virtual address used by the CPU.
+    pub cpu_addr: NonNull<u8>,
+    /// The bus address used by devices.
+    pub bus_addr: BusAddr,
+}
+
+/// The direction of the data transfer of a streaming DMA mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaDirection {
+    /// The data is transferred from the memory to the device.
+    ToDevice,
+    /// The data is transferred from the device to the memory.
+    FromDevice,
+    /// The data may be transferred in both directions.
+    Bidirectional,
+}
+
+/// Converts a physical address to a bus address.
+#[inline]
+pub fn phys_to_bus(paddr: PhysAddr) -> BusAddr {
+    BusAddr::new((paddr.as_usize() + axconfig::PHYS_BUS_OFFSET) as u64)
+}
+
+/// Converts a bus address to a physical address.
+#[inline]
+pub fn bus_to_phys(bus_addr: BusAddr) -> PhysAddr {
+    PhysAddr::from(bus_addr.as_u64() as usize - axconfig::PHYS_BUS_OFFSET)
+}
+
+/// Converts a virtual address in the linear mapping to a bus address.
+#[inline]
+pub fn virt_to_bus(vaddr: VirtAddr) -> BusAddr {
+    phys_to_bus(virt_to_phys(vaddr))
+}
+
+/// Converts a bus address to a virtual address in the linear mapping.
+#[inline]
+pub fn bus_to_virt(bus_addr: BusAddr) -> VirtAddr {
+    phys_to_virt(bus_to_phys(bus_addr))
+}
+
+/// Allocates a coherent DMA memory region with the given `layout`.
+///
+/// The region is allocated in whole pages and is zeroed, so it never shares
+/// cache lines with other data.
+pub fn alloc_coherent(layout: Layout) -> AllocResult<DMAInfo> {
+    if layout.size() == 0 {
+        return Err(AllocError::InvalidParam);
+    }
+    let num_pages = layout.size().div_ceil(PAGE_SIZE_4K);
+    let align = layout.align().max(PAGE_SIZE_4K);
+    let vaddr = global_allocator().alloc_pages(num_pages, align)?;
+    unsafe { core::ptr::write_bytes(vaddr as *mut u8, 0, num_pages * PAGE_SIZE_4K) };
+    let dma = DMAInfo {
+        cpu_addr: unsafe { NonNull::new_unchecked(vaddr as *mut u8) },
+        bus_addr: virt_to_bus(vaddr.into()),
+    };
+    trace!("alloc_coherent({:?}) => {:?}", layout, dma);
+    Ok(dma)
+}
+
+/// Frees a coherent DMA memory region allocated by [`alloc_coherent`].
+///
+/// # Safety
+///
+/// `dma` must be returned by a previous call of [`alloc_coherent`] with the
+/// same `layout`, and must not be accessed by the CPU or devices after that.
+pub unsafe fn dealloc_coherent(dma: DMAInfo, layout: Layout) {
+    trace!("dealloc_coherent({:?}, {:?})", dma, layout);
+    let num_pages = layout.size().div_ceil(PAGE_SIZE_4K);
+    global_allocator().dealloc_pages(dma.cpu_addr.as_ptr() as usize, num_pages);
+}
+
+/// Maps an existing buffer for a streaming DMA transfer, and returns its bus
+/// address.
+///
+/// The ownership of the buffer is transferred to the device, the CPU must not
+/// access it until [`unmap_single`] is called.
+///
+/// # Safety
+///
+/// `buf` must be a valid buffer in the linear mapping.
+pub unsafe fn map_single(buf: NonNull<[u8]>, dir: DmaDirection) -> BusAddr {
+    let vaddr = VirtAddr::from(buf.as_ptr() as *mut u8 as usize);
+    #[cfg(target_arch = "aarch64")]
+    {
+        match dir {
+            DmaDirection::ToDevice | DmaDirection::Bidirectional => {
+                axhal::arch::clean_dcache_range(vaddr, buf.len())
+            }
+            DmaDirection::FromDevice => axhal::arch::flush_dcache_range(vaddr, buf.len()),
+        }
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    let _ = dir; // DMA is cache coherent
+    virt_to_bus(vaddr)
+}
+
+/// Unmaps a buffer mapped by [`map_single`], and returns its ownership to the
+/// CPU.
+///
+/// # Safety
+///
+/// `bus_addr`, `buf` and `dir` must be the same as the previous call of
+/// [`map_single`], and the device must have finished the transfer.
+pub unsafe fn unmap_single(bus_addr: BusAddr, buf: NonNull<[u8]>, dir: DmaDirection) {
+    let vaddr = VirtAddr::from(buf.as_ptr() as *mut u8 as usize);
+    debug_assert_eq!(virt_to_bus(vaddr), bus_addr);
+    #[cfg(target_arch = "aarch64")]
+    {
+        if dir != DmaDirection::ToDevice {
+            // discard the lines speculatively loaded during the transfer
+            axhal::arch::invalidate_dcache_range(vaddr, buf.len());
+        }
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    let _ = (vaddr, dir);
+}
@@ -0,0 +1,97 @@
+use alloc::vec::Vec;
+use core::alloc::Layout;
+
+use allocator::{AllocError, AllocResult};
+use axhal::mem::PAGE_SIZE_4K;
+use spinlock::SpinNoIrq;
+
+use crate::{alloc_coherent, dealloc_coherent, BusAddr, DMAInfo};
+
+const PAGE_LAYOUT: Layout =
+    unsafe { Layout::from_size_align_unchecked(PAGE_SIZE_4K, PAGE_SIZE_4K) };
+
+struct PoolInner {
+    /// The coherent pages owned by the pool.
+    pages: Vec<DMAInfo>,
+    /// The free blocks.
+    free: Vec<DMAInfo>,
+}
+
+/// A pool of small, fixed-size coherent DMA buffers.
+///
+/// Buffers are carved from coherent pages allocated on demand. The pages are
+/// only freed when the pool is dropped.
+pub struct DmaPool {
+    block_size: usize,
+    inner: SpinNoIrq<PoolInner>,
+}
+
+impl DmaPool {
+    /// Creates a new pool of buffers with `size` bytes, each aligned to
+    /// `align` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two, or the aligned size is larger
+    /// than a page.
+    pub const fn new(size: usize, align: usize) -> Self {
+        assert!(align.is_power_of_two());
+        let block_size = (size + align - 1) & !(align - 1);
+        assert!(block_size > 0 && block_size <= PAGE_SIZE_4K);
+        Self {
+            block_size,
+            inner: SpinNoIrq::new(PoolInner {
+                pages: Vec::new(),
+                free: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns the size of each buffer in the pool.
+    pub const fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Allocates a zeroed buffer from the pool.
+    pub fn alloc(&self) -> AllocResult<DMAInfo> {
+        let mut inner = self.inner.lock();
+        if inner.free.is_empty() {
+            let page = alloc_coherent(PAGE_LAYOUT)?;
+            inner.pages.push(page);
+            for off in (0..PAGE_SIZE_4K / self.block_size)
+                .rev()
+                .map(|i| i * self.block_size)
+            {
+                inner.free.push(DMAInfo {
+                    cpu_addr: unsafe { page.cpu_addr.add(off) },
+                    bus_addr: BusAddr::new(page.bus_addr.as_u64() + off as u64),
+                });
+            }
+        }
+        let block = inner.free.pop().ok_or(AllocError::NoMemory)?;
+        unsafe { core::ptr::write_bytes(block.cpu_addr.as_ptr(), 0, self.block_size) };
+        Ok(block)
+    }
+
+    /// Returns a buffer to the pool.
+    ///
+    /// # Safety
+    ///
+    /// `block` must be allocated from this pool by [`DmaPool::alloc`], and
+    /// must not be accessed by the CPU or devices after that.
+    pub unsafe fn dealloc(&self, block: DMAInfo) {
+        self.inner.lock().free.push(block);
+    }
+}
+
+impl Drop for DmaPool {
+    fn drop(&mut self) {
+        for page in self.inner.get_mut().pages.drain(..) {
+            unsafe { dealloc_coherent(page, PAGE_LAYOUT) };
+        }
+    }
+}
+
+// The buffers are only accessed through the raw pointers returned to the user.
+unsafe impl Send for DmaPool {}
+unsafe impl Sync for DmaPool {}
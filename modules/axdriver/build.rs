@@ -1,6 +1,9 @@
 const NET_DEV_FEATURES: &[&str] = &["ixgbe", "virtio-net"];
 const BLOCK_DEV_FEATURES: &[&str] = &["ramdisk", "bcm2835-sdhci", "virtio-blk"];
 const DISPLAY_DEV_FEATURES: &[&str] = &["virtio-gpu"];
+const NINEP_DEV_FEATURES: &[&str] = &["virtio-9p"];
+const CHAR_DEV_FEATURES: &[&str] = &["virtio-console"];
+const INPUT_DEV_FEATURES: &[&str] = &["virtio-input"];
 
 fn make_cfg_values(str_list: &[&str]) -> String {
     str_list
@@ -36,6 +39,9 @@ fn main() {
         ("net", NET_DEV_FEATURES),
         ("block", BLOCK_DEV_FEATURES),
         ("display", DISPLAY_DEV_FEATURES),
+        ("ninep", NINEP_DEV_FEATURES),
+        ("char", CHAR_DEV_FEATURES),
+        ("input", INPUT_DEV_FEATURES),
     ] {
         if !has_feature(dev_kind) {
             continue;
@@ -72,4 +78,16 @@ fn main() {
         "cargo::rustc-check-cfg=cfg(display_dev, values({}, \"dummy\"))",
         make_cfg_values(DISPLAY_DEV_FEATURES)
     );
+    println!(
+        "cargo::rustc-check-cfg=cfg(ninep_dev, values({}, \"dummy\"))",
+        make_cfg_values(NINEP_DEV_FEATURES)
+    );
+    println!(
+        "cargo::rustc-check-cfg=cfg(char_dev, values({}, \"dummy\"))",
+        make_cfg_values(CHAR_DEV_FEATURES)
+    );
+    println!(
+        "cargo::rustc-check-cfg=cfg(input_dev, values({}, \"dummy\"))",
+        make_cfg_values(INPUT_DEV_FEATURES)
+    );
 }
@@ -51,6 +51,24 @@ register_display_driver!(
     <virtio::VirtIoGpu as VirtIoDevMeta>::Device
 );
 
+#[cfg(ninep_dev = "virtio-9p")]
+register_ninep_driver!(
+    <virtio::VirtIo9p as VirtIoDevMeta>::Driver,
+    <virtio::VirtIo9p as VirtIoDevMeta>::Device
+);
+
+#[cfg(char_dev = "virtio-console")]
+register_char_driver!(
+    <virtio::VirtIoConsole as VirtIoDevMeta>::Driver,
+    <virtio::VirtIoConsole as VirtIoDevMeta>::Device
+);
+
+#[cfg(input_dev = "virtio-input")]
+register_input_driver!(
+    <virtio::VirtIoInput as VirtIoDevMeta>::Driver,
+    <virtio::VirtIoInput as VirtIoDevMeta>::Device
+);
+
 cfg_if::cfg_if! {
     if #[cfg(block_dev = "ramdisk")] {
         pub struct RamDiskDriver;
@@ -100,3 +100,94 @@ cfg_if! {
         }
     }
 }
+
+cfg_if! {
+    if #[cfg(ninep_dev = "dummy")] {
+        pub struct DummyNinePDev;
+        pub struct DummyNinePDriver;
+        register_ninep_driver!(DummyNinePDriver, DummyNinePDev);
+
+        impl BaseDriverOps for DummyNinePDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::NineP
+            }
+            fn device_name(&self) -> &str {
+                "dummy-9p"
+            }
+        }
+
+        impl NinePDriverOps for DummyNinePDev {
+            fn mount_tag(&self) -> &str {
+                ""
+            }
+            fn request(&mut self, _: &[u8], _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(char_dev = "dummy")] {
+        use driver_char::PollState;
+
+        pub struct DummyCharDev;
+        pub struct DummyCharDriver;
+        register_char_driver!(DummyCharDriver, DummyCharDev);
+
+        impl BaseDriverOps for DummyCharDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Char
+            }
+            fn device_name(&self) -> &str {
+                "dummy-char"
+            }
+        }
+
+        impl CharDriverOps for DummyCharDev {
+            fn read(&mut self, _: usize, _: &mut [u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+            fn write(&mut self, _: usize, _: &[u8]) -> DevResult<usize> {
+                Err(DevError::Unsupported)
+            }
+            fn poll(&mut self, _: usize) -> DevResult<PollState> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(input_dev = "dummy")] {
+        use driver_input::{Event, EventType, InputDeviceId};
+
+        pub struct DummyInputDev;
+        pub struct DummyInputDriver;
+        register_input_driver!(DummyInputDriver, DummyInputDev);
+
+        impl BaseDriverOps for DummyInputDev {
+            fn device_type(&self) -> DeviceType {
+                DeviceType::Input
+            }
+            fn device_name(&self) -> &str {
+                "dummy-input"
+            }
+        }
+
+        impl InputDriverOps for DummyInputDev {
+            fn device_id(&self) -> InputDeviceId {
+                InputDeviceId::default()
+            }
+            fn name(&self) -> &str {
+                ""
+            }
+            fn get_event_bits(&mut self, _: EventType, _: &mut [u8]) -> DevResult<bool> {
+                Err(DevError::Unsupported)
+            }
+            fn read_event(&mut self) -> DevResult<Event> {
+                Err(DevError::Unsupported)
+            }
+        }
+    }
+}
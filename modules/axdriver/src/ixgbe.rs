@@ -1,25 +1,45 @@
-use axalloc::global_allocator;
-use axhal::mem::{phys_to_virt, virt_to_phys};
+use axdma::{BusAddr, DMAInfo, DmaPool};
+use axhal::mem::phys_to_virt;
 use core::{alloc::Layout, ptr::NonNull};
 use driver_net::ixgbe::{IxgbeHal, PhysAddr as IxgbePhysAddr};
 
+/// Pools for sub-page allocations, so that small descriptor rings do not
+/// take a whole page each. Buffers are aligned to 128 bytes, as required by
+/// the descriptor rings.
+static DMA_POOLS: [DmaPool; 4] = [
+    DmaPool::new(256, 128),
+    DmaPool::new(512, 128),
+    DmaPool::new(1024, 128),
+    DmaPool::new(2048, 128),
+];
+
+fn dma_pool(size: usize) -> Option<&'static DmaPool> {
+    DMA_POOLS.iter().find(|pool| size <= pool.block_size())
+}
+
 pub struct IxgbeHalImpl;
 
 unsafe impl IxgbeHal for IxgbeHalImpl {
     fn dma_alloc(size: usize) -> (IxgbePhysAddr, NonNull<u8>) {
-        let layout = Layout::from_size_align(size, 8).unwrap();
-        let vaddr = if let Ok(vaddr) = global_allocator().alloc(layout) {
-            vaddr
-        } else {
-            return (0, NonNull::dangling());
+        let res = match dma_pool(size) {
+            Some(pool) => pool.alloc(),
+            None => axdma::alloc_coherent(Layout::from_size_align(size, 8).unwrap()),
         };
-        let paddr = virt_to_phys((vaddr.as_ptr() as usize).into());
-        (paddr.as_usize(), vaddr)
+        match res {
+            Ok(dma) => (dma.bus_addr.as_u64() as usize, dma.cpu_addr),
+            Err(_) => (0, NonNull::dangling()),
+        }
     }
 
-    unsafe fn dma_dealloc(_paddr: IxgbePhysAddr, vaddr: NonNull<u8>, size: usize) -> i32 {
-        let layout = Layout::from_size_align(size, 8).unwrap();
-        global_allocator().dealloc(vaddr, layout);
+    unsafe fn dma_dealloc(paddr: IxgbePhysAddr, vaddr: NonNull<u8>, size: usize) -> i32 {
+        let dma = DMAInfo {
+            cpu_addr: vaddr,
+            bus_addr: BusAddr::new(paddr as u64),
+        };
+        match dma_pool(size) {
+            Some(pool) => pool.dealloc(dma),
+            None => axdma::dealloc_coherent(dma, Layout::from_size_align(size, 8).unwrap()),
+        }
         0
     }
 
@@ -28,7 +48,8 @@ unsafe impl IxgbeHal for IxgbeHalImpl {
     }
 
     unsafe fn mmio_virt_to_phys(vaddr: NonNull<u8>, _size: usize) -> IxgbePhysAddr {
-        virt_to_phys((vaddr.as_ptr() as usize).into()).into()
+        // used to get the bus address of the packet buffers
+        axdma::virt_to_bus((vaddr.as_ptr() as usize).into()).as_u64() as usize
     }
 
     fn wait_until(duration: core::time::Duration) -> Result<(), &'static str> {
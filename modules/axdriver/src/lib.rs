@@ -8,8 +8,9 @@
 //! driver they want.
 //!
 //! For each device category (i.e., net, block, display, etc.), an unified type
-//! is used to represent all devices in that category. Currently, there are 3
-//! categories: [`AxNetDevice`], [`AxBlockDevice`], and [`AxDisplayDevice`].
+//! is used to represent all devices in that category. Currently, there are 6
+//! categories: [`AxNetDevice`], [`AxBlockDevice`], [`AxDisplayDevice`],
+//! [`AxNinePDevice`], [`AxCharDevice`], and [`AxInputDevice`].
 //!
 //! # Concepts
 //!
@@ -34,6 +35,9 @@
 //! | Block | `virtio-blk` | VirtIO block device |
 //! | Network | `virtio-net` | VirtIO network device |
 //! | Display | `virtio-gpu` | VirtIO graphics device |
+//! | 9P | `virtio-9p` | VirtIO 9P transport of a host shared directory |
+//! | Char | `virtio-console` | VirtIO console device, with multiple ports |
+//! | Input | `virtio-input` | VirtIO input device (keyboard, mouse or tablet) |
 //!
 //! # Other Cargo Features
 //!
@@ -42,12 +46,16 @@
 //!    enabeld by default.
 //! - `bus-pci`: use PCI bus to probe all PCI devices.
 //! - `virtio`: use VirtIO devices. This is enabled if any of `virtio-blk`,
-//!   `virtio-net` or `virtio-gpu` is enabled.
+//!   `virtio-net`, `virtio-gpu`, `virtio-9p`, `virtio-console` or
+//!   `virtio-input` is enabled.
 //! - `net`: use network devices. This is enabled if any feature of network
 //!    devices is selected. If this feature is enabled without any network device
 //!    features, a dummy struct is used for [`AxNetDevice`].
 //! - `block`: use block storage devices. Similar to the `net` feature.
 //! - `display`: use graphics display devices. Similar to the `net` feature.
+//! - `ninep`: use 9P transport devices. Similar to the `net` feature.
+//! - `char`: use character devices. Similar to the `net` feature.
+//! - `input`: use input devices. Similar to the `net` feature.
 //!
 //! [`VirtioNetDev`]: driver_virtio::VirtIoNetDev
 //! [`Box<dyn NetDriverOps>`]: driver_net::NetDriverOps
@@ -86,10 +94,16 @@ pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
 
 #[cfg(feature = "block")]
 pub use self::structs::AxBlockDevice;
+#[cfg(feature = "char")]
+pub use self::structs::AxCharDevice;
 #[cfg(feature = "display")]
 pub use self::structs::AxDisplayDevice;
+#[cfg(feature = "input")]
+pub use self::structs::AxInputDevice;
 #[cfg(feature = "net")]
 pub use self::structs::AxNetDevice;
+#[cfg(feature = "ninep")]
+pub use self::structs::AxNinePDevice;
 
 /// A structure that contains all device drivers, organized by their category.
 #[derive(Default)]
@@ -103,6 +117,15 @@ pub struct AllDevices {
     /// All graphics device drivers.
     #[cfg(feature = "display")]
     pub display: AxDeviceContainer<AxDisplayDevice>,
+    /// All 9P transport device drivers.
+    #[cfg(feature = "ninep")]
+    pub ninep: AxDeviceContainer<AxNinePDevice>,
+    /// All character device drivers.
+    #[cfg(feature = "char")]
+    pub char: AxDeviceContainer<AxCharDevice>,
+    /// All input device drivers.
+    #[cfg(feature = "input")]
+    pub input: AxDeviceContainer<AxInputDevice>,
 }
 
 impl AllDevices {
@@ -143,6 +166,12 @@ impl AllDevices {
             AxDeviceEnum::Block(dev) => self.block.push(dev),
             #[cfg(feature = "display")]
             AxDeviceEnum::Display(dev) => self.display.push(dev),
+            #[cfg(feature = "ninep")]
+            AxDeviceEnum::NineP(dev) => self.ninep.push(dev),
+            #[cfg(feature = "char")]
+            AxDeviceEnum::Char(dev) => self.char.push(dev),
+            #[cfg(feature = "input")]
+            AxDeviceEnum::Input(dev) => self.input.push(dev),
         }
     }
 }
@@ -179,6 +208,30 @@ pub fn init_drivers() -> AllDevices {
             debug!("  graphics device {}: {:?}", i, dev.device_name());
         }
     }
+    #[cfg(feature = "ninep")]
+    {
+        debug!("number of 9P devices: {}", all_devs.ninep.len());
+        for (i, dev) in all_devs.ninep.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::NineP);
+            debug!("  9P device {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "char")]
+    {
+        debug!("number of character devices: {}", all_devs.char.len());
+        for (i, dev) in all_devs.char.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Char);
+            debug!("  character device {}: {:?}", i, dev.device_name());
+        }
+    }
+    #[cfg(feature = "input")]
+    {
+        debug!("number of input devices: {}", all_devs.input.len());
+        for (i, dev) in all_devs.input.iter().enumerate() {
+            assert_eq!(dev.device_type(), DeviceType::Input);
+            debug!("  input device {}: {:?}", i, dev.device_name());
+        }
+    }
 
     all_devs
 }
@@ -4,7 +4,16 @@ pub use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
 
 #[cfg(feature = "block")]
 pub use {crate::structs::AxBlockDevice, driver_block::BlockDriverOps};
+#[cfg(feature = "char")]
+pub use {crate::structs::AxCharDevice, driver_char::CharDriverOps};
 #[cfg(feature = "display")]
 pub use {crate::structs::AxDisplayDevice, driver_display::DisplayDriverOps};
+#[cfg(feature = "input")]
+pub use {crate::structs::AxInputDevice, driver_input::InputDriverOps};
 #[cfg(feature = "net")]
 pub use {crate::structs::AxNetDevice, driver_net::NetDriverOps};
+#[cfg(feature = "ninep")]
+pub use {
+    crate::structs::AxNinePDevice,
+    driver_9p::{NinePDriverOps, WaitHooks},
+};
@@ -12,6 +12,15 @@ pub type AxBlockDevice = Box<dyn BlockDriverOps>;
 /// The unified type of the graphics display devices.
 #[cfg(feature = "display")]
 pub type AxDisplayDevice = Box<dyn DisplayDriverOps>;
+/// The unified type of the 9P transport devices.
+#[cfg(feature = "ninep")]
+pub type AxNinePDevice = Box<dyn NinePDriverOps>;
+/// The unified type of the character devices.
+#[cfg(feature = "char")]
+pub type AxCharDevice = Box<dyn CharDriverOps>;
+/// The unified type of the input devices.
+#[cfg(feature = "input")]
+pub type AxInputDevice = Box<dyn InputDriverOps>;
 
 impl super::AxDeviceEnum {
     /// Constructs a network device.
@@ -31,6 +40,24 @@ impl super::AxDeviceEnum {
     pub fn from_display(dev: impl DisplayDriverOps + 'static) -> Self {
         Self::Display(Box::new(dev))
     }
+
+    /// Constructs a 9P transport device.
+    #[cfg(feature = "ninep")]
+    pub fn from_ninep(dev: impl NinePDriverOps + 'static) -> Self {
+        Self::NineP(Box::new(dev))
+    }
+
+    /// Constructs a character device.
+    #[cfg(feature = "char")]
+    pub fn from_char(dev: impl CharDriverOps + 'static) -> Self {
+        Self::Char(Box::new(dev))
+    }
+
+    /// Constructs an input device.
+    #[cfg(feature = "input")]
+    pub fn from_input(dev: impl InputDriverOps + 'static) -> Self {
+        Self::Input(Box::new(dev))
+    }
 }
 
 /// A structure that contains all device drivers of a certain category.
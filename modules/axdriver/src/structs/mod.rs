@@ -18,6 +18,15 @@ pub enum AxDeviceEnum {
     /// Graphic display device.
     #[cfg(feature = "display")]
     Display(AxDisplayDevice),
+    /// 9P transport device.
+    #[cfg(feature = "ninep")]
+    NineP(AxNinePDevice),
+    /// Character device.
+    #[cfg(feature = "char")]
+    Char(AxCharDevice),
+    /// Input device.
+    #[cfg(feature = "input")]
+    Input(AxInputDevice),
 }
 
 impl BaseDriverOps for AxDeviceEnum {
@@ -31,6 +40,12 @@ impl BaseDriverOps for AxDeviceEnum {
             Self::Block(_) => DeviceType::Block,
             #[cfg(feature = "display")]
             Self::Display(_) => DeviceType::Display,
+            #[cfg(feature = "ninep")]
+            Self::NineP(_) => DeviceType::NineP,
+            #[cfg(feature = "char")]
+            Self::Char(_) => DeviceType::Char,
+            #[cfg(feature = "input")]
+            Self::Input(_) => DeviceType::Input,
             _ => unreachable!(),
         }
     }
@@ -45,6 +60,12 @@ impl BaseDriverOps for AxDeviceEnum {
             Self::Block(dev) => dev.device_name(),
             #[cfg(feature = "display")]
             Self::Display(dev) => dev.device_name(),
+            #[cfg(feature = "ninep")]
+            Self::NineP(dev) => dev.device_name(),
+            #[cfg(feature = "char")]
+            Self::Char(dev) => dev.device_name(),
+            #[cfg(feature = "input")]
+            Self::Input(dev) => dev.device_name(),
             _ => unreachable!(),
         }
     }
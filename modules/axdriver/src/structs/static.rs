@@ -1,9 +1,15 @@
 #[cfg(feature = "block")]
 pub use crate::drivers::AxBlockDevice;
+#[cfg(feature = "char")]
+pub use crate::drivers::AxCharDevice;
 #[cfg(feature = "display")]
 pub use crate::drivers::AxDisplayDevice;
+#[cfg(feature = "input")]
+pub use crate::drivers::AxInputDevice;
 #[cfg(feature = "net")]
 pub use crate::drivers::AxNetDevice;
+#[cfg(feature = "ninep")]
+pub use crate::drivers::AxNinePDevice;
 
 impl super::AxDeviceEnum {
     /// Constructs a network device.
@@ -23,6 +29,24 @@ impl super::AxDeviceEnum {
     pub const fn from_display(dev: AxDisplayDevice) -> Self {
         Self::Display(dev)
     }
+
+    /// Constructs a 9P transport device.
+    #[cfg(feature = "ninep")]
+    pub const fn from_ninep(dev: AxNinePDevice) -> Self {
+        Self::NineP(dev)
+    }
+
+    /// Constructs a character device.
+    #[cfg(feature = "char")]
+    pub const fn from_char(dev: AxCharDevice) -> Self {
+        Self::Char(dev)
+    }
+
+    /// Constructs an input device.
+    #[cfg(feature = "input")]
+    pub const fn from_input(dev: AxInputDevice) -> Self {
+        Self::Input(dev)
+    }
 }
 
 /// A structure that contains all device drivers of a certain category.
@@ -1,8 +1,9 @@
+use core::alloc::Layout;
 use core::marker::PhantomData;
 use core::ptr::NonNull;
 
-use axalloc::global_allocator;
-use axhal::mem::{phys_to_virt, virt_to_phys};
+use axdma::{BusAddr, DMAInfo, DmaDirection};
+use axhal::mem::phys_to_virt;
 use cfg_if::cfg_if;
 use driver_common::{BaseDriverOps, DevResult, DeviceType};
 use driver_virtio::{BufferDirection, PhysAddr, VirtIoHal};
@@ -73,6 +74,51 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(ninep_dev = "virtio-9p")] {
+        pub struct VirtIo9p;
+
+        impl VirtIoDevMeta for VirtIo9p {
+            const DEVICE_TYPE: DeviceType = DeviceType::NineP;
+            type Device = driver_virtio::VirtIo9pDev<VirtIoHalImpl, VirtIoTransport>;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_ninep(Self::Device::try_new(transport)?))
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(char_dev = "virtio-console")] {
+        pub struct VirtIoConsole;
+
+        impl VirtIoDevMeta for VirtIoConsole {
+            const DEVICE_TYPE: DeviceType = DeviceType::Char;
+            type Device = driver_virtio::VirtIoConsoleDev<VirtIoHalImpl, VirtIoTransport>;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_char(Self::Device::try_new(transport)?))
+            }
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(input_dev = "virtio-input")] {
+        pub struct VirtIoInput;
+
+        impl VirtIoDevMeta for VirtIoInput {
+            const DEVICE_TYPE: DeviceType = DeviceType::Input;
+            type Device = driver_virtio::VirtIoInputDev<VirtIoHalImpl, VirtIoTransport>;
+
+            fn try_new(transport: VirtIoTransport) -> DevResult<AxDeviceEnum> {
+                Ok(AxDeviceEnum::from_input(Self::Device::try_new(transport)?))
+            }
+        }
+    }
+}
+
 /// A common driver for all VirtIO devices that implements [`DriverProbe`].
 pub struct VirtIoDriver<D: VirtIoDevMeta + ?Sized>(PhantomData<D>);
 
@@ -114,6 +160,9 @@ impl<D: VirtIoDevMeta> DriverProbe for VirtIoDriver<D> {
             (DeviceType::Net, 0x1000) | (DeviceType::Net, 0x1040) => {}
             (DeviceType::Block, 0x1001) | (DeviceType::Block, 0x1041) => {}
             (DeviceType::Display, 0x1050) => {}
+            (DeviceType::NineP, 0x1009) | (DeviceType::NineP, 0x1049) => {}
+            (DeviceType::Char, 0x1003) | (DeviceType::Char, 0x1043) => {}
+            (DeviceType::Input, 0x1052) => {}
             _ => return None,
         }
 
@@ -141,18 +190,20 @@ pub struct VirtIoHalImpl;
 
 unsafe impl VirtIoHal for VirtIoHalImpl {
     fn dma_alloc(pages: usize, _direction: BufferDirection) -> (PhysAddr, NonNull<u8>) {
-        let vaddr = if let Ok(vaddr) = global_allocator().alloc_pages(pages, 0x1000) {
-            vaddr
-        } else {
-            return (0, NonNull::dangling());
-        };
-        let paddr = virt_to_phys(vaddr.into());
-        let ptr = NonNull::new(vaddr as _).unwrap();
-        (paddr.as_usize(), ptr)
+        let layout = Layout::from_size_align(pages * 0x1000, 0x1000).unwrap();
+        match axdma::alloc_coherent(layout) {
+            Ok(dma) => (dma.bus_addr.as_u64() as usize, dma.cpu_addr),
+            Err(_) => (0, NonNull::dangling()),
+        }
     }
 
-    unsafe fn dma_dealloc(_paddr: PhysAddr, vaddr: NonNull<u8>, pages: usize) -> i32 {
-        global_allocator().dealloc_pages(vaddr.as_ptr() as usize, pages);
+    unsafe fn dma_dealloc(paddr: PhysAddr, vaddr: NonNull<u8>, pages: usize) -> i32 {
+        let layout = Layout::from_size_align(pages * 0x1000, 0x1000).unwrap();
+        let dma = DMAInfo {
+            cpu_addr: vaddr,
+            bus_addr: BusAddr::new(paddr as u64),
+        };
+        axdma::dealloc_coherent(dma, layout);
         0
     }
 
@@ -162,11 +213,20 @@ unsafe impl VirtIoHal for VirtIoHalImpl {
     }
 
     #[inline]
-    unsafe fn share(buffer: NonNull<[u8]>, _direction: BufferDirection) -> PhysAddr {
-        let vaddr = buffer.as_ptr() as *mut u8 as usize;
-        virt_to_phys(vaddr.into()).into()
+    unsafe fn share(buffer: NonNull<[u8]>, direction: BufferDirection) -> PhysAddr {
+        axdma::map_single(buffer, dma_direction(direction)).as_u64() as usize
     }
 
     #[inline]
-    unsafe fn unshare(_paddr: PhysAddr, _buffer: NonNull<[u8]>, _direction: BufferDirection) {}
+    unsafe fn unshare(paddr: PhysAddr, buffer: NonNull<[u8]>, direction: BufferDirection) {
+        axdma::unmap_single(BusAddr::new(paddr as u64), buffer, dma_direction(direction))
+    }
+}
+
+const fn dma_direction(direction: BufferDirection) -> DmaDirection {
+    match direction {
+        BufferDirection::DriverToDevice => DmaDirection::ToDevice,
+        BufferDirection::DeviceToDriver => DmaDirection::FromDevice,
+        BufferDirection::Both => DmaDirection::Bidirectional,
+    }
 }
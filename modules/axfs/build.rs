@@ -0,0 +1,22 @@
+use std::path::Path;
+
+/// Embeds the initramfs archive at the path in the `AX_INITRAMFS` environment
+/// variable, or an empty one if it is not set.
+fn main() {
+    println!("cargo:rerun-if-env-changed=AX_INITRAMFS");
+    if std::env::var("CARGO_FEATURE_INITRAMFS").is_err() {
+        return;
+    }
+
+    let archive = match std::env::var("AX_INITRAMFS") {
+        Ok(path) if !path.is_empty() => {
+            let path = std::fs::canonicalize(&path)
+                .unwrap_or_else(|e| panic!("failed to open initramfs {:?}: {}", path, e));
+            println!("cargo:rerun-if-changed={}", path.display());
+            format!("include_bytes!({:?})", path)
+        }
+        _ => String::from("&[]"),
+    };
+    let out_path = Path::new(&std::env::var("OUT_DIR").unwrap()).join("initramfs.rs");
+    std::fs::write(out_path, archive).unwrap();
+}
@@ -0,0 +1,284 @@
+//! Asynchronous I/O with submission and completion queues.
+//!
+//! Requests are submitted to an [`IoRing`] without waiting for them, and their
+//! results are collected from the ring later, in the order they complete. With
+//! the `multitask` feature, requests are served by the worker tasks of the
+//! ring, so a single task can keep many disk requests in flight. Otherwise they
+//! are performed when submitted, and complete immediately.
+//!
+//! Buffers are owned by the requests during the I/O, and handed back with the
+//! completions.
+
+use alloc::{collections::VecDeque, sync::Arc, vec, vec::Vec};
+use axerrno::AxResult;
+use axsync::spin::SpinNoIrq;
+use core::time::Duration;
+
+/// A file that asynchronous requests can be performed on.
+pub trait AioFile: Send + Sync {
+    /// Reads the file at the given position. Returns the number of bytes read.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize>;
+    /// Writes the file at the given position. Returns the number of bytes
+    /// written.
+    fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize>;
+    /// Writes all buffered data of the file to the underlying device.
+    fn flush(&self) -> AxResult;
+}
+
+impl AioFile for crate::fops::File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+        self.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
+        self.write_at(offset, buf)
+    }
+
+    fn flush(&self) -> AxResult {
+        self.flush()
+    }
+}
+
+/// The operation of an asynchronous request.
+pub enum IoOp {
+    /// Reads at most `len` bytes at `offset`.
+    Read { offset: u64, len: usize },
+    /// Writes `data` at `offset`.
+    Write { offset: u64, data: Vec<u8> },
+    /// Flushes the file.
+    Fsync,
+}
+
+/// An asynchronous request.
+pub struct IoRequest {
+    /// The file to perform the operation on.
+    pub file: Arc<dyn AioFile>,
+    /// The operation.
+    pub op: IoOp,
+    /// A value chosen by the submitter to identify the request, which is
+    /// copied to its completion.
+    pub user_data: u64,
+}
+
+/// The result of a completed request.
+pub struct IoCompletion {
+    /// The `user_data` of the request.
+    pub user_data: u64,
+    /// The number of bytes read or written, or the error.
+    pub result: AxResult<usize>,
+    /// The data read for [`IoOp::Read`], truncated to the bytes read, or the
+    /// data of [`IoOp::Write`]. It is empty for [`IoOp::Fsync`].
+    pub buf: Vec<u8>,
+}
+
+impl IoRequest {
+    /// Performs the request synchronously.
+    fn perform(self) -> IoCompletion {
+        let (result, buf) = match self.op {
+            IoOp::Read { offset, len } => {
+                let mut buf = vec![0; len];
+                let result = self.file.read_at(offset, &mut buf);
+                buf.truncate(*result.as_ref().unwrap_or(&0));
+                (result, buf)
+            }
+            IoOp::Write { offset, data } => (self.file.write_at(offset, &data), data),
+            IoOp::Fsync => (self.file.flush().map(|_| 0), Vec::new()),
+        };
+        IoCompletion {
+            user_data: self.user_data,
+            result,
+            buf,
+        }
+    }
+}
+
+struct Queues {
+    submissions: VecDeque<IoRequest>,
+    completions: VecDeque<IoCompletion>,
+    /// The number of requests submitted but not completed yet.
+    in_flight: usize,
+    /// The number of requests completed or cancelled, increased on each of
+    /// them to wake up the waiters.
+    events: u64,
+    /// Whether the ring is dropped, the workers exit after serving the
+    /// remaining requests.
+    #[cfg(feature = "multitask")]
+    closed: bool,
+}
+
+struct Shared {
+    queues: SpinNoIrq<Queues>,
+    #[cfg(feature = "multitask")]
+    submitted: axtask::WaitQueue,
+    #[cfg(feature = "multitask")]
+    completed: axtask::WaitQueue,
+}
+
+impl Shared {
+    fn complete(&self, completion: IoCompletion) {
+        let mut queues = self.queues.lock();
+        queues.in_flight -= 1;
+        queues.events += 1;
+        queues.completions.push_back(completion);
+        drop(queues);
+        #[cfg(feature = "multitask")]
+        self.completed.notify_all(false);
+    }
+}
+
+/// A pair of submission and completion queues of asynchronous requests.
+pub struct IoRing {
+    shared: Arc<Shared>,
+}
+
+impl IoRing {
+    /// Creates a ring whose requests are served by `workers` tasks, at most
+    /// `workers` requests are performed at the same time.
+    ///
+    /// `workers` is ignored without the `multitask` feature.
+    pub fn new(workers: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queues: SpinNoIrq::new(Queues {
+                submissions: VecDeque::new(),
+                completions: VecDeque::new(),
+                in_flight: 0,
+                events: 0,
+                #[cfg(feature = "multitask")]
+                closed: false,
+            }),
+            #[cfg(feature = "multitask")]
+            submitted: axtask::WaitQueue::new(),
+            #[cfg(feature = "multitask")]
+            completed: axtask::WaitQueue::new(),
+        });
+        #[cfg(feature = "multitask")]
+        for _ in 0..workers.max(1) {
+            let shared = shared.clone();
+            axtask::spawn(move || worker(&shared));
+        }
+        #[cfg(not(feature = "multitask"))]
+        let _ = workers;
+        Self { shared }
+    }
+
+    /// Submits a request, which is performed later.
+    pub fn submit(&self, req: IoRequest) {
+        let mut queues = self.shared.queues.lock();
+        queues.in_flight += 1;
+        #[cfg(feature = "multitask")]
+        {
+            queues.submissions.push_back(req);
+            drop(queues);
+            self.shared.submitted.notify_one(false);
+        }
+        #[cfg(not(feature = "multitask"))]
+        {
+            drop(queues);
+            self.shared.complete(req.perform());
+        }
+    }
+
+    /// Cancels the submitted request identified by `user_data`, if it has not
+    /// been started. Returns whether it is cancelled.
+    ///
+    /// A cancelled request has no completion.
+    pub fn cancel(&self, user_data: u64) -> bool {
+        let mut queues = self.shared.queues.lock();
+        match queues
+            .submissions
+            .iter()
+            .position(|req| req.user_data == user_data)
+        {
+            Some(idx) => {
+                queues.submissions.remove(idx);
+                queues.in_flight -= 1;
+                queues.events += 1;
+                drop(queues);
+                #[cfg(feature = "multitask")]
+                self.shared.completed.notify_all(false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of requests submitted but not completed yet.
+    pub fn in_flight(&self) -> usize {
+        self.shared.queues.lock().in_flight
+    }
+
+    /// Takes a completion from the completion queue, without waiting.
+    pub fn try_complete(&self) -> Option<IoCompletion> {
+        self.shared.queues.lock().completions.pop_front()
+    }
+
+    /// Takes a completion from the completion queue, waits for one if there
+    /// are requests in flight.
+    ///
+    /// Returns `None` if there is no completion and no request in flight.
+    pub fn wait_complete(&self) -> Option<IoCompletion> {
+        #[cfg(feature = "multitask")]
+        self.shared.completed.wait_until(|| {
+            let queues = self.shared.queues.lock();
+            !queues.completions.is_empty() || queues.in_flight == 0
+        });
+        self.try_complete()
+    }
+
+    /// Returns a counter which is increased whenever a request completes or
+    /// is cancelled, to be passed to [`wait_event`](Self::wait_event).
+    pub fn events(&self) -> u64 {
+        self.shared.queues.lock().events
+    }
+
+    /// Waits until a request completes or is cancelled after
+    /// [`events`](Self::events) returned `seen`, or `timeout` elapses.
+    ///
+    /// It is useful when the completions are taken by other tasks. Returns
+    /// whether a request has completed or been cancelled.
+    pub fn wait_event(&self, seen: u64, timeout: Option<Duration>) -> bool {
+        let changed = || self.shared.queues.lock().events != seen;
+        #[cfg(feature = "multitask")]
+        match timeout {
+            #[cfg(feature = "irq")]
+            Some(dur) => return !self.shared.completed.wait_timeout_until(dur, changed),
+            // the deadline can only be polled without timers
+            #[cfg(not(feature = "irq"))]
+            Some(_) => axtask::yield_now(),
+            None => self.shared.completed.wait_until(changed),
+        }
+        // requests complete when submitted without the `multitask` feature
+        #[cfg(not(feature = "multitask"))]
+        let _ = timeout;
+        changed()
+    }
+}
+
+#[cfg(feature = "multitask")]
+impl Drop for IoRing {
+    fn drop(&mut self) {
+        self.shared.queues.lock().closed = true;
+        self.shared.submitted.notify_all(false);
+    }
+}
+
+/// The main loop of a worker task, which performs the submitted requests one
+/// by one.
+#[cfg(feature = "multitask")]
+fn worker(shared: &Shared) {
+    loop {
+        shared.submitted.wait_until(|| {
+            let queues = shared.queues.lock();
+            !queues.submissions.is_empty() || queues.closed
+        });
+        let mut queues = shared.queues.lock();
+        match queues.submissions.pop_front() {
+            Some(req) => {
+                drop(queues);
+                shared.complete(req.perform());
+            }
+            None if queues.closed => return,
+            None => {}
+        }
+    }
+}
@@ -6,9 +6,15 @@ mod file;
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
 
-use alloc::{string::String, vec::Vec};
+#[cfg(all(feature = "fatfs", not(feature = "myfs")))]
+pub use crate::fs::fatfs::{FatCheckMode, FatCheckReport};
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use axfs_vfs::VfsOps;
 use axio::{self as io, prelude::*};
 
+use crate::fops::{FileSetAttr, FileSystemInfo};
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -56,7 +62,39 @@ pub fn write<C: AsRef<[u8]>>(path: &str, contents: C) -> io::Result<()> {
 /// Given a path, query the file system to get information about a file,
 /// directory, etc.
 pub fn metadata(path: &str) -> io::Result<Metadata> {
-    File::open(path)?.metadata()
+    let node = crate::root::lookup(None, path)?;
+    Ok(Metadata(node.get_attr()?))
+}
+
+/// Query the metadata about a file without following symbolic links.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    let node = crate::root::lookup_no_follow(None, path)?;
+    Ok(Metadata(node.get_attr()?))
+}
+
+/// Returns the attributes of the filesystem that contains `path`, e.g. the
+/// block size and the number of free blocks.
+pub fn statfs(path: &str) -> io::Result<FileSystemInfo> {
+    crate::root::statfs(path)
+}
+
+/// Reads a symbolic link, returning the path that the link points to.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(None, path)
+}
+
+/// Creates a new symbolic link `link` on the filesystem, which points to
+/// `original`.
+pub fn soft_link(original: &str, link: &str) -> io::Result<()> {
+    crate::root::symlink(None, original, link)
+}
+
+/// Creates a new hard link `link` on the filesystem, which refers to the
+/// same file as `original`.
+///
+/// The two paths must be in the same mounted fs.
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    crate::root::link(original, link)
 }
 
 /// Creates a new, empty directory at the provided path.
@@ -80,10 +118,123 @@ pub fn remove_file(path: &str) -> io::Result<()> {
     crate::root::remove_file(None, path)
 }
 
+/// Changes the permission, owner or timestamps of the file at `path`,
+/// following symbolic links.
+pub fn set_attr(path: &str, attr: FileSetAttr) -> io::Result<()> {
+    crate::root::set_attr(None, path, attr, true)
+}
+
+/// Changes the permissions found on a file or a directory.
+pub fn set_permissions(path: &str, perm: Permissions) -> io::Result<()> {
+    set_attr(path, FileSetAttr::new().with_perm(perm))
+}
+
+/// Changes the owner and group of the specified path, `None` leaves the ID
+/// unchanged.
+///
+/// Only root can change the owner.
+pub fn chown(path: &str, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    let mut attr = FileSetAttr::new();
+    if let Some(uid) = uid {
+        attr = attr.with_uid(uid);
+    }
+    if let Some(gid) = gid {
+        attr = attr.with_gid(gid);
+    }
+    set_attr(path, attr)
+}
+
 /// Rename a file or directory to a new name.
 /// Delete the original file if `old` already exists.
 ///
 /// This only works then the new path is in the same mounted fs.
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
-    crate::root::rename(old, new)
+    crate::root::rename(old, new, true)
+}
+
+/// Mounts the filesystem `fs` at `path`.
+///
+/// The mount point is created if it does not exist, and it can be inside
+/// another mounted filesystem.
+pub fn mount(path: &str, fs: Arc<dyn VfsOps>) -> io::Result<()> {
+    crate::root::mount(path, fs)
+}
+
+/// Unmounts the filesystem mounted at `path`.
+///
+/// It fails with [`ResourceBusy`](io::Error::ResourceBusy) if there are still
+/// opened files in the filesystem, or other filesystems mounted inside it.
+pub fn umount(path: &str) -> io::Result<()> {
+    crate::root::umount(path)
+}
+
+/// Writes all cached data of block devices back to the storage.
+pub fn sync() -> io::Result<()> {
+    crate::cache::sync_all().map_err(|_| io::Error::Io)
+}
+
+/// Returns the paths of all mount points, starting with the root `/`.
+pub fn mounts() -> Vec<String> {
+    crate::root::mount_paths()
+}
+
+/// Creates a new filesystem by the type name, which can be mounted by
+/// [`mount`].
+///
+/// Supported types are `ramfs` (or `tmpfs`), `devfs` and `9p` (the
+/// filesystem shared by the first 9P device), depending on the enabled
+/// features.
+pub fn new_fs(fs_type: &str) -> io::Result<Arc<dyn VfsOps>> {
+    crate::mounts::new_fs(fs_type)
+}
+
+/// Attaches to the filesystem shared by the 9P device with `mount_tag` (e.g.
+/// the `mount_tag` of QEMU `-virtfs`), which can be mounted by [`mount`].
+#[cfg(feature = "9p")]
+pub fn new_9p_fs(mount_tag: &str) -> io::Result<Arc<dyn VfsOps>> {
+    crate::mounts::ninep_fs(Some(mount_tag))
+}
+
+/// Attaches the file at `path` (e.g. a filesystem image) as a loop device,
+/// and returns its name, e.g. `loop0`.
+///
+/// The partition table on it is scanned like other block devices. The
+/// volumes are named `loop<n>` for an unpartitioned image, or `loop<n>p<part>`
+/// for partitions, and added to `/dev` if devfs is enabled. They can be
+/// opened by [`loop_fs`]. The file is opened for writing unless `read_only`
+/// is true.
+pub fn attach_loop(path: &str, read_only: bool) -> io::Result<String> {
+    crate::loopdev::attach(path, read_only)
+}
+
+/// Opens the filesystem on the volume `name` of a loop device attached by
+/// [`attach_loop`], which can be mounted by [`mount`].
+///
+/// It fails with [`Unsupported`](io::Error::Unsupported) if the volume does
+/// not contain a supported filesystem.
+pub fn loop_fs(name: &str) -> io::Result<Arc<dyn VfsOps>> {
+    crate::loopdev::volume_fs(name)
+}
+
+/// Checks the FAT filesystem on the volume `name` of a loop device attached
+/// by [`attach_loop`], and repairs it in [`FatCheckMode::Repair`] mode.
+///
+/// It fails with [`ResourceBusy`](io::Error::ResourceBusy) if the volume is
+/// mounted, or with [`InvalidData`](io::Error::InvalidData) if the volume does
+/// not contain a FAT filesystem.
+#[cfg(all(feature = "fatfs", not(feature = "myfs")))]
+pub fn check_loop_fat(name: &str, mode: FatCheckMode) -> io::Result<FatCheckReport> {
+    crate::fs::fatfs::check(crate::loopdev::unmounted_volume_disk(name)?, mode)
+}
+
+/// Creates an overlay filesystem of the writable `upper` filesystem over the
+/// filesystem mounted at `lower`, which can be mounted by [`mount`].
+///
+/// The lower filesystem is never modified: files are copied up to `upper`
+/// when they are written, and removed files are hidden by whiteouts in
+/// `upper`. Directories in both layers are merged.
+#[cfg(feature = "overlayfs")]
+pub fn overlay_fs(lower: &str, upper: Arc<dyn VfsOps>) -> io::Result<Arc<dyn VfsOps>> {
+    let lower = crate::root::mounted_fs(lower)?;
+    Ok(crate::mounts::overlayfs(lower, upper))
 }
@@ -0,0 +1,172 @@
+//! Block buffer cache between filesystems and block devices.
+//!
+//! Each block device has an LRU cache of its blocks. Writes only modify the
+//! cached blocks, which are written back when evicted, or when the cache is
+//! flushed by `fsync` or [`sync_all`].
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, vec::Vec};
+use axdriver::prelude::*;
+use axsync::Mutex;
+
+use crate::dev::{BlockDev, BLOCK_SIZE};
+
+/// Maximum number of contiguous dirty blocks written back in one request.
+const MAX_WRITEBACK_BLOCKS: u64 = 64;
+
+/// All block caches, to be flushed by [`sync_all`].
+static CACHES: Mutex<Vec<Weak<Mutex<BlockCache>>>> = Mutex::new(Vec::new());
+
+struct CachedBlock {
+    data: Box<[u8; BLOCK_SIZE]>,
+    dirty: bool,
+    /// The time of the last access, the key in [`BlockCache::lru`].
+    last_used: u64,
+}
+
+/// A block device with an LRU buffer cache.
+///
+/// Only single blocks can be read or written.
+pub(crate) struct BlockCache {
+    dev: BlockDev,
+    capacity: usize,
+    blocks: BTreeMap<u64, CachedBlock>,
+    /// Block IDs ordered by the time of the last access.
+    lru: BTreeMap<u64, u64>,
+    clock: u64,
+}
+
+impl BlockCache {
+    /// Creates a cache that holds at most `capacity` blocks of the device.
+    /// Caching is disabled if `capacity` is 0.
+    pub fn new(dev: BlockDev, capacity: usize) -> Arc<Mutex<Self>> {
+        let cache = Arc::new(Mutex::new(Self {
+            dev,
+            capacity,
+            blocks: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+        }));
+        let mut caches = CACHES.lock();
+        caches.retain(|c| c.strong_count() > 0);
+        caches.push(Arc::downgrade(&cache));
+        cache
+    }
+
+    /// The number of blocks in the device.
+    pub fn num_blocks(&self) -> u64 {
+        self.dev.num_blocks()
+    }
+
+    pub fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        if self.capacity == 0 {
+            return self.dev.read_block(block_id, buf);
+        }
+        if let Some(block) = self.touch(block_id) {
+            buf.copy_from_slice(&block.data[..]);
+            return Ok(());
+        }
+        let mut data = Box::new([0; BLOCK_SIZE]);
+        self.dev.read_block(block_id, &mut data[..])?;
+        buf.copy_from_slice(&data[..]);
+        self.insert(block_id, data, false)
+    }
+
+    pub fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        if self.capacity == 0 {
+            return self.dev.write_block(block_id, buf);
+        }
+        if let Some(block) = self.touch(block_id) {
+            block.data.copy_from_slice(buf);
+            block.dirty = true;
+            return Ok(());
+        }
+        let data = Box::new(buf.try_into().map_err(|_| DevError::InvalidParam)?);
+        self.insert(block_id, data, true)
+    }
+
+    /// Writes all dirty blocks back to the device.
+    pub fn flush(&mut self) -> DevResult {
+        let mut next = 0;
+        while let Some(start) = self
+            .blocks
+            .range(next..)
+            .find(|(_, block)| block.dirty)
+            .map(|(&id, _)| id)
+        {
+            next = self.write_back(start)?;
+        }
+        self.dev.flush()
+    }
+
+    /// Finds the cached block and marks it as the most recently used.
+    fn touch(&mut self, block_id: u64) -> Option<&mut CachedBlock> {
+        let block = self.blocks.get_mut(&block_id)?;
+        self.lru.remove(&block.last_used);
+        self.clock += 1;
+        block.last_used = self.clock;
+        self.lru.insert(self.clock, block_id);
+        Some(block)
+    }
+
+    fn insert(&mut self, block_id: u64, data: Box<[u8; BLOCK_SIZE]>, dirty: bool) -> DevResult {
+        while self.blocks.len() >= self.capacity {
+            self.evict()?;
+        }
+        self.clock += 1;
+        self.lru.insert(self.clock, block_id);
+        let last_used = self.clock;
+        self.blocks.insert(
+            block_id,
+            CachedBlock {
+                data,
+                dirty,
+                last_used,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes the least recently used block, writes it back first if dirty.
+    fn evict(&mut self) -> DevResult {
+        let Some((_, &block_id)) = self.lru.first_key_value() else {
+            return Ok(());
+        };
+        if self.blocks[&block_id].dirty {
+            self.write_back(block_id)?;
+        }
+        self.lru.pop_first();
+        self.blocks.remove(&block_id);
+        Ok(())
+    }
+
+    /// Writes back the dirty block `start` and the following contiguous dirty
+    /// blocks in one request. Returns the block ID after the last written one.
+    fn write_back(&mut self, start: u64) -> DevResult<u64> {
+        let mut buf = Vec::new();
+        let mut end = start;
+        for (&id, block) in self.blocks.range(start..) {
+            if id != end || !block.dirty || end - start >= MAX_WRITEBACK_BLOCKS {
+                break;
+            }
+            buf.extend_from_slice(&block.data[..]);
+            end += 1;
+        }
+        self.dev.write_block(start, &buf)?;
+        for block in self.blocks.range_mut(start..end).map(|(_, b)| b) {
+            block.dirty = false;
+        }
+        Ok(end)
+    }
+}
+
+/// Writes back dirty blocks in all block caches.
+pub(crate) fn sync_all() -> DevResult {
+    let caches: Vec<_> = CACHES.lock().iter().filter_map(Weak::upgrade).collect();
+    // loop devices write to the caches of earlier devices, flush them first
+    for cache in caches.into_iter().rev() {
+        cache.lock().flush()?;
+    }
+    Ok(())
+}
@@ -1,28 +1,123 @@
+use alloc::sync::Arc;
 use axdriver::prelude::*;
+use axsync::Mutex;
 
-const BLOCK_SIZE: usize = 512;
+use crate::cache::BlockCache;
+use crate::loopdev::LoopDevice;
+
+#[cfg(feature = "devfs")]
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodePerm, VfsNodeType, VfsResult};
+
+pub(crate) const BLOCK_SIZE: usize = 512;
+
+/// A block device that volumes are on, either found by the driver or a loop
+/// device attached at runtime.
+pub(crate) enum BlockDev {
+    Driver(AxBlockDevice),
+    Loop(LoopDevice),
+}
+
+impl BaseDriverOps for BlockDev {
+    fn device_name(&self) -> &str {
+        match self {
+            Self::Driver(dev) => dev.device_name(),
+            Self::Loop(dev) => dev.device_name(),
+        }
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl BlockDriverOps for BlockDev {
+    fn num_blocks(&self) -> u64 {
+        match self {
+            Self::Driver(dev) => dev.num_blocks(),
+            Self::Loop(dev) => dev.num_blocks(),
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        match self {
+            Self::Driver(dev) => dev.block_size(),
+            Self::Loop(dev) => dev.block_size(),
+        }
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        match self {
+            Self::Driver(dev) => dev.read_block(block_id, buf),
+            Self::Loop(dev) => dev.read_block(block_id, buf),
+        }
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        match self {
+            Self::Driver(dev) => dev.write_block(block_id, buf),
+            Self::Loop(dev) => dev.write_block(block_id, buf),
+        }
+    }
+
+    fn flush(&mut self) -> DevResult {
+        match self {
+            Self::Driver(dev) => dev.flush(),
+            Self::Loop(dev) => dev.flush(),
+        }
+    }
+}
 
 /// A disk device with a cursor.
+///
+/// It covers either a whole block device or a partition of it, partitions of
+/// the same device share the underlying driver and its buffer cache.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    start_block: u64,
+    num_blocks: u64,
+    dev: Arc<Mutex<BlockCache>>,
 }
 
 impl Disk {
     /// Create a new disk.
     pub fn new(dev: AxBlockDevice) -> Self {
         assert_eq!(BLOCK_SIZE, dev.block_size());
+        let num_blocks = dev.num_blocks();
+        let cache = BlockCache::new(BlockDev::Driver(dev), axconfig::BLOCK_CACHE_SIZE);
+        Self::new_shared(cache, 0, num_blocks)
+    }
+
+    /// Create a disk that covers `num_blocks` blocks of a shared device,
+    /// starting from `start_block`.
+    pub(crate) fn new_shared(
+        dev: Arc<Mutex<BlockCache>>,
+        start_block: u64,
+        num_blocks: u64,
+    ) -> Self {
         Self {
             block_id: 0,
             offset: 0,
+            start_block,
+            num_blocks,
             dev,
         }
     }
 
+    /// Create another disk on the same blocks, with its own cursor.
+    pub(crate) fn share(&self) -> Self {
+        Self::new_shared(self.dev.clone(), self.start_block, self.num_blocks)
+    }
+
+    /// Create a disk that covers the whole underlying device.
+    pub(crate) fn whole_device(&self) -> Self {
+        let num_blocks = self.dev.lock().num_blocks();
+        Self::new_shared(self.dev.clone(), 0, num_blocks)
+    }
+
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
-        self.dev.num_blocks() * BLOCK_SIZE as u64
+        self.num_blocks * BLOCK_SIZE as u64
     }
 
     /// Get the position of the cursor.
@@ -36,12 +131,23 @@ impl Disk {
         self.offset = pos as usize % BLOCK_SIZE;
     }
 
+    /// The block on the underlying device at the cursor.
+    fn dev_block_id(&self) -> DevResult<u64> {
+        if self.block_id < self.num_blocks {
+            Ok(self.start_block + self.block_id)
+        } else {
+            Err(DevError::Io) // out of the partition
+        }
+    }
+
     /// Read within one block, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        let block_id = self.dev_block_id()?;
         let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
             // whole block
             self.dev
-                .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
+                .lock()
+                .read_block(block_id, &mut buf[0..BLOCK_SIZE])?;
             self.block_id += 1;
             BLOCK_SIZE
         } else {
@@ -50,7 +156,7 @@ impl Disk {
             let start = self.offset;
             let count = buf.len().min(BLOCK_SIZE - self.offset);
 
-            self.dev.read_block(self.block_id, &mut data)?;
+            self.dev.lock().read_block(block_id, &mut data)?;
             buf[..count].copy_from_slice(&data[start..start + count]);
 
             self.offset += count;
@@ -65,9 +171,10 @@ impl Disk {
 
     /// Write within one block, returns the number of bytes written.
     pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
+        let block_id = self.dev_block_id()?;
         let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
             // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
+            self.dev.lock().write_block(block_id, &buf[0..BLOCK_SIZE])?;
             self.block_id += 1;
             BLOCK_SIZE
         } else {
@@ -76,9 +183,10 @@ impl Disk {
             let start = self.offset;
             let count = buf.len().min(BLOCK_SIZE - self.offset);
 
-            self.dev.read_block(self.block_id, &mut data)?;
+            let mut dev = self.dev.lock();
+            dev.read_block(block_id, &mut data)?;
             data[start..start + count].copy_from_slice(&buf[..count]);
-            self.dev.write_block(self.block_id, &data)?;
+            dev.write_block(block_id, &data)?;
 
             self.offset += count;
             if self.offset >= BLOCK_SIZE {
@@ -89,4 +197,80 @@ impl Disk {
         };
         Ok(write_size)
     }
+
+    /// Write all cached data of the underlying device back to the storage.
+    pub fn flush(&mut self) -> DevResult {
+        self.dev.lock().flush()
+    }
+}
+
+/// A block device node in devfs, e.g. `/dev/vda1`, which reads and writes the
+/// raw disk.
+#[cfg(feature = "devfs")]
+pub(crate) struct DiskNode {
+    disk: Mutex<Disk>,
+    ino: u64,
+}
+
+#[cfg(feature = "devfs")]
+impl DiskNode {
+    pub fn new(disk: Disk) -> Self {
+        Self {
+            disk: Mutex::new(disk),
+            ino: axfs_vfs::alloc_ino(),
+        }
+    }
+}
+
+#[cfg(feature = "devfs")]
+impl axfs_vfs::VfsNodeOps for DiskNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.disk.lock().size();
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::BlockDevice,
+            size,
+            size / BLOCK_SIZE as u64,
+        )
+        .with_ino(self.ino))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut disk = self.disk.lock();
+        let len = buf.len().min(disk.size().saturating_sub(offset) as usize);
+        disk.set_position(offset);
+        let mut read_len = 0;
+        while read_len < len {
+            read_len += disk
+                .read_one(&mut buf[read_len..len])
+                .map_err(|_| VfsError::Io)?;
+        }
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut disk = self.disk.lock();
+        let len = buf.len().min(disk.size().saturating_sub(offset) as usize);
+        if len == 0 && !buf.is_empty() {
+            return Err(VfsError::StorageFull);
+        }
+        disk.set_position(offset);
+        let mut write_len = 0;
+        while write_len < len {
+            write_len += disk
+                .write_one(&buf[write_len..len])
+                .map_err(|_| VfsError::Io)?;
+        }
+        Ok(len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.disk.lock().flush().map_err(|_| VfsError::Io)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(()) // opened with `O_TRUNC`, the size cannot be changed
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
 }
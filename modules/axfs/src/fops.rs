@@ -1,10 +1,20 @@
 //! Low-level filesystem operations.
 
+use alloc::{format, string::String};
 use axerrno::{ax_err, ax_err_type, AxResult};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
-use capability::{Cap, WithCap};
+use capability::WithCap;
 use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::lock::{self, LockKey};
+use crate::root::MountRef;
+
+pub use capability::Cap;
+
+pub use crate::lock::{release_task_locks, FileLock, LockHandle, LockKind, LockOwner};
+pub use crate::notify::{WatchEvent, WatchMask, Watcher};
 
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
@@ -19,12 +29,44 @@ pub type DirEntry = axfs_vfs::VfsDirEntry;
 pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
+/// Alias of [`axfs_vfs::VfsSetAttr`].
+pub type FileSetAttr = axfs_vfs::VfsSetAttr;
+/// Alias of [`axfs_vfs::FileSystemInfo`].
+pub type FileSystemInfo = axfs_vfs::FileSystemInfo;
+
+/// The user and group IDs that file accesses are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Credential {
+    /// User ID.
+    pub uid: u32,
+    /// Group ID.
+    pub gid: u32,
+}
+
+/// The interface to get the credential of the current task, which must be
+/// implemented by the kernel.
+#[crate_interface::def_interface]
+pub trait CredentialIf {
+    /// Returns the credential of the current task.
+    fn current_credential() -> Credential;
+}
+
+/// The ID of the next opened file.
+static NEXT_FILE_ID: AtomicU64 = AtomicU64::new(1);
 
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    mount: Option<MountRef>,
+    /// The absolute path that the file is opened at, if known. Events are
+    /// reported to watchers with it, as long as it still refers to the file.
+    path: Option<String>,
+    /// The inode number to identify locks on the file.
+    ino: u64,
+    /// The unique ID of the opened file, as the owner of whole-file locks.
+    id: u64,
 }
 
 /// An opened directory object, with open permissions and a cursor for
@@ -32,6 +74,10 @@ pub struct File {
 pub struct Directory {
     node: WithCap<VfsNodeRef>,
     entry_idx: usize,
+    mount: Option<MountRef>,
+    /// The absolute path of the directory, used to resolve paths relative to
+    /// it that can not be handled by nodes, like renaming.
+    path: Option<String>,
 }
 
 /// Options and flags which can be used to configure how a file is opened.
@@ -144,19 +190,32 @@ impl File {
             return ax_err!(IsADirectory);
         }
         let access_cap = opts.into();
-        if !perm_to_cap(attr.perm()).contains(access_cap) {
+        if !perm_to_cap(&attr).contains(access_cap) {
             return ax_err!(PermissionDenied);
         }
 
+        // nodes without inode numbers are told apart by their addresses
+        let ino = match attr.ino() {
+            0 => alloc::sync::Arc::as_ptr(&node) as *const () as u64,
+            ino => ino,
+        };
         node.open()?;
-        if opts.truncate {
-            node.truncate(0)?;
-        }
-        Ok(Self {
+        let file = Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
-        })
+            mount: crate::root::mount_ref(dir, path),
+            path: match dir {
+                Some(_) if !path.starts_with('/') => None,
+                _ => crate::root::absolute_path(path).ok(),
+            },
+            ino,
+            id: NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed),
+        };
+        if opts.truncate {
+            file.truncate(0)?;
+        }
+        Ok(file)
     }
 
     /// Opens a file at the path relative to the current directory. Returns a
@@ -168,6 +227,7 @@ impl File {
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
         self.node.access(Cap::WRITE)?.truncate(size)?;
+        self.notify(WatchMask::MODIFY);
         Ok(())
     }
 
@@ -203,6 +263,7 @@ impl File {
         };
         let write_len = node.write_at(self.offset, buf)?;
         self.offset += write_len as u64;
+        self.notify(WatchMask::MODIFY);
         Ok(write_len)
     }
 
@@ -213,6 +274,52 @@ impl File {
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
+        self.notify(WatchMask::MODIFY);
+        Ok(write_len)
+    }
+
+    /// Reads the file at the current position into several buffers in order.
+    /// Returns the total number of bytes read.
+    ///
+    /// After the read, the cursor will be advanced by the number of bytes read.
+    pub fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> AxResult<usize> {
+        let read_len = self.read_vectored_at(self.offset, bufs)?;
+        self.offset += read_len as u64;
+        Ok(read_len)
+    }
+
+    /// Reads the file at the given position into several buffers in order.
+    /// Returns the total number of bytes read.
+    ///
+    /// It does not update the file cursor.
+    pub fn read_vectored_at(&self, offset: u64, bufs: &mut [&mut [u8]]) -> AxResult<usize> {
+        let node = self.node.access(Cap::READ)?;
+        node.read_vectored_at(offset, bufs)
+    }
+
+    /// Writes several buffers to the file at the current position in order.
+    /// Returns the total number of bytes written.
+    ///
+    /// After the write, the cursor will be advanced by the number of bytes
+    /// written.
+    pub fn write_vectored(&mut self, bufs: &[&[u8]]) -> AxResult<usize> {
+        self.node.access(Cap::WRITE)?;
+        if self.is_append {
+            self.offset = self.get_attr()?.size();
+        };
+        let write_len = self.write_vectored_at(self.offset, bufs)?;
+        self.offset += write_len as u64;
+        Ok(write_len)
+    }
+
+    /// Writes several buffers to the file at the given position in order.
+    /// Returns the total number of bytes written.
+    ///
+    /// It does not update the file cursor.
+    pub fn write_vectored_at(&self, offset: u64, bufs: &[&[u8]]) -> AxResult<usize> {
+        let node = self.node.access(Cap::WRITE)?;
+        let write_len = node.write_vectored_at(offset, bufs)?;
+        self.notify(WatchMask::MODIFY);
         Ok(write_len)
     }
 
@@ -240,6 +347,52 @@ impl File {
     pub fn get_attr(&self) -> AxResult<FileAttr> {
         self.node.access(Cap::empty())?.get_attr()
     }
+
+    /// Changes the permission, owner or timestamps of the file.
+    ///
+    /// It does not require the file to be opened for writing. Only root can
+    /// change the owner, and only the owner or root can change others.
+    pub fn set_attr(&self, attr: FileSetAttr) -> AxResult {
+        set_node_attr(self.node.access(Cap::empty())?, attr)?;
+        self.notify(WatchMask::ATTRIB);
+        Ok(())
+    }
+
+    /// Gets the attributes of the filesystem that contains the file.
+    pub fn statfs(&self) -> AxResult<FileSystemInfo> {
+        crate::root::statfs_of(self.mount.as_ref())
+    }
+
+    /// Returns the access capabilities that the file is opened with.
+    pub fn access_cap(&self) -> Cap {
+        self.node.cap()
+    }
+
+    /// Returns a handle to place and release advisory locks on this file.
+    pub fn lock_handle(&self) -> LockHandle {
+        LockHandle::new(self.lock_key(), self.id, self.node.cap())
+    }
+
+    fn lock_key(&self) -> LockKey {
+        LockKey {
+            fs: crate::root::fs_id(self.mount.as_ref()),
+            ino: self.ino,
+        }
+    }
+
+    /// Reports the event about this file to watchers.
+    ///
+    /// Nothing is reported if the file is no longer at the path it is opened
+    /// at, e.g. it is renamed.
+    fn notify(&self, mask: WatchMask) {
+        if !crate::notify::has_watchers() {
+            return;
+        }
+        let path = self.path.as_deref();
+        if let Some(path) = path.and_then(|path| crate::root::opened_event_path(path, self.ino)) {
+            crate::notify::notify(&path, mask);
+        }
+    }
 }
 
 impl Directory {
@@ -258,7 +411,7 @@ impl Directory {
             return ax_err!(NotADirectory);
         }
         let access_cap = opts.into();
-        if !perm_to_cap(attr.perm()).contains(access_cap) {
+        if !perm_to_cap(&attr).contains(access_cap) {
             return ax_err!(PermissionDenied);
         }
 
@@ -266,6 +419,8 @@ impl Directory {
         Ok(Self {
             node: WithCap::new(node, access_cap),
             entry_idx: 0,
+            mount: crate::root::mount_ref(dir, path),
+            path: crate::root::event_path(dir, path, true).ok(),
         })
     }
 
@@ -283,16 +438,47 @@ impl Directory {
         Self::_open_dir_at(None, path, opts)
     }
 
+    /// Opens the current directory, which can only be used to access the
+    /// paths relative to it.
+    pub fn current_dir() -> AxResult<Self> {
+        let node = crate::root::lookup(None, ".")?;
+        node.open()?;
+        Ok(Self {
+            node: WithCap::new(node, Cap::EXECUTE),
+            entry_idx: 0,
+            mount: crate::root::mount_ref(None, "."),
+            path: crate::root::current_dir().ok(),
+        })
+    }
+
     /// Opens a directory at the path relative to this directory. Returns a
     /// [`Directory`] object.
     pub fn open_dir_at(&self, path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_dir_at(self.access_at(path)?, path, opts)
+        let dir = self.access_at(path)?;
+        let mut opened = Self::_open_dir_at(dir, path, opts)?;
+        if dir.is_some() {
+            opened.mount.clone_from(&self.mount);
+            opened.path = self
+                .absolute_path_at(path)
+                .ok()
+                .and_then(|path| crate::root::event_path(None, &path, true).ok());
+        }
+        Ok(opened)
     }
 
     /// Opens a file at the path relative to this directory. Returns a [`File`]
     /// object.
     pub fn open_file_at(&self, path: &str, opts: &OpenOptions) -> AxResult<File> {
-        File::_open_at(self.access_at(path)?, path, opts)
+        let dir = self.access_at(path)?;
+        let mut file = File::_open_at(dir, path, opts)?;
+        if dir.is_some() {
+            file.mount.clone_from(&self.mount);
+            file.path = self
+                .absolute_path_at(path)
+                .ok()
+                .and_then(|path| crate::root::absolute_path(&path).ok());
+        }
+        Ok(file)
     }
 
     /// Creates an empty file at the path relative to this directory.
@@ -329,17 +515,122 @@ impl Directory {
         Ok(n)
     }
 
+    /// Returns the index of the next entry to be read.
+    pub fn position(&self) -> usize {
+        self.entry_idx
+    }
+
+    /// Sets the index of the next entry to be read, `0` rewinds the directory.
+    pub fn set_position(&mut self, idx: usize) {
+        self.entry_idx = idx;
+    }
+
+    /// Gets the directory attributes.
+    pub fn get_attr(&self) -> AxResult<FileAttr> {
+        self.node.access(Cap::empty())?.get_attr()
+    }
+
+    /// Gets the attributes of the file at the path relative to this
+    /// directory, follows the symbolic link if `follow` is true.
+    pub fn get_attr_at(&self, path: &str, follow: bool) -> AxResult<FileAttr> {
+        let dir = self.access_at(path)?;
+        let node = if follow {
+            crate::root::lookup(dir, path)?
+        } else {
+            crate::root::lookup_no_follow(dir, path)?
+        };
+        node.get_attr()
+    }
+
+    /// Changes the attributes of the file at the path relative to this
+    /// directory, follows the symbolic link if `follow` is true.
+    pub fn set_attr_at(&self, path: &str, attr: FileSetAttr, follow: bool) -> AxResult {
+        crate::root::set_attr(self.access_at(path)?, path, attr, follow)
+    }
+
+    /// Checks whether the current task is permitted to access the file at the
+    /// path relative to this directory with `cap`.
+    pub fn check_access_at(&self, path: &str, cap: Cap, follow: bool) -> AxResult {
+        if perm_to_cap(&self.get_attr_at(path, follow)?).contains(cap) {
+            Ok(())
+        } else {
+            ax_err!(PermissionDenied)
+        }
+    }
+
+    /// Writes all modified metadata of the directory back to the storage.
+    ///
+    /// It succeeds if the filesystem has nothing to write for directories.
+    pub fn flush(&self) -> AxResult {
+        match self.node.access(Cap::empty())?.fsync() {
+            Err(VfsError::InvalidInput) => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Returns the absolute path of `path` relative to this directory, without
+    /// resolving symbolic links.
+    ///
+    /// It fails if the path of this directory is unknown.
+    pub fn absolute_path_at(&self, path: &str) -> AxResult<String> {
+        if path.starts_with('/') {
+            return Ok(path.into());
+        }
+        match &self.path {
+            Some(base) => Ok(format!("{}/{}", base.trim_end_matches('/'), path)),
+            None => ax_err!(Unsupported),
+        }
+    }
+
+    /// Changes the current directory to this directory.
+    pub fn set_current_dir(&self) -> AxResult {
+        match &self.path {
+            Some(path) => crate::root::set_current_dir(path),
+            None => ax_err!(Unsupported),
+        }
+    }
+
+    /// Gets the attributes of the filesystem that contains the directory.
+    pub fn statfs(&self) -> AxResult<FileSystemInfo> {
+        crate::root::statfs_of(self.mount.as_ref())
+    }
+
     /// Rename a file or directory to a new name.
     /// Delete the original file if `old` already exists.
     ///
     /// This only works then the new path is in the same mounted fs.
     pub fn rename(&self, old: &str, new: &str) -> AxResult {
-        crate::root::rename(old, new)
+        crate::root::rename(old, new, true)
+    }
+
+    /// Renames `old` to `new`, both relative to this directory, the same as
+    /// [`Directory::rename`] otherwise.
+    ///
+    /// It fails if the path of this directory is unknown.
+    pub fn rename_at(&self, old: &str, new: &str) -> AxResult {
+        crate::root::rename(
+            &self.absolute_path_at(old)?,
+            &self.absolute_path_at(new)?,
+            true,
+        )
+    }
+
+    /// Renames `old` to `new` like [`Directory::rename_at`], but fails with
+    /// [`AlreadyExists`](axerrno::AxError::AlreadyExists) instead of
+    /// replacing `new`.
+    pub fn rename_noreplace_at(&self, old: &str, new: &str) -> AxResult {
+        crate::root::rename(
+            &self.absolute_path_at(old)?,
+            &self.absolute_path_at(new)?,
+            false,
+        )
     }
 }
 
 impl Drop for File {
     fn drop(&mut self) {
+        let key = self.lock_key();
+        lock::unlock(&key, LockOwner::File(self.id), 0, u64::MAX);
         unsafe { self.node.access_unchecked().release().ok() };
     }
 }
@@ -388,16 +679,86 @@ impl From<&OpenOptions> for Cap {
     }
 }
 
-fn perm_to_cap(perm: FilePerm) -> Cap {
+impl Credential {
+    /// The credential of root, which bypasses permission checks.
+    pub const ROOT: Self = Self::new(0, 0);
+
+    /// Creates a new credential with the given user and group IDs.
+    pub const fn new(uid: u32, gid: u32) -> Self {
+        Self { uid, gid }
+    }
+
+    /// Whether the user is root.
+    pub const fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+}
+
+/// Returns the credential of the current task.
+pub(crate) fn current_credential() -> Credential {
+    crate_interface::call_interface!(CredentialIf::current_credential)
+}
+
+/// Returns the accesses to the node with `attr` that the current task is
+/// permitted.
+///
+/// The owner, group or other bits are used, in that order, depending on which
+/// the credential matches. Root can read and write anything, and can execute
+/// directories and files executable by anyone.
+pub(crate) fn perm_to_cap(attr: &FileAttr) -> Cap {
+    let cred = current_credential();
+    let mode = attr.perm().bits();
+    let bits = if cred.is_root() {
+        let exec = if attr.is_dir() || mode & 0o111 != 0 {
+            0o1
+        } else {
+            0
+        };
+        0o6 | exec
+    } else if cred.uid == attr.uid() {
+        mode >> 6
+    } else if cred.gid == attr.gid() {
+        mode >> 3
+    } else {
+        mode
+    };
+
     let mut cap = Cap::empty();
-    if perm.owner_readable() {
+    if bits & 0o4 != 0 {
         cap |= Cap::READ;
     }
-    if perm.owner_writable() {
+    if bits & 0o2 != 0 {
         cap |= Cap::WRITE;
     }
-    if perm.owner_executable() {
+    if bits & 0o1 != 0 {
         cap |= Cap::EXECUTE;
     }
     cap
 }
+
+/// Changes the attributes of `node` on behalf of the current task.
+///
+/// Only root can change the owner, the permission and timestamps can also be
+/// changed by the owner.
+pub(crate) fn set_node_attr(node: &VfsNodeRef, attr: FileSetAttr) -> AxResult {
+    let cred = current_credential();
+    if !cred.is_root() {
+        let old = node.get_attr()?;
+        let chown = attr.uid().is_some_and(|uid| uid != old.uid())
+            || attr.gid().is_some_and(|gid| gid != old.gid());
+        if chown || cred.uid != old.uid() {
+            return ax_err!(PermissionDenied);
+        }
+    }
+    node.set_attr(attr)
+}
+
+/// Changes the owner of a newly created node to the current task, it is
+/// ignored if not supported by the filesystem.
+pub(crate) fn set_new_node_owner(node: &VfsNodeRef) {
+    let cred = current_credential();
+    if !cred.is_root() {
+        let attr = FileSetAttr::new().with_uid(cred.uid).with_gid(cred.gid);
+        node.set_attr(attr).ok();
+    }
+}
@@ -0,0 +1,53 @@
+use axfs_vfs::{VfsError, VfsResult};
+use axsync::Mutex;
+
+use crate::dev::Disk;
+
+pub use axfs_ext4::Ext4FileSystem;
+
+/// A [`Disk`] that contains an ext2/ext4 filesystem.
+pub struct Ext4Disk(Mutex<Disk>);
+
+impl Ext4Disk {
+    /// Checks whether the disk contains an ext filesystem, gives the disk
+    /// back if not.
+    pub fn probe(disk: Disk) -> Result<Self, Disk> {
+        let dev = Self(Mutex::new(disk));
+        if Ext4FileSystem::probe(&dev) {
+            Ok(dev)
+        } else {
+            Err(dev.0.into_inner())
+        }
+    }
+}
+
+impl axfs_ext4::BlockDevice for Ext4Disk {
+    fn read_at(&self, pos: u64, mut buf: &mut [u8]) -> VfsResult {
+        let mut disk = self.0.lock();
+        disk.set_position(pos);
+        while !buf.is_empty() {
+            let n = disk.read_one(buf).map_err(|_| VfsError::Io)?;
+            let tmp = buf;
+            buf = &mut tmp[n..];
+        }
+        Ok(())
+    }
+
+    fn write_at(&self, pos: u64, mut buf: &[u8]) -> VfsResult {
+        let mut disk = self.0.lock();
+        disk.set_position(pos);
+        while !buf.is_empty() {
+            let n = disk.write_one(buf).map_err(|_| VfsError::Io)?;
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.0.lock().size()
+    }
+
+    fn flush(&self) -> VfsResult {
+        self.0.lock().flush().map_err(|_| VfsError::Io)
+    }
+}
@@ -1,22 +1,83 @@
-use alloc::sync::Arc;
+mod check;
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{format, string::String, vec::Vec};
 use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
 
-use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
+use axfs_vfs::path::canonicalize;
+use axfs_vfs::{FileSystemInfo, VfsDirEntry, VfsError, VfsNodePerm, VfsResult, VfsSetAttr};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{Date, DateTime, DirEntry, Time, TimeProvider};
+use fatfs::{Dir, File, LossyOemCpConverter, Read, Seek, SeekFrom, Write};
 
 use crate::dev::Disk;
 
+pub(crate) use self::check::check;
+pub use self::check::{FatCheckMode, FatCheckReport};
+
 const BLOCK_SIZE: usize = 512;
 
+/// 1980-01-01 00:00:00, the earliest time of FAT, in seconds since the Unix
+/// epoch.
+const FAT_TIME_MIN: u64 = 315_532_800;
+/// 2107-12-31 23:59:59, the latest time of FAT.
+const FAT_TIME_MAX: u64 = 4_354_819_199;
+
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<Disk, VfsTimeProvider, LossyOemCpConverter>,
+    /// Another handle of the disk, to write data back before metadata.
+    disk: Mutex<Disk>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
+    /// The [`Arc`] that owns the filesystem, held by the nodes other than the
+    /// root directory to keep the filesystem alive.
+    this: UnsafeCell<Weak<FatFileSystem>>,
+    /// Inode numbers by the lowercase absolute paths, FAT does not store
+    /// them, so they are assigned when the nodes are first looked up.
+    inodes: Mutex<BTreeMap<String, u64>>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+/// A file on FAT.
+///
+/// Writes are ordered: the directory entry of the file, which records its
+/// size and first cluster, is only written after the data and the FAT are on
+/// the disk. A crash can leave lost clusters, but never an entry that refers
+/// to clusters whose data is not written yet.
+pub struct FileWrapper<'a> {
+    fs: &'a FatFileSystem,
+    _owner: Option<Arc<FatFileSystem>>,
+    file: Mutex<File<'a, Disk, VfsTimeProvider, LossyOemCpConverter>>,
+    /// Whether the directory entry is changed by writes but not written yet.
+    dirty: AtomicBool,
+    /// Where the directory entry which contains the timestamps is found.
+    location: Mutex<FileLocation<'a>>,
+    ino: u64,
+}
+
+/// The parent directory and the name of a file, refreshed after the file is
+/// renamed.
+struct FileLocation<'a> {
+    parent: Dir<'a, Disk, VfsTimeProvider, LossyOemCpConverter>,
+    name: String,
+    /// The lowercase absolute path, the file is renamed if it no longer has
+    /// the inode number of the file.
+    path: String,
+}
+pub struct DirWrapper<'a> {
+    dir: Dir<'a, Disk, VfsTimeProvider, LossyOemCpConverter>,
+    fs: &'a FatFileSystem,
+    _owner: Option<Arc<FatFileSystem>>,
+    /// The absolute path in the filesystem, to assign inode numbers.
+    path: String,
+    ino: u64,
+}
+
+/// Provides the current time to fatfs from [`axfs_vfs::current_time`].
+#[derive(Debug, Clone, Copy)]
+pub struct VfsTimeProvider;
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -30,35 +91,197 @@ impl FatFileSystem {
     pub fn new(mut disk: Disk) -> Self {
         let opts = fatfs::FormatVolumeOptions::new();
         fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
+        let handle = disk.share();
+        let opts = fatfs::FsOptions::new().time_provider(VfsTimeProvider);
+        let inner =
+            fatfs::FileSystem::new(disk, opts).expect("failed to initialize FAT filesystem");
         Self {
             inner,
+            disk: Mutex::new(handle),
             root_dir: UnsafeCell::new(None),
+            this: UnsafeCell::new(Weak::new()),
+            inodes: Mutex::new(BTreeMap::new()),
         }
     }
 
     #[cfg(not(feature = "use-ramdisk"))]
     pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+        Self::try_new(disk).expect("failed to initialize FAT filesystem")
+    }
+
+    /// Opens the FAT filesystem on the disk, returns `None` if the disk does
+    /// not contain one.
+    ///
+    /// The volume is checked first if the `fat-check` config is `report` or
+    /// `repair`.
+    pub fn try_new(disk: Disk) -> Option<Self> {
+        if let Some(mode) = FatCheckMode::on_mount() {
+            match check(disk.share(), mode) {
+                Ok(report) if report.is_clean() => {}
+                Ok(report) => warn!("inconsistent FAT volume: {:?}", report),
+                Err(VfsError::InvalidData) => {} // not a FAT volume
+                Err(e) => warn!("failed to check FAT volume: {:?}", e),
+            }
+        }
+        let handle = disk.share();
+        let opts = fatfs::FsOptions::new().time_provider(VfsTimeProvider);
+        let inner = fatfs::FileSystem::new(disk, opts).ok()?;
+        Some(Self {
             inner,
+            disk: Mutex::new(handle),
             root_dir: UnsafeCell::new(None),
-        }
+            this: UnsafeCell::new(Weak::new()),
+            inodes: Mutex::new(BTreeMap::new()),
+        })
     }
 
-    pub fn init(&'static self) {
+    pub fn init(self: &Arc<Self>) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        // SAFETY: nodes hold the `Arc`, or are dropped with the filesystem
+        // (the root directory), so that they never outlive it
+        let this: &'static Self = unsafe { &*Arc::as_ptr(self) };
+        // the root directory does not hold the `Arc`, which would be a cycle
+        let root = this.new_dir(this.inner.root_dir(), "/");
+        unsafe {
+            *self.root_dir.get() = Some(root);
+            *self.this.get() = Arc::downgrade(self);
+        }
+    }
+
+    fn owner(&self) -> Option<Arc<FatFileSystem>> {
+        unsafe { (*self.this.get()).upgrade() }
+    }
+
+    fn new_file<'a>(
+        &'a self,
+        file: File<'a, Disk, VfsTimeProvider, LossyOemCpConverter>,
+        parent: Dir<'a, Disk, VfsTimeProvider, LossyOemCpConverter>,
+        path: &str,
+    ) -> Arc<FileWrapper<'a>> {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        Arc::new(FileWrapper {
+            fs: self,
+            _owner: self.owner(),
+            file: Mutex::new(file),
+            dirty: AtomicBool::new(false),
+            location: Mutex::new(FileLocation {
+                parent,
+                name: name.into(),
+                path: path.to_lowercase(),
+            }),
+            ino: self.ino(path),
+        })
+    }
+
+    fn new_dir<'a>(
+        &'a self,
+        dir: Dir<'a, Disk, VfsTimeProvider, LossyOemCpConverter>,
+        path: &str,
+    ) -> Arc<DirWrapper<'a>> {
+        Arc::new(DirWrapper {
+            dir,
+            fs: self,
+            _owner: self.owner(),
+            path: path.into(),
+            ino: self.ino(path),
+        })
+    }
+
+    /// Returns the inode number of the node at the absolute `path`.
+    fn ino(&self, path: &str) -> u64 {
+        *self
+            .inodes
+            .lock()
+            .entry(path.to_lowercase())
+            .or_insert_with(axfs_vfs::alloc_ino)
+    }
+
+    /// Returns the lowercase absolute path of the node with the inode number
+    /// `ino`, or `None` if it has been removed.
+    fn path_of(&self, ino: u64) -> Option<String> {
+        self.inodes
+            .lock()
+            .iter()
+            .find(|(_, &i)| i == ino)
+            .map(|(path, _)| path.clone())
+    }
+
+    /// Moves the inode numbers of the node at the absolute path `src` and its
+    /// descendants to `dst`, or drops them if `dst` is `None`.
+    fn move_inodes(&self, src: &str, dst: Option<&str>) {
+        let src = src.to_lowercase();
+        let mut inodes = self.inodes.lock();
+        let moved: Vec<_> = inodes
+            .keys()
+            .filter(|path| {
+                path.strip_prefix(&src)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .cloned()
+            .collect();
+        if let Some(dst) = dst {
+            inodes.remove(&dst.to_lowercase()); // replaced
+        }
+        for path in moved {
+            let ino = inodes.remove(&path).unwrap();
+            if let Some(dst) = dst {
+                inodes.insert(format!("{}{}", dst.to_lowercase(), &path[src.len()..]), ino);
+            }
+        }
+    }
+
+    /// Writes all cached blocks of the disk back.
+    fn flush_disk(&self) -> VfsResult {
+        self.disk.lock().flush().map_err(|_| VfsError::Io)
+    }
+}
+
+impl<'a> FileWrapper<'a> {
+    /// Writes the directory entry of the file after its data and the FAT.
+    fn flush_ordered(
+        &self,
+        file: &mut File<'a, Disk, VfsTimeProvider, LossyOemCpConverter>,
+    ) -> VfsResult {
+        self.fs.flush_disk()?;
+        file.flush().map_err(as_vfs_err)?;
+        self.dirty.store(false, Ordering::Release);
+        Ok(())
     }
+}
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+impl Drop for FileWrapper<'_> {
+    fn drop(&mut self) {
+        // the file also writes its directory entry when dropped, but without
+        // writing the data first
+        if self.dirty.load(Ordering::Acquire) {
+            if let Err(e) = self.flush_ordered(&mut self.file.lock()) {
+                warn!("failed to flush FAT file {}: {:?}", self.ino, e);
+            }
+        }
     }
+}
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+impl FileWrapper<'static> {
+    /// Finds the directory entry of the file, fatfs does not tell its
+    /// position, so the parent directory is scanned.
+    fn dir_entry(&self) -> Option<DirEntry<'static, Disk, VfsTimeProvider, LossyOemCpConverter>> {
+        let mut loc = self.location.lock();
+        if self.fs.inodes.lock().get(&loc.path) != Some(&self.ino) {
+            // renamed, or removed if the inode number is dropped
+            let path = self.fs.path_of(self.ino)?;
+            let (parent, name) = path.rsplit_once('/')?;
+            let root = self.fs.inner.root_dir();
+            loc.parent = match parent.trim_start_matches('/') {
+                "" => root,
+                parent => root.open_dir(parent).ok()?,
+            };
+            loc.name = name.into();
+            loc.path = path;
+        }
+        loc.parent
+            .iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name().eq_ignore_ascii_case(&loc.name))
     }
 }
 
@@ -66,29 +289,134 @@ impl VfsNodeOps for FileWrapper<'static> {
     axfs_vfs::impl_vfs_non_dir_default! {}
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        let size = self.0.lock().seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
+        let size = self
+            .file
+            .lock()
+            .seek(SeekFrom::End(0))
+            .map_err(as_vfs_err)?;
         let blocks = (size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64;
         // FAT fs doesn't support permissions, we just set everything to 755
         let perm = VfsNodePerm::from_bits_truncate(0o755);
-        Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks))
+        let attr = VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks).with_ino(self.ino);
+        // FAT only records the date of the last access, and has no change time
+        Ok(match self.dir_entry() {
+            Some(entry) => {
+                let atime = from_fat_time(DateTime::new(entry.accessed(), Time::new(0, 0, 0, 0)));
+                let mtime = from_fat_time(entry.modified());
+                attr.with_times(atime, mtime, mtime)
+            }
+            None => attr,
+        })
+    }
+
+    /// Only the access and modification times can be changed.
+    fn set_attr(&self, attr: VfsSetAttr) -> VfsResult {
+        if attr.perm().is_some() || attr.uid().is_some() || attr.gid().is_some() {
+            return Err(VfsError::Unsupported);
+        }
+        let mut file = self.file.lock();
+        if let Some(atime) = attr.atime() {
+            file.set_accessed(to_fat_time(atime).date);
+        }
+        if let Some(mtime) = attr.mtime() {
+            file.set_modified(to_fat_time(mtime));
+        }
+        // write the directory entry
+        self.flush_ordered(&mut file)
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
         file.read(buf).map_err(as_vfs_err)
     }
 
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
+        self.dirty.store(true, Ordering::Release);
         file.write(buf).map_err(as_vfs_err)
     }
 
+    /// Seeks only once, and reads the buffers until the end of the file.
+    fn read_vectored_at(&self, offset: u64, bufs: &mut [&mut [u8]]) -> VfsResult<usize> {
+        let mut file = self.file.lock();
+        file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?;
+        let mut read_len = 0;
+        for buf in bufs.iter_mut() {
+            let mut buf = &mut buf[..];
+            while !buf.is_empty() {
+                match file.read(buf) {
+                    Ok(0) => return Ok(read_len),
+                    Ok(n) => {
+                        buf = &mut buf[n..];
+                        read_len += n;
+                    }
+                    Err(e) if read_len == 0 => return Err(as_vfs_err(e)),
+                    Err(_) => return Ok(read_len),
+                }
+            }
+        }
+        Ok(read_len)
+    }
+
+    /// Seeks only once, and writes the buffers one after another.
+    fn write_vectored_at(&self, offset: u64, bufs: &[&[u8]]) -> VfsResult<usize> {
+        let mut file = self.file.lock();
+        file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?;
+        self.dirty.store(true, Ordering::Release);
+        let mut write_len = 0;
+        for mut buf in bufs.iter().copied() {
+            while !buf.is_empty() {
+                match file.write(buf) {
+                    Ok(0) => return Ok(write_len),
+                    Ok(n) => {
+                        buf = &buf[n..];
+                        write_len += n;
+                    }
+                    Err(e) if write_len == 0 => return Err(as_vfs_err(e)),
+                    Err(_) => return Ok(write_len),
+                }
+            }
+        }
+        Ok(write_len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.flush_ordered(&mut self.file.lock())
+    }
+
+    /// Only growing the file keeps the volume consistent if the system stops
+    /// before the next flush.
+    ///
+    /// When shrinking, `fatfs` frees the clusters in the FAT at once, and the
+    /// directory entry cannot be written before them, so the entry may point
+    /// to free clusters on the disk. To keep the window short, the pending
+    /// writes are flushed before, and the shrunk entry is written back with
+    /// the FAT right after.
     fn truncate(&self, size: u64) -> VfsResult {
-        let mut file = self.0.lock();
+        let mut file = self.file.lock();
+        let shrink = file.seek(SeekFrom::End(0)).map_err(as_vfs_err)? > size;
+        if shrink && self.dirty.load(Ordering::Acquire) {
+            self.flush_ordered(&mut file)?;
+        }
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.truncate().map_err(as_vfs_err)
+        self.dirty.store(true, Ordering::Release);
+        file.truncate().map_err(as_vfs_err)?;
+        if shrink {
+            file.flush().map_err(as_vfs_err)?;
+            self.fs.flush_disk()?;
+            self.dirty.store(false, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+impl DirWrapper<'_> {
+    /// Returns the absolute path of the node at `path` relative to this
+    /// directory.
+    fn abs_path(&self, path: &str) -> String {
+        canonicalize(&format!("{}/{}", self.path, path))
     }
 }
 
@@ -102,13 +430,18 @@ impl VfsNodeOps for DirWrapper<'static> {
             VfsNodeType::Dir,
             BLOCK_SIZE as u64,
             1,
-        ))
+        )
+        .with_ino(self.ino))
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
+        let path = match self.path.rsplit_once('/') {
+            Some(("", _)) | None => "/",
+            Some((parent, _)) => parent,
+        };
+        self.dir
             .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+            .map_or(None, |dir| Some(self.fs.new_dir(dir, path)))
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -122,10 +455,15 @@ impl VfsNodeOps for DirWrapper<'static> {
         }
 
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
-        } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+        let abs_path = self.abs_path(path);
+        if let Ok(file) = self.dir.open_file(path) {
+            let parent = match path.rsplit_once('/') {
+                Some((dir, _)) => self.dir.open_dir(dir).map_err(as_vfs_err)?,
+                None => self.dir.clone(),
+            };
+            Ok(self.fs.new_file(file, parent, &abs_path))
+        } else if let Ok(dir) = self.dir.open_dir(path) {
+            Ok(self.fs.new_dir(dir, &abs_path))
         } else {
             Err(VfsError::NotFound)
         }
@@ -143,11 +481,11 @@ impl VfsNodeOps for DirWrapper<'static> {
 
         match ty {
             VfsNodeType::File => {
-                self.0.create_file(path).map_err(as_vfs_err)?;
+                self.dir.create_file(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             VfsNodeType::Dir => {
-                self.0.create_dir(path).map_err(as_vfs_err)?;
+                self.dir.create_dir(path).map_err(as_vfs_err)?;
                 Ok(())
             }
             _ => Err(VfsError::Unsupported),
@@ -161,11 +499,13 @@ impl VfsNodeOps for DirWrapper<'static> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.remove(rest);
         }
-        self.0.remove(path).map_err(as_vfs_err)
+        self.dir.remove(path).map_err(as_vfs_err)?;
+        self.fs.move_inodes(&self.abs_path(path), None);
+        Ok(())
     }
 
     fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
-        let mut iter = self.0.iter().skip(start_idx);
+        let mut iter = self.dir.iter().skip(start_idx);
         for (i, out_entry) in dirents.iter_mut().enumerate() {
             let x = iter.next();
             match x {
@@ -177,7 +517,9 @@ impl VfsNodeOps for DirWrapper<'static> {
                     } else {
                         unreachable!()
                     };
-                    *out_entry = VfsDirEntry::new(&entry.file_name(), ty);
+                    let name = entry.file_name();
+                    let ino = self.fs.ino(&self.abs_path(&name));
+                    *out_entry = VfsDirEntry::new(&name, ty).with_ino(ino);
                 }
                 _ => return Ok(i),
             }
@@ -192,19 +534,90 @@ impl VfsNodeOps for DirWrapper<'static> {
             src_path, dst_path
         );
 
-        self.0
-            .rename(src_path, &self.0, dst_path)
-            .map_err(as_vfs_err)
+        self.dir
+            .rename(src_path, &self.dir, dst_path)
+            .map_err(as_vfs_err)?;
+        let (src, dst) = (self.abs_path(src_path), self.abs_path(dst_path));
+        self.fs.move_inodes(&src, Some(&dst));
+        Ok(())
+    }
+}
+
+impl Drop for FatFileSystem {
+    fn drop(&mut self) {
+        // the root directory borrows `inner`, which is dropped before the field
+        self.root_dir.get_mut().take();
     }
 }
 
 impl VfsOps for FatFileSystem {
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        let stats = self.inner.stats().map_err(as_vfs_err)?;
+        // FAT has no inodes, and long names are at most 255 characters
+        Ok(FileSystemInfo::new(
+            stats.cluster_size() as _,
+            stats.total_clusters() as _,
+            stats.free_clusters() as _,
+        ))
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         let root_dir = unsafe { (*self.root_dir.get()).as_ref().unwrap() };
         root_dir.clone()
     }
 }
 
+impl TimeProvider for VfsTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        to_fat_time(axfs_vfs::current_time())
+    }
+}
+
+/// Converts a duration since the Unix epoch to a FAT timestamp, clamped to the
+/// range that FAT can represent.
+fn to_fat_time(time: Duration) -> DateTime {
+    let secs = time.as_secs().clamp(FAT_TIME_MIN, FAT_TIME_MAX);
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // the inverse of `from_fat_time`, see <http://howardhinnant.github.io/date_algorithms.html>
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+
+    let date = Date::new(year as u16, month as u16, day as u16);
+    let time = Time::new(
+        (secs / 3600) as u16,
+        (secs / 60 % 60) as u16,
+        (secs % 60) as u16,
+        time.subsec_millis() as u16,
+    );
+    DateTime::new(date, time)
+}
+
+/// Converts a FAT timestamp to a duration since the Unix epoch.
+fn from_fat_time(date_time: DateTime) -> Duration {
+    let (date, time) = (date_time.date, date_time.time);
+    let (month, day) = (date.month as u64, date.day as u64);
+    let year = date.year as u64 - (month <= 2) as u64;
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86400 + time.hour as u64 * 3600 + time.min as u64 * 60 + time.sec as u64;
+    Duration::from_secs(secs) + Duration::from_millis(time.millis as u64)
+}
+
 impl fatfs::IoBase for Disk {
     type Error = ();
 }
@@ -243,7 +656,7 @@ impl Write for Disk {
         Ok(write_len)
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        Disk::flush(self).map_err(|_| ())
     }
 }
 
@@ -0,0 +1,576 @@
+//! Consistency checks of FAT filesystems, like `fsck.vfat`.
+//!
+//! The check reads the boot sector and the first FAT directly from the disk,
+//! walks all directories from the root, and follows the cluster chain of every
+//! entry. It finds:
+//!
+//! - chains that link to a free, bad or out-of-range cluster;
+//! - chains that link to a cluster already in a chain (cross-linked or looped);
+//! - entries whose first cluster is invalid, or already in another chain;
+//! - files whose size does not match the length of their chains;
+//! - allocated clusters that are not in any chain (lost clusters).
+//!
+//! In [`FatCheckMode::Repair`] mode, chains are cut before the invalid links
+//! (the first entry that claims a cluster keeps it), invalid entries are
+//! emptied (files) or deleted (directories), chains longer than the file size
+//! are truncated, sizes beyond the chains are shrunk to them, and lost
+//! clusters are freed. All copies of the FAT are rewritten.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use axfs_vfs::{VfsError, VfsResult};
+
+use crate::dev::Disk;
+
+const DIR_ENTRY_SIZE: usize = 32;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0f;
+const ENTRY_END: u8 = 0x00;
+const ENTRY_DELETED: u8 = 0xe5;
+/// The free cluster count in FSInfo when it is unknown.
+const FREE_COUNT_UNKNOWN: u32 = 0xffff_ffff;
+
+/// How to check a FAT volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatCheckMode {
+    /// Only report the problems.
+    Report,
+    /// Report and repair the problems.
+    Repair,
+}
+
+impl FatCheckMode {
+    /// The mode to check volumes when they are mounted, set by the
+    /// `fat-check` config.
+    pub(crate) fn on_mount() -> Option<Self> {
+        match axconfig::FAT_CHECK {
+            "report" => Some(Self::Report),
+            "repair" => Some(Self::Repair),
+            _ => None,
+        }
+    }
+}
+
+/// The problems found by a check of a FAT volume.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FatCheckReport {
+    /// Number of files and directories checked, excluding the root.
+    pub entries: usize,
+    /// Number of chains that link to a free, bad or out-of-range cluster.
+    pub bad_chains: usize,
+    /// Number of chains that link to a cluster already in a chain.
+    pub cross_links: usize,
+    /// Number of entries whose first cluster is invalid, or already in another
+    /// chain.
+    pub bad_entries: usize,
+    /// Number of files whose size does not match the length of their chains.
+    pub bad_sizes: usize,
+    /// Number of allocated clusters not in any chain.
+    pub lost_clusters: usize,
+    /// Whether the problems are repaired.
+    pub repaired: bool,
+}
+
+impl FatCheckReport {
+    /// Whether no problem is found.
+    pub fn is_clean(&self) -> bool {
+        self.bad_chains == 0
+            && self.cross_links == 0
+            && self.bad_entries == 0
+            && self.bad_sizes == 0
+            && self.lost_clusters == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// The layout of a FAT volume, from the BIOS parameter block.
+struct Layout {
+    fat_type: FatType,
+    bytes_per_sector: u64,
+    cluster_size: u64,
+    /// The offset and size in bytes of the first FAT.
+    fat_offset: u64,
+    fat_size: u64,
+    num_fats: u64,
+    /// The offset and size in bytes of the root directory of FAT12/16.
+    root_offset: u64,
+    root_size: u64,
+    /// The first cluster of the root directory of FAT32.
+    root_cluster: u32,
+    data_offset: u64,
+    /// The number of FAT entries, including the two reserved ones.
+    num_entries: u32,
+    /// The sector of the FSInfo structure of FAT32.
+    fs_info_sector: u64,
+}
+
+impl Layout {
+    /// Parses the boot sector of a volume of `disk_size` bytes.
+    fn parse(boot: &[u8; 512], disk_size: u64) -> VfsResult<Self> {
+        let u16_at = |off: usize| u16::from_le_bytes([boot[off], boot[off + 1]]) as u64;
+        let u32_at = |off: usize| u32::from_le_bytes(boot[off..off + 4].try_into().unwrap()) as u64;
+
+        let bytes_per_sector = u16_at(11);
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved_sectors = u16_at(14);
+        let num_fats = boot[16] as u64;
+        let root_entries = u16_at(17);
+        let total_sectors = match u16_at(19) {
+            0 => u32_at(32),
+            n => n,
+        };
+        let fat_sectors = match u16_at(22) {
+            0 => u32_at(36),
+            n => n,
+        };
+        if boot[510..512] != [0x55, 0xaa]
+            || !matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved_sectors == 0
+            || num_fats == 0
+            || fat_sectors == 0
+        {
+            return Err(VfsError::InvalidData);
+        }
+
+        let root_sectors = (root_entries * DIR_ENTRY_SIZE as u64).div_ceil(bytes_per_sector);
+        let root_sector = reserved_sectors + num_fats * fat_sectors;
+        let data_sector = root_sector + root_sectors;
+        // the FATs are read into the memory, they must be on the disk
+        if total_sectors <= data_sector || total_sectors * bytes_per_sector > disk_size {
+            return Err(VfsError::InvalidData);
+        }
+        let num_clusters = (total_sectors - data_sector) / sectors_per_cluster;
+        let fat_type = match num_clusters {
+            0..=4084 => FatType::Fat12,
+            4085..=65524 => FatType::Fat16,
+            _ => FatType::Fat32,
+        };
+        if (fat_type == FatType::Fat32) != (root_entries == 0) {
+            return Err(VfsError::InvalidData);
+        }
+
+        let fat_size = fat_sectors * bytes_per_sector;
+        let entry_bits = match fat_type {
+            FatType::Fat12 => 12,
+            FatType::Fat16 => 16,
+            FatType::Fat32 => 32,
+        };
+        // entries that do not fit in the FAT can not be used
+        let num_entries = (num_clusters + 2).min(fat_size * 8 / entry_bits);
+        Ok(Self {
+            fat_type,
+            bytes_per_sector,
+            cluster_size: sectors_per_cluster * bytes_per_sector,
+            fat_offset: reserved_sectors * bytes_per_sector,
+            fat_size,
+            num_fats,
+            root_offset: root_sector * bytes_per_sector,
+            root_size: root_entries * DIR_ENTRY_SIZE as u64,
+            root_cluster: if fat_type == FatType::Fat32 {
+                u32_at(44) as u32
+            } else {
+                0
+            },
+            data_offset: data_sector * bytes_per_sector,
+            num_entries: num_entries as u32,
+            fs_info_sector: if fat_type == FatType::Fat32 {
+                u16_at(48)
+            } else {
+                0
+            },
+        })
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_offset + (cluster as u64 - 2) * self.cluster_size
+    }
+}
+
+/// The file allocation table, in its on-disk format.
+struct Fat {
+    fat_type: FatType,
+    data: Vec<u8>,
+    modified: bool,
+}
+
+impl Fat {
+    fn get(&self, cluster: u32) -> u32 {
+        let n = cluster as usize;
+        match self.fat_type {
+            FatType::Fat12 => {
+                let off = n + n / 2;
+                let v = u16::from_le_bytes([self.data[off], self.data[off + 1]]);
+                (if n % 2 == 0 { v & 0xfff } else { v >> 4 }) as u32
+            }
+            FatType::Fat16 => u16::from_le_bytes([self.data[2 * n], self.data[2 * n + 1]]) as u32,
+            FatType::Fat32 => {
+                u32::from_le_bytes(self.data[4 * n..4 * n + 4].try_into().unwrap()) & 0x0fff_ffff
+            }
+        }
+    }
+
+    fn set(&mut self, cluster: u32, value: u32) {
+        let n = cluster as usize;
+        match self.fat_type {
+            FatType::Fat12 => {
+                let off = n + n / 2;
+                let old = u16::from_le_bytes([self.data[off], self.data[off + 1]]);
+                let v = if n % 2 == 0 {
+                    (old & 0xf000) | (value as u16 & 0xfff)
+                } else {
+                    (old & 0x000f) | ((value as u16) << 4)
+                };
+                self.data[off..off + 2].copy_from_slice(&v.to_le_bytes());
+            }
+            FatType::Fat16 => {
+                self.data[2 * n..2 * n + 2].copy_from_slice(&(value as u16).to_le_bytes())
+            }
+            FatType::Fat32 => {
+                // the high 4 bits are reserved, and kept
+                let old = u32::from_le_bytes(self.data[4 * n..4 * n + 4].try_into().unwrap());
+                let v = (old & 0xf000_0000) | (value & 0x0fff_ffff);
+                self.data[4 * n..4 * n + 4].copy_from_slice(&v.to_le_bytes());
+            }
+        }
+        self.modified = true;
+    }
+
+    /// The value that marks the end of a chain.
+    fn end_of_chain(&self) -> u32 {
+        match self.fat_type {
+            FatType::Fat12 => 0xfff,
+            FatType::Fat16 => 0xffff,
+            FatType::Fat32 => 0x0fff_ffff,
+        }
+    }
+
+    /// The value that marks a bad cluster, values above it are the end of
+    /// chains.
+    fn bad_cluster(&self) -> u32 {
+        self.end_of_chain() - 8
+    }
+}
+
+/// A directory entry that has been read.
+struct Entry {
+    /// The offset of the entry on the disk.
+    offset: u64,
+    raw: [u8; DIR_ENTRY_SIZE],
+}
+
+impl Entry {
+    fn name(&self) -> String {
+        let name = String::from_utf8_lossy(&self.raw[0..8]);
+        let ext = String::from_utf8_lossy(&self.raw[8..11]);
+        match ext.trim_end() {
+            "" => name.trim_end().into(),
+            ext => format!("{}.{}", name.trim_end(), ext),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.raw[11] & ATTR_DIRECTORY != 0
+    }
+
+    fn first_cluster(&self, fat_type: FatType) -> u32 {
+        let lo = u16::from_le_bytes([self.raw[26], self.raw[27]]) as u32;
+        let hi = u16::from_le_bytes([self.raw[20], self.raw[21]]) as u32;
+        if fat_type == FatType::Fat32 {
+            (hi << 16) | lo
+        } else {
+            lo
+        }
+    }
+
+    fn set_first_cluster(&mut self, cluster: u32) {
+        self.raw[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+        self.raw[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+    }
+
+    fn size(&self) -> u64 {
+        u32::from_le_bytes(self.raw[28..32].try_into().unwrap()) as u64
+    }
+
+    fn set_size(&mut self, size: u64) {
+        self.raw[28..32].copy_from_slice(&(size as u32).to_le_bytes());
+    }
+}
+
+struct Checker {
+    disk: Disk,
+    layout: Layout,
+    fat: Fat,
+    /// Whether each cluster is in a chain.
+    used: Vec<bool>,
+    repair: bool,
+    report: FatCheckReport,
+}
+
+impl Checker {
+    fn read_at(&mut self, pos: u64, mut buf: &mut [u8]) -> VfsResult {
+        self.disk.set_position(pos);
+        while !buf.is_empty() {
+            match self.disk.read_one(buf) {
+                Ok(n) if n > 0 => buf = &mut buf[n..],
+                _ => return Err(VfsError::Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, pos: u64, mut buf: &[u8]) -> VfsResult {
+        self.disk.set_position(pos);
+        while !buf.is_empty() {
+            match self.disk.write_one(buf) {
+                Ok(n) if n > 0 => buf = &buf[n..],
+                _ => return Err(VfsError::Io),
+            }
+        }
+        Ok(())
+    }
+
+    /// Follows the chain from `start`, and marks its clusters as used.
+    ///
+    /// The chain is cut before the first invalid link, the clusters before it
+    /// are returned. Nothing is returned if `start` itself is invalid.
+    fn claim_chain(&mut self, path: &str, start: u32) -> Vec<u32> {
+        let mut chain = Vec::new();
+        let mut cluster = start;
+        if cluster == 0 {
+            return chain;
+        }
+        loop {
+            let valid = (2..self.layout.num_entries).contains(&cluster) && {
+                let next = self.fat.get(cluster);
+                next != 0 && next != self.fat.bad_cluster()
+            };
+            let problem = if !valid {
+                Some("links to an invalid cluster")
+            } else if self.used[cluster as usize] {
+                Some("is cross-linked")
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                // an invalid first cluster is a problem of the entry
+                if let Some(&last) = chain.last() {
+                    warn!("fsck: {}: chain {} at cluster {}", path, problem, last);
+                    if valid {
+                        self.report.cross_links += 1;
+                    } else {
+                        self.report.bad_chains += 1;
+                    }
+                    if self.repair {
+                        let eoc = self.fat.end_of_chain();
+                        self.fat.set(last, eoc);
+                    }
+                }
+                return chain;
+            }
+
+            self.used[cluster as usize] = true;
+            chain.push(cluster);
+            match self.fat.get(cluster) {
+                next if next > self.fat.bad_cluster() => return chain,
+                next => cluster = next,
+            }
+        }
+    }
+
+    /// Reads the entries of a directory stored in `chain`, or the root
+    /// directory of FAT12/16 if `chain` is `None`.
+    fn read_dir(&mut self, chain: Option<&[u32]>) -> VfsResult<Vec<Entry>> {
+        let regions: Vec<(u64, u64)> = match chain {
+            Some(chain) => chain
+                .iter()
+                .map(|&c| (self.layout.cluster_offset(c), self.layout.cluster_size))
+                .collect(),
+            None => vec![(self.layout.root_offset, self.layout.root_size)],
+        };
+        let mut entries = Vec::new();
+        let mut buf = vec![0; self.layout.bytes_per_sector as usize];
+        for (start, size) in regions {
+            for sector in (start..start + size).step_by(buf.len()) {
+                self.read_at(sector, &mut buf)?;
+                for (i, raw) in buf.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+                    match raw[0] {
+                        ENTRY_END => return Ok(entries),
+                        ENTRY_DELETED | b'.' => continue,
+                        _ if raw[11] == ATTR_LONG_NAME || raw[11] & ATTR_VOLUME_ID != 0 => continue,
+                        _ => {}
+                    }
+                    entries.push(Entry {
+                        offset: sector + (i * DIR_ENTRY_SIZE) as u64,
+                        raw: raw.try_into().unwrap(),
+                    });
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Checks an entry, returns the chain of it if it is a valid directory.
+    fn check_entry(&mut self, path: &str, entry: &mut Entry) -> VfsResult<Option<Vec<u32>>> {
+        self.report.entries += 1;
+        let first = entry.first_cluster(self.layout.fat_type);
+        let mut chain = self.claim_chain(path, first);
+
+        if entry.is_dir() {
+            if chain.is_empty() {
+                warn!("fsck: {}: invalid first cluster {}", path, first);
+                self.report.bad_entries += 1;
+                if self.repair {
+                    entry.raw[0] = ENTRY_DELETED;
+                    self.write_at(entry.offset, &entry.raw)?;
+                }
+                return Ok(None);
+            }
+            return Ok(Some(chain));
+        }
+
+        if first != 0 && chain.is_empty() {
+            warn!("fsck: {}: invalid first cluster {}", path, first);
+            self.report.bad_entries += 1;
+            if self.repair {
+                entry.set_first_cluster(0);
+                entry.set_size(0);
+                self.write_at(entry.offset, &entry.raw)?;
+            }
+            return Ok(None);
+        }
+        let cluster_size = self.layout.cluster_size;
+        let needed = entry.size().div_ceil(cluster_size) as usize;
+        if chain.len() != needed {
+            warn!(
+                "fsck: {}: size {} does not match {} clusters",
+                path,
+                entry.size(),
+                chain.len()
+            );
+            self.report.bad_sizes += 1;
+            if self.repair {
+                if chain.len() > needed {
+                    for &c in &chain[needed..] {
+                        self.fat.set(c, 0);
+                    }
+                    chain.truncate(needed);
+                    match chain.last() {
+                        Some(&last) => {
+                            let eoc = self.fat.end_of_chain();
+                            self.fat.set(last, eoc);
+                        }
+                        None => entry.set_first_cluster(0),
+                    }
+                } else {
+                    entry.set_size(chain.len() as u64 * cluster_size);
+                }
+                self.write_at(entry.offset, &entry.raw)?;
+            }
+        }
+        Ok(None)
+    }
+
+    fn check(&mut self) -> VfsResult {
+        // directories to check, with their paths and chains
+        let mut dirs: Vec<(String, Option<Vec<u32>>)> = Vec::new();
+        if self.layout.fat_type == FatType::Fat32 {
+            let root = self.claim_chain("/", self.layout.root_cluster);
+            if root.is_empty() {
+                warn!("fsck: invalid root cluster {}", self.layout.root_cluster);
+                return Err(VfsError::InvalidData);
+            }
+            dirs.push((String::new(), Some(root)));
+        } else {
+            dirs.push((String::new(), None));
+        }
+
+        while let Some((dir_path, chain)) = dirs.pop() {
+            for mut entry in self.read_dir(chain.as_deref())? {
+                let path = format!("{}/{}", dir_path, entry.name());
+                if let Some(chain) = self.check_entry(&path, &mut entry)? {
+                    dirs.push((path, Some(chain)));
+                }
+            }
+        }
+
+        for cluster in 2..self.layout.num_entries {
+            let value = self.fat.get(cluster);
+            if value != 0 && value != self.fat.bad_cluster() && !self.used[cluster as usize] {
+                self.report.lost_clusters += 1;
+                if self.repair {
+                    self.fat.set(cluster, 0);
+                }
+            }
+        }
+        if self.report.lost_clusters > 0 {
+            warn!("fsck: {} lost clusters", self.report.lost_clusters);
+        }
+        Ok(())
+    }
+
+    /// Writes the FAT to all copies, and invalidates the free cluster count.
+    fn write_fat(&mut self) -> VfsResult {
+        let data = core::mem::take(&mut self.fat.data);
+        for i in 0..self.layout.num_fats {
+            let offset = self.layout.fat_offset + i * self.layout.fat_size;
+            self.write_at(offset, &data)?;
+        }
+        if self.layout.fs_info_sector != 0 {
+            let offset = self.layout.fs_info_sector * self.layout.bytes_per_sector + 488;
+            self.write_at(offset, &FREE_COUNT_UNKNOWN.to_le_bytes())?;
+        }
+        self.disk.flush().map_err(|_| VfsError::Io)
+    }
+}
+
+/// Checks the FAT volume on `disk`, and repairs it in
+/// [`FatCheckMode::Repair`] mode.
+///
+/// The volume must not be mounted. Returns [`VfsError::InvalidData`] if the
+/// disk does not contain a FAT filesystem.
+pub(crate) fn check(mut disk: Disk, mode: FatCheckMode) -> VfsResult<FatCheckReport> {
+    let mut boot = [0; 512];
+    disk.set_position(0);
+    let mut read_len = 0;
+    while read_len < boot.len() {
+        match disk.read_one(&mut boot[read_len..]) {
+            Ok(n) if n > 0 => read_len += n,
+            _ => return Err(VfsError::InvalidData),
+        }
+    }
+    let layout = Layout::parse(&boot, disk.size())?;
+    let fat = Fat {
+        fat_type: layout.fat_type,
+        data: vec![0; layout.fat_size as usize],
+        modified: false,
+    };
+    let mut checker = Checker {
+        disk,
+        used: vec![false; layout.num_entries as usize],
+        layout,
+        fat,
+        repair: mode == FatCheckMode::Repair,
+        report: FatCheckReport::default(),
+    };
+    let mut data = core::mem::take(&mut checker.fat.data);
+    checker.read_at(checker.layout.fat_offset, &mut data)?;
+    checker.fat.data = data;
+
+    checker.check()?;
+    if checker.repair && !checker.report.is_clean() {
+        if checker.fat.modified {
+            checker.write_fat()?;
+        } else {
+            checker.disk.flush().map_err(|_| VfsError::Io)?;
+        }
+        checker.report.repaired = true;
+    }
+    Ok(checker.report)
+}
@@ -6,8 +6,20 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(all(feature = "ext4", not(feature = "myfs")))]
+pub mod ext4fs;
+
+#[cfg(feature = "9p")]
+pub mod ninep;
+
 #[cfg(feature = "devfs")]
 pub use axfs_devfs as devfs;
 
 #[cfg(feature = "ramfs")]
 pub use axfs_ramfs as ramfs;
+
+#[cfg(feature = "overlayfs")]
+pub use axfs_overlayfs as overlayfs;
+
+#[cfg(feature = "procfs")]
+pub use axfs_procfs as procfs;
@@ -0,0 +1,23 @@
+use axdriver::prelude::*;
+use axfs_vfs::{VfsError, VfsResult};
+use axsync::Mutex;
+
+pub use axfs_9p::{NinePClient, NinePFileSystem};
+
+/// A 9P transport device that carries the messages of a [`NinePClient`].
+pub struct NinePDev(Mutex<AxNinePDevice>);
+
+impl NinePDev {
+    pub fn new(dev: AxNinePDevice) -> Self {
+        Self(Mutex::new(dev))
+    }
+}
+
+impl axfs_9p::Transport for NinePDev {
+    fn request(&self, req: &[u8], resp: &mut [u8]) -> VfsResult<usize> {
+        self.0.lock().request(req, resp).map_err(|e| {
+            warn!("9p: transport error: {:?}", e);
+            VfsError::Io
+        })
+    }
+}
@@ -4,12 +4,38 @@
 //!
 //! # Cargo Features
 //!
-//! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
-//!    is **enabled** by default.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
-//!    **enabled** by default.
+//! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. FAT volumes
+//!    are checked when mounted according to the `fat-check` config, and can
+//!    be checked by [`api::check_loop_fat`]. This feature is **enabled** by
+//!    default.
+//! - `ext4`: Support [ext2/ext4] filesystems. If the root volume contains one,
+//!    it is mounted on `/` instead of FAT. Ext2 is read-write, and filesystems
+//!    with ext4 features (e.g. extents) are read-only. This feature is
+//!    **disabled** by default.
+//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`, block devices
+//!    and their partitions are added as `vda`, `vda1`, etc. Other devices can
+//!    be added by [`register_device`]. This feature is **enabled** by default.
 //! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
 //!    **enabled** by default.
+//! - `procfs`: Mount [`axfs_procfs::ProcFileSystem`] on `/proc`. Other modules
+//!    can add entries by [`proc_root`]. This feature is **enabled** by default.
+//! - `overlayfs`: Support [`axfs_overlayfs::OverlayFileSystem`], which stacks
+//!    a writable filesystem over a read-only one and can be created by
+//!    [`api::overlay_fs`]. This feature is **disabled** by default.
+//! - `9p`: Support [9P2000.L] filesystems shared by the host through 9P
+//!    transport devices (e.g. virtio-9p), which are initialized by
+//!    [`init_9p`] and can be created by [`api::new_9p_fs`]. This feature is
+//!    **disabled** by default.
+//! - `initramfs`: If the `AX_INITRAMFS` environment variable is set to the
+//!    path of a newc-format cpio archive at build time, the archive is
+//!    embedded and unpacked into a ramfs as the root filesystem, and all
+//!    volumes on block devices are mounted at `/mnt`. See
+//!    [`init_filesystems_with_initramfs`]. This feature is **disabled** by
+//!    default.
+//! - `multitask`: Blocking file lock requests sleep on a wait queue until the
+//!    conflicting locks are released, instead of yielding in a loop, and
+//!    requests submitted to an [`aio::IoRing`] are served by its worker tasks.
+//!    This feature is **disabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
@@ -17,6 +43,8 @@
 //!    both are enabled.
 //!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
+//! [ext2/ext4]: https://en.wikipedia.org/wiki/Ext4
+//! [9P2000.L]: https://github.com/chaos/diod/blob/master/protocol.md
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -26,21 +54,192 @@
 extern crate log;
 extern crate alloc;
 
+mod cache;
 mod dev;
 mod fs;
+mod lock;
+mod loopdev;
 mod mounts;
+mod notify;
+mod partition;
 mod root;
 
+pub mod aio;
 pub mod api;
 pub mod fops;
 
+#[cfg(feature = "procfs")]
+pub use axfs_procfs as procfs;
+
+use alloc::{format, vec::Vec};
 use axdriver::{prelude::*, AxDeviceContainer};
+use axerrno::AxError;
 
 /// Initializes filesystems by block devices.
-pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+///
+/// The root filesystem is selected by the `root-partition` config, see
+/// [`init_filesystems_with_root`]. If an initramfs is embedded, it is used as
+/// the root filesystem instead.
+pub fn init_filesystems(blk_devs: AxDeviceContainer<AxBlockDevice>) {
+    init_filesystems_with_root(blk_devs, axconfig::ROOT_PARTITION);
+}
+
+/// Initializes filesystems by block devices, with the root filesystem on the
+/// volume specified by `root`.
+///
+/// Partition tables (MBR or GPT) on all block devices are parsed, each
+/// partition (or a whole disk without partition table) is a volume. `root`
+/// can be:
+///
+/// - empty: the first volume of the first disk.
+/// - `<disk>:<part>`: partition `<part>` (starts from 1) of the disk
+///   `<disk>`, or the whole disk if `<part>` is 0.
+/// - `LABEL=<name>`: the GPT partition with the given name.
+/// - `UUID=<guid>`: the partition with the given unique GUID, or the Linux
+///   `PARTUUID` for MBR partitions.
+///
+/// Other volumes that contain supported filesystems are mounted at
+/// `/mnt/disk<disk>p<part>` (or `/mnt/disk<disk>` for whole disks).
+///
+/// If an initramfs is embedded by the `initramfs` feature, `root` is ignored
+/// and [`init_filesystems_with_initramfs`] is used instead.
+pub fn init_filesystems_with_root(blk_devs: AxDeviceContainer<AxBlockDevice>, root: &str) {
+    #[cfg(feature = "initramfs")]
+    if !mounts::INITRAMFS.is_empty() {
+        return init_filesystems_with_initramfs(blk_devs, mounts::INITRAMFS);
+    }
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    let mut disks = Disks::scan(blk_devs);
+    assert!(!disks.volumes.is_empty(), "No block device found!");
+
+    let root_idx = if root.is_empty() {
+        0
+    } else {
+        disks
+            .volumes
+            .iter()
+            .position(|vol| vol.matches(root))
+            .unwrap_or_else(|| panic!("root volume {:?} not found!", root))
+    };
+    let root_vol = disks.volumes.remove(root_idx);
+    info!("  use {} as the root filesystem", root_vol.name);
+    self::root::init_rootfs(root_vol.disk);
+    disks.mount();
+}
+
+/// Initializes filesystems with the contents of the newc-format cpio
+/// `archive` as the root filesystem, which is usually embedded at build time,
+/// or loaded by the bootloader.
+///
+/// The archive is unpacked into a ramfs, with the permission bits, owners
+/// and modification times preserved. Volumes on block devices (can be none)
+/// are mounted at `/mnt/disk<disk>p<part>` or `/mnt/disk<disk>`.
+#[cfg(feature = "initramfs")]
+pub fn init_filesystems_with_initramfs(blk_devs: AxDeviceContainer<AxBlockDevice>, archive: &[u8]) {
+    info!("Initialize filesystems...");
+
+    let disks = Disks::scan(blk_devs);
+    info!(
+        "  use initramfs ({} bytes) as the root filesystem",
+        archive.len()
+    );
+    self::root::init_root_dir(mounts::initramfs(archive));
+    disks.mount();
+}
+
+/// Volumes on block devices.
+struct Disks {
+    volumes: Vec<partition::Volume>,
+    /// Device nodes of disks and partitions to be added to `/dev`.
+    #[cfg(feature = "devfs")]
+    nodes: Vec<(alloc::string::String, dev::Disk)>,
+}
+
+impl Disks {
+    /// Scans the partition tables of all block devices.
+    fn scan(mut blk_devs: AxDeviceContainer<AxBlockDevice>) -> Self {
+        let mut volumes = Vec::new();
+        #[cfg(feature = "devfs")]
+        let mut nodes = Vec::new();
+        let mut disk_id = 0;
+        while let Some(dev) = blk_devs.take_one() {
+            info!("  use block device {}: {:?}", disk_id, dev.device_name());
+            let disk_name = format!("disk{}", disk_id);
+            let disk_volumes =
+                partition::scan_volumes(disk_id, &disk_name, self::dev::BlockDev::Driver(dev));
+            #[cfg(feature = "devfs")]
+            {
+                nodes.push((
+                    partition::dev_name(disk_id, None),
+                    disk_volumes[0].disk.whole_device(),
+                ));
+                for vol in disk_volumes.iter().filter(|vol| vol.partition.is_some()) {
+                    let name = partition::dev_name(disk_id, vol.partition.as_ref());
+                    nodes.push((name, vol.disk.share()));
+                }
+            }
+            volumes.extend(disk_volumes);
+            disk_id += 1;
+        }
+        Self {
+            volumes,
+            #[cfg(feature = "devfs")]
+            nodes,
+        }
+    }
+
+    /// Adds the device nodes, and mounts the volumes at `/mnt` after the
+    /// root filesystem is initialized.
+    fn mount(self) {
+        #[cfg(feature = "devfs")]
+        for (name, disk) in self.nodes {
+            register_device(&name, alloc::sync::Arc::new(dev::DiskNode::new(disk)));
+        }
+
+        for vol in self.volumes {
+            let Some(fs) = self::mounts::volume_fs(vol.disk) else {
+                info!("  skip {}: no supported filesystem", vol.name);
+                continue;
+            };
+            let path = format!("/mnt/{}", vol.name);
+            let res = match self::root::create_dir(None, "/mnt") {
+                Err(AxError::AlreadyExists) | Ok(_) => self::root::mount(&path, fs),
+                Err(e) => Err(e),
+            };
+            match res {
+                Ok(_) => info!("  mount {} at {}", vol.name, path),
+                Err(e) => warn!("  failed to mount {} at {}: {:?}", vol.name, path, e),
+            }
+        }
+    }
+}
+
+/// Starts a 9P session on each 9P transport device, the shared filesystems
+/// can then be mounted by [`api::new_9p_fs`] and [`api::mount`].
+///
+/// It must be called after [`init_filesystems`].
+#[cfg(feature = "9p")]
+pub fn init_9p(ninep_devs: AxDeviceContainer<AxNinePDevice>) {
+    info!("Initialize 9P sessions...");
+    self::mounts::init_9p(ninep_devs);
+}
+
+/// Registers a device node at `path` in `/dev`, e.g. `ttyS0` or
+/// `input/event0`.
+///
+/// Parent directories are created if they do not exist, and an existing node
+/// at the same path is replaced. It must be called after [`init_filesystems`].
+#[cfg(feature = "devfs")]
+pub fn register_device(path: &str, node: axfs_vfs::VfsNodeRef) {
+    self::mounts::register_device(path, node);
+}
+
+/// Returns the root directory of the procfs mounted on `/proc`, to add
+/// entries that are generated from the states of other modules.
+///
+/// It must be called after [`init_filesystems`].
+#[cfg(feature = "procfs")]
+pub fn proc_root() -> alloc::sync::Arc<procfs::ProcDir> {
+    self::mounts::proc_root()
 }
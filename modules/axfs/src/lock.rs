@@ -0,0 +1,371 @@
+//! Advisory file locks.
+//!
+//! Locks are recorded in a global table indexed by the locked file, and do not
+//! restrict reads or writes. Like Linux, there are two independent kinds of
+//! locks that never conflict with each other:
+//!
+//! - Record locks (`fcntl`): owned by a task, on byte ranges of the file.
+//! - Whole-file locks (`flock`): owned by an opened file.
+//!
+//! A blocked lock request waits until other locks are released, it fails with
+//! [`Deadlock`](axerrno::AxError::Deadlock) if the owners of the conflicting
+//! locks are waiting for the requester, directly or indirectly.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use axerrno::{ax_err, AxResult};
+use axsync::Mutex;
+use capability::Cap;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The table of all locks.
+static LOCKS: Mutex<LockTable> = Mutex::new(LockTable::new());
+
+/// Increased when any lock is released, to wake up waiting requests.
+static RELEASE_COUNT: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "multitask")]
+static WAIT_QUEUE: axtask::WaitQueue = axtask::WaitQueue::new();
+
+/// The file that locks are on, identified by its filesystem and inode
+/// number, so that all paths and opened files of it share the same locks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct LockKey {
+    /// The address of the mounted filesystem.
+    pub fs: usize,
+    /// The inode number of the file.
+    pub ino: u64,
+}
+
+/// The kind of a lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// A shared (read) lock, can be held by multiple owners.
+    Shared,
+    /// An exclusive (write) lock, can only be held by one owner.
+    Exclusive,
+}
+
+/// The owner of a lock.
+///
+/// Locks of different variants never conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LockOwner {
+    /// A record lock owned by the task with the ID.
+    Task(u64),
+    /// A whole-file lock owned by the opened file with the ID.
+    File(u64),
+}
+
+/// A lock on the byte range `start..end` of a file.
+///
+/// The range of whole-file locks is `0..u64::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileLock {
+    /// The owner of the lock.
+    pub owner: LockOwner,
+    /// Whether the lock is shared or exclusive.
+    pub kind: LockKind,
+    /// The start offset of the locked range.
+    pub start: u64,
+    /// The end offset (exclusive) of the locked range, [`u64::MAX`] means
+    /// the range extends to any future end of the file.
+    pub end: u64,
+}
+
+impl FileLock {
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        self.start < end && start < self.end
+    }
+
+    /// Whether this lock prevents `other` from being acquired.
+    fn conflicts(&self, other: &FileLock) -> bool {
+        self.owner != other.owner
+            && core::mem::discriminant(&self.owner) == core::mem::discriminant(&other.owner)
+            && self.overlaps(other.start, other.end)
+            && (self.kind == LockKind::Exclusive || other.kind == LockKind::Exclusive)
+    }
+}
+
+struct LockTable {
+    locks: BTreeMap<LockKey, Vec<FileLock>>,
+    /// The lock request that each blocked owner is waiting for.
+    waiting: BTreeMap<LockOwner, (LockKey, FileLock)>,
+}
+
+impl LockTable {
+    const fn new() -> Self {
+        Self {
+            locks: BTreeMap::new(),
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a lock that conflicts with `lock`.
+    fn conflict(&self, key: &LockKey, lock: &FileLock) -> Option<FileLock> {
+        self.locks
+            .get(key)?
+            .iter()
+            .find(|l| l.conflicts(lock))
+            .copied()
+    }
+
+    /// Returns the owners of the locks that conflict with `lock`.
+    fn blockers(&self, key: &LockKey, lock: &FileLock) -> Vec<LockOwner> {
+        self.locks.get(key).map_or(Vec::new(), |locks| {
+            locks
+                .iter()
+                .filter(|l| l.conflicts(lock))
+                .map(|l| l.owner)
+                .collect()
+        })
+    }
+
+    /// Whether waiting for `lock` would deadlock, i.e., the owner of `lock`
+    /// can be reached by following the owners that block each other.
+    fn would_deadlock(&self, key: &LockKey, lock: &FileLock) -> bool {
+        let mut visited = Vec::new();
+        let mut pending = self.blockers(key, lock);
+        while let Some(owner) = pending.pop() {
+            if owner == lock.owner {
+                return true;
+            }
+            if visited.contains(&owner) {
+                continue;
+            }
+            visited.push(owner);
+            if let Some((key, lock)) = self.waiting.get(&owner) {
+                pending.extend(self.blockers(key, lock));
+            }
+        }
+        false
+    }
+
+    /// Removes the locks of `owner` in the range `start..end`. Locks that are
+    /// partially in the range are shrunk or split.
+    ///
+    /// Returns whether any lock is removed or shrunk.
+    fn remove(&mut self, key: &LockKey, owner: LockOwner, start: u64, end: u64) -> bool {
+        let Some(locks) = self.locks.get_mut(key) else {
+            return false;
+        };
+        let mut removed = false;
+        let mut remaining = Vec::with_capacity(locks.len());
+        for lock in locks.drain(..) {
+            if lock.owner != owner || !lock.overlaps(start, end) {
+                remaining.push(lock);
+                continue;
+            }
+            removed = true;
+            if lock.start < start {
+                remaining.push(FileLock { end: start, ..lock });
+            }
+            if end < lock.end {
+                remaining.push(FileLock { start: end, ..lock });
+            }
+        }
+        if remaining.is_empty() {
+            self.locks.remove(key);
+        } else {
+            *locks = remaining;
+        }
+        removed
+    }
+
+    /// Removes all locks of `owner` on all files.
+    ///
+    /// Returns whether any lock is removed.
+    fn remove_owner(&mut self, owner: LockOwner) -> bool {
+        let mut removed = false;
+        self.locks.retain(|_, locks| {
+            let len = locks.len();
+            locks.retain(|l| l.owner != owner);
+            removed |= locks.len() != len;
+            !locks.is_empty()
+        });
+        self.waiting.remove(&owner);
+        removed
+    }
+
+    /// Adds the lock, replaces the locks of the same owner in its range, and
+    /// merges the adjacent locks of the same owner and kind.
+    fn insert(&mut self, key: LockKey, mut lock: FileLock) -> bool {
+        let removed = self.remove(&key, lock.owner, lock.start, lock.end);
+        let locks = self.locks.entry(key).or_default();
+        locks.retain(|l| {
+            let adjacent = l.end == lock.start || lock.end == l.start;
+            if l.owner == lock.owner && l.kind == lock.kind && adjacent {
+                lock.start = lock.start.min(l.start);
+                lock.end = lock.end.max(l.end);
+                false
+            } else {
+                true
+            }
+        });
+        locks.push(lock);
+        removed
+    }
+}
+
+/// A handle to place and release advisory locks on an opened file, returned
+/// by [`File::lock_handle`](crate::fops::File::lock_handle).
+///
+/// It does not borrow the file, so that the file can still be used by others
+/// while waiting for a lock.
+#[derive(Debug, Clone)]
+pub struct LockHandle {
+    key: LockKey,
+    file_id: u64,
+    cap: Cap,
+}
+
+impl LockHandle {
+    pub(crate) fn new(key: LockKey, file_id: u64, cap: Cap) -> Self {
+        Self { key, file_id, cap }
+    }
+
+    /// Places a record lock on the byte range `start..end`, owned by the task
+    /// `owner`. Existing locks of the owner in the range are replaced.
+    ///
+    /// A shared lock requires the file to be opened for reading, and an
+    /// exclusive lock requires writing. If the lock conflicts with locks of
+    /// other tasks, it waits for them to be released if `wait` is true,
+    /// otherwise returns [`WouldBlock`](axerrno::AxError::WouldBlock).
+    pub fn lock_range(
+        &self,
+        owner: u64,
+        kind: LockKind,
+        start: u64,
+        end: u64,
+        wait: bool,
+    ) -> AxResult {
+        let cap = match kind {
+            LockKind::Shared => Cap::READ,
+            LockKind::Exclusive => Cap::WRITE,
+        };
+        if !self.cap.contains(cap) {
+            return ax_err!(PermissionDenied);
+        } else if start >= end {
+            return ax_err!(InvalidInput);
+        }
+        let lock = FileLock {
+            owner: LockOwner::Task(owner),
+            kind,
+            start,
+            end,
+        };
+        self::lock(&self.key, lock, wait)
+    }
+
+    /// Releases the record locks of the task `owner` on the byte range
+    /// `start..end`.
+    pub fn unlock_range(&self, owner: u64, start: u64, end: u64) {
+        unlock(&self.key, LockOwner::Task(owner), start, end);
+    }
+
+    /// Returns a lock that prevents the record lock from being placed by the
+    /// task `owner`, or `None` if it can be placed.
+    pub fn test_lock_range(
+        &self,
+        owner: u64,
+        kind: LockKind,
+        start: u64,
+        end: u64,
+    ) -> Option<FileLock> {
+        let lock = FileLock {
+            owner: LockOwner::Task(owner),
+            kind,
+            start,
+            end,
+        };
+        LOCKS.lock().conflict(&self.key, &lock)
+    }
+
+    /// Places a whole-file lock owned by the opened file, or converts the
+    /// existing one.
+    ///
+    /// If it conflicts with locks of other opened files, it waits for them
+    /// to be released if `wait` is true, otherwise returns
+    /// [`WouldBlock`](axerrno::AxError::WouldBlock). The lock is released
+    /// when the file is closed.
+    pub fn lock_file(&self, kind: LockKind, wait: bool) -> AxResult {
+        let lock = FileLock {
+            owner: LockOwner::File(self.file_id),
+            kind,
+            start: 0,
+            end: u64::MAX,
+        };
+        self::lock(&self.key, lock, wait)
+    }
+
+    /// Releases the whole-file lock owned by the opened file.
+    pub fn unlock_file(&self) {
+        unlock(&self.key, LockOwner::File(self.file_id), 0, u64::MAX);
+    }
+}
+
+/// Notifies waiting requests that some locks are released.
+fn notify_released() {
+    RELEASE_COUNT.fetch_add(1, Ordering::Release);
+    #[cfg(feature = "multitask")]
+    WAIT_QUEUE.notify_all(false);
+}
+
+/// Waits until some locks are released after `count` is read from
+/// [`RELEASE_COUNT`].
+fn wait_released(count: u64) {
+    #[cfg(feature = "multitask")]
+    WAIT_QUEUE.wait_until(|| RELEASE_COUNT.load(Ordering::Acquire) != count);
+    #[cfg(not(feature = "multitask"))]
+    while RELEASE_COUNT.load(Ordering::Acquire) == count {
+        axtask::yield_now();
+    }
+}
+
+/// Acquires the lock, or converts the existing locks of the same owner in its
+/// range.
+///
+/// If it conflicts with locks of other owners, waits for them to be released
+/// if `wait` is true, otherwise returns [`WouldBlock`](axerrno::AxError::WouldBlock).
+pub(crate) fn lock(key: &LockKey, lock: FileLock, wait: bool) -> AxResult {
+    loop {
+        let count = RELEASE_COUNT.load(Ordering::Acquire);
+        let mut table = LOCKS.lock();
+        if table.conflict(key, &lock).is_none() {
+            table.waiting.remove(&lock.owner);
+            // downgrading or shrinking may unblock others
+            if table.insert(*key, lock) {
+                drop(table);
+                notify_released();
+            }
+            return Ok(());
+        }
+        if !wait {
+            return ax_err!(WouldBlock);
+        }
+        if table.would_deadlock(key, &lock) {
+            table.waiting.remove(&lock.owner);
+            return ax_err!(Deadlock);
+        }
+        table.waiting.insert(lock.owner, (*key, lock));
+        drop(table);
+        wait_released(count);
+    }
+}
+
+/// Releases the locks of `owner` in the range `start..end`.
+pub(crate) fn unlock(key: &LockKey, owner: LockOwner, start: u64, end: u64) {
+    if LOCKS.lock().remove(key, owner, start, end) {
+        notify_released();
+    }
+}
+
+/// Releases all record locks of the task `owner` on all files.
+///
+/// It should be called when the task exits, as its locks are not released
+/// if it does not close the files.
+pub fn release_task_locks(owner: u64) {
+    if LOCKS.lock().remove_owner(LockOwner::Task(owner)) {
+        notify_released();
+    }
+}
@@ -0,0 +1,198 @@
+//! Loop devices, block devices backed by regular files.
+//!
+//! A filesystem image stored as a file can be attached as a loop device at
+//! runtime. Its partition table is scanned like other block devices, and the
+//! volumes on it can then be opened and mounted.
+
+use alloc::sync::{Arc, Weak};
+use alloc::{format, string::String, vec::Vec};
+use axdriver::prelude::*;
+use axerrno::{ax_err, ax_err_type, AxError, AxResult};
+use axfs_vfs::VfsOps;
+use axsync::Mutex;
+
+use crate::dev::{BlockDev, Disk, BLOCK_SIZE};
+use crate::fops::{File, OpenOptions};
+use crate::partition::{self, Volume};
+
+/// Volumes on all attached loop devices.
+static LOOP_VOLUMES: Mutex<Vec<Volume>> = Mutex::new(Vec::new());
+/// Number of attached loop devices, the index of the next one.
+static NUM_LOOPS: Mutex<usize> = Mutex::new(0);
+/// Filesystems opened on the loop volumes, with the names of the volumes.
+static VOLUME_FS: Mutex<Vec<(String, Weak<dyn VfsOps>)>> = Mutex::new(Vec::new());
+
+/// A block device that reads and writes a file.
+///
+/// The size of the device is the size of the file when attached, rounded
+/// down to blocks.
+pub(crate) struct LoopDevice {
+    file: File,
+    num_blocks: u64,
+    read_only: bool,
+}
+
+impl LoopDevice {
+    /// Creates a loop device on `file`, which must be opened for writing
+    /// unless `read_only` is true.
+    pub fn new(file: File, read_only: bool) -> AxResult<Self> {
+        let num_blocks = file.get_attr()?.size() / BLOCK_SIZE as u64;
+        if num_blocks == 0 {
+            return ax_err!(InvalidInput, "backing file is smaller than a block");
+        }
+        Ok(Self {
+            file,
+            num_blocks,
+            read_only,
+        })
+    }
+
+    fn offset_of(&self, block_id: u64, len: usize) -> DevResult<u64> {
+        let blocks = len.div_ceil(BLOCK_SIZE) as u64;
+        if block_id
+            .checked_add(blocks)
+            .is_some_and(|end| end <= self.num_blocks)
+        {
+            Ok(block_id * BLOCK_SIZE as u64)
+        } else {
+            Err(DevError::InvalidParam)
+        }
+    }
+}
+
+impl BaseDriverOps for LoopDevice {
+    fn device_name(&self) -> &str {
+        "loop"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl BlockDriverOps for LoopDevice {
+    fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn read_block(&mut self, block_id: u64, mut buf: &mut [u8]) -> DevResult {
+        let mut offset = self.offset_of(block_id, buf.len())?;
+        while !buf.is_empty() {
+            match self.file.read_at(offset, buf) {
+                Ok(0) => {
+                    // the file is truncated after attached
+                    buf.fill(0);
+                    break;
+                }
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(_) => return Err(DevError::Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, block_id: u64, mut buf: &[u8]) -> DevResult {
+        if self.read_only {
+            return Err(DevError::Unsupported);
+        }
+        let mut offset = self.offset_of(block_id, buf.len())?;
+        while !buf.is_empty() {
+            match self.file.write_at(offset, buf) {
+                Ok(0) => return Err(DevError::NoMemory),
+                Ok(n) => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+                Err(_) => return Err(DevError::Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> DevResult {
+        if self.read_only {
+            return Ok(());
+        }
+        match self.file.flush() {
+            // files on filesystems without storage (e.g. ramfs) can not be
+            // flushed, and have nothing to write
+            Ok(()) | Err(AxError::InvalidInput) => Ok(()),
+            Err(_) => Err(DevError::Io),
+        }
+    }
+}
+
+/// Attaches the file at `path` as a loop device, returns its name, e.g.
+/// `loop0`.
+///
+/// Its volumes are named `loop<n>` (no partition table) or `loop<n>p<part>`,
+/// and added to `/dev` with the same names.
+pub(crate) fn attach(path: &str, read_only: bool) -> AxResult<String> {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    opts.write(!read_only);
+    let dev = LoopDevice::new(File::open(path, &opts)?, read_only)?;
+
+    let mut num_loops = NUM_LOOPS.lock();
+    let loop_id = *num_loops;
+    let name = format!("loop{}", loop_id);
+    info!("  attach {:?} as {}", path, name);
+    let volumes = partition::scan_volumes(loop_id, &name, BlockDev::Loop(dev));
+    #[cfg(feature = "devfs")]
+    {
+        use crate::dev::DiskNode;
+        let whole = volumes[0].disk.whole_device();
+        crate::mounts::register_device(&name, Arc::new(DiskNode::new(whole)));
+        for vol in volumes.iter().filter(|vol| vol.partition.is_some()) {
+            let node = Arc::new(DiskNode::new(vol.disk.share()));
+            crate::mounts::register_device(&vol.name, node);
+        }
+    }
+    LOOP_VOLUMES.lock().extend(volumes);
+    *num_loops += 1;
+    Ok(name)
+}
+
+/// Returns a disk to access the volume `name` of an attached loop device.
+pub(crate) fn volume_disk(name: &str) -> AxResult<Disk> {
+    LOOP_VOLUMES
+        .lock()
+        .iter()
+        .find(|vol| vol.name == name)
+        .map(|vol| vol.disk.share())
+        .ok_or_else(|| ax_err_type!(NotFound, "loop volume not found"))
+}
+
+/// Returns a disk to access the volume `name` of an attached loop device,
+/// which fails with [`ResourceBusy`](AxError::ResourceBusy) if a filesystem
+/// on the volume is mounted.
+#[cfg(all(feature = "fatfs", not(feature = "myfs")))]
+pub(crate) fn unmounted_volume_disk(name: &str) -> AxResult<Disk> {
+    let disk = volume_disk(name)?;
+    let mut opened = VOLUME_FS.lock();
+    opened.retain(|(_, fs)| fs.strong_count() > 0);
+    let mounted = opened
+        .iter()
+        .filter(|(vol, _)| vol == name)
+        .filter_map(|(_, fs)| fs.upgrade())
+        .any(|fs| crate::root::is_mounted(&fs));
+    if mounted {
+        return ax_err!(ResourceBusy, "loop volume is mounted");
+    }
+    Ok(disk)
+}
+
+/// Opens the filesystem on the volume `name` of an attached loop device.
+pub(crate) fn volume_fs(name: &str) -> AxResult<Arc<dyn VfsOps>> {
+    let fs = crate::mounts::volume_fs(volume_disk(name)?)
+        .ok_or_else(|| ax_err_type!(Unsupported, "no supported filesystem"))?;
+    VOLUME_FS.lock().push((name.into(), Arc::downgrade(&fs)));
+    Ok(fs)
+}
@@ -1,55 +1,232 @@
 use alloc::sync::Arc;
+use axerrno::{ax_err, AxResult};
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 
-use crate::fs;
+use crate::{dev::Disk, fs};
 
+/// Creates a new filesystem to be mounted at runtime by the type name.
+pub(crate) fn new_fs(fs_type: &str) -> AxResult<Arc<dyn VfsOps>> {
+    match fs_type {
+        #[cfg(feature = "ramfs")]
+        "ramfs" | "tmpfs" => Ok(ramfs()),
+        #[cfg(feature = "devfs")]
+        "devfs" => Ok(devfs()),
+        #[cfg(feature = "9p")]
+        "9p" => ninep_fs(None),
+        _ => ax_err!(NotFound, "unknown filesystem type"),
+    }
+}
+
+/// Opens the filesystem on the root volume, ext2/ext4 is tried before FAT.
+#[cfg(not(feature = "myfs"))]
+pub(crate) fn rootfs(disk: Disk) -> Arc<dyn VfsOps> {
+    #[cfg(feature = "ext4")]
+    let disk = match fs::ext4fs::Ext4Disk::probe(disk) {
+        Ok(dev) => {
+            let ext_fs =
+                fs::ext4fs::Ext4FileSystem::new(dev).expect("failed to initialize ext4 filesystem");
+            return Arc::new(ext_fs);
+        }
+        Err(disk) => disk,
+    };
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "fatfs")] {
+            let fat_fs = Arc::new(fs::fatfs::FatFileSystem::new(disk));
+            fat_fs.init();
+            fat_fs
+        } else {
+            let _ = disk;
+            panic!("no supported filesystem found on the root volume");
+        }
+    }
+}
+
+/// Opens the filesystem on a disk volume other than the root, returns `None`
+/// if it does not contain a supported filesystem.
+#[allow(unused_variables)]
+pub(crate) fn volume_fs(disk: Disk) -> Option<Arc<dyn VfsOps>> {
+    #[cfg(all(feature = "ext4", not(feature = "myfs")))]
+    let disk = match fs::ext4fs::Ext4Disk::probe(disk) {
+        Ok(dev) => {
+            return match fs::ext4fs::Ext4FileSystem::new(dev) {
+                Ok(ext_fs) => Some(Arc::new(ext_fs)),
+                Err(e) => {
+                    warn!("failed to open ext4 filesystem: {:?}", e);
+                    None
+                }
+            };
+        }
+        Err(disk) => disk,
+    };
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "fatfs", not(feature = "myfs")))] {
+            let fat_fs = Arc::new(fs::fatfs::FatFileSystem::try_new(disk)?);
+            fat_fs.init();
+            Some(fat_fs)
+        } else {
+            None
+        }
+    }
+}
+
+/// The devfs mounted on `/dev`, where device nodes are registered.
+#[cfg(feature = "devfs")]
+pub(crate) static DEV_FS: lazy_init::LazyInit<Arc<fs::devfs::DeviceFileSystem>> =
+    lazy_init::LazyInit::new();
+
+/// Creates a devfs with `null` and `zero`.
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
     let zero = fs::devfs::ZeroDev;
-    let bar = fs::devfs::ZeroDev;
     let devfs = fs::devfs::DeviceFileSystem::new();
-    let foo_dir = devfs.mkdir("foo");
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
-    foo_dir.add("bar", Arc::new(bar));
     Arc::new(devfs)
 }
 
+/// Adds a node at `path` in the devfs on `/dev`, and creates the parent
+/// directories.
+#[cfg(feature = "devfs")]
+pub(crate) fn register_device(path: &str, node: axfs_vfs::VfsNodeRef) {
+    let path = path.trim_matches('/');
+    let (dir_path, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let dir = dir_path
+        .split('/')
+        .filter(|name| !name.is_empty())
+        .fold(DEV_FS.root_dir_node(), |dir, name| dir.mkdir(name));
+    dir.add(name, node);
+}
+
+/// Sessions on 9P transport devices, with their mount tags.
+#[cfg(feature = "9p")]
+static NINEP_CLIENTS: axsync::Mutex<
+    alloc::vec::Vec<(alloc::string::String, Arc<fs::ninep::NinePClient>)>,
+> = axsync::Mutex::new(alloc::vec::Vec::new());
+
+/// Starts a 9P session on each transport device.
+#[cfg(feature = "9p")]
+pub(crate) fn init_9p(mut devs: axdriver::AxDeviceContainer<axdriver::AxNinePDevice>) {
+    use axdriver::prelude::*;
+    use fs::ninep::{NinePClient, NinePDev};
+
+    while let Some(mut dev) = devs.take_one() {
+        // let other tasks run while waiting for responses
+        dev.set_wait_hooks(WaitHooks {
+            now: axhal::time::current_time,
+            relax: axtask::yield_now,
+        });
+        let tag = alloc::string::String::from(dev.mount_tag());
+        match NinePClient::new(NinePDev::new(dev)) {
+            Ok(client) => {
+                info!("  use 9P device {:?}, msize = {}", tag, client.msize());
+                NINEP_CLIENTS.lock().push((tag, Arc::new(client)));
+            }
+            Err(e) => warn!("  failed to start 9P session on {:?}: {:?}", tag, e),
+        }
+    }
+}
+
+/// Attaches to the filesystem shared by the 9P device with `mount_tag`, or
+/// the first device if it is `None`.
+#[cfg(feature = "9p")]
+pub(crate) fn ninep_fs(mount_tag: Option<&str>) -> AxResult<Arc<dyn VfsOps>> {
+    let client = NINEP_CLIENTS
+        .lock()
+        .iter()
+        .find(|(tag, _)| mount_tag.is_none() || mount_tag == Some(tag.as_str()))
+        .map(|(_, client)| client.clone());
+    let Some(client) = client else {
+        return ax_err!(NotFound, "9P device not found");
+    };
+    Ok(Arc::new(fs::ninep::NinePFileSystem::new(&client, "")?))
+}
+
 #[cfg(feature = "ramfs")]
 pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
-    Arc::new(fs::ramfs::RamFileSystem::new())
+    Arc::new(fs::ramfs::RamFileSystem::new(None, None))
+}
+
+/// Creates an overlay of the writable `upper` filesystem over `lower`.
+#[cfg(feature = "overlayfs")]
+pub(crate) fn overlayfs(
+    lower: Arc<dyn VfsOps>,
+    upper: Arc<dyn VfsOps>,
+) -> Arc<fs::overlayfs::OverlayFileSystem> {
+    Arc::new(fs::overlayfs::OverlayFileSystem::new(lower, upper))
+}
+
+/// The initramfs archive embedded at build time from the path in the
+/// `AX_INITRAMFS` environment variable, empty if it is not set.
+#[cfg(feature = "initramfs")]
+pub(crate) static INITRAMFS: &[u8] = include!(concat!(env!("OUT_DIR"), "/initramfs.rs"));
+
+/// Creates a ramfs with the contents of the newc-format cpio `archive`.
+#[cfg(feature = "initramfs")]
+pub(crate) fn initramfs(archive: &[u8]) -> Arc<fs::ramfs::RamFileSystem> {
+    let fs = fs::ramfs::RamFileSystem::from_cpio(archive).expect("failed to unpack initramfs");
+    Arc::new(fs)
+}
+
+#[cfg(feature = "procfs")]
+static PROC_FS: lazy_init::LazyInit<Arc<fs::procfs::ProcFileSystem>> = lazy_init::LazyInit::new();
+
+/// Creates the procfs, other modules can add more entries to it by
+/// [`proc_root`].
+#[cfg(feature = "procfs")]
+pub(crate) fn procfs() -> VfsResult<Arc<fs::procfs::ProcFileSystem>> {
+    use alloc::string::String;
+    use core::sync::atomic::{AtomicU8, Ordering};
+    use fs::procfs::{ProcFile, ProcFileSystem};
+
+    let procfs = ProcFileSystem::new();
+    let proc_root = procfs.root_dir_node();
+
+    // Create /proc/mounts
+    proc_root.add(
+        "mounts",
+        Arc::new(ProcFile::new(|| {
+            crate::root::mount_paths()
+                .iter()
+                .fold(String::new(), |mut s, path| {
+                    s += &alloc::format!("none {} auto rw 0 0\n", path);
+                    s
+                })
+        })),
+    );
+
+    // Create /proc/sys/vm/overcommit_memory, the mode is only remembered for
+    // applications that configure it, since memory is never overcommitted
+    static OVERCOMMIT_MEMORY: AtomicU8 = AtomicU8::new(0);
+    let vm_dir = proc_root.mkdir("sys").mkdir("vm");
+    vm_dir.add(
+        "overcommit_memory",
+        Arc::new(ProcFile::new_rw(
+            || alloc::format!("{}\n", OVERCOMMIT_MEMORY.load(Ordering::Relaxed)),
+            |value| match value.trim() {
+                mode @ ("0" | "1" | "2") => {
+                    OVERCOMMIT_MEMORY.store(mode.parse().unwrap(), Ordering::Relaxed);
+                    Ok(())
+                }
+                _ => Err(axfs_vfs::VfsError::InvalidInput),
+            },
+        )),
+    );
+
+    let procfs = Arc::new(procfs);
+    PROC_FS.init_by(procfs.clone());
+    Ok(procfs)
 }
 
+/// Returns the root directory of the procfs.
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let procfs = fs::ramfs::RamFileSystem::new();
-    let proc_root = procfs.root_dir();
-
-    // Create /proc/sys/net/core/somaxconn
-    proc_root.create("sys", VfsNodeType::Dir)?;
-    proc_root.create("sys/net", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core/somaxconn", VfsNodeType::File)?;
-    let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
-    file_somaxconn.write_at(0, b"4096\n")?;
-
-    // Create /proc/sys/vm/overcommit_memory
-    proc_root.create("sys/vm", VfsNodeType::Dir)?;
-    proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
-
-    // Create /proc/self/stat
-    proc_root.create("self", VfsNodeType::Dir)?;
-    proc_root.create("self/stat", VfsNodeType::File)?;
-
-    Ok(Arc::new(procfs))
+pub(crate) fn proc_root() -> Arc<fs::procfs::ProcDir> {
+    PROC_FS.root_dir_node()
 }
 
 #[cfg(feature = "sysfs")]
 pub(crate) fn sysfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let sysfs = fs::ramfs::RamFileSystem::new();
+    let sysfs = fs::ramfs::RamFileSystem::new(None, None);
     let sys_root = sysfs.root_dir();
 
     // Create /sys/kernel/mm/transparent_hugepage/enabled
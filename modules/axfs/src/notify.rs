@@ -0,0 +1,282 @@
+//! File change notification.
+//!
+//! A [`Watcher`] watches files and directories by their absolute paths, and
+//! receives events generated by operations in [`crate::root`] and
+//! [`crate::fops`]. Operations on paths relative to an opened
+//! [`Directory`](crate::fops::Directory) are not reported.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use axerrno::{ax_err, AxResult};
+use axsync::Mutex;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Maximum number of events queued in a watcher, further events are dropped
+/// and a [`WatchMask::Q_OVERFLOW`] event is queued instead.
+const MAX_QUEUED_EVENTS: usize = 1024;
+
+/// All watchers, to receive events by [`notify`].
+static WATCHERS: Mutex<Vec<Weak<Watcher>>> = Mutex::new(Vec::new());
+
+/// The cookie to connect the two events of a rename.
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+bitflags::bitflags! {
+    /// Kinds of events to watch, and of received events.
+    ///
+    /// The values are the same as Linux `inotify`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct WatchMask: u32 {
+        /// The file was written or truncated.
+        const MODIFY = 0x2;
+        /// The permission, owner or timestamps were changed.
+        const ATTRIB = 0x4;
+        /// A file was renamed from the watched directory.
+        const MOVED_FROM = 0x40;
+        /// A file was renamed into the watched directory.
+        const MOVED_TO = 0x80;
+        /// A file was created in the watched directory.
+        const CREATE = 0x100;
+        /// A file was removed from the watched directory.
+        const DELETE = 0x200;
+        /// The watched file itself was removed.
+        const DELETE_SELF = 0x400;
+        /// The watched file itself was renamed.
+        const MOVE_SELF = 0x800;
+
+        /// Events were dropped because the queue is full (event only).
+        const Q_OVERFLOW = 0x4000;
+        /// The watch was removed (event only).
+        const IGNORED = 0x8000;
+        /// The subject of the event is a directory (event only).
+        const IS_DIR = 0x4000_0000;
+
+        /// All events that can be watched.
+        const ALL_EVENTS = 0xfc6;
+    }
+}
+
+/// An event received by a [`Watcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    /// The watch descriptor returned by [`Watcher::add_watch`], -1 for
+    /// [`WatchMask::Q_OVERFLOW`].
+    pub wd: isize,
+    /// The kind of the event.
+    pub mask: WatchMask,
+    /// The same non-zero value for the [`MOVED_FROM`](WatchMask::MOVED_FROM)
+    /// and [`MOVED_TO`](WatchMask::MOVED_TO) events of a rename.
+    pub cookie: u32,
+    /// The name of the file in the watched directory, or `None` if the event
+    /// is about the watched file itself.
+    pub name: Option<String>,
+}
+
+struct Watch {
+    path: String,
+    mask: WatchMask,
+}
+
+#[derive(Default)]
+struct WatcherInner {
+    watches: BTreeMap<usize, Watch>,
+    next_wd: usize,
+    events: VecDeque<WatchEvent>,
+}
+
+/// A set of watches and the queue of events received from them.
+pub struct Watcher {
+    inner: Mutex<WatcherInner>,
+}
+
+impl Watcher {
+    /// Creates a new watcher without any watch.
+    pub fn new() -> Arc<Self> {
+        let watcher = Arc::new(Self {
+            inner: Mutex::new(WatcherInner {
+                next_wd: 1,
+                ..Default::default()
+            }),
+        });
+        let mut watchers = WATCHERS.lock();
+        watchers.retain(|w| w.strong_count() > 0);
+        watchers.push(Arc::downgrade(&watcher));
+        watcher
+    }
+
+    /// Watches the file or directory at `path` for events in `mask`, follows
+    /// the symbolic link. Returns the watch descriptor.
+    ///
+    /// If the path is already watched, the mask is replaced and the same
+    /// watch descriptor is returned.
+    pub fn add_watch(&self, path: &str, mask: WatchMask) -> AxResult<usize> {
+        let mask = mask & WatchMask::ALL_EVENTS;
+        if mask.is_empty() {
+            return ax_err!(InvalidInput);
+        }
+        crate::root::lookup(None, path)?;
+        let path = crate::root::event_path(None, path, true)?;
+
+        let mut inner = self.inner.lock();
+        if let Some((&wd, watch)) = inner.watches.iter_mut().find(|(_, w)| w.path == path) {
+            watch.mask = mask;
+            return Ok(wd);
+        }
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.insert(wd, Watch { path, mask });
+        Ok(wd)
+    }
+
+    /// Removes the watch, a [`WatchMask::IGNORED`] event is queued.
+    pub fn remove_watch(&self, wd: usize) -> AxResult {
+        let mut inner = self.inner.lock();
+        if inner.watches.remove(&wd).is_none() {
+            return ax_err!(InvalidInput);
+        }
+        inner.push(wd, WatchMask::IGNORED, 0, None);
+        Ok(())
+    }
+
+    /// Removes and returns the earliest queued event, or `None` if there is
+    /// no event.
+    pub fn pop_event(&self) -> Option<WatchEvent> {
+        self.inner.lock().events.pop_front()
+    }
+
+    /// Returns the earliest queued event without removing it.
+    pub fn peek_event(&self) -> Option<WatchEvent> {
+        self.inner.lock().events.front().cloned()
+    }
+
+    /// Whether there are queued events.
+    pub fn has_events(&self) -> bool {
+        !self.inner.lock().events.is_empty()
+    }
+}
+
+impl WatcherInner {
+    fn push(&mut self, wd: usize, mask: WatchMask, cookie: u32, name: Option<String>) {
+        let event = WatchEvent {
+            wd: wd as isize,
+            mask,
+            cookie,
+            name,
+        };
+        // merge with the same event at the end of the queue, like Linux
+        if self.events.back() == Some(&event) {
+            return;
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            if self.events.len() == MAX_QUEUED_EVENTS {
+                self.events.push_back(WatchEvent {
+                    wd: -1,
+                    mask: WatchMask::Q_OVERFLOW,
+                    cookie: 0,
+                    name: None,
+                });
+            }
+            return;
+        }
+        self.events.push_back(event);
+    }
+
+    /// Queues the event `mask` about the absolute `path`, for watches on the
+    /// path itself and on its parent directory.
+    fn notify(&mut self, path: &str, mask: WatchMask, cookie: u32) {
+        let (parent, name) = split_path(path);
+        let kind = mask - WatchMask::IS_DIR;
+        let self_kind = if kind == WatchMask::DELETE {
+            WatchMask::DELETE_SELF
+        } else if kind == WatchMask::MOVED_FROM {
+            WatchMask::MOVE_SELF
+        } else {
+            kind & (WatchMask::MODIFY | WatchMask::ATTRIB)
+        };
+
+        let mut events = Vec::new();
+        let mut removed = Vec::new();
+        for (&wd, watch) in self.watches.iter() {
+            if watch.path == parent && watch.mask.contains(kind) {
+                events.push((wd, mask, Some(name.to_string())));
+            }
+            if watch.path == path && !self_kind.is_empty() {
+                if watch.mask.contains(self_kind) {
+                    events.push((wd, self_kind | (mask & WatchMask::IS_DIR), None));
+                }
+                if self_kind == WatchMask::DELETE_SELF {
+                    removed.push(wd);
+                }
+            }
+        }
+        for (wd, mask, name) in events {
+            self.push(wd, mask, cookie, name);
+        }
+        for wd in removed {
+            self.watches.remove(&wd);
+            self.push(wd, WatchMask::IGNORED, 0, None);
+        }
+    }
+
+    /// Updates the paths of watches on `old` and its descendants after it is
+    /// renamed to `new`.
+    fn rename(&mut self, old: &str, new: &str) {
+        for watch in self.watches.values_mut() {
+            if let Some(rest) = watch.path.strip_prefix(old) {
+                if rest.is_empty() || rest.starts_with('/') {
+                    watch.path = String::from(new) + rest;
+                }
+            }
+        }
+    }
+}
+
+/// Splits an absolute path into the parent directory and the file name.
+fn split_path(path: &str) -> (&str, &str) {
+    match path.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => ("", path),
+    }
+}
+
+fn watchers() -> Vec<Arc<Watcher>> {
+    WATCHERS.lock().iter().filter_map(Weak::upgrade).collect()
+}
+
+/// Reports the event `mask` about the file at the absolute `path` to all
+/// watchers.
+pub(crate) fn notify(path: &str, mask: WatchMask) {
+    for watcher in watchers() {
+        watcher.inner.lock().notify(path, mask, 0);
+    }
+}
+
+/// Reports the rename from `old` to `new` (both absolute paths) to all
+/// watchers.
+pub(crate) fn notify_rename(old: &str, new: &str, is_dir: bool) {
+    let watchers = watchers();
+    if watchers.is_empty() {
+        return;
+    }
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    let dir_flag = if is_dir {
+        WatchMask::IS_DIR
+    } else {
+        WatchMask::empty()
+    };
+    for watcher in watchers {
+        let mut inner = watcher.inner.lock();
+        inner.notify(old, WatchMask::MOVED_FROM | dir_flag, cookie);
+        inner.notify(new, WatchMask::MOVED_TO | dir_flag, cookie);
+        inner.rename(old, new);
+    }
+}
+
+/// Whether there are any watchers, so that the paths of events need not be
+/// computed if there are not.
+pub(crate) fn has_watchers() -> bool {
+    WATCHERS.lock().iter().any(|w| w.strong_count() > 0)
+}
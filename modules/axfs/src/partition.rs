@@ -0,0 +1,326 @@
+//! Parsing of MBR and GPT partition tables.
+//!
+//! Every block device is split into volumes: one per partition, or a single
+//! one covering the whole device if it has no partition table. Each volume is
+//! accessed through its own [`Disk`].
+
+use alloc::{format, string::String, vec, vec::Vec};
+use axdriver::prelude::*;
+
+use crate::cache::BlockCache;
+use crate::dev::{BlockDev, Disk, BLOCK_SIZE};
+
+/// Partition type of the protective MBR entry of a GPT disk.
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xee;
+/// Partition types of MBR extended partitions.
+const MBR_TYPES_EXTENDED: [u8; 3] = [0x05, 0x0f, 0x85];
+/// Maximum number of logical partitions in an extended partition.
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// Maximum number of GPT entries to scan.
+const MAX_GPT_ENTRIES: u32 = 256;
+
+/// A partition in a partition table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Partition {
+    /// Partition number, starts from 1. Logical partitions of MBR start
+    /// from 5.
+    pub number: usize,
+    /// The first block of the partition.
+    pub start_block: u64,
+    /// Number of blocks in the partition.
+    pub num_blocks: u64,
+    /// Partition name, only available for GPT.
+    pub label: Option<String>,
+    /// Unique partition GUID for GPT, or `<disk signature>-<number>` for MBR
+    /// (the same as `PARTUUID` on Linux).
+    pub uuid: String,
+}
+
+/// A volume that can contain a filesystem.
+pub(crate) struct Volume {
+    /// Name of the volume, e.g. `disk0p1`, `disk1` (the whole disk) or
+    /// `loop0p1`.
+    pub name: String,
+    /// Index of the block device.
+    pub disk_id: usize,
+    /// The partition, or `None` if the volume is the whole disk.
+    pub partition: Option<Partition>,
+    /// The disk to access the volume.
+    pub disk: Disk,
+}
+
+impl Volume {
+    /// Whether the volume matches the root volume specifier, which can be:
+    ///
+    /// - `<disk>:<part>`: partition `<part>` of the block device `<disk>`,
+    ///   `<part>` is 0 for an unpartitioned disk.
+    /// - `LABEL=<name>`: the GPT partition with the given name.
+    /// - `UUID=<guid>`: the partition with the given unique GUID (or the
+    ///   Linux `PARTUUID` for MBR), case insensitive.
+    pub fn matches(&self, spec: &str) -> bool {
+        if let Some(label) = spec.strip_prefix("LABEL=") {
+            self.partition
+                .as_ref()
+                .is_some_and(|p| p.label.as_deref() == Some(label))
+        } else if let Some(uuid) = spec.strip_prefix("UUID=") {
+            self.partition
+                .as_ref()
+                .is_some_and(|p| p.uuid.eq_ignore_ascii_case(uuid))
+        } else if let Some((disk, part)) = spec.split_once(':') {
+            let number = self.partition.as_ref().map_or(0, |p| p.number);
+            disk.parse() == Ok(self.disk_id) && part.parse() == Ok(number)
+        } else {
+            false
+        }
+    }
+}
+
+/// Name of the block device node in `/dev`, e.g. `vda` for disk 0 and `vdab`
+/// for disk 27. The partition number is appended for partitions.
+pub(crate) fn dev_name(disk_id: usize, partition: Option<&Partition>) -> String {
+    let mut letters = Vec::new();
+    let mut id = disk_id;
+    loop {
+        letters.push(b'a' + (id % 26) as u8);
+        if id < 26 {
+            break;
+        }
+        id = id / 26 - 1;
+    }
+    letters.reverse();
+    let name = format!("vd{}", core::str::from_utf8(&letters).unwrap());
+    match partition {
+        Some(p) => format!("{}{}", name, p.number),
+        None => name,
+    }
+}
+
+/// Splits the block device into volumes by its partition table, the volumes
+/// are named `<disk_name>` (the whole disk) or `<disk_name>p<part>`.
+pub(crate) fn scan_volumes(disk_id: usize, disk_name: &str, mut dev: BlockDev) -> Vec<Volume> {
+    assert_eq!(BLOCK_SIZE, dev.block_size());
+    let num_blocks = dev.num_blocks();
+    let partitions = match scan_partitions(&mut dev) {
+        Ok(partitions) => partitions,
+        Err(e) => {
+            warn!("failed to read partition table of {}: {:?}", disk_name, e);
+            Vec::new()
+        }
+    };
+
+    // loop devices are cached as well, so that their blocks are written back
+    // in the same order as other disks, e.g. the FAT before directory entries
+    let dev = BlockCache::new(dev, axconfig::BLOCK_CACHE_SIZE);
+    if partitions.is_empty() {
+        info!("  {}: {} blocks, no partition table", disk_name, num_blocks);
+        return vec![Volume {
+            name: disk_name.into(),
+            disk_id,
+            partition: None,
+            disk: Disk::new_shared(dev, 0, num_blocks),
+        }];
+    }
+    partitions
+        .into_iter()
+        .map(|p| {
+            let name = format!("{}p{}", disk_name, p.number);
+            info!(
+                "  {}: blocks {:#x}..{:#x}, label {:?}, uuid {}",
+                name,
+                p.start_block,
+                p.start_block + p.num_blocks,
+                p.label,
+                p.uuid
+            );
+            Volume {
+                name,
+                disk_id,
+                disk: Disk::new_shared(dev.clone(), p.start_block, p.num_blocks),
+                partition: Some(p),
+            }
+        })
+        .collect()
+}
+
+/// Reads the partition table of the block device.
+///
+/// Returns an empty list if there is no partition table. CRCs of GPT are not
+/// verified.
+fn scan_partitions(dev: &mut BlockDev) -> DevResult<Vec<Partition>> {
+    let mut mbr = [0u8; BLOCK_SIZE];
+    dev.read_block(0, &mut mbr)?;
+    let Some(entries) = parse_mbr(&mbr, dev.num_blocks()) else {
+        return Ok(Vec::new());
+    };
+    if entries.iter().any(|e| e.ty == MBR_TYPE_GPT_PROTECTIVE) {
+        return scan_gpt(dev);
+    }
+
+    let signature = read_u32(&mbr, 440);
+    let mut partitions = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if MBR_TYPES_EXTENDED.contains(&entry.ty) {
+            scan_logical(dev, entry.start, signature, &mut partitions)?;
+        } else if entry.ty != 0 {
+            partitions.push(mbr_partition(i + 1, entry.start, entry.len, signature));
+        }
+    }
+    partitions.sort_by_key(|p| p.number);
+    Ok(partitions)
+}
+
+#[derive(Clone, Copy)]
+struct MbrEntry {
+    ty: u8,
+    start: u64,
+    len: u64,
+}
+
+/// Parses the 4 primary entries of an MBR (or EBR), returns `None` if the
+/// sector is not a valid one.
+///
+/// A FAT boot sector also ends with `0x55aa`, but its status bytes are
+/// usually boot code, which can be told apart.
+fn parse_mbr(sector: &[u8; BLOCK_SIZE], num_blocks: u64) -> Option<[MbrEntry; 4]> {
+    if sector[510..] != [0x55, 0xaa] {
+        return None;
+    }
+    let mut entries = [MbrEntry {
+        ty: 0,
+        start: 0,
+        len: 0,
+    }; 4];
+    for (i, entry) in entries.iter_mut().enumerate() {
+        let raw = &sector[446 + i * 16..446 + (i + 1) * 16];
+        if raw[0] != 0 && raw[0] != 0x80 {
+            return None;
+        }
+        entry.ty = raw[4];
+        entry.start = read_u32(raw, 8) as u64;
+        entry.len = read_u32(raw, 12) as u64;
+        if entry.ty != 0 && entry.ty != MBR_TYPE_GPT_PROTECTIVE {
+            let end = entry.start.checked_add(entry.len)?;
+            if entry.start == 0 || entry.len == 0 || end > num_blocks {
+                return None;
+            }
+        }
+    }
+    if entries.iter().all(|e| e.ty == 0) {
+        return None;
+    }
+    Some(entries)
+}
+
+/// Follows the EBR chain of an extended partition.
+fn scan_logical(
+    dev: &mut BlockDev,
+    ext_start: u64,
+    signature: u32,
+    partitions: &mut Vec<Partition>,
+) -> DevResult {
+    let mut ebr = [0u8; BLOCK_SIZE];
+    let mut ebr_block = ext_start;
+    for number in 5..5 + MAX_LOGICAL_PARTITIONS {
+        dev.read_block(ebr_block, &mut ebr)?;
+        let Some(entries) = parse_mbr(&ebr, dev.num_blocks()) else {
+            break;
+        };
+        // the first entry is relative to the EBR, and the second one (the
+        // next EBR) is relative to the extended partition
+        if entries[0].ty != 0 {
+            let start = ebr_block + entries[0].start;
+            partitions.push(mbr_partition(number, start, entries[0].len, signature));
+        }
+        if entries[1].ty == 0 {
+            break;
+        }
+        ebr_block = ext_start + entries[1].start;
+    }
+    Ok(())
+}
+
+fn mbr_partition(number: usize, start_block: u64, num_blocks: u64, signature: u32) -> Partition {
+    Partition {
+        number,
+        start_block,
+        num_blocks,
+        label: None,
+        uuid: format!("{:08x}-{:02x}", signature, number),
+    }
+}
+
+fn scan_gpt(dev: &mut BlockDev) -> DevResult<Vec<Partition>> {
+    let mut header = [0u8; BLOCK_SIZE];
+    dev.read_block(1, &mut header)?;
+    if &header[..8] != GPT_SIGNATURE {
+        warn!("invalid GPT header");
+        return Ok(Vec::new());
+    }
+    let entries_block = read_u64(&header, 72);
+    let num_entries = read_u32(&header, 80).min(MAX_GPT_ENTRIES) as usize;
+    let entry_size = read_u32(&header, 84) as usize;
+    if entry_size < 128 || entry_size > BLOCK_SIZE || BLOCK_SIZE % entry_size != 0 {
+        warn!("invalid GPT entry size: {}", entry_size);
+        return Ok(Vec::new());
+    }
+
+    let entries_per_block = BLOCK_SIZE / entry_size;
+    let mut block = [0u8; BLOCK_SIZE];
+    let mut partitions = Vec::new();
+    for i in 0..num_entries {
+        if i % entries_per_block == 0 {
+            dev.read_block(entries_block + (i / entries_per_block) as u64, &mut block)?;
+        }
+        let raw = &block[(i % entries_per_block) * entry_size..][..entry_size];
+        if raw[..16].iter().all(|&b| b == 0) {
+            continue; // unused entry
+        }
+        let first = read_u64(raw, 32);
+        let last = read_u64(raw, 40);
+        if last < first || last >= dev.num_blocks() {
+            warn!("invalid GPT entry {}: blocks {:#x}..={:#x}", i, first, last);
+            continue;
+        }
+        let name: Vec<u16> = raw[56..128]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        partitions.push(Partition {
+            number: i + 1,
+            start_block: first,
+            num_blocks: last - first + 1,
+            label: Some(String::from_utf16_lossy(&name)),
+            uuid: format_guid(&raw[16..32]),
+        });
+    }
+    Ok(partitions)
+}
+
+/// Formats a mixed-endian GUID, e.g. `c12a7328-f81f-11d2-ba4b-00a0c93ec93b`.
+fn format_guid(raw: &[u8]) -> String {
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        read_u32(raw, 0),
+        u16::from_le_bytes([raw[4], raw[5]]),
+        u16::from_le_bytes([raw[6], raw[7]]),
+        raw[8],
+        raw[9],
+        raw[10],
+        raw[11],
+        raw[12],
+        raw[13],
+        raw[14],
+        raw[15]
+    )
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
@@ -1,33 +1,57 @@
 //! Root directory of the filesystem
-//!
-//! TODO: it doesn't work very well if the mount points have containment relationships.
 
-use alloc::{string::String, sync::Arc, vec::Vec};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
 use axerrno::{ax_err, AxError, AxResult};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axsync::Mutex;
+use capability::Cap;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_init::LazyInit;
 
-use crate::{api::FileType, fs, mounts};
+use crate::fops::{self, FileSetAttr, FileSystemInfo};
+use crate::notify::{self, WatchMask};
+use crate::{api::FileType, mounts};
+
+/// Maximum number of symbolic links that can be followed in a path resolution.
+const MAX_SYMLINK_FOLLOWS: usize = 40;
 
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
 static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
+/// Serializes renames, so that the destination checked by one cannot be
+/// replaced by another before it is renamed.
+static RENAME_LOCK: Mutex<()> = Mutex::new(());
 
 struct MountPoint {
-    path: &'static str,
+    path: String,
     fs: Arc<dyn VfsOps>,
+    /// The number of [`MountRef`]s to it.
+    refs: AtomicUsize,
 }
 
+/// A reference to a mount point, held by opened files and directories to
+/// keep the filesystem from being unmounted.
+pub(crate) struct MountRef(Arc<MountPoint>);
+
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    mounts: Mutex<Vec<Arc<MountPoint>>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: String, fs: Arc<dyn VfsOps>) -> Self {
+        Self {
+            path,
+            fs,
+            refs: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether `path` (without the leading '/') is in this mounted filesystem.
+    fn covers(&self, path: &str) -> bool {
+        path.strip_prefix(&self.path[1..])
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
     }
 }
 
@@ -37,37 +61,135 @@ impl Drop for MountPoint {
     }
 }
 
+impl MountRef {
+    fn new(mp: &Arc<MountPoint>) -> Self {
+        mp.refs.fetch_add(1, Ordering::Relaxed);
+        Self(mp.clone())
+    }
+}
+
+impl Clone for MountRef {
+    fn clone(&self) -> Self {
+        Self::new(&self.0)
+    }
+}
+
+impl Drop for MountRef {
+    fn drop(&mut self) {
+        self.0.refs.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Finds the mount point in `mounts` that has the longest match with `path`
+/// (without the leading '/').
+fn find_mount_in<'a>(mounts: &'a [Arc<MountPoint>], path: &str) -> Option<&'a Arc<MountPoint>> {
+    // TODO: more efficient, e.g. trie
+    mounts
+        .iter()
+        .filter(|mp| mp.covers(path))
+        .max_by_key(|mp| mp.path.len())
+}
+
 impl RootDirectory {
     pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    /// Mounts `fs` at the canonical absolute `path`, which can be inside
+    /// another mounted filesystem.
+    pub fn mount(&self, path: &str, fs: Arc<dyn VfsOps>) -> AxResult {
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
         if !path.starts_with('/') {
             return ax_err!(InvalidInput, "mount path must start with '/'");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        // hold the lock until the filesystem is added, so that no other one
+        // can be mounted at the same path meanwhile
+        let mut mounts = self.mounts.lock();
+        if mounts.iter().any(|mp| mp.path == path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        // create the mount point in the filesystem it belongs to if it does
+        // not exist
+        let rel_path = path.trim_matches('/');
+        let (parent_fs, rest_path) = match find_mount_in(&mounts, rel_path) {
+            Some(mp) => (mp.fs.clone(), &rel_path[mp.path.len() - 1..]),
+            None => (self.main_fs.clone(), rel_path),
+        };
+        let root = parent_fs.root_dir();
+        let mount_point = match root.clone().lookup(rest_path) {
+            Err(AxError::NotFound) => {
+                root.create(rest_path, FileType::Dir)?;
+                root.lookup(rest_path)?
+            }
+            res => res?,
+        };
+        if !mount_point.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        fs.mount(path, mount_point)?;
+        mounts.push(Arc::new(MountPoint::new(path.into(), fs)));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    /// Unmounts the filesystem mounted at the canonical absolute `path`.
+    ///
+    /// Fails with [`AxError::ResourceBusy`] if there are opened files in it,
+    /// other filesystems mounted inside it, or the current directory is in it.
+    pub fn umount(&self, path: &str) -> AxResult {
+        let mut mounts = self.mounts.lock();
+        let Some(idx) = mounts.iter().position(|mp| mp.path == path) else {
+            return ax_err!(InvalidInput, "not a mount point");
+        };
+        let mp = &mounts[idx];
+        let busy = mp.refs.load(Ordering::Relaxed) > 0
+            || mounts
+                .iter()
+                .any(|other| other.path != mp.path && mp.covers(&other.path[1..]))
+            || mp.covers(CURRENT_DIR_PATH.lock().trim_matches('/'));
+        if busy {
+            return ax_err!(ResourceBusy);
+        }
+        mounts.remove(idx); // `VfsOps::umount` is called on drop
+        Ok(())
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        self.mounts.lock().iter().any(|mp| mp.path == path)
+    }
+
+    /// Returns the filesystem mounted at the canonical absolute `path`.
+    pub fn mounted_fs(&self, path: &str) -> AxResult<Arc<dyn VfsOps>> {
+        if path == "/" {
+            return Ok(self.main_fs.clone());
+        }
+        match self.mounts.lock().iter().find(|mp| mp.path == path) {
+            Some(mp) => Ok(mp.fs.clone()),
+            None => ax_err!(InvalidInput, "not a mount point"),
+        }
+    }
+
+    /// Returns the paths of all mount points, including the root.
+    pub fn mount_paths(&self) -> Vec<String> {
+        let mut paths = vec![String::from("/")];
+        paths.extend(self.mounts.lock().iter().map(|mp| mp.path.clone()));
+        paths
+    }
+
+    /// Finds the mount point that has the longest match with `path` (without
+    /// the leading '/').
+    fn find_mount(&self, path: &str) -> Option<Arc<MountPoint>> {
+        find_mount_in(&self.mounts.lock(), path).cloned()
+    }
+
+    /// Returns a reference to the mount point that `path` (without the
+    /// leading '/') is in, taken before it can be unmounted.
+    fn mount_ref(&self, path: &str) -> Option<MountRef> {
+        find_mount_in(&self.mounts.lock(), path).map(MountRef::new)
     }
 
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
@@ -80,23 +202,10 @@ impl RootDirectory {
             return self.lookup_mounted_fs(rest, f);
         }
 
-        let mut idx = 0;
-        let mut max_len = 0;
-
-        // Find the filesystem that has the longest mounted path match
-        // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
-                max_len = mp.path.len() - 1;
-                idx = i;
-            }
-        }
-
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
-        } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
+        match self.find_mount(path) {
+            // skip the first '/' of the mount path
+            Some(mp) => f(mp.fs.clone(), &path[mp.path.len() - 1..]),
+            None => f(self.main_fs.clone(), path), // not matched any mount point
         }
     }
 }
@@ -132,40 +241,79 @@ impl VfsNodeOps for RootDirectory {
         })
     }
 
-    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        self.lookup_mounted_fs(path, |fs, rest_path| {
             if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot rename mount points
+                ax_err!(AlreadyExists) // mount points already exist
             } else {
-                fs.root_dir().rename(rest_path, dst_path)
+                fs.root_dir().symlink(rest_path, target)
             }
         })
     }
+
+    fn link(&self, path: &str, node: &VfsNodeRef) -> VfsResult {
+        self.lookup_mounted_fs(path, |fs, rest_path| {
+            if rest_path.is_empty() {
+                ax_err!(AlreadyExists) // mount points already exist
+            } else {
+                fs.root_dir().link(rest_path, node)
+            }
+        })
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        self.lookup_mounted_fs(src_path, |fs, src_rest| {
+            self.lookup_mounted_fs(dst_path, |dst_fs, dst_rest| {
+                if src_rest.is_empty() || dst_rest.is_empty() {
+                    ax_err!(PermissionDenied) // cannot rename mount points
+                } else if !core::ptr::addr_eq(Arc::as_ptr(&fs), Arc::as_ptr(&dst_fs)) {
+                    ax_err!(CrossesDevices)
+                } else {
+                    fs.root_dir().rename(src_rest, dst_rest)
+                }
+            })
+        })
+    }
 }
 
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
-            let main_fs = fs::myfs::new_myfs(disk);
-        } else if #[cfg(feature = "fatfs")] {
-            static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
-            FAT_FS.init_by(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
-            FAT_FS.init();
-            let main_fs = FAT_FS.clone();
+            let main_fs = crate::fs::myfs::new_myfs(disk);
+        } else {
+            let main_fs = mounts::rootfs(disk);
         }
     }
+    init_root_dir(main_fs);
+}
 
-    let mut root_dir = RootDirectory::new(main_fs);
+/// Initializes the root directory with `main_fs` mounted on `/`, and other
+/// filesystems mounted on it.
+pub(crate) fn init_root_dir(main_fs: Arc<dyn VfsOps>) {
+    let root_dir = RootDirectory::new(main_fs);
 
     #[cfg(feature = "devfs")]
-    root_dir
-        .mount("/dev", mounts::devfs())
-        .expect("failed to mount devfs at /dev");
+    {
+        let devfs = mounts::devfs();
+        mounts::DEV_FS.init_by(devfs.clone());
+        root_dir
+            .mount("/dev", devfs)
+            .expect("failed to mount devfs at /dev");
+    }
 
     #[cfg(feature = "ramfs")]
-    root_dir
-        .mount("/tmp", mounts::ramfs())
-        .expect("failed to mount ramfs at /tmp");
+    {
+        let ramfs = mounts::ramfs();
+        // anyone can create files in `/tmp`, but only remove their own
+        let perm = VfsNodePerm::from_bits_truncate(0o1777);
+        ramfs
+            .root_dir()
+            .set_attr(FileSetAttr::new().with_perm(perm))
+            .ok();
+        root_dir
+            .mount("/tmp", ramfs)
+            .expect("failed to mount ramfs at /tmp");
+    }
 
     // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
@@ -192,6 +340,42 @@ fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     }
 }
 
+/// Returns the directory that contains the entry at `path`, which is
+/// relative to `base` and has no symbolic links (see [`resolve_path`]).
+fn parent_dir_of(base: &VfsNodeRef, path: &str) -> AxResult<VfsNodeRef> {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some((dir, _)) if !dir.is_empty() => base.clone().lookup(dir),
+        _ => Ok(base.clone()),
+    }
+}
+
+/// Checks that the current task can add entries to the directory `dir`, i.e.
+/// it has write and search permissions on it.
+fn check_dir_writable(dir: &VfsNodeRef) -> AxResult {
+    if fops::perm_to_cap(&dir.get_attr()?).contains(Cap::WRITE | Cap::EXECUTE) {
+        Ok(())
+    } else {
+        ax_err!(PermissionDenied)
+    }
+}
+
+/// Checks that the current task can remove or rename the entry `node` of the
+/// directory `dir`.
+///
+/// It needs write and search permissions on `dir`, and if `dir` is sticky, it
+/// must own either `dir` or `node`.
+fn check_removable(dir: &VfsNodeRef, node: &VfsNodeRef) -> AxResult {
+    check_dir_writable(dir)?;
+    let dir_attr = dir.get_attr()?;
+    if dir_attr.perm().contains(VfsNodePerm::STICKY) {
+        let cred = fops::current_credential();
+        if !cred.is_root() && cred.uid != dir_attr.uid() && cred.uid != node.get_attr()?.uid() {
+            return ax_err!(PermissionDenied);
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     if path.starts_with('/') {
         Ok(axfs_vfs::path::canonicalize(path))
@@ -201,11 +385,142 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
+/// Reads the target path of the symbolic link `node`.
+fn read_symlink(node: &VfsNodeRef) -> AxResult<String> {
+    let mut buf = vec![0; node.get_attr()?.size() as usize];
+    let len = node.readlink(&mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Resolves the symbolic links in `path`, the last component is followed only
+/// if `follow_last` is true.
+///
+/// Returns the directory node and a path relative to it without symbolic links.
+/// The last component may not exist.
+fn resolve_path(
+    dir: Option<&VfsNodeRef>,
+    path: &str,
+    follow_last: bool,
+) -> AxResult<(VfsNodeRef, String)> {
+    let mut base = parent_node_of(dir, path);
+    let mut resolved = String::from(if path.starts_with('/') { "/" } else { "" });
+    let mut rest = String::from(path);
+    let mut follows = 0;
+    loop {
+        let trimmed = rest.trim_start_matches('/');
+        if trimmed.is_empty() {
+            break;
+        }
+        let (name, remaining) = trimmed.split_once('/').unwrap_or((trimmed, ""));
+        let is_last = remaining.trim_matches('/').is_empty() && !path.ends_with('/');
+
+        let mut candidate = resolved.clone();
+        if !candidate.is_empty() && !candidate.ends_with('/') {
+            candidate.push('/');
+        }
+        candidate.push_str(name);
+        if name == "." || name == ".." {
+            resolved = candidate;
+            rest = remaining.into();
+            continue;
+        }
+
+        let node = match base.clone().lookup(&candidate) {
+            Ok(node) => node,
+            Err(AxError::NotFound) if remaining.trim_matches('/').is_empty() => {
+                resolved = candidate; // the last component to be created
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        if !node.get_attr()?.is_symlink() || (is_last && !follow_last) {
+            resolved = candidate;
+            rest = remaining.into();
+            continue;
+        }
+
+        follows += 1;
+        if follows > MAX_SYMLINK_FOLLOWS {
+            return ax_err!(FilesystemLoop);
+        }
+        let target = read_symlink(&node)?;
+        if target.is_empty() {
+            return ax_err!(NotFound);
+        }
+        if target.starts_with('/') {
+            base = ROOT_DIR.clone();
+            resolved = "/".into();
+        }
+        rest = target + "/" + remaining;
+    }
+    if path.ends_with('/') && !resolved.ends_with('/') {
+        resolved.push('/');
+    }
+    if resolved.is_empty() {
+        resolved.push('.');
+    }
+    Ok((base, resolved))
+}
+
+/// Returns the absolute path of `path` with symbolic links resolved, which is
+/// matched against the paths of watches.
+///
+/// Fails if `path` is relative to `dir`, since the path of `dir` is unknown.
+pub(crate) fn event_path(
+    dir: Option<&VfsNodeRef>,
+    path: &str,
+    follow_last: bool,
+) -> AxResult<String> {
+    let (_, resolved) = resolve_path(dir, path, follow_last)?;
+    if dir.is_some() && !resolved.starts_with('/') {
+        return ax_err!(Unsupported);
+    }
+    absolute_path(&resolved)
+}
+
+/// Returns the event path of the file opened at the absolute `path`, or
+/// `None` if the path no longer refers to the file with the inode number
+/// `ino`, e.g. the file is renamed or replaced.
+pub(crate) fn opened_event_path(path: &str, ino: u64) -> Option<String> {
+    let (base, resolved) = resolve_path(None, path, true).ok()?;
+    let node = base.lookup(&resolved).ok()?;
+    if node.get_attr().ok()?.ino() != ino {
+        return None;
+    }
+    absolute_path(&resolved).ok()
+}
+
+/// Reports the event `mask` about `path` to watchers, if there are any.
+fn notify_at(dir: Option<&VfsNodeRef>, path: &str, mask: WatchMask) {
+    if notify::has_watchers() {
+        if let Ok(path) = event_path(dir, path, false) {
+            notify::notify(&path, mask);
+        }
+    }
+}
+
 pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    let (base, path) = resolve_path(dir, path, true)?;
+    let node = base.lookup(&path)?;
+    if path.ends_with('/') && !node.get_attr()?.is_dir() {
+        ax_err!(NotADirectory)
+    } else {
+        Ok(node)
+    }
+}
+
+/// Like [`lookup`], but does not follow the last component if it is a
+/// symbolic link.
+pub(crate) fn lookup_no_follow(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let (base, path) = resolve_path(dir, path, false)?;
+    let node = base.lookup(&path)?;
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -219,28 +534,41 @@ pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsN
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
-    let parent = parent_node_of(dir, path);
-    parent.create(path, VfsNodeType::File)?;
-    parent.lookup(path)
+    let (parent, rel_path) = resolve_path(dir, path, true)?;
+    check_dir_writable(&parent_dir_of(&parent, &rel_path)?)?;
+    parent.create(&rel_path, VfsNodeType::File)?;
+    let node = parent.lookup(&rel_path)?;
+    fops::set_new_node_owner(&node);
+    notify_at(dir, path, WatchMask::CREATE);
+    Ok(node)
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
+    match lookup_no_follow(dir, path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            let (parent, rel_path) = resolve_path(dir, path, false)?;
+            check_dir_writable(&parent_dir_of(&parent, &rel_path)?)?;
+            parent.create(&rel_path, VfsNodeType::Dir)?;
+            fops::set_new_node_owner(&parent.lookup(&rel_path)?);
+            notify_at(dir, path, WatchMask::CREATE | WatchMask::IS_DIR);
+            Ok(())
+        }
         Err(e) => Err(e),
     }
 }
 
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
+    let node = lookup_no_follow(dir, path)?;
     let attr = node.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
-    } else if !attr.perm().owner_writable() {
-        ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        let (parent, rel_path) = resolve_path(dir, path, false)?;
+        check_removable(&parent_dir_of(&parent, &rel_path)?, &node)?;
+        parent.remove(&rel_path)?;
+        notify_at(dir, path, WatchMask::DELETE);
+        Ok(())
     }
 }
 
@@ -262,14 +590,17 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
         return ax_err!(PermissionDenied);
     }
 
-    let node = lookup(dir, path)?;
+    let node = lookup_no_follow(dir, path.trim_end_matches('/'))?;
     let attr = node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
-    } else if !attr.perm().owner_writable() {
-        ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        let path = path.trim_end_matches('/');
+        let (parent, rel_path) = resolve_path(dir, path, false)?;
+        check_removable(&parent_dir_of(&parent, &rel_path)?, &node)?;
+        parent.remove(&rel_path)?;
+        notify_at(dir, path, WatchMask::DELETE | WatchMask::IS_DIR);
+        Ok(())
     }
 }
 
@@ -292,7 +623,7 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
     let attr = node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
-    } else if !attr.perm().owner_executable() {
+    } else if !fops::perm_to_cap(&attr).contains(Cap::EXECUTE) {
         ax_err!(PermissionDenied)
     } else {
         *CURRENT_DIR.lock() = node;
@@ -301,10 +632,199 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
     }
 }
 
-pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
-        warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+pub(crate) fn mount(path: &str, fs: Arc<dyn VfsOps>) -> AxResult {
+    ROOT_DIR.mount(&absolute_path(path)?, fs)
+}
+
+pub(crate) fn umount(path: &str) -> AxResult {
+    ROOT_DIR.umount(&absolute_path(path)?)
+}
+
+pub(crate) fn mount_paths() -> Vec<String> {
+    ROOT_DIR.mount_paths()
+}
+
+pub(crate) fn mounted_fs(path: &str) -> AxResult<Arc<dyn VfsOps>> {
+    ROOT_DIR.mounted_fs(&absolute_path(path)?)
+}
+
+/// Returns the mount point that `path` belongs to, to be held by an opened
+/// file or directory.
+///
+/// Returns `None` for the root filesystem, or if `path` is relative to `dir`,
+/// in which case the mount point of `dir` is used by the caller.
+pub(crate) fn mount_ref(dir: Option<&VfsNodeRef>, path: &str) -> Option<MountRef> {
+    if dir.is_some() && !path.starts_with('/') {
+        return None;
+    }
+    let (_, path) = resolve_path(None, path, true).ok()?;
+    let abs_path = absolute_path(&path).ok()?;
+    ROOT_DIR.mount_ref(abs_path.trim_matches('/'))
+}
+
+/// Renames `old` to `new`. If `new` exists, it is replaced if `replace` is
+/// set, or [`AlreadyExists`](AxError::AlreadyExists) is returned otherwise.
+pub(crate) fn rename(old: &str, new: &str, replace: bool) -> AxResult {
+    let _guard = RENAME_LOCK.lock();
+    let node = lookup_no_follow(None, old)?;
+    let (old_base, rel_old) = resolve_path(None, old, false)?;
+    check_removable(&parent_dir_of(&old_base, &rel_old)?, &node)?;
+    let (new_base, rel_new) = resolve_path(None, new, false)?;
+    check_dir_writable(&parent_dir_of(&new_base, &rel_new)?)?;
+    let dst_node = lookup_no_follow(None, new).ok();
+    if let Some(dst_node) = &dst_node {
+        if !replace {
+            return ax_err!(AlreadyExists);
+        }
+        check_removable(&parent_dir_of(&new_base, &rel_new)?, dst_node)?;
+    }
+    let (src, dst) = (absolute_path(&rel_old)?, absolute_path(&rel_new)?);
+    if dst
+        .strip_prefix(src.as_str())
+        .is_some_and(|rest| rest.starts_with('/'))
+    {
+        return ax_err!(InvalidInput); // move a directory into itself
+    }
+    let is_dir = node.get_attr()?.is_dir();
+    let old_path = if notify::has_watchers() {
+        event_path(None, old, false).ok()
+    } else {
+        None
+    };
+    // the root directory translates both paths into the mounted filesystem,
+    // nothing is removed before the filesystem accepts the rename
+    match (ROOT_DIR.rename(&src, &dst), dst_node) {
+        // the filesystem does not replace the destination itself
+        (Err(AxError::AlreadyExists), Some(dst_node)) => {
+            let dst_attr = dst_node.get_attr()?;
+            let src_attr = node.get_attr()?;
+            if src_attr.ino() != 0 && src_attr.ino() == dst_attr.ino() {
+                return Ok(()); // links to the same file
+            }
+            match (is_dir, dst_attr.is_dir()) {
+                (true, false) => return ax_err!(NotADirectory),
+                (false, true) => return ax_err!(IsADirectory),
+                (true, true) => remove_dir(None, new)?,
+                (false, false) => remove_file(None, new)?,
+            }
+            ROOT_DIR.rename(&src, &dst)?;
+        }
+        (res, _) => res?,
+    }
+    if let Some(old_path) = old_path {
+        if let Ok(new_path) = event_path(None, new, false) {
+            notify::notify_rename(&old_path, &new_path, is_dir);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn symlink(dir: Option<&VfsNodeRef>, target: &str, path: &str) -> AxResult {
+    if path.is_empty() || target.is_empty() {
+        return ax_err!(NotFound);
+    } else if path.ends_with('/') {
+        return ax_err!(NotADirectory);
+    }
+    let (parent, rel_path) = resolve_path(dir, path, false)?;
+    if parent.clone().lookup(&rel_path).is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+    check_dir_writable(&parent_dir_of(&parent, &rel_path)?)?;
+    parent.symlink(&rel_path, target)?;
+    fops::set_new_node_owner(&parent.lookup(&rel_path)?);
+    notify_at(dir, path, WatchMask::CREATE);
+    Ok(())
+}
+
+/// Changes the attributes of the node at `path`, follows the symbolic link
+/// if `follow` is true.
+pub(crate) fn set_attr(
+    dir: Option<&VfsNodeRef>,
+    path: &str,
+    attr: FileSetAttr,
+    follow: bool,
+) -> AxResult {
+    let node = if follow {
+        lookup(dir, path)?
+    } else {
+        lookup_no_follow(dir, path)?
+    };
+    fops::set_node_attr(&node, attr)?;
+    if notify::has_watchers() {
+        if let Ok(path) = event_path(dir, path, follow) {
+            notify::notify(&path, WatchMask::ATTRIB);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_link(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<String> {
+    let node = lookup_no_follow(dir, path)?;
+    if !node.get_attr()?.is_symlink() {
+        return ax_err!(InvalidInput);
+    }
+    read_symlink(&node)
+}
+
+pub(crate) fn link(old: &str, new: &str) -> AxResult {
+    let node = lookup_no_follow(None, old)?;
+    if node.get_attr()?.is_dir() {
+        return ax_err!(PermissionDenied);
     }
-    parent_node_of(None, old).rename(old, new)
+    if new.ends_with('/') {
+        return ax_err!(NotADirectory);
+    }
+    let (parent, rel_new) = resolve_path(None, new, false)?;
+    if parent.clone().lookup(&rel_new).is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+    check_dir_writable(&parent_dir_of(&parent, &rel_new)?)?;
+    // hard links cannot cross mount points
+    let (_, old) = resolve_path(None, old, false)?;
+    let old_fs = mounted_fs_of(&old)?;
+    let new_fs = mounted_fs_of(&rel_new)?;
+    if !core::ptr::addr_eq(Arc::as_ptr(&old_fs), Arc::as_ptr(&new_fs)) {
+        return ax_err!(CrossesDevices);
+    }
+    parent.link(&rel_new, &node)?;
+    notify_at(None, new, WatchMask::CREATE);
+    Ok(())
+}
+
+/// Returns the attributes of the filesystem that `path` belongs to.
+pub(crate) fn statfs(path: &str) -> AxResult<FileSystemInfo> {
+    lookup(None, path)?;
+    let (_, path) = resolve_path(None, path, true)?;
+    mounted_fs_of(&path)?.statfs()
+}
+
+/// Returns the attributes of the filesystem of an opened file or directory,
+/// which holds `mount`.
+pub(crate) fn statfs_of(mount: Option<&MountRef>) -> AxResult<FileSystemInfo> {
+    match mount {
+        Some(mount) => mount.0.fs.statfs(),
+        None => ROOT_DIR.main_fs.statfs(),
+    }
+}
+
+/// Whether `fs` is mounted, or is the root filesystem.
+#[cfg(all(feature = "fatfs", not(feature = "myfs")))]
+pub(crate) fn is_mounted(fs: &Arc<dyn VfsOps>) -> bool {
+    let same = |other: &Arc<dyn VfsOps>| core::ptr::addr_eq(Arc::as_ptr(fs), Arc::as_ptr(other));
+    same(&ROOT_DIR.main_fs) || ROOT_DIR.mounts.lock().iter().any(|mp| same(&mp.fs))
+}
+
+/// Returns the identity of the filesystem of an opened file or directory,
+/// which holds `mount`.
+pub(crate) fn fs_id(mount: Option<&MountRef>) -> usize {
+    let fs = match mount {
+        Some(mount) => &mount.0.fs,
+        None => &ROOT_DIR.main_fs,
+    };
+    Arc::as_ptr(fs) as *const () as usize
+}
+
+/// Returns the mounted filesystem that `path` belongs to.
+fn mounted_fs_of(path: &str) -> AxResult<Arc<dyn VfsOps>> {
+    ROOT_DIR.lookup_mounted_fs(&absolute_path(path)?, |fs, _| Ok(fs))
 }
@@ -0,0 +1,135 @@
+#![cfg(not(feature = "myfs"))]
+
+#[allow(dead_code)] // only the `CredentialIf` implementation is used
+mod test_common;
+
+use std::sync::Arc;
+
+use axdriver::AxDeviceContainer;
+use axfs::aio::{IoOp, IoRequest, IoRing};
+use axfs::fops::{File, OpenOptions};
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "resources/fat16.img";
+const NUM_REQUESTS: usize = 16;
+const REQUEST_SIZE: usize = 1000;
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+fn open_rw(path: &str) -> File {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    opts.write(true);
+    opts.create(true);
+    File::open(path, &opts).unwrap()
+}
+
+fn test_vectored(path: &str) {
+    let mut file = open_rw(path);
+    let bufs: [&[u8]; 3] = [b"Rust", b"", b" is cool!\n"];
+    assert_eq!(file.write_vectored(&bufs).unwrap(), 14);
+    assert_eq!(file.write_vectored(&bufs).unwrap(), 14);
+
+    let (mut a, mut b) = ([0; 10], [0; 32]);
+    let mut bufs: [&mut [u8]; 2] = [&mut a, &mut b];
+    assert_eq!(file.read_vectored_at(4, &mut bufs).unwrap(), 24);
+    assert_eq!(&a, b" is cool!\n");
+    assert_eq!(&b[..14], b"Rust is cool!\n");
+
+    let mut bufs: [&mut [u8]; 1] = [&mut a];
+    assert_eq!(file.read_vectored(&mut bufs).unwrap(), 0);
+}
+
+fn test_ring(path: &str) {
+    let file = Arc::new(open_rw(path));
+    let ring = IoRing::new(4);
+
+    // many writes in flight, complete in any order
+    for i in 0..NUM_REQUESTS {
+        ring.submit(IoRequest {
+            file: file.clone(),
+            op: IoOp::Write {
+                offset: (i * REQUEST_SIZE) as u64,
+                data: vec![i as u8; REQUEST_SIZE],
+            },
+            user_data: i as u64,
+        });
+    }
+    let mut done = [false; NUM_REQUESTS];
+    while let Some(c) = ring.wait_complete() {
+        assert_eq!(c.result, Ok(REQUEST_SIZE));
+        assert_eq!(c.buf.len(), REQUEST_SIZE);
+        done[c.user_data as usize] = true;
+    }
+    assert!(done.iter().all(|&d| d));
+    assert_eq!(ring.in_flight(), 0);
+
+    ring.submit(IoRequest {
+        file: file.clone(),
+        op: IoOp::Fsync,
+        user_data: 100,
+    });
+    for i in 0..NUM_REQUESTS {
+        ring.submit(IoRequest {
+            file: file.clone(),
+            op: IoOp::Read {
+                offset: (i * REQUEST_SIZE + 500) as u64,
+                len: REQUEST_SIZE,
+            },
+            user_data: i as u64,
+        });
+    }
+    let mut completed = 0;
+    while let Some(c) = ring.wait_complete() {
+        completed += 1;
+        if c.user_data == 100 {
+            assert_eq!(c.result, Ok(0));
+            continue;
+        }
+        let i = c.user_data as usize;
+        if i == NUM_REQUESTS - 1 {
+            // short read at the end of the file
+            assert_eq!(c.result, Ok(500));
+            assert_eq!(c.buf, [i as u8; 500]);
+        } else {
+            assert_eq!(c.result, Ok(REQUEST_SIZE));
+            assert_eq!(c.buf[..500], [i as u8; 500]);
+            assert_eq!(c.buf[500..], [i as u8 + 1; 500]);
+        }
+    }
+    assert_eq!(completed, NUM_REQUESTS + 1);
+    assert!(ring.try_complete().is_none());
+
+    // waiters see the completions taken by others
+    let seen = ring.events();
+    ring.submit(IoRequest {
+        file,
+        op: IoOp::Fsync,
+        user_data: 200,
+    });
+    assert!(ring.wait_event(seen, None));
+    assert!(ring.events() > seen);
+    assert_eq!(ring.wait_complete().unwrap().user_data, 200);
+}
+
+#[test]
+fn test_aio() {
+    println!("Testing vectored and asynchronous I/O ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_vectored("/vectored.txt");
+    test_vectored("/tmp/vectored.txt");
+    test_ring("/aio.bin");
+    test_ring("/tmp/aio.bin");
+
+    println!("Vectored and asynchronous I/O tests passed!");
+}
@@ -1,7 +1,11 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
 use axfs::api as fs;
+use axfs::fops::{self, Credential, CredentialIf, LockKind, LockOwner, WatchMask, Watcher};
 use axio as io;
 
-use fs::{File, FileType, OpenOptions};
+use fs::{File, FileType, OpenOptions, Permissions};
 use io::{prelude::*, Error, Result};
 
 macro_rules! assert_err {
@@ -13,6 +17,22 @@ macro_rules! assert_err {
     };
 }
 
+/// The credential of all tasks, changed by tests.
+static CREDENTIAL: Mutex<Credential> = Mutex::new(Credential::ROOT);
+
+struct CredentialIfImpl;
+
+#[crate_interface::impl_interface]
+impl CredentialIf for CredentialIfImpl {
+    fn current_credential() -> Credential {
+        *CREDENTIAL.lock().unwrap()
+    }
+}
+
+fn set_credential(uid: u32, gid: u32) {
+    *CREDENTIAL.lock().unwrap() = Credential::new(uid, gid);
+}
+
 fn test_read_write_file() -> Result<()> {
     let fname = "///very/long//.././long//./path/./test.txt";
     println!("read and write file {:?}:", fname);
@@ -113,6 +133,41 @@ fn test_create_file_dir() -> Result<()> {
     assert_eq!(fs::read_to_string(fname)?, contents);
     assert_err!(File::create_new(fname), AlreadyExists);
 
+    // the inode number is kept by renames
+    let ino = fs::metadata(fname)?.ino();
+    assert_ne!(fs::metadata(".")?.ino(), ino);
+    fs::rename(fname, "/renamed-file.txt")?;
+    assert_eq!(fs::metadata("/renamed-file.txt")?.ino(), ino);
+    fs::rename("/renamed-file.txt", fname)?;
+    assert_eq!(fs::metadata(fname)?.ino(), ino);
+
+    // the destination is only replaced if the rename succeeds
+    fs::rename(fname, fname)?;
+    assert_eq!(fs::read_to_string(fname)?, contents);
+    fs::create_dir("/empty-dir")?;
+    assert_err!(fs::rename(fname, "/empty-dir"), IsADirectory);
+    assert!(fs::metadata("/empty-dir")?.is_dir());
+    assert_err!(fs::rename("/empty-dir", fname), NotADirectory);
+    assert_eq!(fs::read_to_string(fname)?, contents);
+    fs::write("/old-file.txt", "old")?;
+    fs::write("/replaced-file.txt", "replaced")?;
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    let root = fops::Directory::open_dir("/", &opts)?;
+    assert_err!(
+        root.rename_noreplace_at("old-file.txt", "replaced-file.txt"),
+        AlreadyExists
+    );
+    assert_eq!(fs::read_to_string("/replaced-file.txt")?, "replaced");
+    root.rename_at("old-file.txt", "replaced-file.txt")?;
+    assert_eq!(fs::read_to_string("/replaced-file.txt")?, "old");
+    assert_err!(fs::metadata("/old-file.txt"), NotFound);
+    fs::remove_file("/replaced-file.txt")?;
+    fs::create_dir("/replaced-dir")?;
+    fs::rename("/empty-dir", "/replaced-dir")?;
+    assert_err!(fs::metadata("/empty-dir"), NotFound);
+    fs::remove_dir("/replaced-dir")?;
+
     // create a directory and test existence
     let dirname = "///././/very//.//long/./new-dir";
     println!("test create dir {:?}:", dirname);
@@ -208,13 +263,14 @@ fn test_devfs_ramfs() -> Result<()> {
     assert!(!md.is_file());
     assert!(md.is_dir());
 
-    // stat /dev/foo/bar
-    let fname = ".//.///././/./dev///.///./foo//././bar";
+    // stat /dev/vda
+    let fname = ".//.///././/./dev///.///./vda";
     let file = File::open(fname)?;
     let md = file.metadata()?;
     println!("metadata of {:?}: {:?}", fname, md);
-    assert_eq!(md.file_type(), FileType::CharDevice);
+    assert_eq!(md.file_type(), FileType::BlockDevice);
     assert!(!md.is_dir());
+    assert!(md.len() > 0);
 
     // error cases
     assert_err!(fs::metadata("/dev/null/"), NotADirectory);
@@ -233,7 +289,7 @@ fn test_devfs_ramfs() -> Result<()> {
     assert_eq!(fs::write(".///dev//..//233//.///test.txt", "test"), Ok(()));
     assert_err!(fs::remove_file("./dev//../..//233//.///test.txt"), NotFound);
     assert_eq!(fs::remove_file("./dev//..//233//../233/./test.txt"), Ok(()));
-    assert_eq!(fs::remove_dir("dev//foo/../foo/../.././/233"), Ok(()));
+    assert_eq!(fs::remove_dir("dev//../tmp/../dev/../.././/233"), Ok(()));
     assert_err!(fs::remove_dir("very/../dev//"), PermissionDenied);
 
     // tests in /tmp
@@ -252,6 +308,229 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+fn test_owner_permission() -> Result<()> {
+    let fname = "/tmp/owner.txt";
+    println!("test owner and permission {:?}:", fname);
+
+    fs::write(fname, "secret")?;
+    let meta = fs::metadata(fname)?;
+    assert_eq!((meta.uid(), meta.gid()), (0, 0));
+    fs::set_permissions(fname, Permissions::from_bits_truncate(0o640))?;
+    assert_eq!(fs::metadata(fname)?.permissions().mode(), 0o640);
+
+    // other users
+    set_credential(1000, 1000);
+    assert_eq!(fs::metadata(fname)?.len(), 6);
+    assert_err!(fs::read(fname), PermissionDenied);
+    assert_err!(fs::write(fname, "test"), PermissionDenied);
+    assert_err!(fs::remove_file(fname), PermissionDenied);
+    assert_err!(
+        fs::set_permissions(fname, Permissions::from_bits_truncate(0o666)),
+        PermissionDenied
+    );
+    assert_err!(fs::chown(fname, Some(1000), None), PermissionDenied);
+
+    // the owner
+    set_credential(0, 0);
+    fs::chown(fname, Some(1000), Some(100))?;
+    set_credential(1000, 1000);
+    assert_eq!(fs::read_to_string(fname)?, "secret");
+    fs::write(fname, "new secret")?;
+    let file = File::open(fname)?;
+    file.set_modified(Duration::from_secs(1_000_000_000))?;
+    assert_eq!(
+        file.metadata()?.modified(),
+        Duration::from_secs(1_000_000_000)
+    );
+    drop(file);
+    assert_err!(fs::chown(fname, None, Some(0)), PermissionDenied);
+
+    // the group
+    set_credential(1001, 100);
+    assert_eq!(fs::read_to_string(fname)?, "new secret");
+    assert_err!(fs::write(fname, "test"), PermissionDenied);
+
+    // new files are owned by the creator
+    set_credential(1001, 100);
+    fs::write("/tmp/user.txt", "test")?;
+    let meta = fs::metadata("/tmp/user.txt")?;
+    assert_eq!((meta.uid(), meta.gid()), (1001, 100));
+
+    // only the owner can remove files in the sticky `/tmp`
+    set_credential(1000, 1000);
+    assert_err!(fs::remove_file("/tmp/user.txt"), PermissionDenied);
+    set_credential(1001, 100);
+    fs::remove_file("/tmp/user.txt")?;
+
+    set_credential(0, 0);
+    fs::remove_file(fname)?;
+
+    println!("test_owner_permission() OK!");
+    Ok(())
+}
+
+fn test_watch() -> Result<()> {
+    let dname = "/tmp/watch";
+    println!("test watch {:?}:", dname);
+
+    fs::create_dir(dname)?;
+    let watcher = Watcher::new();
+    let dir_wd = watcher.add_watch(dname, WatchMask::ALL_EVENTS)?;
+    assert_eq!(
+        watcher.add_watch("/tmp/./watch/", WatchMask::ALL_EVENTS)?,
+        dir_wd
+    );
+    assert_err!(
+        watcher.add_watch("/tmp/watch/none", WatchMask::ALL_EVENTS),
+        NotFound
+    );
+
+    fs::write("/tmp/watch/a.txt", "hello")?;
+    let file_wd = watcher.add_watch(
+        "/tmp/watch/a.txt",
+        WatchMask::MODIFY | WatchMask::DELETE_SELF,
+    )?;
+    File::options()
+        .append(true)
+        .open("/tmp/watch/a.txt")?
+        .write_all(b" world")?;
+    fs::rename("/tmp/watch/a.txt", "/tmp/watch/b.txt")?;
+    fs::create_dir("/tmp/watch/sub")?;
+    fs::remove_dir("/tmp/watch/sub")?;
+    fs::remove_file("/tmp/watch/b.txt")?;
+
+    let mut events = Vec::new();
+    while let Some(event) = watcher.pop_event() {
+        events.push(event);
+    }
+    let expected = [
+        (dir_wd, WatchMask::CREATE, Some("a.txt")),
+        // repeated events are merged
+        (dir_wd, WatchMask::MODIFY, Some("a.txt")),
+        (file_wd, WatchMask::MODIFY, None),
+        (dir_wd, WatchMask::MOVED_FROM, Some("a.txt")),
+        (dir_wd, WatchMask::MOVED_TO, Some("b.txt")),
+        (dir_wd, WatchMask::CREATE | WatchMask::IS_DIR, Some("sub")),
+        (dir_wd, WatchMask::DELETE | WatchMask::IS_DIR, Some("sub")),
+        (dir_wd, WatchMask::DELETE, Some("b.txt")),
+        (file_wd, WatchMask::DELETE_SELF, None),
+        (file_wd, WatchMask::IGNORED, None),
+    ];
+    assert_eq!(events.len(), expected.len());
+    for (event, (wd, mask, name)) in events.iter().zip(expected) {
+        assert_eq!(
+            (event.wd, event.mask, event.name.as_deref()),
+            (wd as isize, mask, name)
+        );
+    }
+    assert_ne!(events[3].cookie, 0);
+    assert_eq!(events[3].cookie, events[4].cookie);
+
+    // the watch on the file is removed after it is deleted
+    assert_err!(watcher.remove_watch(file_wd), InvalidInput);
+    watcher.remove_watch(dir_wd)?;
+    assert_eq!(watcher.pop_event().unwrap().mask, WatchMask::IGNORED);
+    fs::remove_dir(dname)?;
+    assert!(!watcher.has_events());
+
+    println!("test_watch() OK!");
+    Ok(())
+}
+
+fn test_file_lock() -> Result<()> {
+    let fname = "/tmp/lock.txt";
+    println!("test file lock {:?}:", fname);
+
+    fs::write(fname, "0123456789")?;
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    opts.write(true);
+    let file1 = fops::File::open(fname, &opts)?;
+    let file2 = fops::File::open("/tmp/./lock.txt", &opts)?;
+    let (locks1, locks2) = (file1.lock_handle(), file2.lock_handle());
+
+    // record locks of different tasks
+    locks1.lock_range(1, LockKind::Exclusive, 0, 5, false)?;
+    locks2.lock_range(2, LockKind::Shared, 5, 10, false)?;
+    assert_err!(
+        locks2.lock_range(2, LockKind::Shared, 4, 6, false),
+        WouldBlock
+    );
+    let conflict = locks2
+        .test_lock_range(2, LockKind::Shared, 0, u64::MAX)
+        .unwrap();
+    assert_eq!(
+        (conflict.owner, conflict.start, conflict.end),
+        (LockOwner::Task(1), 0, 5)
+    );
+    assert!(locks2
+        .test_lock_range(2, LockKind::Exclusive, 10, 20)
+        .is_none());
+
+    // locks of the same task are split when converted
+    locks1.lock_range(1, LockKind::Shared, 2, 3, false)?;
+    locks2.lock_range(2, LockKind::Shared, 2, 3, false)?;
+    assert_err!(
+        locks2.lock_range(2, LockKind::Shared, 3, 4, false),
+        WouldBlock
+    );
+    locks1.unlock_range(1, 0, u64::MAX);
+    locks2.lock_range(2, LockKind::Exclusive, 0, 5, false)?;
+    locks2.unlock_range(2, 0, u64::MAX);
+
+    // whole-file locks are independent of record locks, and are released
+    // when the file is closed
+    locks1.lock_range(1, LockKind::Exclusive, 0, u64::MAX, false)?;
+    locks1.lock_file(LockKind::Exclusive, false)?;
+    assert_err!(locks2.lock_file(LockKind::Shared, false), WouldBlock);
+    drop(file1);
+    locks2.lock_file(LockKind::Shared, false)?;
+    locks2.unlock_file();
+    assert!(locks2
+        .test_lock_range(2, LockKind::Exclusive, 0, 1)
+        .is_some());
+    locks2.unlock_range(1, 0, u64::MAX);
+
+    // record locks are released when the task exits
+    locks2.lock_range(5, LockKind::Exclusive, 0, 1, false)?;
+    fops::release_task_locks(5);
+    locks2.lock_range(2, LockKind::Exclusive, 0, 1, false)?;
+    locks2.unlock_range(2, 0, u64::MAX);
+
+    // a read-only file cannot be locked exclusively
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    let file3 = fops::File::open(fname, &opts)?;
+    assert_err!(
+        file3
+            .lock_handle()
+            .lock_range(3, LockKind::Exclusive, 0, 1, false),
+        PermissionDenied
+    );
+    drop(file3);
+
+    // locks are shared by all links of the file
+    let lname = "/tmp/lock-link.txt";
+    fs::hard_link(fname, lname)?;
+    let file4 = fops::File::open(lname, &opts)?;
+    locks2.lock_range(2, LockKind::Exclusive, 0, 1, false)?;
+    assert_err!(
+        file4
+            .lock_handle()
+            .lock_range(4, LockKind::Shared, 0, 1, false),
+        WouldBlock
+    );
+    locks2.unlock_range(2, 0, u64::MAX);
+    drop(file4);
+    fs::remove_file(lname)?;
+
+    drop(file2);
+    fs::remove_file(fname)?;
+
+    println!("test_file_lock() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
@@ -259,4 +538,7 @@ pub fn test_all() {
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_owner_permission().expect("test_owner_permission() failed");
+    test_watch().expect("test_watch() failed");
+    test_file_lock().expect("test_file_lock() failed");
 }
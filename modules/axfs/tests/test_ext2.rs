@@ -0,0 +1,38 @@
+#![cfg(all(feature = "ext4", not(feature = "myfs")))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api as fs;
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "../../crates/axfs_ext4/resources/ext2.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_ext2() {
+    println!("Testing ext2 with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_common::test_all();
+
+    // symbolic links in the image
+    assert_eq!(
+        fs::read_link("/link.txt").unwrap(),
+        "very/long/path/test.txt"
+    );
+    assert_eq!(
+        fs::read_to_string("link.txt").unwrap(),
+        fs::read_to_string("very/long/path/test.txt").unwrap()
+    );
+}
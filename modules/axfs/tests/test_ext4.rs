@@ -0,0 +1,90 @@
+#![cfg(all(feature = "ext4", not(feature = "myfs")))]
+
+use axdriver::AxDeviceContainer;
+use axfs::api as fs;
+use axfs::fops::{Credential, CredentialIf};
+use axio::Error;
+use driver_block::ramdisk::RamDisk;
+
+const FAT_IMG_PATH: &str = "resources/fat16.img";
+const EXT4_IMG_PATH: &str = "../../crates/axfs_ext4/resources/ext4.img";
+const BLOCK_SIZE: usize = 512;
+const PART_START: usize = 2048;
+
+struct CredentialIfImpl;
+
+#[crate_interface::impl_interface]
+impl CredentialIf for CredentialIfImpl {
+    fn current_credential() -> Credential {
+        Credential::ROOT
+    }
+}
+
+/// Makes an MBR disk with a FAT partition and an ext4 partition.
+fn make_disk() -> std::io::Result<RamDisk> {
+    let mut images = Vec::new();
+    for img in [FAT_IMG_PATH, EXT4_IMG_PATH] {
+        let path = std::env::current_dir()?.join(img);
+        println!("Loading disk image from {:?} ...", path);
+        images.push(std::fs::read(path)?);
+    }
+    let total_blocks: usize = images
+        .iter()
+        .map(|img| img.len().div_ceil(BLOCK_SIZE))
+        .sum();
+
+    let mut data = vec![0; (PART_START + total_blocks) * BLOCK_SIZE];
+    let mut start = PART_START;
+    for (i, (img, ty)) in images.iter().zip([0x0e, 0x83]).enumerate() {
+        let part_blocks = img.len().div_ceil(BLOCK_SIZE);
+        let entry = &mut data[446 + i * 16..446 + (i + 1) * 16];
+        entry[4] = ty; // FAT16 (LBA) or Linux
+        entry[8..12].copy_from_slice(&(start as u32).to_le_bytes());
+        entry[12..16].copy_from_slice(&(part_blocks as u32).to_le_bytes());
+        data[start * BLOCK_SIZE..][..img.len()].copy_from_slice(img);
+        start += part_blocks;
+    }
+    data[510..512].copy_from_slice(&[0x55, 0xaa]);
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_ext4() {
+    println!("Testing ext4 with ramdisk ...");
+
+    let disk = make_disk().expect("failed to make disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    // the FAT partition is the root, and the ext4 one is mounted
+    assert!(fs::mounts().contains(&"/mnt/disk0p2".into()));
+
+    let long = fs::read_to_string("/mnt/disk0p2/long.txt").unwrap();
+    assert_eq!(long, "Rust is cool!\n".repeat(1000));
+    let fname = "/mnt/disk0p2/very/long/path/test.txt";
+    assert_eq!(fs::read_to_string(fname).unwrap(), "Rust is cool!\n");
+    assert_eq!(
+        fs::read_to_string("/mnt/disk0p2/link.txt").unwrap(),
+        "Rust is cool!\n"
+    );
+    let dirents = fs::read_dir("/mnt/disk0p2/very-long-dir-name")
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(dirents, ["very-long-file-name.txt"]);
+
+    // ext4 is mounted read-only
+    assert_eq!(
+        fs::write("/mnt/disk0p2/new.txt", "test").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::remove_file("/mnt/disk0p2/short.txt").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::create_dir("/mnt/disk0p2/new-dir").err(),
+        Some(Error::PermissionDenied)
+    );
+}
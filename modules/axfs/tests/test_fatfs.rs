@@ -2,7 +2,11 @@
 
 mod test_common;
 
+use std::time::Duration;
+
 use axdriver::AxDeviceContainer;
+use axfs::api::{self as fs, File};
+use axio::Result;
 use driver_block::ramdisk::RamDisk;
 
 const IMG_PATH: &str = "resources/fat16.img";
@@ -24,4 +28,24 @@ fn test_fatfs() {
     axfs::init_filesystems(AxDeviceContainer::from_one(disk));
 
     test_common::test_all();
+    test_rename_open_file().expect("test_rename_open_file() failed");
+
+    let info = axfs::api::statfs("/").expect("failed to get filesystem info");
+    assert!(info.block_size() >= 512);
+    assert!(info.blocks_free() > 0 && info.blocks_free() < info.blocks());
+}
+
+fn test_rename_open_file() -> Result<()> {
+    // the timestamps are still found in the directory entry after renaming
+    let file = File::create("/renamed.txt")?;
+    file.set_modified(Duration::from_secs(1_000_000_000))?;
+    fs::create_dir("/renamed")?;
+    fs::rename("/renamed.txt", "/renamed/NEW.txt")?;
+    assert_eq!(
+        file.metadata()?.modified(),
+        Duration::from_secs(1_000_000_000)
+    );
+    drop(file);
+    fs::remove_file("/renamed/new.txt")?;
+    fs::remove_dir("/renamed")
 }
@@ -0,0 +1,111 @@
+#![cfg(not(feature = "myfs"))]
+
+#[allow(dead_code)] // only the `CredentialIf` implementation is used
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api::{self as fs, FatCheckMode};
+use axio::Write;
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+// the layout of the FAT16 image: 512-byte clusters, two FATs of 20 sectors
+const FAT_OFFSETS: [usize; 2] = [512, 512 + 20 * 512];
+/// The directory entry of `/short.txt` in the root directory.
+const SHORT_ENTRY: usize = 21120;
+/// The last cluster of `/long.txt`.
+const LONG_LAST_CLUSTER: usize = 30;
+const FREE_CLUSTER: usize = 1000;
+
+fn set_fat(img: &mut [u8], cluster: usize, value: u16) {
+    for fat in FAT_OFFSETS {
+        img[fat + 2 * cluster..][..2].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+#[test]
+fn test_fsck() {
+    println!("Testing FAT checks ...");
+
+    let path = std::env::current_dir().unwrap().join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let fat = std::fs::read(path).expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(RamDisk::from(&fat)));
+
+    // a lost cluster, a chain linked to a free cluster, and a size beyond the
+    // chain
+    let mut img = fat.clone();
+    set_fat(&mut img, FREE_CLUSTER, 0xffff);
+    set_fat(&mut img, LONG_LAST_CLUSTER, FREE_CLUSTER as u16 + 1);
+    img[SHORT_ENTRY + 28..][..4].copy_from_slice(&2000u32.to_le_bytes());
+    fs::write("/tmp/bad.img", &img).unwrap();
+    assert_eq!(fs::attach_loop("/tmp/bad.img", false).unwrap(), "loop0");
+
+    let report = fs::check_loop_fat("loop0", FatCheckMode::Report).unwrap();
+    assert_eq!(report.lost_clusters, 1);
+    assert_eq!(report.bad_chains, 1);
+    assert_eq!(report.bad_sizes, 1);
+    assert!(!report.repaired);
+    assert_eq!(fs::read("/tmp/bad.img").unwrap(), img); // nothing is changed
+
+    let report = fs::check_loop_fat("loop0", FatCheckMode::Repair).unwrap();
+    assert!(!report.is_clean());
+    assert!(report.repaired);
+    assert!(fs::check_loop_fat("loop0", FatCheckMode::Report)
+        .unwrap()
+        .is_clean());
+
+    fs::mount("/mnt/loop0", fs::loop_fs("loop0").unwrap()).unwrap();
+    assert_eq!(
+        fs::check_loop_fat("loop0", FatCheckMode::Report).err(),
+        Some(axio::Error::ResourceBusy)
+    );
+    assert_eq!(fs::read("/mnt/loop0/long.txt").unwrap().len(), 14000);
+    let short = fs::read("/mnt/loop0/short.txt").unwrap();
+    assert_eq!(short.len(), 512); // shrunk to the chain
+    assert_eq!(&short[..14], b"Rust is cool!\n");
+
+    // files written through the wrapper leave the volume consistent
+    fs::write("/mnt/loop0/new.txt", vec![b'x'; 3000]).unwrap();
+    let mut file = fs::File::options()
+        .append(true)
+        .open("/mnt/loop0/long.txt")
+        .unwrap();
+    file.write_all(b"more").unwrap();
+    drop(file);
+
+    // the data and the FAT are written before the directory entry, so that
+    // the volume only has lost clusters if it stops in between
+    let mut file = fs::File::create("/mnt/loop0/ordered.txt").unwrap();
+    file.write_all(&[b'y'; 3000]).unwrap();
+    file.flush().unwrap(); // the entry is only updated in the cache
+    fs::write("/tmp/ordered.img", fs::read("/tmp/bad.img").unwrap()).unwrap();
+    assert_eq!(fs::attach_loop("/tmp/ordered.img", true).unwrap(), "loop1");
+    let report = fs::check_loop_fat("loop1", FatCheckMode::Report).unwrap();
+    assert_eq!(report.bad_chains + report.bad_entries + report.bad_sizes, 0);
+    assert!(report.lost_clusters > 0);
+    drop(file);
+
+    fs::sync().unwrap();
+    fs::umount("/mnt/loop0").unwrap();
+    let report = fs::check_loop_fat("loop0", FatCheckMode::Report).unwrap();
+    assert!(report.is_clean(), "{:?}", report);
+
+    // not a FAT volume
+    fs::write("/tmp/zero.img", vec![0; 64 * 512]).unwrap();
+    assert_eq!(fs::attach_loop("/tmp/zero.img", true).unwrap(), "loop2");
+    assert_eq!(
+        fs::check_loop_fat("loop2", FatCheckMode::Report).err(),
+        Some(axio::Error::InvalidData)
+    );
+
+    // a boot sector describing a volume larger than the disk
+    fs::write("/tmp/short.img", &fat[..64 * 512]).unwrap();
+    assert_eq!(fs::attach_loop("/tmp/short.img", true).unwrap(), "loop3");
+    assert_eq!(
+        fs::check_loop_fat("loop3", FatCheckMode::Report).err(),
+        Some(axio::Error::InvalidData)
+    );
+}
@@ -0,0 +1,81 @@
+#![cfg(not(feature = "myfs"))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api as fs;
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "resources/fat16.img";
+const BLOCK_SIZE: usize = 512;
+const PART_START: usize = 2048;
+const ENTRY_SIZE: usize = 128;
+const NUM_ENTRIES: usize = 128;
+
+/// Basic data partition type GUID.
+const TYPE_GUID: [u8; 16] = [
+    0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7,
+];
+
+/// Makes a GPT disk with two partitions named "boot" and "root", each
+/// contains a copy of the FAT image.
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let fat = std::fs::read(path)?;
+    let part_blocks = fat.len().div_ceil(BLOCK_SIZE);
+    let num_blocks = PART_START + part_blocks * 2 + 34; // with the backup GPT
+
+    let mut data = vec![0; num_blocks * BLOCK_SIZE];
+    // protective MBR
+    data[446 + 4] = 0xee;
+    data[446 + 8..446 + 12].copy_from_slice(&1u32.to_le_bytes());
+    data[446 + 12..446 + 16].copy_from_slice(&(num_blocks as u32 - 1).to_le_bytes());
+    data[510..512].copy_from_slice(&[0x55, 0xaa]);
+
+    // GPT header, CRCs are left empty
+    let header = &mut data[BLOCK_SIZE..BLOCK_SIZE * 2];
+    header[..8].copy_from_slice(b"EFI PART");
+    header[72..80].copy_from_slice(&2u64.to_le_bytes());
+    header[80..84].copy_from_slice(&(NUM_ENTRIES as u32).to_le_bytes());
+    header[84..88].copy_from_slice(&(ENTRY_SIZE as u32).to_le_bytes());
+
+    for (i, name) in ["boot", "root"].iter().enumerate() {
+        let start = PART_START + part_blocks * i;
+        let entry = &mut data[BLOCK_SIZE * 2 + i * ENTRY_SIZE..][..ENTRY_SIZE];
+        entry[..16].copy_from_slice(&TYPE_GUID);
+        entry[16..32].fill(i as u8 + 1); // unique GUID
+        entry[32..40].copy_from_slice(&(start as u64).to_le_bytes());
+        entry[40..48].copy_from_slice(&((start + part_blocks - 1) as u64).to_le_bytes());
+        for (j, c) in name.encode_utf16().enumerate() {
+            entry[56 + j * 2..58 + j * 2].copy_from_slice(&c.to_le_bytes());
+        }
+        data[start * BLOCK_SIZE..][..fat.len()].copy_from_slice(&fat);
+    }
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_gpt() {
+    println!("Testing GPT partitions with ramdisk ...");
+
+    let disk = make_disk().expect("failed to make disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems_with_root(AxDeviceContainer::from_one(disk), "LABEL=root");
+
+    // the second partition is the root, and the first one is mounted
+    assert!(fs::mounts().contains(&"/mnt/disk0p1".into()));
+
+    test_common::test_all();
+
+    // changes on the root are not visible in the other partition
+    let fname = "/mnt/disk0p1/very/long/path/test.txt";
+    assert_eq!(fs::read_to_string(fname).unwrap(), "Rust is cool!\n");
+    fs::write("/mnt/disk0p1/new.txt", "partition 1\n").unwrap();
+    assert_eq!(
+        fs::read_to_string("/mnt/disk0p1/new.txt").unwrap(),
+        "partition 1\n"
+    );
+    assert!(fs::metadata("/new.txt").is_err());
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "initramfs")]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api as fs;
+
+/// Appends an entry of a newc-format cpio archive.
+fn add_entry(archive: &mut Vec<u8>, name: &str, mode: u32, data: &[u8]) {
+    let ino = archive.len() as u32 + 1;
+    let header = format!(
+        "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+        ino,
+        mode,
+        0,
+        0,
+        1,
+        1_700_000_000,
+        data.len(),
+        0,
+        0,
+        0,
+        0,
+        name.len() + 1,
+        0,
+    );
+    archive.extend(header.as_bytes());
+    archive.extend(name.as_bytes());
+    archive.push(0);
+    archive.resize(archive.len().next_multiple_of(4), 0);
+    archive.extend(data);
+    archive.resize(archive.len().next_multiple_of(4), 0);
+}
+
+/// Creates an archive with the initial files of `test_common`.
+fn make_archive() -> Vec<u8> {
+    let mut archive = Vec::new();
+    add_entry(&mut archive, ".", 0o040755, b"");
+    add_entry(&mut archive, "short.txt", 0o100644, b"Rust is cool!\n");
+    add_entry(
+        &mut archive,
+        "long.txt",
+        0o100644,
+        "Rust is cool!\n".repeat(100).as_bytes(),
+    );
+    add_entry(&mut archive, "very-long-dir-name", 0o040755, b"");
+    add_entry(
+        &mut archive,
+        "very-long-dir-name/very-long-file-name.txt",
+        0o100644,
+        b"Rust is cool!\n",
+    );
+    // parent directories are not in the archive
+    add_entry(
+        &mut archive,
+        "very/long/path/test.txt",
+        0o100644,
+        b"Rust is cool!\n",
+    );
+    add_entry(&mut archive, "etc/secret", 0o100600, b"42\n");
+    add_entry(&mut archive, "etc/motd", 0o120777, b"../short.txt");
+    add_entry(&mut archive, "TRAILER!!!", 0, b"");
+    archive
+}
+
+#[test]
+fn test_initramfs() {
+    println!("Testing initramfs ...");
+
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems_with_initramfs(AxDeviceContainer::default(), &make_archive());
+
+    assert_eq!(
+        fs::metadata("/etc/secret").unwrap().permissions().mode(),
+        0o600
+    );
+    assert_eq!(fs::read_to_string("/etc/motd").unwrap(), "Rust is cool!\n");
+    assert!(fs::metadata("/very/long").unwrap().is_dir());
+    assert!(fs::metadata("/tmp").unwrap().is_dir());
+
+    test_common::test_all();
+}
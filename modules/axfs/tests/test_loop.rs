@@ -0,0 +1,73 @@
+#![cfg(not(feature = "myfs"))]
+
+#[allow(dead_code)] // only the `CredentialIf` implementation is used
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api as fs;
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "resources/fat16.img";
+const BLOCK_SIZE: usize = 512;
+const PART_START: usize = 64;
+const TEST_FILE: &str = "very/long/path/test.txt";
+
+/// Makes an MBR disk image with one partition that contains the FAT image.
+fn make_mbr_image(fat: &[u8]) -> Vec<u8> {
+    let part_blocks = fat.len().div_ceil(BLOCK_SIZE);
+    let mut data = vec![0; (PART_START + part_blocks) * BLOCK_SIZE];
+    let entry = &mut data[446..462];
+    entry[4] = 0x0e; // FAT16 (LBA)
+    entry[8..12].copy_from_slice(&(PART_START as u32).to_le_bytes());
+    entry[12..16].copy_from_slice(&(part_blocks as u32).to_le_bytes());
+    data[PART_START * BLOCK_SIZE..][..fat.len()].copy_from_slice(fat);
+    data[510..512].copy_from_slice(&[0x55, 0xaa]);
+    data
+}
+
+#[test]
+fn test_loop() {
+    println!("Testing loop devices ...");
+
+    let path = std::env::current_dir().unwrap().join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let fat = std::fs::read(path).expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(RamDisk::from(&fat)));
+
+    // an unpartitioned image stored in ramfs
+    fs::write("/tmp/fat.img", &fat).unwrap();
+    assert_eq!(fs::attach_loop("/tmp/fat.img", false).unwrap(), "loop0");
+    assert_eq!(
+        fs::metadata("/dev/loop0").unwrap().file_type(),
+        fs::FileType::BlockDevice
+    );
+    fs::mount("/mnt/loop0", fs::loop_fs("loop0").unwrap()).unwrap();
+    let fname = format!("/mnt/loop0/{}", TEST_FILE);
+    assert_eq!(fs::read_to_string(&fname).unwrap(), "Rust is cool!\n");
+
+    // changes are written to the backing file, not the root filesystem
+    fs::write("/mnt/loop0/new.txt", "loop\n").unwrap();
+    fs::sync().unwrap();
+    fs::umount("/mnt/loop0").unwrap();
+    assert!(fs::metadata("/new.txt").is_err());
+    assert_ne!(fs::read("/tmp/fat.img").unwrap(), fat);
+    fs::mount("/mnt/loop0", fs::loop_fs("loop0").unwrap()).unwrap();
+    assert_eq!(fs::read_to_string("/mnt/loop0/new.txt").unwrap(), "loop\n");
+    fs::umount("/mnt/loop0").unwrap();
+
+    // a partitioned image attached read-only
+    fs::write("/tmp/disk.img", make_mbr_image(&fat)).unwrap();
+    assert_eq!(fs::attach_loop("/tmp/disk.img", true).unwrap(), "loop1");
+    assert!(fs::metadata("/dev/loop1p1").is_ok());
+    assert_eq!(
+        fs::loop_fs("loop1").err(),
+        Some(axio::Error::NotFound) // the whole disk is not a volume
+    );
+    fs::mount("/mnt/loop1p1", fs::loop_fs("loop1p1").unwrap()).unwrap();
+    let fname = format!("/mnt/loop1p1/{}", TEST_FILE);
+    assert_eq!(fs::read_to_string(&fname).unwrap(), "Rust is cool!\n");
+    fs::umount("/mnt/loop1p1").unwrap();
+
+    assert!(fs::attach_loop("/tmp/nothing.img", true).is_err());
+}
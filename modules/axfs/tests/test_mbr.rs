@@ -0,0 +1,77 @@
+#![cfg(not(feature = "myfs"))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api as fs;
+use axio::{Read, Seek, SeekFrom};
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "resources/fat16.img";
+const BLOCK_SIZE: usize = 512;
+const PART_START: usize = 2048;
+
+/// Makes an MBR disk with two primary partitions, each contains a copy of the
+/// FAT image.
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let fat = std::fs::read(path)?;
+    let part_blocks = fat.len().div_ceil(BLOCK_SIZE);
+
+    let mut data = vec![0; (PART_START + part_blocks * 2) * BLOCK_SIZE];
+    data[440..444].copy_from_slice(&0x12345678u32.to_le_bytes()); // disk signature
+    for i in 0..2 {
+        let start = PART_START + part_blocks * i;
+        let entry = &mut data[446 + i * 16..446 + (i + 1) * 16];
+        entry[4] = 0x0e; // FAT16 (LBA)
+        entry[8..12].copy_from_slice(&(start as u32).to_le_bytes());
+        entry[12..16].copy_from_slice(&(part_blocks as u32).to_le_bytes());
+        data[start * BLOCK_SIZE..][..fat.len()].copy_from_slice(&fat);
+    }
+    data[510..512].copy_from_slice(&[0x55, 0xaa]);
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_mbr() {
+    println!("Testing MBR partitions with ramdisk ...");
+
+    let disk = make_disk().expect("failed to make disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    // the first partition is the root, and the second one is mounted
+    assert!(fs::mounts().contains(&"/mnt/disk0p2".into()));
+
+    test_common::test_all();
+
+    // changes on the root are not visible in the other partition
+    let fname = "/mnt/disk0p2/very/long/path/test.txt";
+    assert_eq!(fs::read_to_string(fname).unwrap(), "Rust is cool!\n");
+    fs::write("/mnt/disk0p2/new.txt", "partition 2\n").unwrap();
+    assert_eq!(
+        fs::read_to_string("/mnt/disk0p2/new.txt").unwrap(),
+        "partition 2\n"
+    );
+    assert!(fs::metadata("/new.txt").is_err());
+
+    // raw access to the disk and partitions
+    let mut buf = [0; BLOCK_SIZE];
+    let mut disk = fs::File::open("/dev/vda").unwrap();
+    disk.read_exact(&mut buf).unwrap();
+    assert_eq!(buf[510..], [0x55, 0xaa]);
+    let mut part_buf = [0; BLOCK_SIZE];
+    let mut part1 = fs::File::open("/dev/vda1").unwrap();
+    part1.read_exact(&mut part_buf).unwrap();
+    disk.seek(SeekFrom::Start((PART_START * BLOCK_SIZE) as u64))
+        .unwrap();
+    disk.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, part_buf);
+    assert_eq!(
+        fs::metadata("/dev/vda2").unwrap().file_type(),
+        fs::FileType::BlockDevice
+    );
+    assert!(fs::metadata("/dev/vda3").is_err());
+}
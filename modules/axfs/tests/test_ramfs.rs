@@ -6,7 +6,7 @@ use std::sync::Arc;
 
 use axdriver::AxDeviceContainer;
 use axfs::api::{self as fs, File};
-use axfs::fops::{Disk, MyFileSystemIf};
+use axfs::fops::{self, Cap, Directory, Disk, MyFileSystemIf};
 use axfs_ramfs::RamFileSystem;
 use axfs_vfs::VfsOps;
 use axio::{Result, Write};
@@ -17,7 +17,7 @@ struct MyFileSystemIfImpl;
 #[crate_interface::impl_interface]
 impl MyFileSystemIf for MyFileSystemIfImpl {
     fn new_myfs(_disk: Disk) -> Arc<dyn VfsOps> {
-        Arc::new(RamFileSystem::new())
+        Arc::new(RamFileSystem::new(None, None))
     }
 }
 
@@ -41,6 +41,187 @@ fn create_init_files() -> Result<()> {
     Ok(())
 }
 
+fn test_links() -> Result<()> {
+    println!("test links ...");
+
+    fs::create_dir("/links")?;
+    fs::write("/links/file.txt", "Rust is cool!\n")?;
+    fs::soft_link("file.txt", "/links/rel")?;
+    fs::soft_link("/links", "/links-abs")?;
+    fs::soft_link("/links/loop1", "/links/loop2")?;
+    fs::soft_link("/links/loop2", "/links/loop1")?;
+
+    // follow symbolic links in the middle and at the end of a path
+    assert_eq!(fs::read_to_string("/links/rel")?, "Rust is cool!\n");
+    assert_eq!(fs::read_to_string("/links-abs/rel")?, "Rust is cool!\n");
+    assert!(fs::metadata("/links-abs/")?.is_dir());
+    assert_eq!(fs::read_link("/links/rel")?, "file.txt");
+    assert!(fs::symlink_metadata("/links/rel")?.is_symlink());
+    assert!(!fs::metadata("/links/rel")?.is_symlink());
+    assert_eq!(
+        fs::read_link("/links/file.txt").err(),
+        Some(axio::Error::InvalidInput)
+    );
+    assert_eq!(
+        fs::read("/links/loop1").err(),
+        Some(axio::Error::FilesystemLoop)
+    );
+    assert_eq!(
+        fs::soft_link("x", "/links/rel").err(),
+        Some(axio::Error::AlreadyExists)
+    );
+
+    // create a file through a dangling symbolic link
+    fs::soft_link("new.txt", "/links/dangling")?;
+    fs::write("/links/dangling", "new\n")?;
+    assert_eq!(fs::read_to_string("/links/new.txt")?, "new\n");
+
+    // hard links
+    fs::hard_link("/links/file.txt", "/links/hard")?;
+    fs::remove_file("/links/file.txt")?;
+    assert_eq!(fs::read_to_string("/links/hard")?, "Rust is cool!\n");
+    assert_eq!(
+        fs::hard_link("/links", "/links2").err(),
+        Some(axio::Error::PermissionDenied)
+    );
+
+    // remove symbolic links themselves
+    fs::remove_file("/links-abs")?;
+    assert!(fs::metadata("/links").is_ok());
+    for name in ["rel", "loop1", "loop2", "dangling", "new.txt", "hard"] {
+        fs::remove_file(&format!("/links/{}", name))?;
+    }
+    fs::remove_dir("/links")?;
+
+    println!("test_links() OK!");
+    Ok(())
+}
+
+fn test_dir_at() -> Result<()> {
+    println!("test dir at ...");
+
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    let root = Directory::open_dir("/", &opts)?;
+    root.create_dir("at")?;
+    let dir = root.open_dir_at("at", &opts)?;
+    dir.create_dir("sub")?;
+    assert!(dir.get_attr_at("sub", true)?.is_dir());
+    assert_eq!(dir.absolute_path_at("sub")?, "/at/sub");
+    dir.check_access_at("sub", Cap::READ | Cap::EXECUTE, true)?;
+
+    // paths relative to a directory opened relative to another one
+    let mut sub = dir.open_dir_at("sub", &opts)?;
+    let mut wopts = fops::OpenOptions::new();
+    wopts.write(true);
+    wopts.create(true);
+    sub.open_file_at("file.txt", &wopts)?.write(b"at\n")?;
+    assert_eq!(fs::read_to_string("/at/sub/file.txt")?, "at\n");
+    assert_eq!(sub.absolute_path_at("file.txt")?, "/at/sub/file.txt");
+
+    // directory entries can be read again after rewinding
+    let mut entries = [fops::DirEntry::default(), fops::DirEntry::default()];
+    let n = sub.read_dir(&mut entries)?;
+    assert!(n > 0 && sub.position() == n);
+    sub.set_position(0);
+    assert_eq!(sub.read_dir(&mut entries)?, n);
+
+    sub.set_current_dir()?;
+    assert_eq!(fs::current_dir()?, "/at/sub/");
+    assert_eq!(fs::read_to_string("file.txt")?, "at\n");
+    fs::set_current_dir("/")?;
+
+    sub.remove_file("file.txt")?;
+    dir.remove_dir("sub")?;
+    root.remove_dir("at")?;
+
+    println!("test_dir_at() OK!");
+    Ok(())
+}
+
+fn test_mount() -> Result<()> {
+    println!("test mount ...");
+
+    fs::mount("/mnt", fs::new_fs("ramfs")?)?;
+    fs::write("/mnt/outer.txt", "outer\n")?;
+    fs::mount("/mnt/inner/", fs::new_fs("tmpfs")?)?;
+    fs::write("/mnt/inner/inner.txt", "inner\n")?;
+    assert!(fs::mounts().iter().any(|p| p == "/mnt"));
+    assert!(fs::mounts().iter().any(|p| p == "/mnt/inner"));
+    assert!(fs::new_fs("nofs").is_err());
+    assert_eq!(fs::statfs("/mnt/inner/inner.txt")?.block_size(), 4096);
+    assert_eq!(
+        fs::statfs("/mnt/nothing").err(),
+        Some(axio::Error::NotFound)
+    );
+    assert_eq!(
+        fs::mount("/mnt", fs::new_fs("ramfs")?).err(),
+        Some(axio::Error::InvalidInput)
+    );
+    assert_eq!(fs::umount("/very").err(), Some(axio::Error::InvalidInput));
+
+    // busy with nested mount points or opened files
+    assert_eq!(fs::umount("/mnt").err(), Some(axio::Error::ResourceBusy));
+    let file = File::open("/mnt/inner/inner.txt")?;
+    assert_eq!(
+        fs::umount("/mnt/inner").err(),
+        Some(axio::Error::ResourceBusy)
+    );
+    drop(file);
+    fs::umount("/mnt/inner")?;
+    assert!(fs::metadata("/mnt/inner/inner.txt").is_err());
+    assert!(fs::metadata("/mnt/inner")?.is_dir());
+
+    fs::umount("/mnt")?;
+    assert!(fs::metadata("/mnt/outer.txt").is_err());
+    assert!(!fs::mounts().iter().any(|p| p.starts_with("/mnt")));
+    fs::remove_dir("/mnt")?;
+
+    println!("test_mount() OK!");
+    Ok(())
+}
+
+#[cfg(feature = "overlayfs")]
+fn test_overlay() -> Result<()> {
+    println!("test overlay ...");
+
+    fs::mount("/lower", fs::new_fs("ramfs")?)?;
+    fs::create_dir("/lower/dir")?;
+    fs::write("/lower/dir/a.txt", "lower a\n")?;
+    fs::write("/lower/b.txt", "lower b\n")?;
+    fs::mount("/merged", fs::overlay_fs("/lower", fs::new_fs("ramfs")?)?)?;
+    assert_eq!(
+        fs::overlay_fs("/lower/dir", fs::new_fs("ramfs")?).err(),
+        Some(axio::Error::InvalidInput)
+    );
+
+    // files are copied up on write
+    assert_eq!(fs::read_to_string("/merged/dir/a.txt")?, "lower a\n");
+    fs::write("/merged/dir/a.txt", "upper a\n")?;
+    assert_eq!(fs::read_to_string("/merged/dir/a.txt")?, "upper a\n");
+    assert_eq!(fs::read_to_string("/lower/dir/a.txt")?, "lower a\n");
+
+    // removed files are hidden, and directories are merged
+    fs::remove_file("/merged/b.txt")?;
+    assert!(fs::metadata("/merged/b.txt").is_err());
+    assert!(fs::metadata("/lower/b.txt").is_ok());
+    fs::write("/merged/dir/c.txt", "upper c\n")?;
+    let mut names = fs::read_dir("/merged/dir")?
+        .map(|e| e.map(|e| e.file_name()))
+        .collect::<Result<Vec<_>>>()?;
+    names.sort();
+    assert_eq!(names, ["a.txt", "c.txt"]);
+    assert!(fs::metadata("/lower/dir/c.txt").is_err());
+
+    fs::umount("/merged")?;
+    fs::umount("/lower")?;
+    fs::remove_dir("/merged")?;
+    fs::remove_dir("/lower")?;
+
+    println!("test_overlay() OK!");
+    Ok(())
+}
+
 #[test]
 fn test_ramfs() {
     println!("Testing ramfs ...");
@@ -53,4 +234,9 @@ fn test_ramfs() {
     }
 
     test_common::test_all();
+    test_links().expect("test_links() failed");
+    test_dir_at().expect("test_dir_at() failed");
+    test_mount().expect("test_mount() failed");
+    #[cfg(feature = "overlayfs")]
+    test_overlay().expect("test_overlay() failed");
 }
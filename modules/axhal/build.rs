@@ -17,6 +17,12 @@ const BUILTIN_PLATFORM_FAMILIES: &[&str] = &[
     "x86-pc",
 ];
 
+const SUPPORTED_PAGING_LEVELS: &[(&str, &[usize])] = &[
+    ("x86_64", &[4, 5]),
+    ("riscv64", &[3, 4, 5]),
+    ("aarch64", &[4]),
+];
+
 fn make_cfg_values(str_list: &[&str]) -> String {
     str_list
         .iter()
@@ -32,8 +38,16 @@ fn main() {
         gen_linker_script(&arch, platform).unwrap();
     }
 
+    let levels = axconfig::PAGING_LEVELS;
+    if let Some((_, supported)) = SUPPORTED_PAGING_LEVELS.iter().find(|(a, _)| *a == arch) {
+        if !supported.contains(&levels) {
+            panic!("unsupported paging levels {levels} for architecture {arch}");
+        }
+    }
+
     println!("cargo:rustc-cfg=platform=\"{}\"", platform);
     println!("cargo:rustc-cfg=platform_family=\"{}\"", axconfig::FAMILY);
+    println!("cargo:rustc-cfg=paging_levels=\"{}\"", levels);
     println!(
         "cargo::rustc-check-cfg=cfg(platform, values({}))",
         make_cfg_values(BUILTIN_PLATFORMS)
@@ -42,6 +56,7 @@ fn main() {
         "cargo::rustc-check-cfg=cfg(platform_family, values({}))",
         make_cfg_values(BUILTIN_PLATFORM_FAMILIES)
     );
+    println!("cargo::rustc-check-cfg=cfg(paging_levels, values(\"3\", \"4\", \"5\"))");
 }
 
 fn gen_linker_script(arch: &str, platform: &str) -> Result<()> {
@@ -3,8 +3,9 @@ pub(crate) mod trap;
 
 use core::arch::asm;
 
-use aarch64_cpu::registers::{DAIF, TPIDR_EL0, TTBR0_EL1, TTBR1_EL1, VBAR_EL1};
+use aarch64_cpu::registers::{DAIF, TCR_EL1, TPIDR_EL0, TTBR0_EL1, TTBR1_EL1, VBAR_EL1};
 use memory_addr::{PhysAddr, VirtAddr};
+use page_table_entry::aarch64::A64PTE;
 use tock_registers::interfaces::{Readable, Writeable};
 
 pub use self::context::{FpState, TaskContext, TrapFrame};
@@ -104,6 +105,18 @@ pub fn flush_icache_all() {
     unsafe { asm!("ic iallu; dsb sy; isb") };
 }
 
+/// Hardware update of the access flag in `TCR_EL1`.
+pub(crate) const TCR_HA: u64 = 1 << 39;
+/// Hardware update of the dirty state in `TCR_EL1`.
+pub(crate) const TCR_HD: u64 = 1 << 40;
+
+/// Tells the page table entries whether the hardware updates the access flag
+/// and the dirty state, as enabled in `TCR_EL1` at boot.
+pub(crate) fn init_hw_access_dirty() {
+    let tcr = TCR_EL1.get();
+    A64PTE::set_hw_access_dirty(tcr & TCR_HA != 0, tcr & TCR_HD != 0);
+}
+
 /// Sets the base address of the exception vector (writes `VBAR_EL1`).
 #[inline]
 pub fn set_exception_vector_base(vbar_el1: usize) {
@@ -116,6 +129,62 @@ pub fn flush_dcache_line(vaddr: VirtAddr) {
     unsafe { asm!("dc ivac, {0:x}; dsb sy; isb", in(reg) vaddr.as_usize()) };
 }
 
+/// Returns the size of the smallest data cache line (reads `CTR_EL0.DminLine`).
+#[inline]
+fn dcache_line_size() -> usize {
+    let ctr_el0: usize;
+    unsafe { asm!("mrs {}, ctr_el0", out(reg) ctr_el0) };
+    4 << ((ctr_el0 >> 16) & 0xf)
+}
+
+/// Applies the data cache maintenance instruction `op` to each cache line in
+/// `[vaddr, vaddr + size)`, and then waits for completion.
+macro_rules! dcache_range_op {
+    ($op: literal, $vaddr: expr, $size: expr) => {{
+        let line_size = dcache_line_size();
+        let end = $vaddr + $size;
+        let mut addr = $vaddr & !(line_size - 1);
+        while addr < end {
+            unsafe { asm!(concat!("dc ", $op, ", {0:x}"), in(reg) addr) };
+            addr += line_size;
+        }
+        unsafe { asm!("dsb sy") };
+    }};
+}
+
+/// Cleans the data cache in the given range to the point of coherency, so that
+/// the data written by the CPU can be seen by devices.
+pub fn clean_dcache_range(vaddr: VirtAddr, size: usize) {
+    dcache_range_op!("cvac", vaddr.as_usize(), size);
+}
+
+/// Cleans and invalidates the data cache in the given range to the point of
+/// coherency.
+pub fn flush_dcache_range(vaddr: VirtAddr, size: usize) {
+    dcache_range_op!("civac", vaddr.as_usize(), size);
+}
+
+/// Invalidates the data cache in the given range, so that the data written by
+/// devices can be seen by the CPU.
+///
+/// Cache lines partially covered by the range are cleaned and invalidated
+/// instead, to avoid discarding the data outside the range.
+pub fn invalidate_dcache_range(vaddr: VirtAddr, size: usize) {
+    let line_mask = dcache_line_size() - 1;
+    let (mut start, mut end) = (vaddr.as_usize(), vaddr.as_usize() + size);
+    if start & line_mask != 0 {
+        dcache_range_op!("civac", start, 1);
+        start = (start | line_mask) + 1;
+    }
+    if end & line_mask != 0 {
+        dcache_range_op!("civac", end - 1, 1);
+        end &= !line_mask;
+    }
+    if start < end {
+        dcache_range_op!("ivac", start, end - start);
+    }
+}
+
 /// Reads the thread pointer of the current CPU.
 ///
 /// It is used to implement TLS (Thread Local Storage).
@@ -5,11 +5,60 @@ mod context;
 mod trap;
 
 use memory_addr::{PhysAddr, VirtAddr};
+use page_table_entry::riscv::Rv64PTE;
 use riscv::asm;
 use riscv::register::{satp, sstatus, stvec};
 
 pub use self::context::{GeneralRegisters, TaskContext, TrapFrame};
 
+/// The address translation mode of the page tables, selected by the
+/// `paging-levels` config.
+const SATP_MODE: satp::Mode = match axconfig::PAGING_LEVELS {
+    3 => satp::Mode::Sv39,
+    4 => satp::Mode::Sv48,
+    5 => satp::Mode::Sv57,
+    _ => panic!("unsupported paging levels"),
+};
+
+/// The SBI firmware features extension (FWFT).
+const SBI_EID_FWFT: usize = 0x4657_4654;
+/// The FWFT feature of the hardware update of the A and D bits (Svadu).
+const SBI_FWFT_PTE_AD_HW_UPDATING: usize = 3;
+
+/// Asks the SBI to enable the hardware update of the A and D bits on the
+/// current CPU, returns whether it succeeded.
+fn enable_hw_access_dirty() -> bool {
+    let error: isize;
+    // sbi_fwft_set(PTE_AD_HW_UPDATING, 1, 0)
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            in("a7") SBI_EID_FWFT,
+            in("a6") 0,
+            inlateout("a0") SBI_FWFT_PTE_AD_HW_UPDATING => error,
+            inlateout("a1") 1 => _,
+            in("a2") 0,
+        )
+    };
+    error == 0
+}
+
+/// Enables the hardware update of the A and D bits on the primary CPU if the
+/// platform supports it (Svadu), and tells the page table entries the result.
+pub(crate) fn init_hw_access_dirty() {
+    Rv64PTE::set_hw_access_dirty(enable_hw_access_dirty());
+}
+
+/// Enables the hardware update of the A and D bits on a secondary CPU.
+///
+/// If it fails, the A and D bits are never cleared, as on CPUs without Svadu.
+#[cfg(feature = "smp")]
+pub(crate) fn init_hw_access_dirty_secondary() {
+    if !enable_hw_access_dirty() {
+        Rv64PTE::set_hw_access_dirty(false);
+    }
+}
+
 /// Allows the current CPU to respond to interrupts.
 #[inline]
 pub fn enable_irqs() {
@@ -60,7 +109,7 @@ pub unsafe fn write_page_table_root(root_paddr: PhysAddr) {
     let old_root = read_page_table_root();
     trace!("set page table root: {:#x} => {:#x}", old_root, root_paddr);
     if old_root != root_paddr {
-        satp::set(satp::Mode::Sv39, 0, root_paddr.as_usize() >> 12);
+        satp::set(SATP_MODE, 0, root_paddr.as_usize() >> 12);
         asm::sfence_vma_all();
     }
 }
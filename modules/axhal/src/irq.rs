@@ -1,5 +1,6 @@
 //! Interrupt management.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
 use handler_table::HandlerTable;
 
 use crate::platform::irq::MAX_IRQ_COUNT;
@@ -11,10 +12,37 @@ pub type IrqHandler = handler_table::Handler;
 
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
+/// Number of times each IRQ has been dispatched.
+static IRQ_COUNTS: [AtomicUsize; MAX_IRQ_COUNT] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicUsize = AtomicUsize::new(0);
+    [ZERO; MAX_IRQ_COUNT]
+};
+
+/// Returns the number of times each IRQ has been dispatched since boot, as
+/// `(irq_num, count)` pairs for the IRQs that have occurred.
+pub fn irq_counts() -> impl Iterator<Item = (usize, usize)> {
+    IRQ_COUNTS
+        .iter()
+        .map(|count| count.load(Ordering::Relaxed))
+        .enumerate()
+        .filter(|&(_, count)| count > 0)
+}
+
+/// Counts an IRQ that does not go through [`dispatch_irq_common`], such as
+/// the timer interrupt on some platforms.
+#[allow(dead_code)]
+pub(crate) fn count_irq(irq_num: usize) {
+    if let Some(count) = IRQ_COUNTS.get(irq_num) {
+        count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 /// Platform-independent IRQ dispatching.
 #[allow(dead_code)]
 pub(crate) fn dispatch_irq_common(irq_num: usize) {
     trace!("IRQ {}", irq_num);
+    count_irq(irq_num);
     if !IRQ_HANDLER_TABLE.handle(irq_num) {
         warn!("Unhandled IRQ {}", irq_num);
     }
@@ -1,7 +1,9 @@
 //! Page table manipulation.
 
 use axalloc::global_allocator;
+use lazy_init::LazyInit;
 use page_table::PagingIf;
+use spinlock::SpinNoIrq;
 
 use crate::mem::{phys_to_virt, virt_to_phys, MemRegionFlags, PhysAddr, VirtAddr, PAGE_SIZE_4K};
 
@@ -53,9 +55,18 @@ impl PagingIf for PagingIfImpl {
 }
 
 cfg_if::cfg_if! {
-    if #[cfg(target_arch = "x86_64")] {
+    if #[cfg(all(target_arch = "x86_64", paging_levels = "5"))] {
+        /// The architecture-specific page table.
+        pub type PageTable = page_table::x86_64::X64La57PageTable<PagingIfImpl>;
+    } else if #[cfg(target_arch = "x86_64")] {
         /// The architecture-specific page table.
         pub type PageTable = page_table::x86_64::X64PageTable<PagingIfImpl>;
+    } else if #[cfg(all(any(target_arch = "riscv32", target_arch = "riscv64"), paging_levels = "5"))] {
+        /// The architecture-specific page table.
+        pub type PageTable = page_table::riscv::Sv57PageTable<PagingIfImpl>;
+    } else if #[cfg(all(any(target_arch = "riscv32", target_arch = "riscv64"), paging_levels = "4"))] {
+        /// The architecture-specific page table.
+        pub type PageTable = page_table::riscv::Sv48PageTable<PagingIfImpl>;
     } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
         /// The architecture-specific page table.
         pub type PageTable = page_table::riscv::Sv39PageTable<PagingIfImpl>;
@@ -64,3 +75,21 @@ cfg_if::cfg_if! {
         pub type PageTable = page_table::aarch64::A64PageTable<PagingIfImpl>;
     }
 }
+
+static KERNEL_PAGE_TABLE: LazyInit<SpinNoIrq<PageTable>> = LazyInit::new();
+
+/// Sets the kernel page table, which is shared by all CPUs.
+///
+/// It must be called only once (on the primary CPU) before any call of
+/// [`kernel_page_table`].
+pub fn init_kernel_page_table(page_table: PageTable) {
+    KERNEL_PAGE_TABLE.init_by(SpinNoIrq::new(page_table));
+}
+
+/// Returns a reference to the kernel page table.
+///
+/// The mappings of the kernel page table can be modified at runtime (e.g.,
+/// by `mmap`), and the caller is responsible for flushing the TLB.
+pub fn kernel_page_table() -> &'static SpinNoIrq<PageTable> {
+    &KERNEL_PAGE_TABLE
+}
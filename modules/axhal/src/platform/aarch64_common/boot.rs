@@ -75,6 +75,15 @@ unsafe fn init_mmu() {
         + TCR_EL1::IRGN1::WriteBack_ReadAlloc_WriteAlloc_Cacheable
         + TCR_EL1::T1SZ.val(16);
     TCR_EL1.write(TCR_EL1::IPS::Bits_48 + tcr_flags0 + tcr_flags1);
+    // Let the hardware update the access flag and the dirty state if supported.
+    let hafdbs: u64;
+    core::arch::asm!("mrs {}, id_aa64mmfr1_el1", out(reg) hafdbs);
+    let tcr_hw_flags = match hafdbs & 0xf {
+        0 => 0,
+        1 => crate::arch::TCR_HA,
+        _ => crate::arch::TCR_HA | crate::arch::TCR_HD,
+    };
+    TCR_EL1.set(TCR_EL1.get() | tcr_hw_flags);
     barrier::isb(barrier::SY);
 
     // Set both TTBR0 and TTBR1
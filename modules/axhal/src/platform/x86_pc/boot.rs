@@ -23,11 +23,16 @@ const CR0: u64 = Cr0Flags::PROTECTED_MODE_ENABLE.bits()
     | Cr0Flags::PAGING.bits();
 const CR4: u64 = Cr4Flags::PHYSICAL_ADDRESS_EXTENSION.bits()
     | Cr4Flags::PAGE_GLOBAL.bits()
+    | if LA57 { Cr4Flags::L5_PAGING.bits() } else { 0 }
     | if cfg!(feature = "fp_simd") {
         Cr4Flags::OSFXSR.bits() | Cr4Flags::OSXMMEXCPT_ENABLE.bits()
     } else {
         0
     };
+/// Whether to enable 5-level paging, which can only be set before entering
+/// the long mode.
+const LA57: bool = axconfig::PAGING_LEVELS == 5;
+
 const EFER: u64 = EferFlags::LONG_MODE_ENABLE.bits() | EferFlags::NO_EXECUTE_ENABLE.bits();
 
 #[link_section = ".bss.stack"]
@@ -47,6 +52,7 @@ global_asm!(
 
     cr0 = const CR0,
     cr4 = const CR4,
+    la57 = const LA57 as usize,
     efer_msr = const x86::msr::IA32_EFER,
     efer = const EFER,
 );
@@ -0,0 +1,162 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) input module.
+//!
+//! Events of input devices (e.g. keyboards, mice and tablets) are queued per
+//! device in the format of Linux evdev, with the time they are fetched from
+//! the driver. Devices are numbered in the order they are probed.
+//!
+//! Events are fetched from the drivers when the queues are read, so that
+//! devices must be read regularly, or events may be lost by the device. If a
+//! queue is full, its events are dropped and replaced by a `SYN_DROPPED`
+//! event, as evdev does.
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+#[doc(no_inline)]
+pub use driver_input::{AbsInfo, EventType, InputDeviceId};
+
+use alloc::{collections::VecDeque, string::String, vec::Vec};
+use axdriver::{prelude::*, AxDeviceContainer};
+use axhal::time::TimeValue;
+use axsync::Mutex;
+use lazy_init::LazyInit;
+
+/// The maximum number of queued events of each device.
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// The code of the event that replaces dropped events (`SYN_DROPPED`).
+const SYN_DROPPED: u16 = 3;
+
+/// An input event, as `struct input_event` of Linux.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    /// The time the event is fetched from the driver.
+    pub time: TimeValue,
+    /// The type of the event, see [`EventType`].
+    pub event_type: u16,
+    /// The code of the event, e.g. `KEY_A` or `REL_X`.
+    pub code: u16,
+    /// The value of the event.
+    pub value: i32,
+}
+
+/// The information of an input device.
+#[derive(Debug, Clone)]
+pub struct InputDeviceInfo {
+    /// The name of the device, e.g. `QEMU Virtio Keyboard`.
+    pub name: String,
+    /// The identity of the device.
+    pub id: InputDeviceId,
+}
+
+struct InputDevice {
+    dev: AxInputDevice,
+    events: VecDeque<InputEvent>,
+}
+
+impl InputDevice {
+    /// Moves the pending events of the driver to the queue.
+    fn fetch_events(&mut self) {
+        loop {
+            let event = match self.dev.read_event() {
+                Ok(event) => event,
+                Err(DevError::Again) => return,
+                Err(e) => {
+                    warn!("failed to read input event: {:?}", e);
+                    return;
+                }
+            };
+            let time = axhal::time::current_time();
+            if self.events.len() >= MAX_QUEUED_EVENTS {
+                self.events.clear();
+                self.events.push_back(InputEvent {
+                    time,
+                    event_type: EventType::Synchronization as u16,
+                    code: SYN_DROPPED,
+                    value: 0,
+                });
+            }
+            self.events.push_back(InputEvent {
+                time,
+                event_type: event.event_type,
+                code: event.code,
+                value: event.value,
+            });
+        }
+    }
+}
+
+static INPUT_DEVICES: LazyInit<Vec<Mutex<InputDevice>>> = LazyInit::new();
+
+/// Initializes the input subsystem by underlayer devices.
+pub fn init_input(mut input_devs: AxDeviceContainer<AxInputDevice>) {
+    info!("Initialize input subsystem...");
+
+    let mut devices = Vec::new();
+    while let Some(dev) = input_devs.take_one() {
+        info!(
+            "  use input device {}: {:?} ({:?})",
+            devices.len(),
+            dev.device_name(),
+            dev.name()
+        );
+        devices.push(Mutex::new(InputDevice {
+            dev,
+            events: VecDeque::new(),
+        }));
+    }
+    if devices.is_empty() {
+        warn!("No input device found!");
+    }
+    INPUT_DEVICES.init_by(devices);
+}
+
+fn device(index: usize) -> Option<&'static Mutex<InputDevice>> {
+    INPUT_DEVICES.get(index)
+}
+
+/// Returns the number of input devices.
+pub fn num_devices() -> usize {
+    INPUT_DEVICES.len()
+}
+
+/// Returns the information of the input device `index`.
+pub fn device_info(index: usize) -> Option<InputDeviceInfo> {
+    let dev = device(index)?.lock();
+    Some(InputDeviceInfo {
+        name: dev.dev.name().into(),
+        id: dev.dev.device_id(),
+    })
+}
+
+/// Writes the bitmap of the codes that the input device `index` supports for
+/// the event type `ty` to `out`.
+///
+/// Returns whether the device supports the event type at all.
+pub fn event_bits(index: usize, ty: EventType, out: &mut [u8]) -> bool {
+    device(index).is_some_and(|dev| dev.lock().dev.get_event_bits(ty, out).unwrap_or(false))
+}
+
+/// Returns the range of the absolute axis `axis` of the input device `index`.
+pub fn abs_info(index: usize, axis: u8) -> Option<AbsInfo> {
+    device(index)?.lock().dev.get_abs_info(axis).ok()
+}
+
+/// Takes the next event of the input device `index`, without waiting.
+pub fn read_event(index: usize) -> Option<InputEvent> {
+    let mut dev = device(index)?.lock();
+    dev.fetch_events();
+    dev.events.pop_front()
+}
+
+/// Returns whether the input device `index` has queued events.
+pub fn has_events(index: usize) -> bool {
+    device(index).is_some_and(|dev| {
+        let mut dev = dev.lock();
+        dev.fetch_events();
+        !dev.events.is_empty()
+    })
+}
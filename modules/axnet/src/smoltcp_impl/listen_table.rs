@@ -1,4 +1,4 @@
-use alloc::{boxed::Box, collections::VecDeque};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::ops::{Deref, DerefMut};
 
 use axerrno::{ax_err, AxError, AxResult};
@@ -7,7 +7,8 @@ use smoltcp::iface::{SocketHandle, SocketSet};
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{SocketSetWrapper, LISTEN_QUEUE_SIZE, SOCKET_SET};
+use super::addr::UNSPECIFIED_IP;
+use super::{listen_backlog, SocketSetWrapper, SOCKET_SET};
 
 const PORT_NUM: usize = 65536;
 
@@ -20,7 +21,7 @@ impl ListenTableEntry {
     pub fn new(listen_endpoint: IpListenEndpoint) -> Self {
         Self {
             listen_endpoint,
-            syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            syn_queue: VecDeque::new(),
         }
     }
 
@@ -73,6 +74,21 @@ impl ListenTable {
         }
     }
 
+    /// Returns the endpoints of all listening sockets.
+    pub fn listen_endpoints(&self) -> Vec<IpEndpoint> {
+        self.tcp
+            .iter()
+            .filter_map(|entry| {
+                let entry = entry.lock();
+                let endpoint = entry.as_ref()?.listen_endpoint;
+                Some(IpEndpoint::new(
+                    endpoint.addr.unwrap_or(UNSPECIFIED_IP),
+                    endpoint.port,
+                ))
+            })
+            .collect()
+    }
+
     pub fn unlisten(&self, port: u16) {
         debug!("TCP socket unlisten on {}", port);
         *self.tcp[port as usize].lock() = None;
@@ -121,7 +137,7 @@ impl ListenTable {
                 // not listening on this address
                 return;
             }
-            if entry.syn_queue.len() >= LISTEN_QUEUE_SIZE {
+            if entry.syn_queue.len() >= listen_backlog() {
                 // SYN queue is full, drop the packet
                 warn!("SYN queue overflow!");
                 return;
@@ -5,11 +5,14 @@ mod listen_table;
 mod tcp;
 mod udp;
 
-use alloc::vec;
+use alloc::{vec, vec::Vec};
 use core::cell::RefCell;
+use core::net::SocketAddr;
 use core::ops::DerefMut;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use axdriver::prelude::*;
+use axerrno::{ax_err, AxResult};
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS};
 use axsync::Mutex;
 use driver_net::{DevError, NetBufPtr};
@@ -20,6 +23,7 @@ use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
 use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
 
+use self::addr::{into_core_sockaddr, UNSPECIFIED_ENDPOINT};
 use self::listen_table::ListenTable;
 
 pub use self::dns::dns_query;
@@ -49,7 +53,9 @@ const TCP_TX_BUF_LEN: usize = 64 * 1024;
 const UDP_RX_BUF_LEN: usize = 64 * 1024;
 const UDP_TX_BUF_LEN: usize = 64 * 1024;
 const LISTEN_QUEUE_SIZE: usize = 512;
+const MAX_LISTEN_QUEUE_SIZE: usize = 4096;
 
+static LISTEN_BACKLOG: AtomicUsize = AtomicUsize::new(LISTEN_QUEUE_SIZE);
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
 static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
 static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
@@ -300,6 +306,83 @@ pub fn poll_interfaces() {
     SOCKET_SET.poll_interfaces();
 }
 
+/// Returns the maximum number of pending connections of each listening TCP
+/// socket.
+pub fn listen_backlog() -> usize {
+    LISTEN_BACKLOG.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum number of pending connections of each listening TCP
+/// socket, like `net.core.somaxconn` in Linux. Connections beyond the limit
+/// are dropped.
+///
+/// The value is clamped to 4096, and returns [`InvalidInput`] if it is 0.
+///
+/// [`InvalidInput`]: axerrno::AxError::InvalidInput
+pub fn set_listen_backlog(backlog: usize) -> AxResult {
+    if backlog == 0 {
+        return ax_err!(InvalidInput, "listen backlog must be positive");
+    }
+    LISTEN_BACKLOG.store(backlog.min(MAX_LISTEN_QUEUE_SIZE), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Information of a TCP socket, returned by [`tcp_sockets`].
+#[derive(Debug, Clone, Copy)]
+pub struct TcpSocketInfo {
+    /// The local address.
+    pub local_addr: SocketAddr,
+    /// The remote address, which is unspecified for listening sockets.
+    pub peer_addr: SocketAddr,
+    /// The state number used by Linux `/proc/net/tcp`, e.g. 1 for
+    /// `ESTABLISHED` and 10 for `LISTEN`.
+    pub state: u8,
+}
+
+/// Returns the information of all listening and connected TCP sockets.
+pub fn tcp_sockets() -> Vec<TcpSocketInfo> {
+    let mut sockets: Vec<_> = LISTEN_TABLE
+        .listen_endpoints()
+        .into_iter()
+        .map(|endpoint| TcpSocketInfo {
+            local_addr: into_core_sockaddr(endpoint),
+            peer_addr: into_core_sockaddr(UNSPECIFIED_ENDPOINT),
+            state: 10,
+        })
+        .collect();
+    let set = SOCKET_SET.0.lock();
+    for (_, socket) in set.iter() {
+        let Some(socket) = socket::tcp::Socket::downcast(socket) else {
+            continue;
+        };
+        if let (Some(local), Some(peer)) = (socket.local_endpoint(), socket.remote_endpoint()) {
+            sockets.push(TcpSocketInfo {
+                local_addr: into_core_sockaddr(local),
+                peer_addr: into_core_sockaddr(peer),
+                state: linux_tcp_state(socket.state()),
+            });
+        }
+    }
+    sockets
+}
+
+fn linux_tcp_state(state: socket::tcp::State) -> u8 {
+    use socket::tcp::State::*;
+    match state {
+        Established => 1,
+        SynSent => 2,
+        SynReceived => 3,
+        FinWait1 => 4,
+        FinWait2 => 5,
+        TimeWait => 6,
+        Closed => 7,
+        CloseWait => 8,
+        LastAck => 9,
+        Listen => 10,
+        Closing => 11,
+    }
+}
+
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
     ETH0.dev.lock().bench_transmit_bandwidth();
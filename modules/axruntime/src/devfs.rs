@@ -0,0 +1,202 @@
+//! Device nodes of `/dev` for devices that are not managed by `axfs`.
+
+use alloc::sync::Arc;
+use axfs_vfs::{alloc_ino, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+pub(crate) fn init() {
+    let console = Arc::new(ConsoleDev { ino: alloc_ino() });
+    axfs::register_device("console", console.clone());
+    axfs::register_device("ttyS0", console);
+
+    #[cfg(feature = "display")]
+    axfs::register_device("fb0", Arc::new(FramebufferDev { ino: alloc_ino() }));
+
+    #[cfg(feature = "input")]
+    for index in 0..axinput::num_devices() {
+        let path = alloc::format!("input/event{}", index);
+        let dev = InputEventDev {
+            index,
+            ino: alloc_ino(),
+        };
+        axfs::register_device(&path, Arc::new(dev));
+    }
+}
+
+/// The UART console, as `/dev/console` and `/dev/ttyS0`.
+struct ConsoleDev {
+    ino: u64,
+}
+
+impl VfsNodeOps for ConsoleDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o620),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        )
+        .with_ino(self.ino))
+    }
+
+    /// Blocks until at least one byte is read.
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut read_len = 0;
+        while read_len == 0 {
+            while let Some(c) = axhal::console::getchar() {
+                buf[read_len] = if c == b'\r' { b'\n' } else { c };
+                read_len += 1;
+                if read_len == buf.len() {
+                    break;
+                }
+            }
+            if read_len == 0 {
+                #[cfg(feature = "multitask")]
+                axtask::yield_now();
+                #[cfg(not(feature = "multitask"))]
+                core::hint::spin_loop();
+            }
+        }
+        Ok(read_len)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        axhal::console::write_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The framebuffer of the main display, as `/dev/fb0`.
+///
+/// Writes are shown on the screen immediately.
+#[cfg(feature = "display")]
+struct FramebufferDev {
+    ino: u64,
+}
+
+#[cfg(feature = "display")]
+impl FramebufferDev {
+    fn framebuffer() -> &'static mut [u8] {
+        let info = axdisplay::framebuffer_info();
+        unsafe { core::slice::from_raw_parts_mut(info.fb_base_vaddr as *mut u8, info.fb_size) }
+    }
+}
+
+#[cfg(feature = "display")]
+impl VfsNodeOps for FramebufferDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = axdisplay::framebuffer_info().fb_size as u64;
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::CharDevice,
+            size,
+            0,
+        )
+        .with_ino(self.ino))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let fb = Self::framebuffer();
+        let start = fb.len().min(offset as usize);
+        let len = buf.len().min(fb.len() - start);
+        buf[..len].copy_from_slice(&fb[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let fb = Self::framebuffer();
+        let start = fb.len().min(offset as usize);
+        let len = buf.len().min(fb.len() - start);
+        if len == 0 && !buf.is_empty() {
+            return Err(axfs_vfs::VfsError::StorageFull);
+        }
+        fb[start..start + len].copy_from_slice(&buf[..len]);
+        axdisplay::framebuffer_flush();
+        Ok(len)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// An input device, as `/dev/input/eventN`.
+///
+/// Reads return whole `struct input_event` records of Linux. Like evdev
+/// opened with `O_NONBLOCK`, they fail with `WouldBlock` if no event is
+/// queued, instead of waiting for the device, which is only polled on reads.
+#[cfg(feature = "input")]
+struct InputEventDev {
+    index: usize,
+    ino: u64,
+}
+
+#[cfg(feature = "input")]
+impl InputEventDev {
+    /// The size of `struct input_event { struct timeval time; u16 type;
+    /// u16 code; i32 value; }`.
+    const EVENT_SIZE: usize = 24;
+
+    fn encode(event: &axinput::InputEvent, buf: &mut [u8]) {
+        let sec = event.time.as_secs() as i64;
+        let usec = event.time.subsec_micros() as i64;
+        buf[0..8].copy_from_slice(&sec.to_ne_bytes());
+        buf[8..16].copy_from_slice(&usec.to_ne_bytes());
+        buf[16..18].copy_from_slice(&event.event_type.to_ne_bytes());
+        buf[18..20].copy_from_slice(&event.code.to_ne_bytes());
+        buf[20..24].copy_from_slice(&event.value.to_ne_bytes());
+    }
+}
+
+#[cfg(feature = "input")]
+impl VfsNodeOps for InputEventDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        )
+        .with_ino(self.ino))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if buf.len() < Self::EVENT_SIZE {
+            return Err(axfs_vfs::VfsError::InvalidInput);
+        }
+        let mut read_len = 0;
+        while buf.len() - read_len >= Self::EVENT_SIZE {
+            match axinput::read_event(self.index) {
+                Some(event) => {
+                    Self::encode(&event, &mut buf[read_len..]);
+                    read_len += Self::EVENT_SIZE;
+                }
+                None => break,
+            }
+        }
+        if read_len == 0 {
+            return Err(axfs_vfs::VfsError::WouldBlock);
+        }
+        Ok(read_len)
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(axfs_vfs::VfsError::Unsupported)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
@@ -10,9 +10,15 @@
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
-//! - `fs`: Enable filesystem support.
+//! - `fs`: Enable filesystem support. Device nodes of the console and the
+//!   framebuffer are added to `/dev`, and `/proc` is filled with the states
+//!   of other enabled modules.
+//! - `9p`: Start 9P sessions on 9P transport devices, to mount filesystems
+//!   shared by the host.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
+//! - `input`: Enable input device support. With `fs`, input devices are added
+//!   to `/dev/input`.
 //!
 //! All the features are optional and disabled by default.
 
@@ -22,6 +28,9 @@
 #[macro_use]
 extern crate axlog;
 
+#[cfg(feature = "fs")]
+extern crate alloc;
+
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
 mod trap;
@@ -29,6 +38,11 @@ mod trap;
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(feature = "fs")]
+mod devfs;
+#[cfg(feature = "fs")]
+mod procfs;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
@@ -84,6 +98,22 @@ impl axlog::LogIf for LogIfImpl {
     }
 }
 
+#[cfg(feature = "fs")]
+struct CredentialIfImpl;
+
+#[cfg(feature = "fs")]
+#[crate_interface::impl_interface]
+impl axfs::fops::CredentialIf for CredentialIfImpl {
+    fn current_credential() -> axfs::fops::Credential {
+        #[cfg(feature = "multitask")]
+        if let Some(curr) = axtask::current_may_uninit() {
+            let (uid, gid) = curr.credential();
+            return axfs::fops::Credential::new(uid, gid);
+        }
+        axfs::fops::Credential::ROOT
+    }
+}
+
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
@@ -152,19 +182,41 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     #[cfg(feature = "multitask")]
     axtask::init_scheduler();
 
-    #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
+    #[cfg(any(
+        feature = "fs",
+        feature = "net",
+        feature = "display",
+        feature = "input"
+    ))]
     {
         #[allow(unused_variables)]
         let all_devices = axdriver::init_drivers();
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        {
+            // there is no real-time clock, timestamps are the time since boot
+            // like `CLOCK_REALTIME`
+            axfs_vfs::set_time_source(axhal::time::current_time);
+            axfs::init_filesystems(all_devices.block);
+        }
+
+        #[cfg(feature = "9p")]
+        axfs::init_9p(all_devices.ninep);
 
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
 
         #[cfg(feature = "display")]
         axdisplay::init_display(all_devices.display);
+
+        #[cfg(feature = "input")]
+        axinput::init_input(all_devices.input);
+
+        #[cfg(feature = "fs")]
+        {
+            self::devfs::init();
+            self::procfs::init();
+        }
     }
 
     #[cfg(feature = "smp")]
@@ -182,6 +234,11 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         init_tls();
     }
 
+    #[cfg(all(feature = "fs", feature = "multitask", feature = "irq"))]
+    if axconfig::BLOCK_CACHE_WRITEBACK_MS > 0 {
+        axtask::spawn(block_cache_writeback);
+    }
+
     info!("Primary CPU {} init OK.", cpu_id);
     INITED_CPUS.fetch_add(1, Ordering::Relaxed);
 
@@ -191,6 +248,11 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
     unsafe { main() };
 
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::api::sync() {
+        warn!("failed to write back block caches: {:?}", e);
+    }
+
     #[cfg(feature = "multitask")]
     axtask::exit(0);
     #[cfg(not(feature = "multitask"))]
@@ -232,15 +294,12 @@ fn init_allocator() {
 #[cfg(feature = "paging")]
 fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
     use axhal::mem::{memory_regions, phys_to_virt};
-    use axhal::paging::PageTable;
-    use lazy_init::LazyInit;
-
-    static KERNEL_PAGE_TABLE: LazyInit<PageTable> = LazyInit::new();
+    use axhal::paging::{init_kernel_page_table, kernel_page_table, PageTable};
 
     if axhal::cpu::this_cpu_is_bsp() {
-        let mut kernel_page_table = PageTable::try_new()?;
+        let mut page_table = PageTable::try_new()?;
         for r in memory_regions() {
-            kernel_page_table.map_region(
+            page_table.map_region(
                 phys_to_virt(r.paddr),
                 r.paddr,
                 r.size,
@@ -248,10 +307,10 @@ fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
                 true,
             )?;
         }
-        KERNEL_PAGE_TABLE.init_by(kernel_page_table);
+        init_kernel_page_table(page_table);
     }
 
-    unsafe { axhal::arch::write_page_table_root(KERNEL_PAGE_TABLE.root_paddr()) };
+    unsafe { axhal::arch::write_page_table_root(kernel_page_table().lock().root_paddr()) };
     Ok(())
 }
 
@@ -293,3 +352,15 @@ fn init_tls() {
     unsafe { axhal::arch::write_thread_pointer(main_tls.tls_ptr() as usize) };
     core::mem::forget(main_tls);
 }
+
+/// Writes back dirty blocks in buffer caches periodically.
+#[cfg(all(feature = "fs", feature = "multitask", feature = "irq"))]
+fn block_cache_writeback() {
+    let interval = core::time::Duration::from_millis(axconfig::BLOCK_CACHE_WRITEBACK_MS as u64);
+    loop {
+        axtask::sleep(interval);
+        if let Err(e) = axfs::api::sync() {
+            warn!("failed to write back block caches: {:?}", e);
+        }
+    }
+}
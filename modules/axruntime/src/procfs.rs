@@ -0,0 +1,183 @@
+//! Entries of `/proc` that are generated from the states of other modules.
+
+use alloc::{format, string::String, sync::Arc};
+use axfs::procfs::ProcFile;
+use core::fmt::Write;
+use core::sync::atomic::Ordering;
+
+pub(crate) fn init() {
+    let root = axfs::proc_root();
+    root.add("cpuinfo", Arc::new(ProcFile::new(cpuinfo)));
+    root.add("uptime", Arc::new(ProcFile::new(uptime)));
+
+    #[cfg(feature = "alloc")]
+    root.add("meminfo", Arc::new(ProcFile::new(meminfo)));
+
+    #[cfg(feature = "irq")]
+    root.add("interrupts", Arc::new(ProcFile::new(interrupts)));
+
+    #[cfg(feature = "multitask")]
+    root.set_dynamic(task::list, task::open);
+
+    #[cfg(feature = "net")]
+    {
+        root.mkdir("net")
+            .add("tcp", Arc::new(ProcFile::new(net_tcp)));
+        let somaxconn = ProcFile::new_rw(
+            || format!("{}\n", axnet::listen_backlog()),
+            |value| {
+                let backlog = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| axfs_vfs::VfsError::InvalidInput)?;
+                axnet::set_listen_backlog(backlog)
+            },
+        );
+        root.mkdir("sys")
+            .mkdir("net")
+            .mkdir("core")
+            .add("somaxconn", Arc::new(somaxconn));
+    }
+}
+
+fn cpuinfo() -> String {
+    let mut s = String::new();
+    for cpu_id in 0..super::INITED_CPUS.load(Ordering::Acquire) {
+        writeln!(s, "processor\t: {}", cpu_id).ok();
+        writeln!(s, "arch\t\t: {}", axconfig::ARCH).ok();
+        writeln!(s, "platform\t: {}", axconfig::PLATFORM).ok();
+        writeln!(s).ok();
+    }
+    s
+}
+
+fn uptime() -> String {
+    let now = axhal::time::current_time();
+    // the idle time is not recorded
+    format!("{}.{:02} 0.00\n", now.as_secs(), now.subsec_millis() / 10)
+}
+
+#[cfg(feature = "alloc")]
+fn meminfo() -> String {
+    use axhal::mem::PAGE_SIZE_4K;
+
+    let allocator = axalloc::global_allocator();
+    let total_pages = allocator.used_pages() + allocator.available_pages();
+    let free = allocator.available_pages() * PAGE_SIZE_4K + allocator.available_bytes();
+    let heap_used = allocator.used_bytes();
+    let mut s = String::new();
+    writeln!(
+        s,
+        "MemTotal:       {:8} kB",
+        total_pages * PAGE_SIZE_4K / 1024
+    )
+    .ok();
+    writeln!(s, "MemFree:        {:8} kB", free / 1024).ok();
+    writeln!(s, "MemAvailable:   {:8} kB", free / 1024).ok();
+    writeln!(s, "HeapUsed:       {:8} kB", heap_used / 1024).ok();
+    s
+}
+
+#[cfg(feature = "irq")]
+fn interrupts() -> String {
+    let mut s = String::from("      count\n");
+    for (irq_num, count) in axhal::irq::irq_counts() {
+        writeln!(s, "{:4}: {:10}", irq_num, count).ok();
+    }
+    s
+}
+
+#[cfg(feature = "net")]
+fn net_tcp() -> String {
+    use core::net::{IpAddr, SocketAddr};
+
+    // the same format as Linux, IPv4 addresses are in little-endian
+    fn hex_addr(addr: SocketAddr) -> String {
+        let ip = match addr.ip() {
+            IpAddr::V4(ip) => u32::from_le_bytes(ip.octets()),
+            IpAddr::V6(_) => 0,
+        };
+        format!("{:08X}:{:04X}", ip, addr.port())
+    }
+
+    let mut s = String::from("  sl  local_address rem_address   st\n");
+    for (i, socket) in axnet::tcp_sockets().iter().enumerate() {
+        writeln!(
+            s,
+            "{:4}: {} {} {:02X}",
+            i,
+            hex_addr(socket.local_addr),
+            hex_addr(socket.peer_addr),
+            socket.state
+        )
+        .ok();
+    }
+    s
+}
+
+/// `/proc/<tid>` directories for all tasks, and `/proc/self` for the current
+/// task.
+#[cfg(feature = "multitask")]
+mod task {
+    use alloc::{format, string::String, sync::Arc, vec::Vec};
+    use axfs::procfs::{ProcDir, ProcFile};
+    use axtask::AxTaskRef;
+
+    pub fn list() -> Vec<String> {
+        axtask::tasks()
+            .iter()
+            .map(|task| format!("{}", task.id().as_u64()))
+            .collect()
+    }
+
+    pub fn open(name: &str) -> Option<Arc<ProcDir>> {
+        let task = if name == "self" {
+            axtask::current().as_task_ref().clone()
+        } else {
+            let id: u64 = name.parse().ok()?;
+            axtask::tasks()
+                .into_iter()
+                .find(|task| task.id().as_u64() == id)?
+        };
+        let dir = ProcDir::new();
+        dir.add("status", task_file(&task, status));
+        dir.add("stat", task_file(&task, stat));
+        Some(dir)
+    }
+
+    /// Creates a file whose contents are generated by `f` from the task, or
+    /// empty if the task has been dropped.
+    fn task_file(task: &AxTaskRef, f: fn(&AxTaskRef) -> String) -> Arc<ProcFile> {
+        let task = Arc::downgrade(task);
+        Arc::new(ProcFile::new(move || {
+            task.upgrade().map_or(String::new(), |task| f(&task))
+        }))
+    }
+
+    fn state_char(task: &AxTaskRef) -> char {
+        match task.state_str() {
+            "running" | "ready" => 'R',
+            "blocked" => 'S',
+            _ => 'Z',
+        }
+    }
+
+    fn status(task: &AxTaskRef) -> String {
+        format!(
+            "Name:\t{}\nState:\t{} ({})\nPid:\t{}\n",
+            task.name(),
+            state_char(task),
+            task.state_str(),
+            task.id().as_u64()
+        )
+    }
+
+    fn stat(task: &AxTaskRef) -> String {
+        format!(
+            "{} ({}) {}\n",
+            task.id().as_u64(),
+            task.name(),
+            state_char(task)
+        )
+    }
+}
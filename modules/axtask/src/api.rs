@@ -1,6 +1,6 @@
 //! Task APIs for multi-task configuration.
 
-use alloc::{string::String, sync::Arc};
+use alloc::{string::String, sync::Arc, vec::Vec};
 
 pub(crate) use crate::run_queue::{AxRunQueue, RUN_QUEUE};
 
@@ -89,12 +89,17 @@ pub fn on_timer_tick() {
 
 /// Spawns a new task with the given parameters.
 ///
-/// Returns the task reference.
+/// The new task inherits the credential of the current task. Returns the task
+/// reference.
 pub fn spawn_raw<F>(f: F, name: String, stack_size: usize) -> AxTaskRef
 where
     F: FnOnce() + Send + 'static,
 {
     let task = TaskInner::new(f, name, stack_size);
+    if let Some(curr) = current_may_uninit() {
+        let (uid, gid) = curr.credential();
+        task.set_credential(uid, gid);
+    }
     RUN_QUEUE.lock().add_task(task.clone());
     task
 }
@@ -112,6 +117,12 @@ where
     spawn_raw(f, "".into(), axconfig::TASK_STACK_SIZE)
 }
 
+/// Returns all tasks that have not been dropped (including the exited ones
+/// that are still referenced), ordered by the task ID.
+pub fn tasks() -> Vec<AxTaskRef> {
+    TaskInner::all_tasks()
+}
+
 /// Set the priority for current task.
 ///
 /// The range of the priority is dependent on the underlying scheduler. For
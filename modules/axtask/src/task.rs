@@ -1,6 +1,8 @@
-use alloc::{boxed::Box, string::String, sync::Arc};
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, AtomicU8, Ordering};
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
 #[cfg(feature = "preempt")]
@@ -11,6 +13,7 @@ use axhal::tls::TlsArea;
 
 use axhal::arch::TaskContext;
 use memory_addr::{align_up_4k, VirtAddr};
+use spinlock::SpinNoIrq;
 
 use crate::{AxRunQueue, AxTask, AxTaskRef, WaitQueue};
 
@@ -28,6 +31,9 @@ pub(crate) enum TaskState {
     Exited = 4,
 }
 
+/// All tasks that have not been dropped, indexed by the task ID.
+static TASK_LIST: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
@@ -50,6 +56,9 @@ pub struct TaskInner {
     exit_code: AtomicI32,
     wait_for_exit: WaitQueue,
 
+    uid: AtomicU32,
+    gid: AtomicU32,
+
     kstack: Option<TaskStack>,
     ctx: UnsafeCell<TaskContext>,
 
@@ -96,11 +105,37 @@ impl TaskInner {
         self.name.as_str()
     }
 
+    /// Gets the state of the task as a string, which is one of `"running"`,
+    /// `"ready"`, `"blocked"` and `"exited"`.
+    pub fn state_str(&self) -> &'static str {
+        match self.state() {
+            TaskState::Running => "running",
+            TaskState::Ready => "ready",
+            TaskState::Blocked => "blocked",
+            TaskState::Exited => "exited",
+        }
+    }
+
     /// Get a combined string of the task ID and name.
     pub fn id_name(&self) -> alloc::string::String {
         alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name)
     }
 
+    /// Gets the user and group IDs of the task.
+    pub fn credential(&self) -> (u32, u32) {
+        (
+            self.uid.load(Ordering::Acquire),
+            self.gid.load(Ordering::Acquire),
+        )
+    }
+
+    /// Sets the user and group IDs of the task, which are inherited by the
+    /// tasks spawned by it.
+    pub fn set_credential(&self, uid: u32, gid: u32) {
+        self.uid.store(uid, Ordering::Release);
+        self.gid.store(gid, Ordering::Release);
+    }
+
     /// Wait for the task to exit, and return the exit code.
     ///
     /// It will return immediately if the task has already exited (but not dropped).
@@ -130,6 +165,8 @@ impl TaskInner {
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            uid: AtomicU32::new(0),
+            gid: AtomicU32::new(0),
             kstack: None,
             ctx: UnsafeCell::new(TaskContext::new()),
             #[cfg(feature = "tls")]
@@ -157,7 +194,7 @@ impl TaskInner {
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        Self::register(Arc::new(AxTask::new(t)))
     }
 
     /// Creates an "init task" using the current CPU states, to use as the
@@ -174,7 +211,24 @@ impl TaskInner {
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        Self::register(Arc::new(AxTask::new(t)))
+    }
+
+    /// Adds the task to the global task list.
+    fn register(task: AxTaskRef) -> AxTaskRef {
+        TASK_LIST
+            .lock()
+            .insert(task.id.as_u64(), Arc::downgrade(&task));
+        task
+    }
+
+    /// Returns all tasks that have not been dropped, ordered by the task ID.
+    pub(crate) fn all_tasks() -> Vec<AxTaskRef> {
+        TASK_LIST
+            .lock()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect()
     }
 
     #[inline]
@@ -296,6 +350,7 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        TASK_LIST.lock().remove(&self.id.as_u64());
     }
 }
 
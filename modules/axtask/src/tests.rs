@@ -128,3 +128,34 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_task_list() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let task = axtask::spawn_raw(|| axtask::exit(0), "listed".into(), 0x1000);
+    let id = task.id();
+    let find = || axtask::tasks().into_iter().find(|t| t.id() == id);
+    assert_eq!(find().unwrap().name(), "listed");
+    assert_eq!(find().unwrap().state_str(), "ready");
+    assert!(axtask::tasks().iter().any(|t| t.id() == current().id()));
+
+    assert_eq!(task.join(), Some(0));
+    assert_eq!(find().unwrap().state_str(), "exited");
+}
+
+#[test]
+fn test_credential() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    current().set_credential(1000, 100);
+    let task = axtask::spawn(|| {
+        assert_eq!(current().credential(), (1000, 100));
+        current().set_credential(0, 0);
+    });
+    assert_eq!(task.join(), Some(0));
+    assert_eq!(current().credential(), (1000, 100));
+    current().set_credential(0, 0);
+}
@@ -5,6 +5,8 @@
 #     The features can be selected from the crate `axfeat` or the user library
 #     (crate `axstd` or `axlibc`).
 #   - `APP_FEATURES`: a list of features to be enabled for the Rust app.
+#   - `INITRAMFS`: if set, the `initramfs` feature is enabled.
+#   - `VIRTFS`: if set, the `9p` feature is enabled.
 #
 # Outputs:
 #   - `AX_FEAT`: features to be enabled for ArceOS modules (crate `axfeat`).
@@ -14,7 +16,7 @@
 ifeq ($(APP_TYPE),c)
   ax_feat_prefix := axfeat/
   lib_feat_prefix := axlibc/
-  lib_features := fp_simd irq alloc multitask fs net fd pipe select epoll
+  lib_features := fp_simd irq alloc multitask fs net fd pipe select epoll mmap
 else
   # TODO: it's better to use `axfeat/` as `ax_feat_prefix`, but all apps need to have `axfeat` as a dependency
   ax_feat_prefix := axstd/
@@ -48,6 +50,14 @@ ifeq ($(BUS),pci)
   ax_feat += bus-pci
 endif
 
+ifneq ($(INITRAMFS),)
+  ax_feat += initramfs
+endif
+
+ifneq ($(VIRTFS),)
+  ax_feat += 9p
+endif
+
 ifeq ($(shell test $(SMP) -gt 1; echo $$?),0)
   lib_feat += smp
 endif
@@ -30,6 +30,12 @@ qemu_args-$(BLK) += \
   -device virtio-blk-$(vdev-suffix),drive=disk0 \
   -drive id=disk0,if=none,format=raw,file=$(DISK_IMG)
 
+ifneq ($(VIRTFS),)
+  qemu_args-y += \
+    -device virtio-9p-$(vdev-suffix),fsdev=fs0,mount_tag=$(VIRTFS_TAG) \
+    -fsdev local,id=fs0,path=$(VIRTFS),security_model=none
+endif
+
 qemu_args-$(NET) += \
   -device virtio-net-$(vdev-suffix),netdev=net0
 
@@ -56,6 +62,7 @@ endif
 
 qemu_args-$(GRAPHIC) += \
   -device virtio-gpu-$(vdev-suffix) -vga none \
+  -device virtio-keyboard-$(vdev-suffix) -device virtio-tablet-$(vdev-suffix) \
   -serial mon:stdio
 
 ifeq ($(GRAPHIC), n)
@@ -2,7 +2,8 @@
 
 define unit_test
   $(call run_cmd,cargo test,-p percpu $(1) -- --nocapture)
-  $(call run_cmd,cargo test,-p axfs $(1) --features "myfs" -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "myfs overlayfs" -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "initramfs" -- --nocapture)
   $(call run_cmd,cargo test,--workspace --exclude "arceos-*" $(1) -- --nocapture)
 endef
 
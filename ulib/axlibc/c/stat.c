@@ -2,26 +2,32 @@
 #include <sys/stat.h>
 #include <sys/types.h>
 
+#ifndef AX_CONFIG_FS
 // TODO:
 int fchmod(int fd, mode_t mode)
 {
     unimplemented();
     return 0;
 }
+#endif // AX_CONFIG_FS
 
+#ifndef AX_CONFIG_FS
 // TODO:
 int mkdir(const char *path, mode_t mode)
 {
     unimplemented();
     return 0;
 }
+#endif // AX_CONFIG_FS
 
+#ifndef AX_CONFIG_FS
 // TODO
 int chmod(const char *path, mode_t mode)
 {
     unimplemented();
     return 0;
 }
+#endif // AX_CONFIG_FS
 
 // TODO
 mode_t umask(mode_t mask)
@@ -30,9 +36,11 @@ mode_t umask(mode_t mask)
     return 0;
 }
 
+#ifndef AX_CONFIG_FS
 // TODO
 int fstatat(int fd, const char *restrict path, struct stat *restrict st, int flag)
 {
     unimplemented();
     return 0;
 }
+#endif // AX_CONFIG_FS
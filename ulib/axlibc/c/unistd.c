@@ -54,34 +54,7 @@ int usleep(unsigned useconds)
 
 #ifdef AX_CONFIG_FS
 
-// TODO:
-int access(const char *pathname, int mode)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-ssize_t readlink(const char *path, char *buf, size_t bufsiz)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int unlink(const char *pathname)
-{
-    unimplemented();
-    return 0;
-}
-
-// TODO:
-int rmdir(const char *pathname)
-{
-    unimplemented();
-    return 0;
-}
-
+#ifndef AX_CONFIG_FS
 // TODO:
 int fsync(int fd)
 {
@@ -95,20 +68,16 @@ int fdatasync(int __fildes)
     unimplemented();
     return 0;
 }
+#endif // AX_CONFIG_FS
 
+#ifndef AX_CONFIG_FS
 // TODO:
 int fchown(int fd, uid_t owner, gid_t group)
 {
     unimplemented("owner: %x group: %x", owner, group);
     return 0;
 }
-
-// TODO:
-int ftruncate(int fd, off_t length)
-{
-    unimplemented();
-    return 0;
-}
+#endif // AX_CONFIG_FS
 
 // TODO
 int chdir(const char *__path)
@@ -2,12 +2,16 @@
 #define _SYS_UIO_H
 
 #include <stddef.h>
+#include <sys/types.h>
 
 struct iovec {
     void *iov_base; /* Pointer to data.  */
     size_t iov_len; /* Length of data.  */
 };
 
+ssize_t readv(int, const struct iovec *, int);
 ssize_t writev(int, const struct iovec *, int);
+ssize_t preadv(int, const struct iovec *, int, off_t);
+ssize_t pwritev(int, const struct iovec *, int, off_t);
 
 #endif
@@ -0,0 +1,85 @@
+use core::ffi::c_int;
+
+use arceos_posix_api::{
+    sys_aio_cancel, sys_aio_error, sys_aio_fsync, sys_aio_read, sys_aio_return, sys_aio_suspend,
+    sys_aio_write, sys_lio_listio,
+};
+
+use crate::{ctypes, utils::e};
+
+/// Queue an asynchronous read request.
+///
+/// Return 0 if the request is queued.
+#[no_mangle]
+pub unsafe extern "C" fn aio_read(cb: *mut ctypes::aiocb) -> c_int {
+    e(sys_aio_read(cb))
+}
+
+/// Queue an asynchronous write request.
+///
+/// Return 0 if the request is queued.
+#[no_mangle]
+pub unsafe extern "C" fn aio_write(cb: *mut ctypes::aiocb) -> c_int {
+    e(sys_aio_write(cb))
+}
+
+/// Queue an asynchronous fsync request.
+///
+/// Return 0 if the request is queued.
+#[no_mangle]
+pub unsafe extern "C" fn aio_fsync(op: c_int, cb: *mut ctypes::aiocb) -> c_int {
+    e(sys_aio_fsync(op, cb))
+}
+
+/// Get the error status of an asynchronous request.
+///
+/// Return `EINPROGRESS` if it is not completed, 0 if it succeeded, or the
+/// error number.
+#[no_mangle]
+pub unsafe extern "C" fn aio_error(cb: *const ctypes::aiocb) -> c_int {
+    e(sys_aio_error(cb))
+}
+
+/// Get the return status of a completed asynchronous request.
+///
+/// Return the number of bytes transferred, or -1 if it failed.
+#[no_mangle]
+pub unsafe extern "C" fn aio_return(cb: *mut ctypes::aiocb) -> ctypes::ssize_t {
+    e(sys_aio_return(cb) as _) as _
+}
+
+/// Wait until at least one of the asynchronous requests is completed, or the
+/// timeout expires.
+///
+/// Return 0 if some are completed.
+#[no_mangle]
+pub unsafe extern "C" fn aio_suspend(
+    list: *const *const ctypes::aiocb,
+    nent: c_int,
+    timeout: *const ctypes::timespec,
+) -> c_int {
+    e(sys_aio_suspend(list, nent, timeout))
+}
+
+/// Cancel the asynchronous request `cb`, or all requests on `fd` if `cb` is
+/// NULL.
+///
+/// Return `AIO_CANCELED`, `AIO_NOTCANCELED` or `AIO_ALLDONE`.
+#[no_mangle]
+pub unsafe extern "C" fn aio_cancel(fd: c_int, cb: *mut ctypes::aiocb) -> c_int {
+    e(sys_aio_cancel(fd, cb))
+}
+
+/// Queue a list of asynchronous requests, and wait for all of them if `mode`
+/// is `LIO_WAIT`.
+///
+/// Return 0 if success.
+#[no_mangle]
+pub unsafe extern "C" fn lio_listio(
+    mode: c_int,
+    list: *const *mut ctypes::aiocb,
+    nent: c_int,
+    sig: *mut ctypes::sigevent,
+) -> c_int {
+    e(sys_lio_listio(mode, list, nent, sig))
+}
@@ -47,7 +47,7 @@ pub unsafe extern "C" fn dup3(old_fd: c_int, new_fd: c_int, flags: c_int) -> c_i
 
 /// Manipulate file descriptor.
 ///
-/// TODO: `SET/GET` command is ignored
+/// TODO: `SET/GET` command is ignored, except the record locks of files
 #[no_mangle]
 pub unsafe extern "C" fn ax_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     e(sys_fcntl(fd, cmd, arg))
@@ -1,6 +1,6 @@
 use core::ffi::{c_int, c_void};
 
-use arceos_posix_api::{sys_read, sys_write, sys_writev};
+use arceos_posix_api::{sys_read, sys_readv, sys_write, sys_writev};
 
 use crate::{ctypes, utils::e};
 
@@ -21,6 +21,18 @@ pub unsafe extern "C" fn write(fd: c_int, buf: *const c_void, count: usize) -> c
     e(sys_write(fd, buf, count) as _) as _
 }
 
+/// Read data from the file indicated by `fd` into a vector of buffers.
+///
+/// Return the read size if success.
+#[no_mangle]
+pub unsafe extern "C" fn readv(
+    fd: c_int,
+    iov: *const ctypes::iovec,
+    iocnt: c_int,
+) -> ctypes::ssize_t {
+    e(sys_readv(fd, iov, iocnt) as _) as _
+}
+
 /// Write a vector.
 #[no_mangle]
 pub unsafe extern "C" fn writev(
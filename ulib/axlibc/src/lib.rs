@@ -20,10 +20,12 @@
 //!     - `pipe`: Enable pipe support.
 //!     - `select`: Enable synchronous I/O multiplexing ([select]) support.
 //!     - `epoll`: Enable event polling ([epoll]) support.
+//!     - `mmap`: Enable memory mapping ([mmap]) support.
 //!
 //! [ArceOS]: https://github.com/rcore-os/arceos
 //! [select]: https://man7.org/linux/man-pages/man2/select.2.html
 //! [epoll]: https://man7.org/linux/man-pages/man7/epoll.7.html
+//! [mmap]: https://man7.org/linux/man-pages/man2/mmap.2.html
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
 #![feature(doc_cfg)]
@@ -49,14 +51,20 @@ mod ctypes {
 #[macro_use]
 mod utils;
 
+#[cfg(feature = "fs")]
+mod aio;
 #[cfg(feature = "fd")]
 mod fd_ops;
 #[cfg(feature = "fs")]
 mod fs;
+#[cfg(feature = "fs")]
+mod inotify;
 #[cfg(any(feature = "select", feature = "epoll"))]
 mod io_mpx;
 #[cfg(feature = "alloc")]
 mod malloc;
+#[cfg(feature = "mmap")]
+mod mmap;
 #[cfg(feature = "net")]
 mod net;
 #[cfg(feature = "pipe")]
@@ -80,7 +88,7 @@ mod unistd;
 
 #[cfg(not(test))]
 pub use self::io::write;
-pub use self::io::{read, writev};
+pub use self::io::{read, readv, writev};
 
 pub use self::errno::strerror;
 pub use self::mktime::mktime;
@@ -96,11 +104,25 @@ pub use self::malloc::{free, malloc};
 #[cfg(feature = "alloc")]
 pub use self::strftime::strftime;
 
+#[cfg(feature = "fs")]
+pub use self::aio::{
+    aio_cancel, aio_error, aio_fsync, aio_read, aio_return, aio_suspend, aio_write, lio_listio,
+};
 #[cfg(feature = "fd")]
 pub use self::fd_ops::{ax_fcntl, close, dup, dup2, dup3};
 
 #[cfg(feature = "fs")]
-pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, rename, stat};
+pub use self::fs::{
+    access, ax_open, ax_openat, chmod, chown, faccessat, fchdir, fchmod, fchown, fdatasync, flock,
+    fstat, fstatat, fstatfs, fsync, ftruncate, getcwd, getdents64, link, lseek, lstat, mkdir,
+    mkdirat, preadv, pwritev, readlink, rename, renameat, renameat2, rmdir, stat, statfs, symlink,
+    sync, unlink, unlinkat,
+};
+#[cfg(feature = "fs")]
+pub use self::inotify::{inotify_add_watch, inotify_init, inotify_init1, inotify_rm_watch};
+
+#[cfg(feature = "mmap")]
+pub use self::mmap::{mmap, mprotect, msync, munmap};
 
 #[cfg(feature = "net")]
 pub use self::net::{
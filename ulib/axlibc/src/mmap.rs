@@ -0,0 +1,45 @@
+use core::ffi::{c_int, c_void};
+
+use arceos_posix_api::{sys_mmap, sys_mprotect, sys_msync, sys_munmap};
+
+use crate::{ctypes, utils::e};
+
+/// Map files or anonymous memory into the address space.
+///
+/// Return the start address of the mapping, or `MAP_FAILED` on error.
+#[no_mangle]
+pub unsafe extern "C" fn mmap(
+    addr: *mut c_void,
+    len: ctypes::size_t,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    off: ctypes::off_t,
+) -> *mut c_void {
+    let ret = sys_mmap(addr, len, prot, flags, fd, off) as isize;
+    // mappings may be placed in the higher half, so only `-4095..=-1` are errors
+    if (-4095..0).contains(&ret) {
+        e(ret as _) as _
+    } else {
+        ret as _
+    }
+}
+
+/// Remove the mappings in the range `[addr, addr + len)`.
+#[no_mangle]
+pub unsafe extern "C" fn munmap(addr: *mut c_void, len: ctypes::size_t) -> c_int {
+    e(sys_munmap(addr, len))
+}
+
+/// Set the protection of the mappings in the range `[addr, addr + len)`.
+#[no_mangle]
+pub unsafe extern "C" fn mprotect(addr: *mut c_void, len: ctypes::size_t, prot: c_int) -> c_int {
+    e(sys_mprotect(addr, len, prot))
+}
+
+/// Write the shared file mappings in the range `[addr, addr + len)` back to
+/// the files.
+#[no_mangle]
+pub unsafe extern "C" fn msync(addr: *mut c_void, len: ctypes::size_t, flags: c_int) -> c_int {
+    e(sys_msync(addr, len, flags))
+}
@@ -1,4 +1,5 @@
 use crate::io::{prelude::*, Result, SeekFrom};
+use crate::time::{SystemTime, UNIX_EPOCH};
 use core::fmt;
 
 use arceos_api::fs as api;
@@ -16,7 +17,7 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(api::AxFileAttr);
+pub struct Metadata(pub(super) api::AxFileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
@@ -90,6 +91,15 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link.
+    ///
+    /// It's only possible for metadata returned by [`symlink_metadata`].
+    ///
+    /// [`symlink_metadata`]: super::symlink_metadata
+    pub const fn is_symlink(&self) -> bool {
+        self.0.is_symlink()
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -110,6 +120,26 @@ impl Metadata {
     pub const fn blocks(&self) -> u64 {
         self.0.blocks()
     }
+
+    /// Returns the user ID of the owner of this file.
+    pub const fn uid(&self) -> u32 {
+        self.0.uid()
+    }
+
+    /// Returns the group ID of the owner of this file.
+    pub const fn gid(&self) -> u32 {
+        self.0.gid()
+    }
+
+    /// Returns the last access time of this file.
+    pub fn accessed(&self) -> Result<SystemTime> {
+        Ok(UNIX_EPOCH + self.0.atime())
+    }
+
+    /// Returns the last modification time of this file.
+    pub fn modified(&self) -> Result<SystemTime> {
+        Ok(UNIX_EPOCH + self.0.mtime())
+    }
 }
 
 impl fmt::Debug for Metadata {
@@ -162,6 +192,17 @@ impl File {
     pub fn metadata(&self) -> Result<Metadata> {
         api::ax_file_attr(&self.inner).map(Metadata)
     }
+
+    /// Changes the permissions on the underlying file.
+    pub fn set_permissions(&self, perm: Permissions) -> Result<()> {
+        api::ax_set_file_attr(&self.inner, api::AxFileSetAttr::new().with_perm(perm))
+    }
+
+    /// Changes the modification time of the underlying file.
+    pub fn set_modified(&self, time: SystemTime) -> Result<()> {
+        let mtime = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        api::ax_set_file_attr(&self.inner, api::AxFileSetAttr::new().with_mtime(mtime))
+    }
 }
 
 impl Read for File {
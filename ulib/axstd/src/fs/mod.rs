@@ -2,6 +2,7 @@
 
 mod dir;
 mod file;
+mod watch;
 
 use crate::io::{self, prelude::*};
 
@@ -10,6 +11,7 @@ use alloc::{string::String, vec::Vec};
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+pub use self::watch::{Events, WatchEvent, WatchMask, Watcher};
 
 /// Read the entire contents of a file into a bytes vector.
 #[cfg(feature = "alloc")]
@@ -42,6 +44,37 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Query the metadata about a file without following symbolic links.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    arceos_api::fs::ax_symlink_attr(path).map(Metadata)
+}
+
+/// Changes the permissions found on a file or a directory.
+pub fn set_permissions(path: &str, perm: Permissions) -> io::Result<()> {
+    let attr = arceos_api::fs::AxFileSetAttr::new().with_perm(perm);
+    arceos_api::fs::ax_set_path_attr(path, attr)
+}
+
+/// Reads a symbolic link, returning the path that the link points to.
+#[cfg(feature = "alloc")]
+pub fn read_link(path: &str) -> io::Result<String> {
+    arceos_api::fs::ax_read_link(path)
+}
+
+/// Creates a new symbolic link `link` on the filesystem, which points to
+/// `original`.
+pub fn soft_link(original: &str, link: &str) -> io::Result<()> {
+    arceos_api::fs::ax_soft_link(original, link)
+}
+
+/// Creates a new hard link `link` on the filesystem, which refers to the
+/// same file as `original`.
+///
+/// This only works then the two paths are in the same mounted fs.
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    arceos_api::fs::ax_hard_link(original, link)
+}
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -75,3 +108,23 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     arceos_api::fs::ax_rename(old, new)
 }
+
+/// Mounts a new filesystem of type `fs_type` (e.g., `ramfs`) at `path`.
+///
+/// The mount point is created if it does not exist.
+pub fn mount(path: &str, fs_type: &str) -> io::Result<()> {
+    arceos_api::fs::ax_mount(path, fs_type)
+}
+
+/// Unmounts the filesystem mounted at `path`.
+///
+/// It fails if there are still opened files in the filesystem.
+pub fn umount(path: &str) -> io::Result<()> {
+    arceos_api::fs::ax_umount(path)
+}
+
+/// Returns the paths of all mount points, starting with the root `/`.
+#[cfg(feature = "alloc")]
+pub fn mounts() -> Vec<String> {
+    arceos_api::fs::ax_mounts()
+}
@@ -0,0 +1,79 @@
+use crate::io::Result;
+use crate::thread;
+
+use arceos_api::fs as api;
+
+/// Kinds of file change events, used to select events to watch and to tell
+/// the kind of a received event.
+pub type WatchMask = api::AxWatchMask;
+
+/// A file change event received by a [`Watcher`].
+pub type WatchEvent = api::AxWatchEvent;
+
+/// Watches files and directories for changes, like Linux `inotify`.
+///
+/// Events are queued in the watcher once it is created, and can be received
+/// by [`try_next`](Watcher::try_next) or the blocking iterator returned by
+/// [`events`](Watcher::events).
+pub struct Watcher {
+    inner: api::AxWatcherHandle,
+}
+
+/// A blocking iterator over the events received by a [`Watcher`].
+///
+/// It never returns `None`, but waits for the next event instead.
+pub struct Events<'a> {
+    watcher: &'a Watcher,
+}
+
+impl Watcher {
+    /// Creates a new watcher without any watch.
+    pub fn new() -> Self {
+        Self {
+            inner: api::ax_new_watcher(),
+        }
+    }
+
+    /// Watches the file or directory at `path` for events in `mask`. Returns
+    /// the watch descriptor, which is the `wd` of received events.
+    ///
+    /// For a directory, events about the files in it are also received. If
+    /// the path is already watched, the mask is replaced.
+    pub fn add_watch(&self, path: &str, mask: WatchMask) -> Result<usize> {
+        api::ax_add_watch(&self.inner, path, mask)
+    }
+
+    /// Removes the watch with the watch descriptor `wd`.
+    pub fn remove_watch(&self, wd: usize) -> Result<()> {
+        api::ax_remove_watch(&self.inner, wd)
+    }
+
+    /// Returns the next event, or `None` if there is no event for now.
+    pub fn try_next(&self) -> Option<WatchEvent> {
+        api::ax_next_watch_event(&self.inner)
+    }
+
+    /// Returns an iterator that waits for and yields the events.
+    pub fn events(&self) -> Events<'_> {
+        Events { watcher: self }
+    }
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Events<'_> {
+    type Item = WatchEvent;
+
+    fn next(&mut self) -> Option<WatchEvent> {
+        loop {
+            if let Some(event) = self.watcher.try_next() {
+                return Some(event);
+            }
+            thread::yield_now();
+        }
+    }
+}